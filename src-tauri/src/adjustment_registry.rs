@@ -0,0 +1,476 @@
+//! Registry of every top-level adjustment key stored in a sidecar.
+//!
+//! Copy/paste defaults, section resets, value clamping and the scales used to
+//! build the GPU uniforms are all derived from `ADJUSTMENTS`, so adding a new
+//! adjustment only requires a new entry here.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum AdjustmentDefault {
+    Number(f64),
+    Bool(bool),
+    Text(&'static str),
+    /// Explicitly `null` in a fresh edit (e.g. no crop, no LUT).
+    Null,
+    /// Nested object whose shape is owned by the frontend; resetting removes the key.
+    Structured,
+}
+
+impl AdjustmentDefault {
+    pub fn as_f64(self) -> Option<f64> {
+        match self {
+            AdjustmentDefault::Number(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(self) -> Option<Value> {
+        match self {
+            // Whole numbers stay integers so `as_u64` readers such as `orientationSteps` work.
+            AdjustmentDefault::Number(v) if v.fract() == 0.0 => Some(Value::from(v as i64)),
+            AdjustmentDefault::Number(v) => Some(Value::from(v)),
+            AdjustmentDefault::Bool(v) => Some(Value::Bool(v)),
+            AdjustmentDefault::Text(v) => Some(Value::from(v)),
+            AdjustmentDefault::Null => Some(Value::Null),
+            AdjustmentDefault::Structured => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjustmentDef {
+    pub key: &'static str,
+    /// Matches the `sectionVisibility` keys for panel adjustments.
+    pub section: &'static str,
+    pub default: AdjustmentDefault,
+    /// Divisor mapping the slider value onto the shader's unit range.
+    pub scale: f32,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub copyable: bool,
+    pub geometry: bool,
+    /// Entry of `PANEL_GROUPS` that hides this adjustment's controls.
+    pub group: Option<&'static str>,
+}
+
+impl AdjustmentDef {
+    const fn in_group(self, group: &'static str) -> Self {
+        AdjustmentDef {
+            group: Some(group),
+            ..self
+        }
+    }
+
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+}
+
+const fn slider(
+    key: &'static str,
+    section: &'static str,
+    default: f64,
+    scale: f32,
+    min: f64,
+    max: f64,
+) -> AdjustmentDef {
+    AdjustmentDef {
+        key,
+        section,
+        default: AdjustmentDefault::Number(default),
+        scale,
+        min: Some(min),
+        max: Some(max),
+        copyable: true,
+        geometry: false,
+        group: None,
+    }
+}
+
+const fn setting(
+    key: &'static str,
+    section: &'static str,
+    default: AdjustmentDefault,
+) -> AdjustmentDef {
+    AdjustmentDef {
+        key,
+        section,
+        default,
+        scale: 1.0,
+        min: None,
+        max: None,
+        copyable: true,
+        geometry: false,
+        group: None,
+    }
+}
+
+const fn geometry_slider(
+    key: &'static str,
+    section: &'static str,
+    default: f64,
+    min: f64,
+    max: f64,
+) -> AdjustmentDef {
+    AdjustmentDef {
+        key,
+        section,
+        default: AdjustmentDefault::Number(default),
+        scale: 1.0,
+        min: Some(min),
+        max: Some(max),
+        copyable: false,
+        geometry: true,
+        group: None,
+    }
+}
+
+const fn geometry_setting(
+    key: &'static str,
+    section: &'static str,
+    default: AdjustmentDefault,
+) -> AdjustmentDef {
+    AdjustmentDef {
+        key,
+        section,
+        default,
+        scale: 1.0,
+        min: None,
+        max: None,
+        copyable: false,
+        geometry: true,
+        group: None,
+    }
+}
+
+pub const ADJUSTMENTS: &[AdjustmentDef] = &[
+    slider("exposure", "basic", 0.0, 0.8, -5.0, 5.0),
+    slider("brightness", "basic", 0.0, 0.8, -5.0, 5.0),
+    slider("contrast", "basic", 0.0, 100.0, -100.0, 100.0),
+    slider("highlights", "basic", 0.0, 120.0, -100.0, 100.0),
+    slider("shadows", "basic", 0.0, 120.0, -100.0, 100.0),
+    slider("whites", "basic", 0.0, 30.0, -100.0, 100.0),
+    slider("blacks", "basic", 0.0, 70.0, -100.0, 100.0),
+    setting("toneMapper", "basic", AdjustmentDefault::Text("basic")),
+    setting("invert", "negative", AdjustmentDefault::Bool(false)),
+    setting("filmBaseColor", "negative", AdjustmentDefault::Null),
+    slider("negativeBlackRed", "negative", 0.0, 100.0, 0.0, 100.0),
+    slider("negativeBlackGreen", "negative", 0.0, 100.0, 0.0, 100.0),
+    slider("negativeBlackBlue", "negative", 0.0, 100.0, 0.0, 100.0),
+    slider("negativeWhiteRed", "negative", 100.0, 100.0, 0.0, 100.0),
+    slider("negativeWhiteGreen", "negative", 100.0, 100.0, 0.0, 100.0),
+    slider("negativeWhiteBlue", "negative", 100.0, 100.0, 0.0, 100.0),
+    setting("curves", "curves", AdjustmentDefault::Structured),
+    setting("parametricCurve", "curves", AdjustmentDefault::Structured),
+    slider("saturation", "color", 0.0, 100.0, -100.0, 100.0),
+    slider("temperature", "color", 0.0, 25.0, -100.0, 100.0),
+    slider("tint", "color", 0.0, 100.0, -100.0, 100.0),
+    slider("vibrance", "color", 0.0, 100.0, -100.0, 100.0),
+    setting("hsl", "color", AdjustmentDefault::Structured),
+    setting("colorGrading", "color", AdjustmentDefault::Structured),
+    setting("colorCalibration", "color", AdjustmentDefault::Structured)
+        .in_group("colorCalibration"),
+    setting("channelMixer", "color", AdjustmentDefault::Structured).in_group("channelMixer"),
+    setting("splitToning", "color", AdjustmentDefault::Structured).in_group("splitToning"),
+    slider("sharpness", "details", 0.0, 40.0, -100.0, 100.0).in_group("sharpening"),
+    slider("lumaNoiseReduction", "details", 0.0, 100.0, 0.0, 100.0).in_group("noiseReduction"),
+    slider("colorNoiseReduction", "details", 0.0, 100.0, 0.0, 100.0).in_group("noiseReduction"),
+    slider("clarity", "details", 0.0, 200.0, -100.0, 100.0).in_group("presence"),
+    slider("dehaze", "details", 0.0, 750.0, -100.0, 100.0).in_group("presence"),
+    slider("structure", "details", 0.0, 200.0, -100.0, 100.0).in_group("presence"),
+    slider("centré", "details", 0.0, 250.0, -100.0, 100.0).in_group("presence"),
+    slider(
+        "chromaticAberrationRedCyan",
+        "details",
+        0.0,
+        10000.0,
+        -100.0,
+        100.0,
+    )
+    .in_group("chromaticAberration"),
+    slider(
+        "chromaticAberrationBlueYellow",
+        "details",
+        0.0,
+        10000.0,
+        -100.0,
+        100.0,
+    )
+    .in_group("chromaticAberration"),
+    slider("defringePurpleAmount", "details", 0.0, 100.0, 0.0, 100.0).in_group("defringe"),
+    slider("defringeGreenAmount", "details", 0.0, 100.0, 0.0, 100.0).in_group("defringe"),
+    slider("defringePurpleHueMin", "details", 260.0, 1.0, 0.0, 360.0).in_group("defringe"),
+    slider("defringePurpleHueMax", "details", 320.0, 1.0, 0.0, 360.0).in_group("defringe"),
+    slider("defringeGreenHueMin", "details", 90.0, 1.0, 0.0, 360.0).in_group("defringe"),
+    slider("defringeGreenHueMax", "details", 150.0, 1.0, 0.0, 360.0).in_group("defringe"),
+    slider("vignetteAmount", "effects", 0.0, 100.0, -100.0, 100.0).in_group("vignette"),
+    slider("vignetteMidpoint", "effects", 50.0, 100.0, 0.0, 100.0).in_group("vignette"),
+    slider("vignetteRoundness", "effects", 0.0, 100.0, -100.0, 100.0).in_group("vignette"),
+    slider("vignetteFeather", "effects", 50.0, 100.0, 0.0, 100.0).in_group("vignette"),
+    slider("grainAmount", "effects", 0.0, 200.0, 0.0, 100.0).in_group("grain"),
+    slider("grainSize", "effects", 25.0, 50.0, 0.0, 100.0).in_group("grain"),
+    slider("grainRoughness", "effects", 50.0, 100.0, 0.0, 100.0).in_group("grain"),
+    slider("grainHighlightRolloff", "effects", 0.0, 100.0, 0.0, 100.0).in_group("grain"),
+    setting("grainColor", "effects", AdjustmentDefault::Bool(false)).in_group("grain"),
+    slider("glowAmount", "effects", 0.0, 100.0, 0.0, 100.0),
+    slider("glowRadius", "effects", 40.0, 1.0, 1.0, 200.0),
+    slider("halationAmount", "effects", 0.0, 100.0, 0.0, 100.0),
+    slider("halationRadius", "effects", 8.0, 1.0, 1.0, 100.0),
+    slider("flareAmount", "effects", 0.0, 100.0, 0.0, 100.0),
+    slider("lutIntensity", "effects", 100.0, 100.0, 0.0, 100.0),
+    setting("lutName", "effects", AdjustmentDefault::Null),
+    setting("lutPath", "effects", AdjustmentDefault::Null),
+    setting("lutSize", "effects", AdjustmentDefault::Number(0.0)),
+    setting("sectionVisibility", "view", AdjustmentDefault::Structured),
+    setting("showClipping", "view", AdjustmentDefault::Bool(false)),
+    geometry_setting("crop", "crop", AdjustmentDefault::Null),
+    geometry_setting("aspectRatio", "crop", AdjustmentDefault::Null),
+    geometry_setting("rotation", "crop", AdjustmentDefault::Number(0.0)),
+    geometry_setting("flipHorizontal", "crop", AdjustmentDefault::Bool(false)),
+    geometry_setting("flipVertical", "crop", AdjustmentDefault::Bool(false)),
+    geometry_setting("orientationSteps", "crop", AdjustmentDefault::Number(0.0)),
+    geometry_slider("transformDistortion", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("transformVertical", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("transformHorizontal", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("transformRotate", "transform", 0.0, -45.0, 45.0),
+    geometry_slider("transformAspect", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("transformScale", "transform", 100.0, 50.0, 150.0),
+    geometry_slider("transformXOffset", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("transformYOffset", "transform", 0.0, -100.0, 100.0),
+    geometry_slider("lensDistortionAmount", "lens", 100.0, 0.0, 200.0),
+    geometry_slider("lensVignetteAmount", "lens", 100.0, 0.0, 200.0),
+    geometry_slider("lensTcaAmount", "lens", 100.0, 0.0, 200.0),
+    geometry_setting(
+        "lensDistortionEnabled",
+        "lens",
+        AdjustmentDefault::Bool(true),
+    ),
+    geometry_setting("lensTcaEnabled", "lens", AdjustmentDefault::Bool(true)),
+    geometry_setting("lensVignetteEnabled", "lens", AdjustmentDefault::Bool(true)),
+    geometry_setting("lensDistortionParams", "lens", AdjustmentDefault::Null),
+    geometry_setting("lensMaker", "lens", AdjustmentDefault::Null),
+    geometry_setting("lensModel", "lens", AdjustmentDefault::Null),
+];
+
+/// Optional panel groups the user can hide in settings, with their default visibility.
+pub const PANEL_GROUPS: &[(&str, bool)] = &[
+    ("sharpening", true),
+    ("presence", true),
+    ("noiseReduction", true),
+    ("chromaticAberration", false),
+    ("defringe", false),
+    ("vignette", true),
+    ("colorCalibration", false),
+    ("channelMixer", false),
+    ("splitToning", false),
+    ("grain", true),
+];
+
+pub fn find(key: &str) -> Option<&'static AdjustmentDef> {
+    ADJUSTMENTS.iter().find(|def| def.key == key)
+}
+
+pub fn copyable_keys() -> impl Iterator<Item = &'static str> {
+    ADJUSTMENTS
+        .iter()
+        .filter(|def| def.copyable)
+        .map(|def| def.key)
+}
+
+pub fn geometry_keys() -> impl Iterator<Item = &'static str> {
+    ADJUSTMENTS
+        .iter()
+        .filter(|def| def.geometry)
+        .map(|def| def.key)
+}
+
+/// Geometry keys consumed by the warp pass, i.e. everything outside the crop section.
+pub fn warp_keys() -> impl Iterator<Item = &'static str> {
+    ADJUSTMENTS
+        .iter()
+        .filter(|def| def.geometry && def.section != "crop")
+        .map(|def| def.key)
+}
+
+pub fn keys_for_section(section: &str) -> impl Iterator<Item = &'static str> + '_ {
+    ADJUSTMENTS
+        .iter()
+        .filter(move |def| def.section == section)
+        .map(|def| def.key)
+}
+
+pub fn default_adjustments() -> Map<String, Value> {
+    ADJUSTMENTS
+        .iter()
+        .filter_map(|def| def.default.to_value().map(|v| (def.key.to_string(), v)))
+        .collect()
+}
+
+pub fn is_known_section(section: &str) -> bool {
+    ADJUSTMENTS.iter().any(|def| def.section == section)
+}
+
+/// Restores every key of `section` to its default, dropping structured values.
+pub fn reset_section(adjustments: &mut Value, section: &str) {
+    if !adjustments.is_object() {
+        *adjustments = Value::Object(Map::new());
+    }
+    let defaults = default_adjustments();
+    let obj = adjustments.as_object_mut().unwrap();
+    for key in keys_for_section(section) {
+        match defaults.get(key) {
+            Some(value) => {
+                obj.insert(key.to_string(), value.clone());
+            }
+            None => {
+                obj.remove(key);
+            }
+        }
+    }
+}
+
+/// Clamps numeric adjustments into their slider range and replaces values of
+/// the wrong type with the registered default.
+pub fn sanitize_adjustments(adjustments: &mut Value) {
+    let Some(obj) = adjustments.as_object_mut() else {
+        return;
+    };
+    for def in ADJUSTMENTS {
+        let Some(default) = def.default.as_f64() else {
+            continue;
+        };
+        let Some(value) = obj.get_mut(def.key) else {
+            continue;
+        };
+        match value.as_f64() {
+            Some(v) if v.is_finite() => {
+                let clamped = def.clamp(v);
+                if clamped != v {
+                    *value = Value::from(clamped);
+                }
+            }
+            _ if value.is_null() => {}
+            _ => *value = Value::from(default),
+        }
+    }
+}
+
+/// Reads `key` from `adjustments` and divides it by the registered scale,
+/// falling back to the default when the key is missing or its section is hidden.
+pub fn scaled_value(adjustments: &Value, key: &str, is_visible: impl Fn(&str) -> bool) -> f32 {
+    let Some(def) = find(key) else {
+        return 0.0;
+    };
+    let default = def.default.as_f64().unwrap_or(0.0);
+    let value = if is_visible(def.section) {
+        adjustments[key].as_f64().map_or(default, |v| def.clamp(v))
+    } else {
+        default
+    };
+    value as f32 / def.scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    #[test]
+    fn keys_are_unique() {
+        let mut seen = HashSet::new();
+        for def in ADJUSTMENTS {
+            assert!(seen.insert(def.key), "duplicate key {}", def.key);
+        }
+    }
+
+    #[test]
+    fn numeric_defaults_lie_within_their_range() {
+        for def in ADJUSTMENTS {
+            if let Some(default) = def.default.as_f64() {
+                assert_eq!(def.clamp(default), default, "{}", def.key);
+            }
+        }
+    }
+
+    #[test]
+    fn panel_groups_match_registered_adjustments() {
+        let listed: HashSet<&str> = PANEL_GROUPS.iter().map(|(group, _)| *group).collect();
+        let used: HashSet<&str> = ADJUSTMENTS.iter().filter_map(|def| def.group).collect();
+        assert_eq!(listed, used);
+    }
+
+    #[test]
+    fn geometry_keys_are_not_copied_and_warp_keys_skip_the_crop() {
+        assert!(
+            ADJUSTMENTS
+                .iter()
+                .all(|def| !(def.geometry && def.copyable))
+        );
+        let warp: Vec<&str> = warp_keys().collect();
+        assert!(warp.contains(&"transformScale"));
+        assert!(warp.contains(&"lensModel"));
+        assert!(!warp.contains(&"crop"));
+        assert!(!warp.contains(&"orientationSteps"));
+    }
+
+    #[test]
+    fn whole_number_defaults_serialize_as_integers() {
+        let defaults = default_adjustments();
+        assert_eq!(defaults["orientationSteps"].as_u64(), Some(0));
+        assert_eq!(defaults["transformScale"].as_f64(), Some(100.0));
+        assert!(!defaults.contains_key("curves"));
+    }
+
+    #[test]
+    fn reset_section_restores_defaults_and_drops_structured_values() {
+        let mut adjustments = json!({
+            "clarity": 40,
+            "vignetteAmount": -30,
+            "grainSize": 80,
+            "hsl": { "reds": { "hue": 10 } },
+            "exposure": 1.5,
+        });
+        reset_section(&mut adjustments, "effects");
+        assert_eq!(adjustments["vignetteAmount"].as_f64(), Some(0.0));
+        assert_eq!(adjustments["grainSize"].as_f64(), Some(25.0));
+        assert_eq!(adjustments["clarity"].as_f64(), Some(40.0));
+
+        reset_section(&mut adjustments, "color");
+        assert!(adjustments.get("hsl").is_none());
+        assert_eq!(adjustments["exposure"].as_f64(), Some(1.5));
+    }
+
+    #[test]
+    fn sanitize_clamps_numbers_and_replaces_wrong_types() {
+        let mut adjustments = json!({
+            "exposure": 12.0,
+            "contrast": "high",
+            "glowRadius": 0.0,
+            "lutPath": "/luts/film.cube",
+            "crop": null,
+        });
+        sanitize_adjustments(&mut adjustments);
+        assert_eq!(adjustments["exposure"].as_f64(), Some(5.0));
+        assert_eq!(adjustments["contrast"].as_f64(), Some(0.0));
+        assert_eq!(adjustments["glowRadius"].as_f64(), Some(1.0));
+        assert_eq!(adjustments["lutPath"], "/luts/film.cube");
+        assert!(adjustments["crop"].is_null());
+    }
+
+    #[test]
+    fn scaled_value_uses_the_default_for_hidden_sections() {
+        let adjustments = json!({ "contrast": 50 });
+        assert_eq!(scaled_value(&adjustments, "contrast", |_| true), 0.5);
+        assert_eq!(scaled_value(&adjustments, "contrast", |_| false), 0.0);
+        assert_eq!(
+            scaled_value(&adjustments, "vignetteMidpoint", |_| true),
+            0.5
+        );
+    }
+}
@@ -163,7 +163,9 @@ fn edt_2d(grid: &[bool], width: usize, height: usize) -> Vec<f32> {
 }
 
 fn get_models_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf> {
-    let models_dir = app_handle.path().app_data_dir()?.join("models");
+    let models_dir = crate::file_management::resolve_app_data_dir(app_handle)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .join("models");
     if !models_dir.exists() {
         fs::create_dir_all(&models_dir)?;
     }
@@ -0,0 +1,371 @@
+use std::sync::LazyLock;
+
+use glam::{Mat3, Vec2, Vec3};
+use image::{DynamicImage, GrayImage, RgbImage};
+use moxcms::{CmsError, ColorProfile, DataColorSpace, Layout, RenderingIntent, TransformOptions};
+use rayon::prelude::*;
+
+use crate::image_processing::{
+    PRIMARIES_REC2020, PRIMARIES_SRGB, WP_D65, primaries_to_xyz_matrix, xy_to_xyz,
+};
+
+const PRIMARIES_DISPLAY_P3: [Vec2; 3] = [
+    Vec2::new(0.680, 0.320),
+    Vec2::new(0.265, 0.690),
+    Vec2::new(0.150, 0.060),
+];
+
+const PCS_D50: Vec3 = Vec3::new(0.9642, 1.0, 0.8249);
+
+const BRADFORD: Mat3 = Mat3::from_cols(
+    Vec3::new(0.8951, -0.7502, 0.0389),
+    Vec3::new(0.2664, 1.7135, -0.0685),
+    Vec3::new(-0.1614, 0.0367, 1.0296),
+);
+
+/// sRGB transfer function as ICC parametric curve type 3: g, a, b, c, d.
+const SRGB_TRC_PARAMS: [f32; 5] = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+
+/// Largest change a color may show after a trip through the output profile and back
+/// before it counts as out of gamut. LUT-based profiles interpolate, so this cannot be 0.
+const ROUND_TRIP_TOLERANCE: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportColorSpace {
+    Srgb,
+    DisplayP3,
+}
+
+impl ExportColorSpace {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "" | "srgb" => Ok(Self::Srgb),
+            "display-p3" | "p3" => Ok(Self::DisplayP3),
+            other => Err(format!("Unsupported export color space: {}", other)),
+        }
+    }
+
+    fn primaries(self) -> &'static [Vec2; 3] {
+        match self {
+            Self::Srgb => &PRIMARIES_SRGB,
+            Self::DisplayP3 => &PRIMARIES_DISPLAY_P3,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Srgb => "sRGB IEC61966-2.1",
+            Self::DisplayP3 => "Display P3",
+        }
+    }
+
+    pub fn output_primaries(self) -> u32 {
+        match self {
+            Self::Srgb => OUTPUT_PRIMARIES_SRGB,
+            Self::DisplayP3 => OUTPUT_PRIMARIES_DISPLAY_P3,
+        }
+    }
+
+    pub fn icc_profile(self) -> Vec<u8> {
+        build_matrix_trc_profile(self.description(), self.primaries(), WP_D65)
+    }
+}
+
+fn bradford_adaptation(source_white: Vec3, dest_white: Vec3) -> Mat3 {
+    let source_cone = BRADFORD * source_white;
+    let dest_cone = BRADFORD * dest_white;
+    BRADFORD.inverse() * Mat3::from_diagonal(dest_cone / source_cone) * BRADFORD
+}
+
+/// RGB to PCS XYZ matrix, chromatically adapted to the D50 profile connection space.
+fn d50_colorants(primaries: &[Vec2; 3], white: Vec2) -> Mat3 {
+    bradford_adaptation(xy_to_xyz(white), PCS_D50) * primaries_to_xyz_matrix(primaries, white)
+}
+
+/// Soft-proofs a preview rendered with Rec. 2020 output primaries through an output
+/// profile, matrix/TRC or LUT-based. Colors go to the profile and back with relative
+/// colorimetric intent, and pixels that do not survive the round trip are out of
+/// gamut. Returns the proof as seen on an sRGB display, the out-of-gamut mask and the
+/// fraction of pixels that clipped.
+pub fn soft_proof(
+    image: &DynamicImage,
+    profile_bytes: &[u8],
+) -> Result<(RgbImage, GrayImage, f32), String> {
+    let cms_error = |e: CmsError| e.to_string();
+    let output = ColorProfile::new_from_slice(profile_bytes)
+        .map_err(|e| format!("Invalid ICC profile: {}", e))?;
+    let (output_layout, output_channels) = match output.color_space {
+        DataColorSpace::Rgb => (Layout::Rgb, 3),
+        DataColorSpace::Cmyk => (Layout::Rgba, 4),
+        DataColorSpace::Gray => (Layout::Gray, 1),
+        other => {
+            return Err(format!(
+                "Profiles with a {:?} color space cannot be soft-proofed",
+                other
+            ));
+        }
+    };
+    let source = ColorProfile::new_from_slice(&build_matrix_trc_profile(
+        "Rec. 2020",
+        &PRIMARIES_REC2020,
+        WP_D65,
+    ))
+    .map_err(cms_error)?;
+    let display = ColorProfile::new_srgb();
+
+    let options = TransformOptions {
+        rendering_intent: RenderingIntent::RelativeColorimetric,
+        prefer_fixed_point: false,
+        ..TransformOptions::default()
+    };
+    let to_output = source
+        .create_transform_f32(Layout::Rgb, &output, output_layout, options)
+        .map_err(cms_error)?;
+    let from_output = output
+        .create_transform_f32(output_layout, &source, Layout::Rgb, options)
+        .map_err(cms_error)?;
+    let to_display = output
+        .create_transform_f32(output_layout, &display, Layout::Rgb, options)
+        .map_err(cms_error)?;
+
+    let source_pixels = image.to_rgb32f();
+    let (width, height) = source_pixels.dimensions();
+    let row_len = width as usize * 3;
+    let mut proofed = RgbImage::new(width, height);
+    let mut mask = GrayImage::new(width, height);
+
+    proofed
+        .par_chunks_mut(row_len.max(3))
+        .zip(mask.par_chunks_mut((width as usize).max(1)))
+        .zip(source_pixels.par_chunks(row_len.max(3)))
+        .try_for_each(|((proofed_row, mask_row), source_row)| {
+            let mut device = vec![0.0f32; width as usize * output_channels];
+            let mut round_trip = vec![0.0f32; row_len];
+            let mut displayed = vec![0.0f32; row_len];
+            to_output.transform(source_row, &mut device)?;
+            from_output.transform(&device, &mut round_trip)?;
+            to_display.transform(&device, &mut displayed)?;
+
+            for (x, mask_px) in mask_row.iter_mut().enumerate() {
+                let pixel = x * 3..x * 3 + 3;
+                let error = source_row[pixel.clone()]
+                    .iter()
+                    .zip(&round_trip[pixel])
+                    .map(|(a, b)| (a - b).abs())
+                    .fold(0.0, f32::max);
+                *mask_px = if error > ROUND_TRIP_TOLERANCE { 255 } else { 0 };
+            }
+            for (out, value) in proofed_row.iter_mut().zip(&displayed) {
+                *out = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+            Ok(())
+        })
+        .map_err(cms_error)?;
+
+    let clipped_count = mask.as_raw().iter().filter(|&&v| v > 0).count();
+    let ratio = clipped_count as f32 / (width as f32 * height as f32).max(1.0);
+    Ok((proofed, mask, ratio))
+}
+
+/// Output primaries the pipeline encodes with, passed to the shader as
+/// `GlobalAdjustments::output_primaries`.
+pub const OUTPUT_PRIMARIES_SRGB: u32 = 0;
+pub const OUTPUT_PRIMARIES_DISPLAY_P3: u32 = 1;
+pub const OUTPUT_PRIMARIES_REC2020: u32 = 2;
+
+static SRGB_TO_DISPLAY_P3: LazyLock<Mat3> = LazyLock::new(|| {
+    primaries_to_xyz_matrix(&PRIMARIES_DISPLAY_P3, WP_D65).inverse()
+        * primaries_to_xyz_matrix(&PRIMARIES_SRGB, WP_D65)
+});
+
+static SRGB_TO_REC2020: LazyLock<Mat3> = LazyLock::new(|| {
+    primaries_to_xyz_matrix(&PRIMARIES_REC2020, WP_D65).inverse()
+        * primaries_to_xyz_matrix(&PRIMARIES_SRGB, WP_D65)
+});
+
+/// Converts linear sRGB to the given linear output primaries. Components outside 0..1
+/// are colors beyond sRGB and land inside the wider gamuts.
+pub fn srgb_linear_to_output_primaries(linear: Vec3, output_primaries: u32) -> Vec3 {
+    match output_primaries {
+        OUTPUT_PRIMARIES_DISPLAY_P3 => *SRGB_TO_DISPLAY_P3 * linear,
+        OUTPUT_PRIMARIES_REC2020 => *SRGB_TO_REC2020 * linear,
+        _ => linear,
+    }
+}
+
+fn push_s15_fixed16(out: &mut Vec<u8>, value: f32) {
+    out.extend_from_slice(&((value as f64 * 65536.0).round() as i32).to_be_bytes());
+}
+
+fn xyz_tag(xyz: Vec3) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    for v in xyz.to_array() {
+        push_s15_fixed16(&mut tag, v);
+    }
+    tag
+}
+
+fn mluc_tag(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut tag = b"mluc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&1u32.to_be_bytes());
+    tag.extend_from_slice(&12u32.to_be_bytes());
+    tag.extend_from_slice(b"enUS");
+    tag.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+    tag.extend_from_slice(&28u32.to_be_bytes());
+    tag.extend_from_slice(&utf16);
+    tag
+}
+
+fn parametric_curve_tag(params: &[f32; 5]) -> Vec<u8> {
+    let mut tag = b"para\0\0\0\0".to_vec();
+    tag.extend_from_slice(&3u16.to_be_bytes());
+    tag.extend_from_slice(&[0, 0]);
+    for &v in params {
+        push_s15_fixed16(&mut tag, v);
+    }
+    tag
+}
+
+fn sf32_tag(matrix: Mat3) -> Vec<u8> {
+    let mut tag = b"sf32\0\0\0\0".to_vec();
+    for v in matrix.transpose().to_cols_array() {
+        push_s15_fixed16(&mut tag, v);
+    }
+    tag
+}
+
+/// Builds a minimal ICC v4 display profile with D50-adapted colorants and the
+/// sRGB tone curve shared by all three channels.
+fn build_matrix_trc_profile(description: &str, primaries: &[Vec2; 3], white: Vec2) -> Vec<u8> {
+    let chad = bradford_adaptation(xy_to_xyz(white), PCS_D50);
+    let colorants = d50_colorants(primaries, white);
+
+    let trc = parametric_curve_tag(&SRGB_TRC_PARAMS);
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", mluc_tag(description)),
+        (b"cprt", mluc_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(PCS_D50)),
+        (b"chad", sf32_tag(chad)),
+        (b"rXYZ", xyz_tag(colorants.x_axis)),
+        (b"gXYZ", xyz_tag(colorants.y_axis)),
+        (b"bXYZ", xyz_tag(colorants.z_axis)),
+        (b"rTRC", trc.clone()),
+        (b"gTRC", trc.clone()),
+        (b"bTRC", trc),
+    ];
+
+    let table_len = 4 + tags.len() * 12;
+    let mut offset = 128 + table_len;
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (signature, tag) in &tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        let padded = tag.len().next_multiple_of(4);
+        data.resize(data.len() + padded - tag.len(), 0);
+        offset += padded;
+    }
+
+    let mut header = vec![0u8; 128];
+    header[0..4].copy_from_slice(&(offset as u32).to_be_bytes());
+    header[8..12].copy_from_slice(&0x0430_0000u32.to_be_bytes());
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    for (i, v) in [2024u16, 1, 1, 0, 0, 0].into_iter().enumerate() {
+        header[24 + i * 2..26 + i * 2].copy_from_slice(&v.to_be_bytes());
+    }
+    header[36..40].copy_from_slice(b"acsp");
+    let mut illuminant = Vec::new();
+    for v in PCS_D50.to_array() {
+        push_s15_fixed16(&mut illuminant, v);
+    }
+    header[68..80].copy_from_slice(&illuminant);
+
+    let mut profile = header;
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn generated_profiles_parse_back() {
+        for color_space in [ExportColorSpace::Srgb, ExportColorSpace::DisplayP3] {
+            let bytes = color_space.icc_profile();
+            assert_eq!(read_u32(&bytes, 0) as usize, bytes.len());
+            for i in 0..read_u32(&bytes, 128) as usize {
+                let offset = read_u32(&bytes, 132 + i * 12 + 4) as usize;
+                let size = read_u32(&bytes, 132 + i * 12 + 8) as usize;
+                assert_eq!(offset % 4, 0);
+                assert!(offset + size <= bytes.len());
+            }
+
+            let profile = ColorProfile::new_from_slice(&bytes).unwrap();
+            assert_eq!(profile.color_space, DataColorSpace::Rgb);
+            let expected = d50_colorants(color_space.primaries(), WP_D65);
+            for (colorant, expected) in [
+                (profile.red_colorant, expected.x_axis),
+                (profile.green_colorant, expected.y_axis),
+                (profile.blue_colorant, expected.z_axis),
+            ] {
+                let actual = Vec3::new(colorant.x as f32, colorant.y as f32, colorant.z as f32);
+                assert!(
+                    (actual - expected).abs().max_element() < 1e-4,
+                    "{actual:?} != {expected:?}"
+                );
+            }
+            assert!(profile.red_trc.is_some() && profile.blue_trc.is_some());
+        }
+    }
+
+    #[test]
+    fn soft_proof_flags_colors_outside_the_output_gamut() {
+        let srgb_profile = ExportColorSpace::Srgb.icc_profile();
+        let rec2020 = |linear: Vec3| {
+            let encoded = crate::image_processing::cpu_linear_to_srgb(
+                srgb_linear_to_output_primaries(linear, OUTPUT_PRIMARIES_REC2020),
+            );
+            image::Rgb(encoded.to_array())
+        };
+        let mut preview = image::Rgb32FImage::new(3, 1);
+        preview.put_pixel(0, 0, rec2020(Vec3::splat(0.2)));
+        preview.put_pixel(1, 0, rec2020(Vec3::new(0.8, 0.1, 0.05)));
+        // Rec. 2020 green lies well outside sRGB.
+        preview.put_pixel(2, 0, image::Rgb([0.0, 1.0, 0.0]));
+
+        let (proofed, mask, ratio) =
+            soft_proof(&DynamicImage::ImageRgb32F(preview), &srgb_profile).unwrap();
+        assert_eq!(mask.as_raw(), &[0, 0, 255]);
+        assert!((ratio - 1.0 / 3.0).abs() < 1e-6);
+        let gray = proofed.get_pixel(0, 0).0;
+        assert!(gray.iter().all(|&v| v.abs_diff(gray[0]) <= 1), "{gray:?}");
+    }
+
+    #[test]
+    fn display_p3_keeps_colors_beyond_srgb() {
+        let to_p3 = |linear| srgb_linear_to_output_primaries(linear, OUTPUT_PRIMARIES_DISPLAY_P3);
+        let gray = to_p3(Vec3::splat(0.5));
+        assert!(
+            (gray - Vec3::splat(0.5)).abs().max_element() < 1e-4,
+            "{gray:?}"
+        );
+
+        let red = to_p3(Vec3::new(1.0, 0.0, 0.0));
+        assert!(red.x < 1.0 && red.y > 0.0, "{red:?}");
+
+        let beyond_srgb = to_p3(Vec3::new(1.0, -0.03, 0.0));
+        assert!(beyond_srgb.min_element() >= 0.0, "{beyond_srgb:?}");
+        assert!(beyond_srgb.y < red.y, "{beyond_srgb:?} vs {red:?}");
+    }
+}
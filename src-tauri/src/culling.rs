@@ -1,14 +1,15 @@
 use crate::file_management::load_settings;
-use image::{GenericImageView, GrayImage, imageops};
+use image::{DynamicImage, GenericImageView, GrayImage, imageops};
 use image_hasher::{HashAlg, HasherConfig};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
-use crate::image_loader;
+use crate::gpu_processing::get_or_init_gpu_context;
+use crate::{AppState, file_management, image_loader};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -320,3 +321,160 @@ pub async fn cull_images(
     let _ = app_handle.emit("culling-complete", &suggestions);
     Ok(suggestions)
 }
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SharpnessScore {
+    pub path: String,
+    pub score: f64,
+}
+
+/// Long edge the thumbnail is resampled to before measuring focus, so scores do not
+/// depend on the source or thumbnail resolution.
+const SHARPNESS_ANALYSIS_DIM: u32 = 512;
+
+/// Variance of the Laplacian on the thumbnail's luma, mapped onto `0.0..=1.0`.
+pub fn compute_sharpness_score(path: &str, app_handle: &AppHandle) -> Result<f64, String> {
+    let state = app_handle.state::<AppState>();
+    let gpu_context = get_or_init_gpu_context(&state, app_handle).ok();
+    let thumbnail = file_management::get_cached_or_generate_thumbnail_image(
+        path,
+        app_handle,
+        gpu_context.as_ref(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let (width, height) = thumbnail.dimensions();
+    let scale = SHARPNESS_ANALYSIS_DIM as f32 / width.max(height).max(1) as f32;
+    let target_w = ((width as f32 * scale).round() as u32).max(3);
+    let target_h = ((height as f32 * scale).round() as u32).max(3);
+    let gray = imageops::resize(
+        &thumbnail.to_luma8(),
+        target_w,
+        target_h,
+        imageops::FilterType::Triangle,
+    );
+
+    let variance = calculate_laplacian_variance(&gray);
+    Ok(((variance + 1.0).log10() / 3.5).min(1.0))
+}
+
+#[tauri::command]
+pub async fn compute_sharpness_scores(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<SharpnessScore>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = paths.len();
+        let completed_count = AtomicUsize::new(0);
+
+        paths
+            .par_iter()
+            .filter_map(|path| {
+                let result = compute_sharpness_score(path, &app_handle);
+                let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
+                let _ = app_handle.emit(
+                    "sharpness-progress",
+                    CullingProgress {
+                        current: completed,
+                        total,
+                        stage: "Measuring sharpness...".to_string(),
+                    },
+                );
+
+                match result {
+                    Ok(score) => Some(SharpnessScore {
+                        path: path.clone(),
+                        score,
+                    }),
+                    Err(e) => {
+                        log::warn!("Failed to score sharpness of {}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 64-bit DCT perceptual hash of a grayscale downscale of `image`.
+fn phash_image(image: &DynamicImage) -> u64 {
+    let hasher = HasherConfig::new()
+        .hash_alg(HashAlg::Mean)
+        .hash_size(8, 8)
+        .preproc_dct()
+        .to_hasher();
+    let hash = hasher.hash_image(&image.grayscale());
+    hash.as_bytes()
+        .iter()
+        .take(8)
+        .fold(0u64, |acc, &byte| (acc << 8) | byte as u64)
+}
+
+/// Hashes the cached (or freshly generated) thumbnail so RAWs are not decoded again.
+pub fn compute_phash(path: &str, app_handle: &AppHandle) -> Result<u64, String> {
+    let state = app_handle.state::<AppState>();
+    let gpu_context = get_or_init_gpu_context(&state, app_handle).ok();
+    let thumbnail = file_management::get_cached_or_generate_thumbnail_image(
+        path,
+        app_handle,
+        gpu_context.as_ref(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(phash_image(&thumbnail))
+}
+
+#[tauri::command]
+pub async fn find_similar_images(
+    paths: Vec<String>,
+    threshold: u32,
+    app_handle: AppHandle,
+) -> Result<Vec<Vec<String>>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let hashes: Vec<(String, u64)> = paths
+            .par_iter()
+            .filter_map(|path| match compute_phash(path, &app_handle) {
+                Ok(hash) => Some((path.clone(), hash)),
+                Err(e) => {
+                    log::warn!("Failed to hash image {}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+
+        let mut processed = vec![false; hashes.len()];
+        let mut groups = Vec::new();
+
+        for i in 0..hashes.len() {
+            if processed[i] {
+                continue;
+            }
+            processed[i] = true;
+
+            let mut group = vec![i];
+            let mut queue = VecDeque::from([i]);
+            while let Some(current) = queue.pop_front() {
+                for j in 0..hashes.len() {
+                    if processed[j] {
+                        continue;
+                    }
+                    if (hashes[current].1 ^ hashes[j].1).count_ones() <= threshold {
+                        processed[j] = true;
+                        group.push(j);
+                        queue.push_back(j);
+                    }
+                }
+            }
+
+            if group.len() > 1 {
+                groups.push(group.into_iter().map(|idx| hashes[idx].0.clone()).collect());
+            }
+        }
+
+        groups
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -489,7 +489,195 @@ pub fn extract_metadata(file_bytes: &[u8]) -> Option<HashMap<String, String>> {
     Some(map)
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GpsCoordinates {
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: Option<f64>,
+}
+
+fn dms_to_decimal(dms: [(u32, u32); 3], negative: bool) -> Option<f64> {
+    let mut decimal = 0.0;
+    for ((num, denom), divisor) in dms.into_iter().zip([1.0, 60.0, 3600.0]) {
+        if denom == 0 {
+            return None;
+        }
+        decimal += num as f64 / denom as f64 / divisor;
+    }
+    Some(if negative { -decimal } else { decimal })
+}
+
+fn is_negative_ref(reference: &str, negative: char) -> bool {
+    reference.trim().to_ascii_uppercase().starts_with(negative)
+}
+
+fn read_gps_from_exif(exif_obj: &Exif) -> Option<GpsCoordinates> {
+    let dms = |tag: exif::Tag| -> Option<[(u32, u32); 3]> {
+        match &exif_obj.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(v) if v.len() >= 3 => Some([
+                (v[0].num, v[0].denom),
+                (v[1].num, v[1].denom),
+                (v[2].num, v[2].denom),
+            ]),
+            _ => None,
+        }
+    };
+    let reference = |tag: exif::Tag| -> String {
+        match exif_obj.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+            Some(Value::Ascii(vec)) => vec
+                .first()
+                .map(|v| String::from_utf8_lossy(v).to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    };
+
+    let lat = dms_to_decimal(
+        dms(exif::Tag::GPSLatitude)?,
+        is_negative_ref(&reference(exif::Tag::GPSLatitudeRef), 'S'),
+    )?;
+    let lon = dms_to_decimal(
+        dms(exif::Tag::GPSLongitude)?,
+        is_negative_ref(&reference(exif::Tag::GPSLongitudeRef), 'W'),
+    )?;
+
+    let below_sea_level = exif_obj
+        .get_field(exif::Tag::GPSAltitudeRef, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        == Some(1);
+    let altitude = match exif_obj
+        .get_field(exif::Tag::GPSAltitude, In::PRIMARY)
+        .map(|f| &f.value)
+    {
+        Some(Value::Rational(v)) if !v.is_empty() && v[0].denom != 0 => {
+            let alt = v[0].num as f64 / v[0].denom as f64;
+            Some(if below_sea_level { -alt } else { alt })
+        }
+        _ => None,
+    };
+
+    Some(GpsCoordinates { lat, lon, altitude })
+}
+
+fn read_gps_from_raw(file_bytes: &[u8]) -> Option<GpsCoordinates> {
+    let gps = read_raw_metadata(file_bytes)?.exif.gps?;
+    let to_pairs = |c: [rawler::formats::tiff::Rational; 3]| {
+        [(c[0].n, c[0].d), (c[1].n, c[1].d), (c[2].n, c[2].d)]
+    };
+
+    let lat = dms_to_decimal(
+        to_pairs(gps.gps_latitude?),
+        is_negative_ref(gps.gps_latitude_ref.as_deref().unwrap_or(""), 'S'),
+    )?;
+    let lon = dms_to_decimal(
+        to_pairs(gps.gps_longitude?),
+        is_negative_ref(gps.gps_longitude_ref.as_deref().unwrap_or(""), 'W'),
+    )?;
+    let altitude = gps.gps_altitude.filter(|a| a.d != 0).map(|a| {
+        let alt = a.n as f64 / a.d as f64;
+        if gps.gps_altitude_ref == Some(1) {
+            -alt
+        } else {
+            alt
+        }
+    });
+
+    Some(GpsCoordinates { lat, lon, altitude })
+}
+
+fn decimal_to_dms(value: f64) -> Vec<uR64> {
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes_full = (value - degrees) * 60.0;
+    let minutes = minutes_full.trunc();
+    let seconds = (minutes_full - minutes) * 60.0;
+    vec![
+        uR64 {
+            nominator: degrees as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: minutes as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: (seconds * 10_000.0).round() as u32,
+            denominator: 10_000,
+        },
+    ]
+}
+
+fn set_gps_tags(metadata: &mut Metadata, gps: &GpsCoordinates) {
+    let lat_ref = if gps.lat < 0.0 { "S" } else { "N" };
+    let lon_ref = if gps.lon < 0.0 { "W" } else { "E" };
+    metadata.set_tag(ExifTag::GPSLatitude(decimal_to_dms(gps.lat)));
+    metadata.set_tag(ExifTag::GPSLatitudeRef(lat_ref.to_string()));
+    metadata.set_tag(ExifTag::GPSLongitude(decimal_to_dms(gps.lon)));
+    metadata.set_tag(ExifTag::GPSLongitudeRef(lon_ref.to_string()));
+    if let Some(alt) = gps.altitude {
+        metadata.set_tag(ExifTag::GPSAltitude(vec![uR64 {
+            nominator: (alt.abs() * 100.0).round() as u32,
+            denominator: 100,
+        }]));
+        metadata.set_tag(ExifTag::GPSAltitudeRef(vec![u8::from(alt < 0.0)]));
+    }
+}
+
+/// Whether `set_gps_in_place` can safely rewrite the EXIF block of `path`.
+pub fn supports_in_place_gps(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    matches!(ext.as_str(), "jpg" | "jpeg" | "tif" | "tiff")
+}
+
+/// Replaces the GPS tags of a JPEG or TIFF in place; existing tags are overwritten
+/// rather than appended.
+pub fn set_gps_in_place(path: &Path, gps: &GpsCoordinates) -> Result<(), String> {
+    let mut metadata = Metadata::new_from_path(path).map_err(|e| e.to_string())?;
+    set_gps_tags(&mut metadata, gps);
+    metadata.write_to_file(path).map_err(|e| e.to_string())
+}
+
+/// Formats a signed decimal coordinate the way XMP's `exif:GPSLatitude` expects,
+/// e.g. `47,22.5120N`.
+pub fn format_xmp_gps_coord(value: f64, positive_ref: char, negative_ref: char) -> String {
+    let abs = value.abs();
+    let degrees = abs.trunc();
+    let minutes = (abs - degrees) * 60.0;
+    let hemisphere = if value < 0.0 {
+        negative_ref
+    } else {
+        positive_ref
+    };
+    format!("{},{:.6}{}", degrees as u32, minutes, hemisphere)
+}
+
+/// Signed decimal GPS position, or `None` when the file carries no usable GPS IFD.
+pub fn read_gps(path: &str, file_bytes: &[u8]) -> Option<GpsCoordinates> {
+    if let Some(coords) = read_exif(file_bytes).and_then(|exif_obj| read_gps_from_exif(&exif_obj)) {
+        return Some(coords);
+    }
+    if is_raw_file(path) {
+        return read_gps_from_raw(file_bytes);
+    }
+    None
+}
+
 pub fn get_creation_date_from_path(path: &Path) -> DateTime<Utc> {
+    read_capture_date(path).unwrap_or_else(|| {
+        fs::metadata(path)
+            .ok()
+            .and_then(|m| m.created().ok())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(Utc::now)
+    })
+}
+
+/// `DateTimeOriginal` from the file's EXIF, or `None` when it carries no capture date.
+pub fn read_capture_date(path: &Path) -> Option<DateTime<Utc>> {
     if let Ok(file) = std::fs::File::open(path) {
         let mut bufreader = BufReader::new(&file);
         let exifreader = exif::Reader::new();
@@ -500,10 +688,10 @@ pub fn get_creation_date_from_path(path: &Path) -> DateTime<Utc> {
             let dt_str = field.display_value().to_string();
             let clean_str = dt_str.replace("\"", "").trim().to_string();
             if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(&clean_str, "%Y-%m-%d %H:%M:%S") {
-                return DateTime::from_naive_utc_and_offset(dt, Utc);
+                return Some(DateTime::from_naive_utc_and_offset(dt, Utc));
             }
             if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(&clean_str, "%Y:%m:%d %H:%M:%S") {
-                return DateTime::from_naive_utc_and_offset(dt, Utc);
+                return Some(DateTime::from_naive_utc_and_offset(dt, Utc));
             }
         }
     }
@@ -516,15 +704,297 @@ pub fn get_creation_date_from_path(path: &Path) -> DateTime<Utc> {
             && let Some(date_str) = metadata.exif.date_time_original
             && let Ok(dt) = chrono::NaiveDateTime::parse_from_str(&date_str, "%Y:%m:%d %H:%M:%S")
         {
-            return DateTime::from_naive_utc_and_offset(dt, Utc);
+            return Some(DateTime::from_naive_utc_and_offset(dt, Utc));
         }
     }
 
-    fs::metadata(path)
-        .ok()
-        .and_then(|m| m.created().ok())
-        .map(DateTime::<Utc>::from)
-        .unwrap_or_else(Utc::now)
+    None
+}
+
+fn collect_container_metadata(
+    original_path: &Path,
+    strip_gps: bool,
+    metadata: &mut Metadata,
+) -> bool {
+    let Ok(file) = std::fs::File::open(original_path) else {
+        return false;
+    };
+    let mut bufreader = std::io::BufReader::new(&file);
+    let exifreader = exif::Reader::new();
+
+    let Ok(exif_obj) = exifreader.read_from_container(&mut bufreader) else {
+        return false;
+    };
+
+    let get_string_val = |field: &exif::Field| -> String {
+        match &field.value {
+            exif::Value::Ascii(vec) => vec
+                .iter()
+                .map(|v| {
+                    String::from_utf8_lossy(v)
+                        .trim_matches(char::from(0))
+                        .to_string()
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+            _ => field
+                .display_value()
+                .to_string()
+                .replace("\"", "")
+                .trim()
+                .to_string(),
+        }
+    };
+
+    if let Some(f) = exif_obj.get_field(exif::Tag::Make, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::Make(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::Model, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::Model(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::LensMake, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::LensMake(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::LensModel, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::LensModel(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::Artist, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::Artist(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::Copyright, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::Copyright(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::DateTimeOriginal(get_string_val(f)));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::DateTime, exif::In::PRIMARY) {
+        metadata.set_tag(ExifTag::CreateDate(get_string_val(f)));
+    }
+
+    if let Some(f) = exif_obj.get_field(exif::Tag::FNumber, exif::In::PRIMARY)
+        && let exif::Value::Rational(v) = &f.value
+        && !v.is_empty()
+    {
+        metadata.set_tag(ExifTag::FNumber(vec![to_ur64(&v[0])]));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::ExposureTime, exif::In::PRIMARY)
+        && let exif::Value::Rational(v) = &f.value
+        && !v.is_empty()
+    {
+        metadata.set_tag(ExifTag::ExposureTime(vec![to_ur64(&v[0])]));
+    }
+    if let Some(f) = exif_obj.get_field(exif::Tag::FocalLength, exif::In::PRIMARY)
+        && let exif::Value::Rational(v) = &f.value
+        && !v.is_empty()
+    {
+        metadata.set_tag(ExifTag::FocalLength(vec![to_ur64(&v[0])]));
+    }
+
+    if let Some(f) = exif_obj.get_field(exif::Tag::ExposureBiasValue, exif::In::PRIMARY) {
+        match &f.value {
+            exif::Value::SRational(v) if !v.is_empty() => {
+                metadata.set_tag(ExifTag::ExposureCompensation(vec![to_ir64(&v[0])]));
+            }
+            exif::Value::Rational(v) if !v.is_empty() => {
+                metadata.set_tag(ExifTag::ExposureCompensation(vec![iR64 {
+                    nominator: v[0].num as i32,
+                    denominator: v[0].denom as i32,
+                }]));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(f) = exif_obj.get_field(exif::Tag::PhotographicSensitivity, exif::In::PRIMARY) {
+        if let Some(val) = f.value.get_uint(0) {
+            metadata.set_tag(ExifTag::ISO(vec![val as u16]));
+        }
+    } else if let Some(f) = exif_obj.get_field(exif::Tag::ISOSpeed, exif::In::PRIMARY)
+        && let Some(val) = f.value.get_uint(0)
+    {
+        metadata.set_tag(ExifTag::ISO(vec![val as u16]));
+    }
+
+    if let Some(f) = exif_obj.get_field(exif::Tag::FocalLengthIn35mmFilm, exif::In::PRIMARY)
+        && let Some(val) = f.value.get_uint(0)
+    {
+        metadata.set_tag(ExifTag::FocalLengthIn35mmFormat(vec![val as u16]));
+    }
+
+    if !strip_gps {
+        if let Some(f) = exif_obj.get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY)
+            && let exif::Value::Rational(v) = &f.value
+            && v.len() >= 3
+        {
+            metadata.set_tag(ExifTag::GPSLatitude(vec![
+                to_ur64(&v[0]),
+                to_ur64(&v[1]),
+                to_ur64(&v[2]),
+            ]));
+        }
+        if let Some(f) = exif_obj.get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY) {
+            metadata.set_tag(ExifTag::GPSLatitudeRef(get_string_val(f)));
+        }
+        if let Some(f) = exif_obj.get_field(exif::Tag::GPSLongitude, exif::In::PRIMARY)
+            && let exif::Value::Rational(v) = &f.value
+            && v.len() >= 3
+        {
+            metadata.set_tag(ExifTag::GPSLongitude(vec![
+                to_ur64(&v[0]),
+                to_ur64(&v[1]),
+                to_ur64(&v[2]),
+            ]));
+        }
+        if let Some(f) = exif_obj.get_field(exif::Tag::GPSLongitudeRef, exif::In::PRIMARY) {
+            metadata.set_tag(ExifTag::GPSLongitudeRef(get_string_val(f)));
+        }
+        if let Some(f) = exif_obj.get_field(exif::Tag::GPSAltitude, exif::In::PRIMARY)
+            && let exif::Value::Rational(v) = &f.value
+            && !v.is_empty()
+        {
+            metadata.set_tag(ExifTag::GPSAltitude(vec![to_ur64(&v[0])]));
+        }
+    }
+
+    true
+}
+
+fn collect_raw_metadata(original_path: &Path, strip_gps: bool, metadata: &mut Metadata) -> bool {
+    let loader = rawler::RawLoader::new();
+    let Ok(raw_source) = rawler::rawsource::RawSource::new(original_path) else {
+        return false;
+    };
+    let Ok(decoder) = loader.get_decoder(&raw_source) else {
+        return false;
+    };
+    let Ok(meta) = decoder.raw_metadata(&raw_source, &Default::default()) else {
+        return false;
+    };
+
+    if !meta.make.is_empty() {
+        metadata.set_tag(ExifTag::Make(meta.make.clone()));
+    }
+    if !meta.model.is_empty() {
+        metadata.set_tag(ExifTag::Model(meta.model.clone()));
+    }
+
+    let exif = meta.exif;
+
+    if let Some(artist) = exif.artist {
+        metadata.set_tag(ExifTag::Artist(artist));
+    }
+    if let Some(copyright) = exif.copyright {
+        metadata.set_tag(ExifTag::Copyright(copyright));
+    }
+    if let Some(dt) = exif.date_time_original {
+        metadata.set_tag(ExifTag::DateTimeOriginal(dt));
+    }
+    if let Some(dt) = exif.create_date {
+        metadata.set_tag(ExifTag::CreateDate(dt));
+    }
+    if let Some(lens_make) = exif.lens_make {
+        metadata.set_tag(ExifTag::LensMake(lens_make));
+    }
+    if let Some(lens_model) = exif.lens_model {
+        metadata.set_tag(ExifTag::LensModel(lens_model));
+    }
+
+    if let Some(f) = exif.fnumber {
+        metadata.set_tag(ExifTag::FNumber(vec![uR64 {
+            nominator: f.n,
+            denominator: f.d,
+        }]));
+    }
+    if let Some(t) = exif.exposure_time {
+        metadata.set_tag(ExifTag::ExposureTime(vec![uR64 {
+            nominator: t.n,
+            denominator: t.d,
+        }]));
+    }
+    if let Some(fl) = exif.focal_length {
+        metadata.set_tag(ExifTag::FocalLength(vec![uR64 {
+            nominator: fl.n,
+            denominator: fl.d,
+        }]));
+    }
+
+    if let Some(iso) = exif.iso_speed {
+        metadata.set_tag(ExifTag::ISO(vec![iso as u16]));
+    } else if let Some(iso) = exif.iso_speed_ratings {
+        metadata.set_tag(ExifTag::ISO(vec![iso]));
+    }
+
+    if let Some(ev) = exif.exposure_bias {
+        metadata.set_tag(ExifTag::ExposureCompensation(vec![iR64 {
+            nominator: ev.n,
+            denominator: ev.d,
+        }]));
+    }
+
+    if let Some(flash) = exif.flash {
+        metadata.set_tag(ExifTag::Flash(vec![flash]));
+    }
+    if let Some(metering) = exif.metering_mode {
+        metadata.set_tag(ExifTag::MeteringMode(vec![metering]));
+    }
+    if let Some(wb) = exif.white_balance {
+        metadata.set_tag(ExifTag::WhiteBalance(vec![wb]));
+    }
+    if let Some(prog) = exif.exposure_program {
+        metadata.set_tag(ExifTag::ExposureProgram(vec![prog]));
+    }
+
+    if !strip_gps && let Some(gps) = exif.gps {
+        if let Some(lat) = gps.gps_latitude {
+            metadata.set_tag(ExifTag::GPSLatitude(vec![
+                uR64 {
+                    nominator: lat[0].n,
+                    denominator: lat[0].d,
+                },
+                uR64 {
+                    nominator: lat[1].n,
+                    denominator: lat[1].d,
+                },
+                uR64 {
+                    nominator: lat[2].n,
+                    denominator: lat[2].d,
+                },
+            ]));
+        }
+        if let Some(lat_ref) = gps.gps_latitude_ref {
+            metadata.set_tag(ExifTag::GPSLatitudeRef(lat_ref));
+        }
+        if let Some(lon) = gps.gps_longitude {
+            metadata.set_tag(ExifTag::GPSLongitude(vec![
+                uR64 {
+                    nominator: lon[0].n,
+                    denominator: lon[0].d,
+                },
+                uR64 {
+                    nominator: lon[1].n,
+                    denominator: lon[1].d,
+                },
+                uR64 {
+                    nominator: lon[2].n,
+                    denominator: lon[2].d,
+                },
+            ]));
+        }
+        if let Some(lon_ref) = gps.gps_longitude_ref {
+            metadata.set_tag(ExifTag::GPSLongitudeRef(lon_ref));
+        }
+        if let Some(alt) = gps.gps_altitude {
+            metadata.set_tag(ExifTag::GPSAltitude(vec![uR64 {
+                nominator: alt.n,
+                denominator: alt.d,
+            }]));
+        }
+        if let Some(alt_ref) = gps.gps_altitude_ref {
+            metadata.set_tag(ExifTag::GPSAltitudeRef(vec![alt_ref]));
+        }
+    }
+
+    true
 }
 
 pub fn write_image_with_metadata(
@@ -535,7 +1005,7 @@ pub fn write_image_with_metadata(
     strip_gps: bool,
 ) -> Result<(), String> {
     // FIXME: temporary solution until I find a way to write metadata to TIFF
-    if !keep_metadata || output_format.to_lowercase() == "tiff" {
+    if !keep_metadata || matches!(output_format.to_lowercase().as_str(), "tiff" | "tif") {
         return Ok(());
     }
 
@@ -560,292 +1030,151 @@ pub fn write_image_with_metadata(
             as_zTXt_chunk: true,
         },
         "tiff" => FileExtension::TIFF,
+        "webp" => FileExtension::WEBP,
+        // AVIF uses the same ISOBMFF container, with EXIF in an `Exif` item.
+        "avif" => FileExtension::HEIF,
         _ => return Ok(()),
     };
 
     let mut metadata = Metadata::new();
-    let mut source_read_success = false;
-
-    if let Ok(file) = std::fs::File::open(original_path) {
-        let mut bufreader = std::io::BufReader::new(&file);
-        let exifreader = exif::Reader::new();
-
-        if let Ok(exif_obj) = exifreader.read_from_container(&mut bufreader) {
-            source_read_success = true;
-
-            let get_string_val = |field: &exif::Field| -> String {
-                match &field.value {
-                    exif::Value::Ascii(vec) => vec
-                        .iter()
-                        .map(|v| {
-                            String::from_utf8_lossy(v)
-                                .trim_matches(char::from(0))
-                                .to_string()
-                        })
-                        .collect::<Vec<String>>()
-                        .join(" "),
-                    _ => field
-                        .display_value()
-                        .to_string()
-                        .replace("\"", "")
-                        .trim()
-                        .to_string(),
-                }
-            };
-
-            if let Some(f) = exif_obj.get_field(exif::Tag::Make, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::Make(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::Model, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::Model(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::LensMake, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::LensMake(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::LensModel, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::LensModel(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::Artist, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::Artist(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::Copyright, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::Copyright(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::DateTimeOriginal(get_string_val(f)));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::DateTime, exif::In::PRIMARY) {
-                metadata.set_tag(ExifTag::CreateDate(get_string_val(f)));
-            }
-
-            if let Some(f) = exif_obj.get_field(exif::Tag::FNumber, exif::In::PRIMARY)
-                && let exif::Value::Rational(v) = &f.value
-                && !v.is_empty()
-            {
-                metadata.set_tag(ExifTag::FNumber(vec![to_ur64(&v[0])]));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::ExposureTime, exif::In::PRIMARY)
-                && let exif::Value::Rational(v) = &f.value
-                && !v.is_empty()
-            {
-                metadata.set_tag(ExifTag::ExposureTime(vec![to_ur64(&v[0])]));
-            }
-            if let Some(f) = exif_obj.get_field(exif::Tag::FocalLength, exif::In::PRIMARY)
-                && let exif::Value::Rational(v) = &f.value
-                && !v.is_empty()
-            {
-                metadata.set_tag(ExifTag::FocalLength(vec![to_ur64(&v[0])]));
-            }
+    let raw_read_success = is_raw_file(original_path_str)
+        && collect_raw_metadata(original_path, strip_gps, &mut metadata);
+    if !raw_read_success {
+        collect_container_metadata(original_path, strip_gps, &mut metadata);
+    }
 
-            if let Some(f) = exif_obj.get_field(exif::Tag::ExposureBiasValue, exif::In::PRIMARY) {
-                match &f.value {
-                    exif::Value::SRational(v) if !v.is_empty() => {
-                        metadata.set_tag(ExifTag::ExposureCompensation(vec![to_ir64(&v[0])]));
-                    }
-                    exif::Value::Rational(v) if !v.is_empty() => {
-                        metadata.set_tag(ExifTag::ExposureCompensation(vec![iR64 {
-                            nominator: v[0].num as i32,
-                            denominator: v[0].denom as i32,
-                        }]));
-                    }
-                    _ => {}
-                }
-            }
+    metadata.set_tag(ExifTag::Software("RapidRAW".to_string()));
+    metadata.set_tag(ExifTag::Orientation(vec![1u16]));
+    metadata.set_tag(ExifTag::ColorSpace(vec![1u16]));
 
-            if let Some(f) =
-                exif_obj.get_field(exif::Tag::PhotographicSensitivity, exif::In::PRIMARY)
-            {
-                if let Some(val) = f.value.get_uint(0) {
-                    metadata.set_tag(ExifTag::ISO(vec![val as u16]));
-                }
-            } else if let Some(f) = exif_obj.get_field(exif::Tag::ISOSpeed, exif::In::PRIMARY)
-                && let Some(val) = f.value.get_uint(0)
-            {
-                metadata.set_tag(ExifTag::ISO(vec![val as u16]));
-            }
+    if let Err(e) = metadata.write_to_vec(image_bytes, file_type) {
+        log::warn!("Failed to write metadata: {}", e);
+    }
 
-            if let Some(f) = exif_obj.get_field(exif::Tag::FocalLengthIn35mmFilm, exif::In::PRIMARY)
-                && let Some(val) = f.value.get_uint(0)
-            {
-                metadata.set_tag(ExifTag::FocalLengthIn35mmFormat(vec![val as u16]));
-            }
+    Ok(())
+}
 
-            if !strip_gps {
-                if let Some(f) = exif_obj.get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY)
-                    && let exif::Value::Rational(v) = &f.value
-                    && v.len() >= 3
-                {
-                    metadata.set_tag(ExifTag::GPSLatitude(vec![
-                        to_ur64(&v[0]),
-                        to_ur64(&v[1]),
-                        to_ur64(&v[2]),
-                    ]));
-                }
-                if let Some(f) = exif_obj.get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY) {
-                    metadata.set_tag(ExifTag::GPSLatitudeRef(get_string_val(f)));
-                }
-                if let Some(f) = exif_obj.get_field(exif::Tag::GPSLongitude, exif::In::PRIMARY)
-                    && let exif::Value::Rational(v) = &f.value
-                    && v.len() >= 3
-                {
-                    metadata.set_tag(ExifTag::GPSLongitude(vec![
-                        to_ur64(&v[0]),
-                        to_ur64(&v[1]),
-                        to_ur64(&v[2]),
-                    ]));
-                }
-                if let Some(f) = exif_obj.get_field(exif::Tag::GPSLongitudeRef, exif::In::PRIMARY) {
-                    metadata.set_tag(ExifTag::GPSLongitudeRef(get_string_val(f)));
-                }
-                if let Some(f) = exif_obj.get_field(exif::Tag::GPSAltitude, exif::In::PRIMARY)
-                    && let exif::Value::Rational(v) = &f.value
-                    && !v.is_empty()
-                {
-                    metadata.set_tag(ExifTag::GPSAltitude(vec![to_ur64(&v[0])]));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, RgbImage, codecs::jpeg::JpegEncoder};
+
+    type IfdEntry = (u16, u16, u32, Vec<u8>);
+
+    fn ifd(offset: u32, entries: &[IfdEntry]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut data = Vec::new();
+        let data_start = offset + 2 + 12 * entries.len() as u32 + 4;
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, kind, count, value) in entries {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.clone();
+                inline.resize(4, 0);
+                out.extend_from_slice(&inline);
+            } else {
+                out.extend_from_slice(&(data_start + data.len() as u32).to_le_bytes());
+                data.extend_from_slice(value);
+                if data.len() % 2 == 1 {
+                    data.push(0);
                 }
             }
         }
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend(data);
+        out
     }
 
-    if !source_read_success && is_raw_file(original_path_str) {
-        let loader = rawler::RawLoader::new();
-        if let Ok(raw_source) =
-            rawler::rawsource::RawSource::new(std::path::Path::new(original_path_str))
-            && let Ok(decoder) = loader.get_decoder(&raw_source)
-            && let Ok(meta) = decoder.raw_metadata(&raw_source, &Default::default())
-        {
-            if !meta.make.is_empty() {
-                metadata.set_tag(ExifTag::Make(meta.make.clone()));
-            }
-            if !meta.model.is_empty() {
-                metadata.set_tag(ExifTag::Model(meta.model.clone()));
-            }
-
-            let exif = meta.exif;
-
-            if let Some(artist) = exif.artist {
-                metadata.set_tag(ExifTag::Artist(artist));
-            }
-            if let Some(copyright) = exif.copyright {
-                metadata.set_tag(ExifTag::Copyright(copyright));
-            }
-            if let Some(dt) = exif.date_time_original {
-                metadata.set_tag(ExifTag::DateTimeOriginal(dt));
-            }
-            if let Some(dt) = exif.create_date {
-                metadata.set_tag(ExifTag::CreateDate(dt));
-            }
-            if let Some(lens_make) = exif.lens_make {
-                metadata.set_tag(ExifTag::LensMake(lens_make));
-            }
-            if let Some(lens_model) = exif.lens_model {
-                metadata.set_tag(ExifTag::LensModel(lens_model));
-            }
-
-            if let Some(f) = exif.fnumber {
-                metadata.set_tag(ExifTag::FNumber(vec![uR64 {
-                    nominator: f.n,
-                    denominator: f.d,
-                }]));
-            }
-            if let Some(t) = exif.exposure_time {
-                metadata.set_tag(ExifTag::ExposureTime(vec![uR64 {
-                    nominator: t.n,
-                    denominator: t.d,
-                }]));
-            }
-            if let Some(fl) = exif.focal_length {
-                metadata.set_tag(ExifTag::FocalLength(vec![uR64 {
-                    nominator: fl.n,
-                    denominator: fl.d,
-                }]));
-            }
+    fn ascii(tag: u16, text: &str) -> IfdEntry {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        (tag, 2, bytes.len() as u32, bytes)
+    }
 
-            if let Some(iso) = exif.iso_speed {
-                metadata.set_tag(ExifTag::ISO(vec![iso as u16]));
-            } else if let Some(iso) = exif.iso_speed_ratings {
-                metadata.set_tag(ExifTag::ISO(vec![iso]));
-            }
+    fn long(tag: u16, value: u32) -> IfdEntry {
+        (tag, 4, 1, value.to_le_bytes().to_vec())
+    }
 
-            if let Some(ev) = exif.exposure_bias {
-                metadata.set_tag(ExifTag::ExposureCompensation(vec![iR64 {
-                    nominator: ev.n,
-                    denominator: ev.d,
-                }]));
-            }
+    /// A TIFF-structured RAW carrying camera, capture date and GPS tags but no image data.
+    fn synthetic_raw() -> Vec<u8> {
+        let exif_entries = [ascii(0x9003, "2024:05:01 10:20:30")];
+        let gps_entries = [
+            ascii(0x0001, "N"),
+            (
+                0x0002,
+                5,
+                3,
+                [48u32, 1, 8, 1, 30, 1]
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect(),
+            ),
+        ];
+        let ifd0_entries = |exif_offset: u32, gps_offset: u32| {
+            [
+                ascii(0x010F, "TestMake"),
+                ascii(0x0110, "TestCam"),
+                long(0x8769, exif_offset),
+                long(0x8825, gps_offset),
+            ]
+        };
 
-            if let Some(flash) = exif.flash {
-                metadata.set_tag(ExifTag::Flash(vec![flash]));
-            }
-            if let Some(metering) = exif.metering_mode {
-                metadata.set_tag(ExifTag::MeteringMode(vec![metering]));
-            }
-            if let Some(wb) = exif.white_balance {
-                metadata.set_tag(ExifTag::WhiteBalance(vec![wb]));
-            }
-            if let Some(prog) = exif.exposure_program {
-                metadata.set_tag(ExifTag::ExposureProgram(vec![prog]));
-            }
+        let ifd0_len = ifd(8, &ifd0_entries(0, 0)).len() as u32;
+        let exif_offset = 8 + ifd0_len;
+        let exif = ifd(exif_offset, &exif_entries);
+        let gps_offset = exif_offset + exif.len() as u32;
+        let gps = ifd(gps_offset, &gps_entries);
+
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend(ifd(8, &ifd0_entries(exif_offset, gps_offset)));
+        tiff.extend(exif);
+        tiff.extend(gps);
+        tiff
+    }
 
-            if !strip_gps && let Some(gps) = exif.gps {
-                if let Some(lat) = gps.gps_latitude {
-                    metadata.set_tag(ExifTag::GPSLatitude(vec![
-                        uR64 {
-                            nominator: lat[0].n,
-                            denominator: lat[0].d,
-                        },
-                        uR64 {
-                            nominator: lat[1].n,
-                            denominator: lat[1].d,
-                        },
-                        uR64 {
-                            nominator: lat[2].n,
-                            denominator: lat[2].d,
-                        },
-                    ]));
-                }
-                if let Some(lat_ref) = gps.gps_latitude_ref {
-                    metadata.set_tag(ExifTag::GPSLatitudeRef(lat_ref));
-                }
-                if let Some(lon) = gps.gps_longitude {
-                    metadata.set_tag(ExifTag::GPSLongitude(vec![
-                        uR64 {
-                            nominator: lon[0].n,
-                            denominator: lon[0].d,
-                        },
-                        uR64 {
-                            nominator: lon[1].n,
-                            denominator: lon[1].d,
-                        },
-                        uR64 {
-                            nominator: lon[2].n,
-                            denominator: lon[2].d,
-                        },
-                    ]));
-                }
-                if let Some(lon_ref) = gps.gps_longitude_ref {
-                    metadata.set_tag(ExifTag::GPSLongitudeRef(lon_ref));
-                }
-                if let Some(alt) = gps.gps_altitude {
-                    metadata.set_tag(ExifTag::GPSAltitude(vec![uR64 {
-                        nominator: alt.n,
-                        denominator: alt.d,
-                    }]));
-                }
-                if let Some(alt_ref) = gps.gps_altitude_ref {
-                    metadata.set_tag(ExifTag::GPSAltitudeRef(vec![alt_ref]));
-                }
-            }
+    fn ascii_field(exif: &Exif, tag: exif::Tag) -> Option<String> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(parts) => Some(String::from_utf8_lossy(parts.first()?).to_string()),
+            _ => None,
         }
     }
 
-    metadata.set_tag(ExifTag::Software("RapidRAW".to_string()));
-    metadata.set_tag(ExifTag::Orientation(vec![1u16]));
-    metadata.set_tag(ExifTag::ColorSpace(vec![1u16]));
-
-    if let Err(e) = metadata.write_to_vec(image_bytes, file_type) {
-        log::warn!("Failed to write metadata: {}", e);
+    #[test]
+    fn raw_to_jpeg_export_keeps_capture_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw_path = dir.path().join("IMG_0001.dng");
+        fs::write(&raw_path, synthetic_raw()).unwrap();
+
+        let mut jpeg = Vec::new();
+        JpegEncoder::new(&mut jpeg)
+            .write_image(
+                RgbImage::new(8, 8).as_raw(),
+                8,
+                8,
+                image::ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+
+        write_image_with_metadata(&mut jpeg, raw_path.to_str().unwrap(), "jpg", true, true)
+            .unwrap();
+
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&jpeg))
+            .unwrap();
+        assert_eq!(
+            ascii_field(&exif, exif::Tag::Model).as_deref(),
+            Some("TestCam")
+        );
+        assert_eq!(
+            ascii_field(&exif, exif::Tag::DateTimeOriginal).as_deref(),
+            Some("2024:05:01 10:20:30")
+        );
+        assert!(
+            exif.get_field(exif::Tag::GPSLatitude, In::PRIMARY)
+                .is_none()
+        );
     }
-
-    Ok(())
 }
@@ -5,27 +5,33 @@ use std::fmt;
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::Cursor;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::Arc;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, mpsc};
 use std::thread;
+use std::time::{Instant, SystemTime};
 
 use anyhow::Result;
 use base64::{Engine as _, engine::general_purpose};
 use chrono::{DateTime, Utc};
 use image::codecs::jpeg::JpegEncoder;
 use image::{DynamicImage, GenericImageView, ImageBuffer, Luma};
+use lru::LruCache;
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
 use rayon::ThreadPoolBuilder;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use tauri::{AppHandle, Emitter, Manager};
 use uuid::Uuid;
 use walkdir::WalkDir;
 
 use crate::AppState;
+use crate::adjustment_registry;
 use crate::calculate_geometry_hash;
 use crate::exif_processing;
 use crate::formats::{is_raw_file, is_supported_image_file};
@@ -33,19 +39,73 @@ use crate::gpu_processing;
 use crate::image_loader;
 use crate::image_processing::GpuContext;
 use crate::image_processing::{
-    Crop, ImageMetadata, apply_coarse_rotation, apply_cpu_default_raw_processing, apply_crop,
-    apply_flip, apply_geometry_warp, apply_rotation, auto_results_to_json,
-    get_all_adjustments_from_json, perform_auto_analysis,
+    AutoSettings, Crop, ImageMetadata, Snapshot, apply_coarse_rotation,
+    apply_cpu_default_raw_processing, apply_crop, apply_flip, apply_geometry_warp_owned,
+    apply_rotation, auto_results_to_json, get_all_adjustments_from_json, perform_auto_analysis,
+    process_image_cpu,
 };
 use crate::mask_generation::MaskDefinition;
 use crate::preset_converter;
-use crate::tagging::COLOR_TAG_PREFIX;
+use crate::tagging::{COLOR_TAG_PREFIX, USER_TAG_PREFIX, invalidate_tag_index};
+
+fn directory_override(value: Option<String>) -> Option<PathBuf> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+fn resolve_cached_dir(
+    cache: Option<&Mutex<Option<PathBuf>>>,
+    resolve: impl FnOnce() -> std::result::Result<PathBuf, String>,
+) -> std::result::Result<PathBuf, String> {
+    if let Some(dir) = cache.and_then(|cache| cache.lock().unwrap().clone()) {
+        return Ok(dir);
+    }
+    let dir = resolve()?;
+    if let Some(cache) = cache {
+        *cache.lock().unwrap() = Some(dir.clone());
+    }
+    Ok(dir)
+}
+
+pub fn resolve_app_cache_dir(app_handle: &AppHandle) -> std::result::Result<PathBuf, String> {
+    let state = app_handle.try_state::<AppState>();
+    resolve_cached_dir(state.as_ref().map(|s| &s.app_cache_dir), || {
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        match directory_override(settings.cache_directory_override) {
+            Some(dir) => Ok(dir),
+            None => app_handle.path().app_cache_dir().map_err(|e| e.to_string()),
+        }
+    })
+}
+
+pub fn resolve_app_data_dir(app_handle: &AppHandle) -> std::result::Result<PathBuf, String> {
+    let state = app_handle.try_state::<AppState>();
+    resolve_cached_dir(state.as_ref().map(|s| &s.app_data_dir), || {
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        match directory_override(settings.data_directory_override) {
+            Some(dir) => Ok(dir),
+            None => app_handle.path().app_data_dir().map_err(|e| e.to_string()),
+        }
+    })
+}
+
+fn validate_directory_override(value: &Option<String>, label: &str) -> Result<(), String> {
+    let Some(dir) = directory_override(value.clone()) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("{} '{}' is not usable: {}", label, dir.display(), e))?;
+    let probe_path = dir.join(format!(".rapidraw_write_probe_{}", Uuid::new_v4()));
+    fs::write(&probe_path, b"")
+        .map_err(|e| format!("{} '{}' is not writable: {}", label, dir.display(), e))?;
+    let _ = fs::remove_file(probe_path);
+    Ok(())
+}
 
 fn resolve_thumbnail_cache_dir(app_handle: &AppHandle) -> std::result::Result<PathBuf, String> {
-    let cache_dir = app_handle
-        .path()
-        .app_cache_dir()
-        .map_err(|e| e.to_string())?;
+    let cache_dir = resolve_app_cache_dir(app_handle)?;
     let thumb_cache_dir = cache_dir.join("thumbnails");
     if !thumb_cache_dir.exists() {
         fs::create_dir_all(&thumb_cache_dir).map_err(|e| e.to_string())?;
@@ -95,6 +155,7 @@ pub struct PresetFile {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SortCriteria {
+    /// e.g. `"name"`, `"date"` (file mtime) or `"captureTime"` (EXIF, see `enrich_capture_times`).
     pub key: String,
     pub order: String,
 }
@@ -108,6 +169,20 @@ pub struct FilterCriteria {
     pub colors: Vec<String>,
 }
 
+const FOLDER_CONFIG_FILENAME: &str = ".rapidraw-folder.json";
+
+/// Per-directory overrides stored in `.rapidraw-folder.json` next to the images.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderConfig {
+    #[serde(default)]
+    pub default_export_preset_id: Option<String>,
+    #[serde(default)]
+    pub sort_criteria: Option<SortCriteria>,
+    #[serde(default)]
+    pub filter_criteria: Option<FilterCriteria>,
+}
+
 impl Default for FilterCriteria {
     fn default() -> Self {
         Self {
@@ -160,52 +235,9 @@ pub enum PasteMode {
 }
 
 fn default_included_adjustments() -> HashSet<String> {
-    [
-        "blacks",
-        "brightness",
-        "clarity",
-        "centré",
-        "chromaticAberrationBlueYellow",
-        "chromaticAberrationRedCyan",
-        "colorCalibration",
-        "colorGrading",
-        "colorNoiseReduction",
-        "contrast",
-        "curves",
-        "dehaze",
-        "exposure",
-        "grainAmount",
-        "grainRoughness",
-        "grainSize",
-        "highlights",
-        "hsl",
-        "lutIntensity",
-        "lutName",
-        "lutPath",
-        "lutSize",
-        "lumaNoiseReduction",
-        "saturation",
-        "sectionVisibility",
-        "shadows",
-        "sharpness",
-        "showClipping",
-        "structure",
-        "temperature",
-        "tint",
-        "toneMapper",
-        "vibrance",
-        "vignetteAmount",
-        "vignetteFeather",
-        "vignetteMidpoint",
-        "flareAmount",
-        "glowAmount",
-        "halationAmount",
-        "vignetteRoundness",
-        "whites",
-    ]
-    .iter()
-    .map(|s| s.to_string())
-    .collect()
+    adjustment_registry::copyable_keys()
+        .map(|s| s.to_string())
+        .collect()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -214,8 +246,63 @@ pub struct CopyPasteSettings {
     pub mode: PasteMode,
     #[serde(default = "default_included_adjustments")]
     pub included_adjustments: HashSet<String>,
+    /// Copyable keys the user deselected. This is what gets persisted, so
+    /// adjustments added to the registry later are included by default.
     #[serde(default)]
-    pub known_adjustments: HashSet<String>,
+    pub excluded_adjustments: Option<HashSet<String>>,
+}
+
+impl CopyPasteSettings {
+    fn apply_exclusions(&mut self) {
+        if let Some(excluded) = &self.excluded_adjustments {
+            self.included_adjustments = adjustment_registry::copyable_keys()
+                .filter(|key| !excluded.contains(*key))
+                .map(str::to_string)
+                .collect();
+        }
+    }
+
+    fn record_exclusions(&mut self) {
+        self.excluded_adjustments = Some(
+            adjustment_registry::copyable_keys()
+                .filter(|key| !self.included_adjustments.contains(*key))
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+
+    /// Nested objects such as `hsl` or `curves` are gated by their top-level key,
+    /// which can also be selected through its registry section.
+    fn includes(&self, key: &str) -> bool {
+        self.included_adjustments.contains(key)
+            || adjustment_registry::find(key)
+                .is_some_and(|def| self.included_adjustments.contains(def.section))
+    }
+
+    pub fn paste_into(&self, target: &mut Map<String, Value>, pasted: &Map<String, Value>) {
+        for (key, value) in pasted {
+            if self.includes(key) {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+
+        if self.mode == PasteMode::Replace {
+            let defaults = adjustment_registry::default_adjustments();
+            for key in adjustment_registry::copyable_keys() {
+                if self.includes(key) {
+                    continue;
+                }
+                match defaults.get(key) {
+                    Some(default) => {
+                        target.insert(key.to_string(), default.clone());
+                    }
+                    None => {
+                        target.remove(key);
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Default for CopyPasteSettings {
@@ -223,7 +310,7 @@ impl Default for CopyPasteSettings {
         Self {
             mode: PasteMode::Merge,
             included_adjustments: default_included_adjustments(),
-            known_adjustments: default_included_adjustments(),
+            excluded_adjustments: Some(HashSet::new()),
         }
     }
 }
@@ -249,12 +336,36 @@ pub struct ExportPreset {
     pub watermark_spacing: u32,
     pub watermark_opacity: u32,
     #[serde(default)]
+    pub watermark_text: Option<String>,
+    #[serde(default = "default_watermark_font_size")]
+    pub watermark_font_size: u32,
+    #[serde(default)]
+    pub watermark_color: Option<String>,
+    #[serde(default)]
     pub export_masks: Option<bool>,
+    /// 8 or 16; only used by the PNG and TIFF formats.
+    #[serde(default)]
+    pub bit_depth: Option<u8>,
+    /// True lossless for WebP. The AVIF encoder has no lossless mode, so AVIF is
+    /// only written at maximum quality, which is still lossy.
+    #[serde(default)]
+    pub lossless: Option<bool>,
+    /// "srgb" or "display-p3"; only PNG and JPEG embed the profile, other formats stay sRGB.
+    #[serde(default = "default_export_color_space")]
+    pub color_space: String,
     /// Last export destination path, stored on the __last_used__ preset only.
     #[serde(default)]
     pub last_export_path: Option<String>,
 }
 
+fn default_watermark_font_size() -> u32 {
+    30
+}
+
+pub fn default_export_color_space() -> String {
+    "srgb".to_string()
+}
+
 fn default_export_presets() -> Vec<ExportPreset> {
     vec![
         ExportPreset {
@@ -275,7 +386,13 @@ fn default_export_presets() -> Vec<ExportPreset> {
             watermark_scale: 10,
             watermark_spacing: 5,
             watermark_opacity: 75,
+            watermark_text: None,
+            watermark_font_size: default_watermark_font_size(),
+            watermark_color: None,
             export_masks: Some(false),
+            bit_depth: None,
+            lossless: None,
+            color_space: default_export_color_space(),
             last_export_path: None,
         },
         ExportPreset {
@@ -296,12 +413,22 @@ fn default_export_presets() -> Vec<ExportPreset> {
             watermark_scale: 10,
             watermark_spacing: 5,
             watermark_opacity: 75,
+            watermark_text: None,
+            watermark_font_size: default_watermark_font_size(),
+            watermark_color: None,
             export_masks: Some(false),
+            bit_depth: None,
+            lossless: None,
+            color_space: default_export_color_space(),
             last_export_path: None,
         },
     ]
 }
 
+fn default_thumbnail_cache_max_mb() -> Option<u32> {
+    Some(1024)
+}
+
 fn default_linear_raw_mode() -> String {
     "auto".to_string()
 }
@@ -376,6 +503,11 @@ pub struct AppSettings {
     #[serde(default)]
     pub linux_gpu_optimization: Option<bool>,
     #[serde(default)]
+    pub preferred_gpu_adapter: Option<String>,
+    /// Overrides the GPU tile edge; clamped to what the adapter supports.
+    #[serde(default)]
+    pub gpu_tile_size: Option<u32>,
+    #[serde(default)]
     pub library_view_mode: Option<String>,
     #[serde(default = "default_export_presets")]
     pub export_presets: Vec<ExportPreset>,
@@ -390,23 +522,44 @@ pub struct AppSettings {
     #[serde(default)]
     pub create_xmp_if_missing: Option<bool>,
     #[serde(default)]
+    pub xmp_sync_develop_settings: Option<bool>,
+    #[serde(default)]
     pub is_waveform_visible: Option<bool>,
     #[serde(default)]
     pub waveform_height: Option<u32>,
     #[serde(default)]
     pub active_waveform_channel: Option<String>,
+    #[serde(default)]
+    pub waveform_mode: Option<String>,
+    #[serde(default)]
+    pub histogram_scale: Option<String>,
+    #[serde(default)]
+    pub gpu_memory_budget_mb: Option<u32>,
+    #[serde(default = "default_thumbnail_cache_max_mb")]
+    pub thumbnail_cache_max_mb: Option<u32>,
+    #[serde(default)]
+    pub thumbnail_quality: Option<u8>,
+    /// `"dataUrl"` (default) embeds thumbnails as base64; `"protocol"` returns
+    /// `thumbnail://` URLs served straight from the cache directory.
+    #[serde(default)]
+    pub thumbnail_delivery: Option<String>,
+    /// Emits an unedited low-resolution placeholder before each generated thumbnail.
+    #[serde(default)]
+    pub enable_progressive_thumbnails: Option<bool>,
+    #[serde(default)]
+    pub cache_directory_override: Option<String>,
+    #[serde(default)]
+    pub data_directory_override: Option<String>,
+    /// Companion file extensions that travel with an image on copy, move, rename and delete.
+    #[serde(default)]
+    pub associated_extensions: Option<Vec<String>>,
 }
 
 fn default_adjustment_visibility() -> HashMap<String, bool> {
-    let mut map = HashMap::new();
-    map.insert("sharpening".to_string(), true);
-    map.insert("presence".to_string(), true);
-    map.insert("noiseReduction".to_string(), true);
-    map.insert("chromaticAberration".to_string(), false);
-    map.insert("vignette".to_string(), true);
-    map.insert("colorCalibration".to_string(), false);
-    map.insert("grain".to_string(), true);
-    map
+    adjustment_registry::PANEL_GROUPS
+        .iter()
+        .map(|(group, visible)| (group.to_string(), *visible))
+        .collect()
 }
 
 impl Default for AppSettings {
@@ -447,6 +600,8 @@ impl Default for AppSettings {
             copy_paste_settings: CopyPasteSettings::default(),
             raw_highlight_compression: Some(2.5),
             processing_backend: Some("auto".to_string()),
+            preferred_gpu_adapter: None,
+            gpu_tile_size: None,
             #[cfg(target_os = "linux")]
             linux_gpu_optimization: Some(true),
             #[cfg(not(target_os = "linux"))]
@@ -459,13 +614,40 @@ impl Default for AppSettings {
             linear_raw_mode: default_linear_raw_mode(),
             enable_xmp_sync: Some(true),
             create_xmp_if_missing: Some(false),
+            xmp_sync_develop_settings: Some(false),
             is_waveform_visible: Some(false),
             waveform_height: Some(220),
             active_waveform_channel: Some("luma".to_string()),
+            waveform_mode: Some("overlay".to_string()),
+            histogram_scale: Some("linear".to_string()),
+            gpu_memory_budget_mb: None,
+            thumbnail_cache_max_mb: default_thumbnail_cache_max_mb(),
+            thumbnail_quality: Some(DEFAULT_THUMBNAIL_QUALITY),
+            thumbnail_delivery: Some("dataUrl".to_string()),
+            enable_progressive_thumbnails: Some(false),
+            cache_directory_override: None,
+            data_directory_override: None,
+            associated_extensions: Some(default_associated_extensions()),
         }
     }
 }
 
+fn default_associated_extensions() -> Vec<String> {
+    vec!["rrdata".to_string(), "xmp".to_string()]
+}
+
+fn associated_extensions(app_handle: &AppHandle) -> HashSet<String> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    settings
+        .associated_extensions
+        .unwrap_or_else(default_associated_extensions)
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .chain(std::iter::once("rrdata".to_string()))
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageFile {
     path: String,
@@ -475,6 +657,12 @@ pub struct ImageFile {
     tags: Option<Vec<String>>,
     exif: Option<HashMap<String, String>>,
     is_virtual_copy: bool,
+    group_id: Option<String>,
+    is_hidden: bool,
+    /// Unix seconds from EXIF `DateTimeOriginal`, or the mtime when there is none. Only
+    /// burst-grouped listings fill it; otherwise it comes from `enrich_capture_times`.
+    #[serde(default)]
+    capture_time: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -484,6 +672,84 @@ pub struct ImportSettings {
     pub organize_by_date: bool,
     pub date_folder_format: String,
     pub delete_after_import: bool,
+    #[serde(default)]
+    pub preserve_timestamps: bool,
+    #[serde(default)]
+    pub skip_duplicates: bool,
+}
+
+/// Content hashes of files already in import destinations. Files are grouped by
+/// size per folder so only same-sized candidates are ever hashed.
+#[derive(Default)]
+struct ImportDuplicateIndex {
+    folders: HashMap<PathBuf, HashMap<u64, Vec<PathBuf>>>,
+    hashes: HashMap<PathBuf, blake3::Hash>,
+}
+
+impl ImportDuplicateIndex {
+    fn file_hash(&mut self, path: &Path) -> Option<blake3::Hash> {
+        if let Some(hash) = self.hashes.get(path) {
+            return Some(*hash);
+        }
+        let data = read_file_mapped(path).ok()?;
+        let hash = blake3::hash(&data);
+        self.hashes.insert(path.to_path_buf(), hash);
+        Some(hash)
+    }
+
+    fn folder_files(&mut self, folder: &Path) -> &mut HashMap<u64, Vec<PathBuf>> {
+        self.folders.entry(folder.to_path_buf()).or_insert_with(|| {
+            let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+            for entry in fs::read_dir(folder).into_iter().flatten().flatten() {
+                if let Ok(metadata) = entry.metadata()
+                    && metadata.is_file()
+                {
+                    by_size
+                        .entry(metadata.len())
+                        .or_default()
+                        .push(entry.path());
+                }
+            }
+            by_size
+        })
+    }
+
+    fn find_duplicate(&mut self, folder: &Path, source: &Path) -> Option<PathBuf> {
+        let size = fs::metadata(source).ok()?.len();
+        let candidates = self.folder_files(folder).get(&size)?.clone();
+        let source_hash = self.file_hash(source)?;
+        candidates
+            .into_iter()
+            .find(|candidate| self.file_hash(candidate) == Some(source_hash))
+    }
+
+    fn record(&mut self, folder: &Path, path: &Path) {
+        if let Ok(metadata) = fs::metadata(path) {
+            self.folder_files(folder)
+                .entry(metadata.len())
+                .or_default()
+                .push(path.to_path_buf());
+        }
+    }
+}
+
+/// `fs::copy` stamps the destination with the current time; this carries over the
+/// source's modification time (or `modified` when given) so date sorting survives.
+fn copy_preserving_timestamps(
+    source: &Path,
+    dest: &Path,
+    modified: Option<SystemTime>,
+) -> std::io::Result<()> {
+    fs::copy(source, dest)?;
+    let modified = match modified {
+        Some(time) => time,
+        None => fs::metadata(source)?.modified()?,
+    };
+    fs::OpenOptions::new().write(true).open(dest)?.set_times(
+        fs::FileTimes::new()
+            .set_modified(modified)
+            .set_accessed(modified),
+    )
 }
 
 pub fn parse_virtual_path(virtual_path: &str) -> (PathBuf, PathBuf) {
@@ -518,6 +784,91 @@ pub fn parse_virtual_path(virtual_path: &str) -> (PathBuf, PathBuf) {
     (source_path, sidecar_path)
 }
 
+fn get_fallback_sidecar_path(
+    app_handle: &AppHandle,
+    sidecar_path: &Path,
+) -> std::result::Result<PathBuf, String> {
+    let sidecar_dir = resolve_app_data_dir(app_handle)?.join("sidecars");
+    let hash = blake3::hash(sidecar_path.to_string_lossy().as_bytes()).to_hex();
+    Ok(sidecar_dir.join(format!("{}.rrdata", hash)))
+}
+
+pub fn resolve_sidecar_path(app_handle: &AppHandle, sidecar_path: &Path) -> PathBuf {
+    if !sidecar_path.exists()
+        && let Ok(fallback_path) = get_fallback_sidecar_path(app_handle, sidecar_path)
+        && fallback_path.exists()
+    {
+        return fallback_path;
+    }
+    sidecar_path.to_path_buf()
+}
+
+/// Writes through a uniquely named temporary sibling and renames it into place, so
+/// readers never observe a half-written file and concurrent writers don't collide.
+fn write_file_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp_file = tempfile::NamedTempFile::new_in(parent)?;
+    std::io::Write::write_all(&mut tmp_file, contents.as_bytes())?;
+    tmp_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Writes a sidecar and drops the cached tag index, since any sidecar write may change
+/// the tags it aggregates.
+pub fn write_sidecar_with_fallback(
+    app_handle: &AppHandle,
+    sidecar_path: &Path,
+    contents: &str,
+) -> std::result::Result<(), String> {
+    let result = write_sidecar_keeping_tag_index(app_handle, sidecar_path, contents);
+    if let Some(state) = app_handle.try_state::<AppState>() {
+        invalidate_tag_index(&state);
+    }
+    result
+}
+
+/// Same as `write_sidecar_with_fallback`, for callers that update the tag index themselves.
+pub fn write_sidecar_keeping_tag_index(
+    app_handle: &AppHandle,
+    sidecar_path: &Path,
+    contents: &str,
+) -> std::result::Result<(), String> {
+    let write_error = match write_file_atomic(sidecar_path, contents) {
+        Ok(()) => {
+            if let Ok(fallback_path) = get_fallback_sidecar_path(app_handle, sidecar_path)
+                && fallback_path.exists()
+            {
+                let _ = fs::remove_file(fallback_path);
+            }
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+
+    if !matches!(
+        write_error.kind(),
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+    ) {
+        return Err(write_error.to_string());
+    }
+
+    let fallback_path = get_fallback_sidecar_path(app_handle, sidecar_path)?;
+    if let Some(parent) = fallback_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    write_file_atomic(&fallback_path, contents).map_err(|e| e.to_string())?;
+    log::warn!(
+        "Could not write sidecar '{}' ({}), stored edits in '{}' instead",
+        sidecar_path.display(),
+        write_error,
+        fallback_path.display()
+    );
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn read_exif_for_paths(
     paths: Vec<String>,
@@ -542,9 +893,195 @@ pub async fn read_exif_for_paths(
 }
 
 #[tauri::command]
-pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<ImageFile>, String> {
-    let settings = load_settings(app_handle).unwrap_or_default();
+pub async fn read_gps_for_paths(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<HashMap<String, exif_processing::GpsCoordinates>, String> {
+    let gps_data = paths
+        .par_iter()
+        .filter_map(|virtual_path| {
+            let (source_path, sidecar_path) = parse_virtual_path(virtual_path);
+            let source_str = source_path.to_string_lossy();
+
+            let coords = if let Ok(mmap) = read_file_mapped(&source_path) {
+                exif_processing::read_gps(&source_str, &mmap)
+            } else {
+                let bytes = fs::read(&source_path).ok()?;
+                exif_processing::read_gps(&source_str, &bytes)
+            };
+
+            let coords = coords.or_else(|| {
+                let content =
+                    fs::read_to_string(resolve_sidecar_path(&app_handle, &sidecar_path)).ok()?;
+                serde_json::from_str::<ImageMetadata>(&content).ok()?.gps
+            });
+
+            coords.map(|c| (virtual_path.clone(), c))
+        })
+        .collect();
+
+    Ok(gps_data)
+}
+
+/// Writes GPS into the EXIF of JPEG/TIFF sources; other formats (including RAWs,
+/// which are never rewritten) keep the location in their sidecar and, with XMP
+/// sync enabled, in the `exif:` GPS properties of their XMP.
+#[tauri::command]
+pub fn set_gps_for_paths(
+    paths: Vec<String>,
+    lat: f64,
+    lon: f64,
+    altitude: Option<f64>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("Invalid GPS coordinates: {}, {}", lat, lon));
+    }
+    let gps = exif_processing::GpsCoordinates { lat, lon, altitude };
+
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+
+    let errors: Vec<String> = paths
+        .par_iter()
+        .filter_map(|path| {
+            let (source_path, sidecar_path) = parse_virtual_path(path);
+
+            if !is_raw_file(&source_path.to_string_lossy())
+                && exif_processing::supports_in_place_gps(&source_path)
+            {
+                match exif_processing::set_gps_in_place(&source_path, &gps) {
+                    Ok(()) => return None,
+                    Err(e) => log::warn!(
+                        "Could not write GPS into {}, storing it in the sidecar: {}",
+                        source_path.display(),
+                        e
+                    ),
+                }
+            }
+
+            let existing_sidecar = resolve_sidecar_path(&app_handle, &sidecar_path);
+            let mut metadata: ImageMetadata = if existing_sidecar.exists() {
+                let parsed = fs::read_to_string(&existing_sidecar)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+                match parsed {
+                    Ok(metadata) => metadata,
+                    Err(e) => return Some(format!("{}: unreadable sidecar: {}", path, e)),
+                }
+            } else {
+                ImageMetadata::default()
+            };
+            metadata.gps = Some(gps);
+
+            let json_string = match serde_json::to_string_pretty(&metadata) {
+                Ok(json) => json,
+                Err(e) => return Some(format!("{}: {}", path, e)),
+            };
+            if let Err(e) = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string) {
+                return Some(format!("{}: {}", path, e));
+            }
+
+            if enable_xmp_sync {
+                if let Err(e) = sync_metadata_to_xmp(
+                    &source_path,
+                    &metadata,
+                    create_xmp_if_missing,
+                    xmp_sync_develop,
+                ) {
+                    log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                }
+            }
+            None
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to set GPS for: {}", errors.join(", ")))
+    }
+}
+
+const BURST_WINDOW_SECS: i64 = 2;
+
+/// Splits a file stem such as `IMG_0042` into its prefix and frame number.
+fn filename_sequence(path: &str) -> Option<(&str, u64)> {
+    let stem = Path::new(path).file_stem()?.to_str()?;
+    let prefix_len = stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let number = stem[prefix_len..].parse().ok()?;
+    Some((&stem[..prefix_len], number))
+}
+
+/// Consecutive frames (or a RAW+JPEG pair of the same frame) from one camera counter.
+/// Names without a frame number don't constrain the grouping.
+fn is_next_in_sequence(prev: &str, next: &str) -> bool {
+    match (filename_sequence(prev), filename_sequence(next)) {
+        (Some((prev_prefix, prev_number)), Some((next_prefix, next_number))) => {
+            prev_prefix == next_prefix
+                && next_number >= prev_number
+                && next_number - prev_number <= 1
+        }
+        _ => true,
+    }
+}
+
+/// Groups frames from the same folder that were captured within `BURST_WINDOW_SECS`
+/// of each other and carry consecutive file numbers. Files without an EXIF capture
+/// date are never grouped.
+fn assign_burst_groups(entries: &mut [(ImageFile, Option<i64>)]) {
+    let mut order: Vec<usize> = (0..entries.len())
+        .filter(|&i| !entries[i].0.is_virtual_copy && entries[i].1.is_some())
+        .collect();
+    order.sort_by_key(|&i| {
+        let path = &entries[i].0.path;
+        let parent = Path::new(path).parent().map(Path::to_path_buf);
+        let sequence = filename_sequence(path).map(|(_, number)| number);
+        (parent, entries[i].1, sequence, path.clone())
+    });
+
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() {
+            let (prev, prev_time) = &entries[order[end - 1]];
+            let (next, next_time) = &entries[order[end]];
+            let same_dir = Path::new(&prev.path).parent() == Path::new(&next.path).parent();
+            let within_window = match (prev_time, next_time) {
+                (Some(a), Some(b)) => b - a <= BURST_WINDOW_SECS,
+                _ => false,
+            };
+            if !same_dir || !within_window || !is_next_in_sequence(&prev.path, &next.path) {
+                break;
+            }
+            end += 1;
+        }
+
+        if end - start > 1 {
+            let group_id = entries[order[start]].0.path.clone();
+            for &i in &order[start..end] {
+                entries[i].0.group_id = Some(group_id.clone());
+            }
+        }
+        start = end;
+    }
+}
+
+#[tauri::command]
+pub fn list_images_in_dir(
+    path: String,
+    group_bursts: Option<bool>,
+    show_hidden: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<ImageFile>, String> {
+    let group_bursts = group_bursts.unwrap_or(false);
+    let show_hidden = show_hidden.unwrap_or(false);
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+    let state = app_handle.state::<AppState>();
 
     let entries = fs::read_dir(&path).map_err(|e| e.to_string())?;
     let mut images = Vec::new();
@@ -557,21 +1094,7 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
             .into_string()
             .unwrap_or_else(|os| os.to_string_lossy().into_owned());
 
-        if file_name.ends_with(".rrdata") {
-            let base = &file_name[..file_name.len() - 7];
-
-            let (source_filename, copy_id) =
-                if base.len() >= 7 && base.as_bytes()[base.len() - 7] == b'.' {
-                    let id = &base[base.len() - 6..];
-                    if id.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
-                        (&base[..base.len() - 7], Some(id.to_string()))
-                    } else {
-                        (base, None)
-                    }
-                } else {
-                    (base, None)
-                };
-
+        if let Some((source_filename, copy_id)) = split_sidecar_file_name(&file_name) {
             sidecars_by_filename
                 .entry(source_filename.to_string())
                 .or_default()
@@ -592,7 +1115,7 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
         })
         .collect();
 
-    let result_list: Vec<ImageFile> = tasks
+    let mut result_list: Vec<(ImageFile, Option<i64>)> = tasks
         .into_par_iter()
         .flat_map(|(path_str, file_name, path_buf, sidecars)| {
             let modified = fs::metadata(&path_buf)
@@ -601,6 +1124,10 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
                 .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
                 .map(|d| d.as_secs())
                 .unwrap_or(0);
+            let exif_time = group_bursts
+                .then(|| exif_capture_time(&state, &path_buf, modified))
+                .flatten();
+            let capture_time = group_bursts.then(|| exif_time.unwrap_or(modified as i64));
 
             let mut file_results = Vec::with_capacity(sidecars.len());
 
@@ -616,92 +1143,367 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
 
                 let sidecar_path = path_buf.with_file_name(sidecar_filename);
 
-                let (is_edited, tags, rating) = {
-                    let mut metadata = if sidecar_path.exists() {
-                        if let Ok(content) = fs::read_to_string(&sidecar_path) {
-                            serde_json::from_str::<ImageMetadata>(&content).unwrap_or_default()
-                        } else {
-                            ImageMetadata::default()
-                        }
-                    } else {
-                        ImageMetadata::default()
-                    };
+                let (is_edited, tags, rating, is_hidden) = {
+                    let (mut metadata, is_corrupt) =
+                        load_listing_metadata(&app_handle, &sidecar_path, &virtual_path, true);
 
                     if enable_xmp_sync
-                        && sync_metadata_from_xmp(&path_buf, &mut metadata)
+                        && !is_corrupt
+                        && sync_metadata_from_xmp(&path_buf, &mut metadata, xmp_sync_develop, false)
                         && let Ok(json) = serde_json::to_string_pretty(&metadata)
                     {
-                        let _ = fs::write(&sidecar_path, json);
+                        let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json);
                     }
 
-                    let edited = metadata.adjustments.as_object().is_some_and(|a| {
-                        a.keys().len() > 1 || (a.keys().len() == 1 && !a.contains_key("rating"))
-                    });
-                    (edited, metadata.tags, metadata.rating)
+                    let edited = has_edits(&metadata.adjustments);
+                    (edited, metadata.tags, metadata.rating, metadata.hidden)
                 };
 
-                file_results.push(ImageFile {
-                    path: virtual_path,
-                    modified,
-                    is_edited,
-                    tags,
-                    exif: None,
-                    is_virtual_copy,
-                    rating,
-                });
+                if is_hidden && !show_hidden {
+                    continue;
+                }
+
+                file_results.push((
+                    ImageFile {
+                        path: virtual_path,
+                        modified,
+                        is_edited,
+                        tags,
+                        exif: None,
+                        is_virtual_copy,
+                        rating,
+                        group_id: None,
+                        is_hidden,
+                        capture_time,
+                    },
+                    exif_time,
+                ));
             }
 
             file_results
         })
         .collect();
 
-    Ok(result_list)
-}
-
-#[tauri::command]
-pub fn list_images_recursive(
-    path: String,
-    app_handle: AppHandle,
-) -> Result<Vec<ImageFile>, String> {
-    let settings = load_settings(app_handle).unwrap_or_default();
-    let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    if group_bursts {
+        assign_burst_groups(&mut result_list);
+    }
 
-    let root_path = Path::new(&path);
-    let mut images = Vec::new();
+    Ok(result_list.into_iter().map(|(image, _)| image).collect())
+}
 
-    let mut sidecars_by_path: HashMap<PathBuf, Vec<Option<String>>> = HashMap::new();
+/// Best-effort recovery of a sidecar with a leading BOM or trailing garbage after the JSON object.
+fn repair_sidecar_content(content: &str) -> Option<ImageMetadata> {
+    let trimmed = content.trim_start_matches('\u{feff}').trim();
+    serde_json::Deserializer::from_str(trimmed)
+        .into_iter::<ImageMetadata>()
+        .next()?
+        .ok()
+}
 
-    for entry in WalkDir::new(root_path).into_iter().filter_map(Result::ok) {
-        let entry_path = entry.path();
-        if !entry_path.is_file() {
-            continue;
+/// Reads a sidecar for listing, reporting parse failures instead of silently dropping edits
+/// unless `report_corrupt` is off.
+fn load_listing_metadata(
+    app_handle: &AppHandle,
+    sidecar_path: &Path,
+    image_path: &str,
+    report_corrupt: bool,
+) -> (ImageMetadata, bool) {
+    let Ok(content) = fs::read_to_string(resolve_sidecar_path(app_handle, sidecar_path)) else {
+        return (ImageMetadata::default(), false);
+    };
+    match serde_json::from_str::<ImageMetadata>(&content) {
+        Ok(metadata) => (metadata, false),
+        Err(e) if !report_corrupt => {
+            log::debug!("Corrupt sidecar {}: {}", sidecar_path.display(), e);
+            (repair_sidecar_content(&content).unwrap_or_default(), true)
         }
+        Err(e) => {
+            log::warn!("Corrupt sidecar {}: {}", sidecar_path.display(), e);
+            let _ = app_handle.emit(
+                "sidecar-corrupt",
+                serde_json::json!({
+                    "path": sidecar_path.to_string_lossy(),
+                    "imagePath": image_path,
+                    "error": e.to_string(),
+                }),
+            );
+            (repair_sidecar_content(&content).unwrap_or_default(), true)
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarIssue {
+    pub path: String,
+    pub error: String,
+    pub repaired: bool,
+}
+
+#[tauri::command]
+pub fn validate_sidecars(root: String, app_handle: AppHandle) -> Result<Vec<SidecarIssue>, String> {
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    let sidecars: Vec<PathBuf> = WalkDir::new(root_path)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || !e.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().ends_with(".rrdata"))
+        .map(|e| e.into_path())
+        .collect();
+
+    let mut issues: Vec<SidecarIssue> = sidecars
+        .into_par_iter()
+        .filter_map(|sidecar_path| {
+            let path = sidecar_path.to_string_lossy().into_owned();
+            let content = match fs::read(&sidecar_path) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(e) => {
+                    return Some(SidecarIssue {
+                        path,
+                        error: e.to_string(),
+                        repaired: false,
+                    });
+                }
+            };
+
+            let error = match serde_json::from_str::<ImageMetadata>(&content) {
+                Ok(_) => return None,
+                Err(e) => e.to_string(),
+            };
+
+            let repaired = repair_sidecar_content(&content)
+                .and_then(|metadata| serde_json::to_string_pretty(&metadata).ok())
+                .is_some_and(|json| {
+                    let backup_path = sidecar_path.with_extension("rrdata.corrupt");
+                    fs::copy(&sidecar_path, &backup_path).is_ok()
+                        && write_sidecar_with_fallback(&app_handle, &sidecar_path, &json).is_ok()
+                });
+
+            Some(SidecarIssue {
+                path,
+                error,
+                repaired,
+            })
+        })
+        .collect();
+
+    issues.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(issues)
+}
+
+/// An image counts as edited once its adjustments hold more than just a rating.
+fn has_edits(adjustments: &Value) -> bool {
+    adjustments
+        .as_object()
+        .is_some_and(|a| a.keys().len() > 1 || (a.keys().len() == 1 && !a.contains_key("rating")))
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EditedImage {
+    path: String,
+    rating: u8,
+    modified: u64,
+}
+
+#[tauri::command]
+pub fn list_edited_images_recursive(
+    root: String,
+    app_handle: AppHandle,
+) -> Result<Vec<EditedImage>, String> {
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    let files: Vec<PathBuf> = WalkDir::new(root_path)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || !e.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+
+    let mut results: Vec<EditedImage> = files
+        .into_par_iter()
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_string_lossy().into_owned();
+            let (source_path, sidecar_path, copy_id) = match split_sidecar_file_name(&file_name) {
+                Some((source_filename, copy_id)) => {
+                    let source_path = path.with_file_name(source_filename);
+                    if !is_supported_image_file(source_filename) || !source_path.is_file() {
+                        return None;
+                    }
+                    (source_path, path, copy_id)
+                }
+                // Images without a local sidecar may keep their edits in the fallback store.
+                None if is_supported_image_file(&file_name) => {
+                    let local_sidecar = path.with_file_name(format!("{}.rrdata", file_name));
+                    let sidecar_path = resolve_sidecar_path(&app_handle, &local_sidecar);
+                    if sidecar_path == local_sidecar {
+                        return None;
+                    }
+                    (path, sidecar_path, None)
+                }
+                None => return None,
+            };
 
-        let file_name = entry_path.file_name().unwrap_or_default().to_string_lossy();
-        if let Some(base) = file_name.strip_suffix(".rrdata") {
-            let (source_filename, copy_id) =
-                if base.len() >= 7 && base.as_bytes()[base.len() - 7] == b'.' {
-                    let id = &base[base.len() - 6..];
-                    if id.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
-                        (&base[..base.len() - 7], Some(id.to_string()))
-                    } else {
-                        (base, None)
-                    }
-                } else {
-                    (base, None)
-                };
-
-            if let Some(parent) = entry_path.parent() {
-                sidecars_by_path
-                    .entry(parent.join(source_filename))
-                    .or_default()
-                    .push(copy_id);
+            let content = fs::read_to_string(&sidecar_path).ok()?;
+            let metadata = serde_json::from_str::<ImageMetadata>(&content).ok()?;
+            if !has_edits(&metadata.adjustments) {
+                return None;
             }
-        } else if is_supported_image_file(entry_path.to_string_lossy().as_ref()) {
-            images.push(entry_path.to_path_buf());
+
+            let modified = fs::metadata(&source_path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let path_str = source_path.to_string_lossy().into_owned();
+            let path = match copy_id {
+                Some(id) => format!("{}?vc={}", path_str, id),
+                None => path_str,
+            };
+
+            Some(EditedImage {
+                path,
+                rating: metadata.rating,
+                modified,
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(results)
+}
+
+/// Splits `<source>.rrdata` / `<source>.<copy id>.rrdata` into the source file name
+/// and the optional virtual copy id. Returns `None` for anything that isn't a sidecar.
+fn split_sidecar_file_name(file_name: &str) -> Option<(&str, Option<String>)> {
+    let base = file_name.strip_suffix(".rrdata")?;
+    if base.len() >= 7 && base.as_bytes()[base.len() - 7] == b'.' {
+        let id = &base[base.len() - 6..];
+        if id.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            return Some((&base[..base.len() - 7], Some(id.to_string())));
+        }
+    }
+    Some((base, None))
+}
+
+#[derive(Default)]
+struct TreeListing {
+    images: Vec<PathBuf>,
+    sidecars: Vec<(PathBuf, Option<String>)>,
+}
+
+impl TreeListing {
+    fn merge(mut self, other: TreeListing) -> TreeListing {
+        self.images.extend(other.images);
+        self.sidecars.extend(other.sidecars);
+        self
+    }
+}
+
+/// Lists images and sidecars below `dir`, reading sibling subdirectories in parallel.
+/// Like `WalkDir`'s defaults, symlinked directories are not followed and unreadable
+/// directories are skipped.
+fn scan_tree_parallel(dir: &Path) -> TreeListing {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return TreeListing::default(),
+    };
+
+    let mut listing = TreeListing::default();
+    let mut subdirs = Vec::new();
+
+    for entry in entries.filter_map(Result::ok) {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let entry_path = entry.path();
+        if file_type.is_dir() {
+            subdirs.push(entry_path);
+            continue;
+        }
+        if !file_type.is_file() && !entry_path.is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if let Some((source_filename, copy_id)) = split_sidecar_file_name(&file_name) {
+            listing.sidecars.push((dir.join(source_filename), copy_id));
+        } else if is_supported_image_file(file_name.as_ref()) {
+            listing.images.push(entry_path);
         }
     }
 
+    subdirs
+        .par_iter()
+        .map(|subdir| scan_tree_parallel(subdir))
+        .reduce(TreeListing::default, TreeListing::merge)
+        .merge(listing)
+}
+
+#[tauri::command]
+pub fn list_images_recursive(
+    path: String,
+    group_bursts: Option<bool>,
+    show_hidden: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<ImageFile>, String> {
+    Ok(collect_library_images(
+        Path::new(&path),
+        group_bursts.unwrap_or(false),
+        show_hidden.unwrap_or(false),
+        false,
+        &app_handle,
+    ))
+}
+
+/// Recursive listing behind `list_images_recursive`. A `read_only` walk neither pulls
+/// XMP changes into sidecars nor reports corrupt ones, for callers that only inspect
+/// the library.
+fn collect_library_images(
+    root: &Path,
+    group_bursts: bool,
+    show_hidden: bool,
+    read_only: bool,
+    app_handle: &AppHandle,
+) -> Vec<ImageFile> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let enable_xmp_sync = !read_only && settings.enable_xmp_sync.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+    let state = app_handle.state::<AppState>();
+
+    let mut listing = scan_tree_parallel(root);
+    // Directories are scanned concurrently, so sort to keep the output stable between calls.
+    listing.images.sort_unstable();
+
+    let mut sidecars_by_path: HashMap<PathBuf, Vec<Option<String>>> = HashMap::new();
+    for (source_path, copy_id) in listing.sidecars {
+        sidecars_by_path
+            .entry(source_path)
+            .or_default()
+            .push(copy_id);
+    }
+    for copy_ids in sidecars_by_path.values_mut() {
+        copy_ids.sort_unstable();
+    }
+    let images = listing.images;
+
     let tasks: Vec<_> = images
         .into_iter()
         .map(|path_buf| {
@@ -718,7 +1520,7 @@ pub fn list_images_recursive(
         })
         .collect();
 
-    let result_list: Vec<ImageFile> = tasks
+    let mut result_list: Vec<(ImageFile, Option<i64>)> = tasks
         .into_par_iter()
         .flat_map(|(path_str, file_name, path_buf, sidecars)| {
             let modified = fs::metadata(&path_buf)
@@ -727,6 +1529,10 @@ pub fn list_images_recursive(
                 .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
                 .map(|d| d.as_secs())
                 .unwrap_or(0);
+            let exif_time = group_bursts
+                .then(|| exif_capture_time(&state, &path_buf, modified))
+                .flatten();
+            let capture_time = group_bursts.then(|| exif_time.unwrap_or(modified as i64));
 
             let mut file_results = Vec::with_capacity(sidecars.len());
 
@@ -742,152 +1548,681 @@ pub fn list_images_recursive(
 
                 let sidecar_path = path_buf.with_file_name(sidecar_filename);
 
-                let (is_edited, tags, rating) = {
-                    let mut metadata = if sidecar_path.exists() {
-                        if let Ok(content) = fs::read_to_string(&sidecar_path) {
-                            serde_json::from_str::<ImageMetadata>(&content).unwrap_or_default()
-                        } else {
-                            ImageMetadata::default()
-                        }
-                    } else {
-                        ImageMetadata::default()
-                    };
+                let (is_edited, tags, rating, is_hidden) = {
+                    let (mut metadata, is_corrupt) =
+                        load_listing_metadata(app_handle, &sidecar_path, &virtual_path, !read_only);
 
                     if enable_xmp_sync
-                        && sync_metadata_from_xmp(&path_buf, &mut metadata)
+                        && !is_corrupt
+                        && sync_metadata_from_xmp(&path_buf, &mut metadata, xmp_sync_develop, false)
                         && let Ok(json) = serde_json::to_string_pretty(&metadata)
                     {
-                        let _ = fs::write(&sidecar_path, json);
+                        let _ = write_sidecar_with_fallback(app_handle, &sidecar_path, &json);
                     }
 
-                    let edited = metadata.adjustments.as_object().is_some_and(|a| {
-                        a.keys().len() > 1 || (a.keys().len() == 1 && !a.contains_key("rating"))
-                    });
-                    (edited, metadata.tags, metadata.rating)
+                    let edited = has_edits(&metadata.adjustments);
+                    (edited, metadata.tags, metadata.rating, metadata.hidden)
                 };
 
-                file_results.push(ImageFile {
-                    path: virtual_path,
-                    modified,
-                    is_edited,
-                    tags,
-                    exif: None,
-                    is_virtual_copy,
-                    rating,
-                });
+                if is_hidden && !show_hidden {
+                    continue;
+                }
+
+                file_results.push((
+                    ImageFile {
+                        path: virtual_path,
+                        modified,
+                        is_edited,
+                        tags,
+                        exif: None,
+                        is_virtual_copy,
+                        rating,
+                        group_id: None,
+                        is_hidden,
+                        capture_time,
+                    },
+                    exif_time,
+                ));
             }
 
             file_results
         })
         .collect();
 
-    Ok(result_list)
+    if group_bursts {
+        assign_burst_groups(&mut result_list);
+    }
+
+    result_list.into_iter().map(|(image, _)| image).collect()
 }
 
-#[derive(Serialize, Debug)]
+/// EXIF capture time of `source_path` in Unix seconds, cached per path and mtime so
+/// listings and `enrich_capture_times` share one lookup.
+fn exif_capture_time(state: &AppState, source_path: &Path, modified: u64) -> Option<i64> {
+    let source_key = source_path.to_string_lossy().into_owned();
+    if let Some((cached_mtime, capture_time)) =
+        state.capture_time_cache.lock().unwrap().get(&source_key)
+        && *cached_mtime == modified
+    {
+        return *capture_time;
+    }
+
+    let capture_time = exif_processing::read_capture_date(source_path).map(|dt| dt.timestamp());
+    state
+        .capture_time_cache
+        .lock()
+        .unwrap()
+        .insert(source_key, (modified, capture_time));
+    capture_time
+}
+
+/// Resolves EXIF capture times for the given (virtual) paths, falling back to the
+/// file's mtime when it has no capture date.
+#[tauri::command]
+pub fn enrich_capture_times(
+    paths: Vec<String>,
+    state: tauri::State<AppState>,
+) -> Result<HashMap<String, i64>, String> {
+    Ok(paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let (source_path, _) = parse_virtual_path(&path);
+            let modified = fs::metadata(&source_path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())?;
+            let capture_time =
+                exif_capture_time(&state, &source_path, modified).unwrap_or(modified as i64);
+            Some((path, capture_time))
+        })
+        .collect())
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
-pub struct FolderNode {
-    pub name: String,
-    pub path: String,
-    pub children: Vec<FolderNode>,
-    pub is_dir: bool,
-    pub image_count: usize,
-    pub has_subdirs: bool,
+pub enum TagMatchMode {
+    #[default]
+    All,
+    Any,
 }
 
-fn has_subdirs(path: &Path) -> bool {
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.filter_map(Result::ok) {
-            if let Ok(file_type) = entry.file_type()
-                && file_type.is_dir()
-            {
-                let name = entry.file_name();
-                if !name.to_string_lossy().starts_with('.') {
-                    return true;
-                }
-            }
-        }
-    }
-    false
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum RatingComparison {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
 }
 
-fn scan_dir_lazy(
-    path: &Path,
-    expanded_folders: &HashSet<&str>,
-    show_image_counts: bool,
-    prefetch_one_level: bool,
-) -> Result<(Vec<FolderNode>, usize), std::io::Error> {
-    let mut children_folders = Vec::new();
-    let mut current_dir_image_count = 0;
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingFilter {
+    pub op: RatingComparison,
+    pub value: u8,
+}
 
-    let entries = match std::fs::read_dir(path) {
-        Ok(entries) => entries,
-        Err(e) => {
-            log::warn!("Could not scan directory '{}': {}", path.display(), e);
-            return Ok((Vec::new(), 0));
+impl RatingFilter {
+    fn matches(&self, rating: u8) -> bool {
+        match self.op {
+            RatingComparison::Eq => rating == self.value,
+            RatingComparison::Gt => rating > self.value,
+            RatingComparison::Gte => rating >= self.value,
+            RatingComparison::Lt => rating < self.value,
+            RatingComparison::Lte => rating <= self.value,
         }
-    };
+    }
+}
 
-    for entry in entries.filter_map(Result::ok) {
-        let current_path = entry.path();
-        let file_type = match entry.file_type() {
-            Ok(ft) => ft,
-            Err(_) => continue,
-        };
+/// Dates are Unix timestamps in seconds; every bound is inclusive and optional.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySearchQuery {
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tag_mode: TagMatchMode,
+    #[serde(default)]
+    pub rating: Option<RatingFilter>,
+    #[serde(default)]
+    pub colors: Vec<String>,
+    #[serde(default)]
+    pub modified_from: Option<i64>,
+    #[serde(default)]
+    pub modified_to: Option<i64>,
+    #[serde(default)]
+    pub captured_from: Option<i64>,
+    #[serde(default)]
+    pub captured_to: Option<i64>,
+    #[serde(default)]
+    pub show_hidden: Option<bool>,
+}
 
-        let file_name = entry.file_name();
-        let name_str = file_name.to_string_lossy();
+fn in_range(value: i64, from: Option<i64>, to: Option<i64>) -> bool {
+    from.is_none_or(|from| value >= from) && to.is_none_or(|to| value <= to)
+}
 
-        if name_str.starts_with('.') {
-            continue;
+impl LibrarySearchQuery {
+    fn matches(&self, image: &ImageFile, state: &AppState) -> bool {
+        let (source_path, _) = parse_virtual_path(&image.path);
+
+        if let Some(needle) = self.filename.as_deref().filter(|n| !n.is_empty()) {
+            let file_name = source_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !file_name.contains(&needle.to_lowercase()) {
+                return false;
+            }
         }
 
-        if file_type.is_dir() {
-            let path_str = current_path.to_string_lossy().into_owned();
-            let is_expanded = expanded_folders.contains(path_str.as_str());
+        if let Some(rating) = &self.rating
+            && !rating.matches(image.rating)
+        {
+            return false;
+        }
 
-            let should_scan = is_expanded || prefetch_one_level;
-            let next_prefetch = is_expanded;
+        let image_tags = image.tags.as_deref().unwrap_or_default();
 
-            let (grand_children, sub_dir_own_images) = if should_scan {
-                scan_dir_lazy(
-                    &current_path,
-                    expanded_folders,
-                    show_image_counts,
-                    next_prefetch,
-                )?
-            } else {
-                let count = if show_image_counts {
-                    WalkDir::new(&current_path)
-                        .into_iter()
-                        .filter_map(Result::ok)
-                        .filter(|e| {
-                            e.file_type().is_file()
-                                && crate::formats::is_supported_image_file(e.path())
-                        })
-                        .count()
-                } else {
-                    0
-                };
-                (Vec::new(), count)
+        if !self.colors.is_empty() {
+            let color = image_tags
+                .iter()
+                .find_map(|t| t.strip_prefix(COLOR_TAG_PREFIX));
+            let matches = match color {
+                Some(color) => self.colors.iter().any(|c| c == color),
+                None => self.colors.iter().any(|c| c == "none"),
             };
+            if !matches {
+                return false;
+            }
+        }
 
-            let has_any_subdirs = if should_scan {
-                grand_children.iter().any(|c| c.is_dir)
-            } else {
-                has_subdirs(&current_path)
+        if !self.tags.is_empty() {
+            let has_tag = |wanted: &String| {
+                image_tags.iter().any(|t| {
+                    let t = t.strip_prefix(USER_TAG_PREFIX).unwrap_or(t);
+                    t.eq_ignore_ascii_case(wanted)
+                })
+            };
+            let matches = match self.tag_mode {
+                TagMatchMode::All => self.tags.iter().all(has_tag),
+                TagMatchMode::Any => self.tags.iter().any(has_tag),
             };
+            if !matches {
+                return false;
+            }
+        }
 
-            let grand_children_sum: usize = grand_children.iter().map(|c| c.image_count).sum();
-            let total_child_count = sub_dir_own_images + grand_children_sum;
+        if !in_range(image.modified as i64, self.modified_from, self.modified_to) {
+            return false;
+        }
 
-            children_folders.push(FolderNode {
-                name: name_str.into_owned(),
-                path: path_str,
-                children: grand_children,
-                is_dir: true,
-                image_count: total_child_count,
-                has_subdirs: has_any_subdirs,
+        if self.captured_from.is_some() || self.captured_to.is_some() {
+            let captured = image.capture_time.unwrap_or_else(|| {
+                exif_capture_time(state, &source_path, image.modified)
+                    .unwrap_or(image.modified as i64)
+            });
+            if !in_range(captured, self.captured_from, self.captured_to) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[tauri::command]
+pub async fn search_library(
+    root: String,
+    query: LibrarySearchQuery,
+    app_handle: AppHandle,
+) -> Result<Vec<ImageFile>, String> {
+    if !Path::new(&root).is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let show_hidden = query.show_hidden.unwrap_or(false);
+        collect_library_images(Path::new(&root), false, show_hidden, true, &app_handle)
+            .into_par_iter()
+            .filter(|image| query.matches(image, &state))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RatingRuleAction {
+    SetRating { rating: u8 },
+    SetColorLabel { color: String },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingRule {
+    pub condition: RatingFilter,
+    pub action: RatingRuleAction,
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+impl RatingRuleAction {
+    /// Returns whether the metadata changed.
+    fn apply(&self, metadata: &mut ImageMetadata) -> bool {
+        match self {
+            RatingRuleAction::SetRating { rating } => {
+                let rating = (*rating).min(5);
+                if metadata.rating == rating {
+                    return false;
+                }
+                metadata.rating = rating;
+                if let Some(obj) = metadata.adjustments.as_object_mut() {
+                    obj.insert("rating".to_string(), serde_json::json!(rating));
+                } else {
+                    metadata.adjustments = serde_json::json!({ "rating": rating });
+                }
+                true
+            }
+            RatingRuleAction::SetColorLabel { color } => {
+                let label = (!color.is_empty()).then(|| format!("{}{}", COLOR_TAG_PREFIX, color));
+                let mut tags = metadata.tags.take().unwrap_or_default();
+                let unchanged = tags
+                    .iter()
+                    .filter(|tag| tag.starts_with(COLOR_TAG_PREFIX))
+                    .eq(label.iter());
+                tags.retain(|tag| !tag.starts_with(COLOR_TAG_PREFIX));
+                tags.extend(label);
+                metadata.tags = (!tags.is_empty()).then_some(tags);
+                !unchanged
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn apply_rating_rule(
+    root: String,
+    rule: RatingRule,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    if !Path::new(&root).is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+
+    let images = collect_library_images(
+        Path::new(&root),
+        false,
+        rule.include_hidden,
+        true,
+        &app_handle,
+    );
+
+    let changed = images
+        .par_iter()
+        .filter(|image| rule.condition.matches(image.rating))
+        .filter(|image| {
+            let (source_path, sidecar_path) = parse_virtual_path(&image.path);
+            let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+
+            let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                match fs::read_to_string(&existing_sidecar_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+                {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        log::warn!("Skipping rating rule for {}: {}", image.path, e);
+                        return false;
+                    }
+                }
+            } else {
+                ImageMetadata::default()
+            };
+
+            if !rule.action.apply(&mut metadata) {
+                return false;
+            }
+
+            let written = serde_json::to_string_pretty(&metadata)
+                .map_err(|e| e.to_string())
+                .and_then(|json| write_sidecar_with_fallback(&app_handle, &sidecar_path, &json));
+            if let Err(e) = written {
+                log::warn!("Failed to apply rating rule to {}: {}", image.path, e);
+                return false;
+            }
+
+            if enable_xmp_sync {
+                if let Err(e) = sync_metadata_to_xmp(
+                    &source_path,
+                    &metadata,
+                    create_xmp_if_missing,
+                    xmp_sync_develop,
+                ) {
+                    log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                }
+            }
+            true
+        })
+        .count();
+
+    Ok(changed)
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct XmpSyncReport {
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+fn has_xmp_sidecar(source_path: &Path) -> bool {
+    source_path.with_extension("xmp").exists() || source_path.with_extension("XMP").exists()
+}
+
+fn count_xmp_results(results: impl ParallelIterator<Item = bool>) -> XmpSyncReport {
+    results
+        .fold(XmpSyncReport::default, |mut report, updated| {
+            if updated {
+                report.updated += 1;
+            } else {
+                report.skipped += 1;
+            }
+            report
+        })
+        .reduce(XmpSyncReport::default, |a, b| XmpSyncReport {
+            updated: a.updated + b.updated,
+            skipped: a.skipped + b.skipped,
+        })
+}
+
+/// Writes every edited image's sidecar metadata below `root` to its XMP file,
+/// regardless of whether XMP sync was enabled when the edits were made.
+#[tauri::command]
+pub async fn sync_all_to_xmp(
+    root: String,
+    create_if_missing: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<XmpSyncReport, String> {
+    let root_path = PathBuf::from(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = load_settings(app_handle).unwrap_or_default();
+        let create_if_missing =
+            create_if_missing.unwrap_or(settings.create_xmp_if_missing.unwrap_or(false));
+        let sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+
+        let sidecars: Vec<(PathBuf, PathBuf)> = WalkDir::new(&root_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let file_name = e.file_name().to_string_lossy();
+                match split_sidecar_file_name(&file_name)? {
+                    (source_filename, None) => Some((
+                        e.path().with_file_name(source_filename),
+                        e.path().to_path_buf(),
+                    )),
+                    (_, Some(_)) => None,
+                }
+            })
+            .collect();
+
+        Ok(count_xmp_results(sidecars.into_par_iter().map(
+            |(source_path, sidecar_path)| {
+                if !source_path.is_file() {
+                    return false;
+                }
+                let metadata = match fs::read_to_string(&sidecar_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        serde_json::from_str::<ImageMetadata>(&content).map_err(|e| e.to_string())
+                    }) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        log::warn!("Skipping XMP sync for {}: {}", sidecar_path.display(), e);
+                        return false;
+                    }
+                };
+                match sync_metadata_to_xmp(&source_path, &metadata, create_if_missing, sync_develop)
+                {
+                    Ok(written) => written,
+                    Err(e) => {
+                        log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                        false
+                    }
+                }
+            },
+        )))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Merges every XMP file below `root` into the matching sidecar, the same way
+/// listing does when XMP sync is enabled. `updated` counts sidecars that changed.
+#[tauri::command]
+pub async fn import_all_from_xmp(
+    root: String,
+    app_handle: AppHandle,
+) -> Result<XmpSyncReport, String> {
+    let root_path = PathBuf::from(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let import_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+
+        let sources: Vec<PathBuf> = WalkDir::new(&root_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file() && is_supported_image_file(e.path()))
+            .map(|e| e.into_path())
+            .filter(|path| has_xmp_sidecar(path))
+            .collect();
+
+        Ok(count_xmp_results(sources.into_par_iter().map(
+            |source_path| {
+                let (_, sidecar_path) = parse_virtual_path(&source_path.to_string_lossy());
+                let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+                let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                    match fs::read_to_string(&existing_sidecar_path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|content| {
+                            serde_json::from_str(&content).map_err(|e| e.to_string())
+                        }) {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            log::warn!("Skipping XMP import for {}: {}", source_path.display(), e);
+                            return false;
+                        }
+                    }
+                } else {
+                    ImageMetadata::default()
+                };
+
+                if !sync_metadata_from_xmp(&source_path, &mut metadata, import_develop, true) {
+                    return false;
+                }
+
+                let written = serde_json::to_string_pretty(&metadata)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| {
+                        write_sidecar_with_fallback(&app_handle, &sidecar_path, &json)
+                    });
+                if let Err(e) = written {
+                    log::warn!("Failed to import XMP for {}: {}", source_path.display(), e);
+                    return false;
+                }
+                true
+            },
+        )))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderNode {
+    pub name: String,
+    pub path: String,
+    pub children: Vec<FolderNode>,
+    pub is_dir: bool,
+    pub image_count: usize,
+    pub has_subdirs: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_config: Option<FolderConfig>,
+}
+
+fn read_folder_config(dir: &Path) -> Option<FolderConfig> {
+    let content = fs::read_to_string(dir.join(FOLDER_CONFIG_FILENAME)).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log::warn!(
+                "Ignoring invalid folder config in '{}': {}",
+                dir.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub fn load_folder_config(path: String) -> Result<FolderConfig, String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    Ok(read_folder_config(dir).unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn save_folder_config(path: String, config: FolderConfig) -> Result<(), String> {
+    let dir = Path::new(&path);
+    if !dir.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    let json_string = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(dir.join(FOLDER_CONFIG_FILENAME), json_string).map_err(|e| e.to_string())
+}
+
+fn has_subdirs(path: &Path) -> bool {
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.filter_map(Result::ok) {
+            if let Ok(file_type) = entry.file_type()
+                && file_type.is_dir()
+            {
+                let name = entry.file_name();
+                if !name.to_string_lossy().starts_with('.') {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+fn scan_dir_lazy(
+    path: &Path,
+    expanded_folders: &HashSet<&str>,
+    show_image_counts: bool,
+    prefetch_one_level: bool,
+) -> Result<(Vec<FolderNode>, usize), std::io::Error> {
+    let mut children_folders = Vec::new();
+    let mut current_dir_image_count = 0;
+
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Could not scan directory '{}': {}", path.display(), e);
+            return Ok((Vec::new(), 0));
+        }
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let current_path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(ft) => ft,
+            Err(_) => continue,
+        };
+
+        let file_name = entry.file_name();
+        let name_str = file_name.to_string_lossy();
+
+        if name_str.starts_with('.') {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            let path_str = current_path.to_string_lossy().into_owned();
+            let is_expanded = expanded_folders.contains(path_str.as_str());
+
+            let should_scan = is_expanded || prefetch_one_level;
+            let next_prefetch = is_expanded;
+
+            let (grand_children, sub_dir_own_images) = if should_scan {
+                scan_dir_lazy(
+                    &current_path,
+                    expanded_folders,
+                    show_image_counts,
+                    next_prefetch,
+                )?
+            } else {
+                let count = if show_image_counts {
+                    WalkDir::new(&current_path)
+                        .into_iter()
+                        .filter_map(Result::ok)
+                        .filter(|e| {
+                            e.file_type().is_file()
+                                && crate::formats::is_supported_image_file(e.path())
+                        })
+                        .count()
+                } else {
+                    0
+                };
+                (Vec::new(), count)
+            };
+
+            let has_any_subdirs = if should_scan {
+                grand_children.iter().any(|c| c.is_dir)
+            } else {
+                has_subdirs(&current_path)
+            };
+
+            let grand_children_sum: usize = grand_children.iter().map(|c| c.image_count).sum();
+            let total_child_count = sub_dir_own_images + grand_children_sum;
+
+            children_folders.push(FolderNode {
+                name: name_str.into_owned(),
+                path: path_str,
+                children: grand_children,
+                is_dir: true,
+                image_count: total_child_count,
+                has_subdirs: has_any_subdirs,
+                folder_config: if should_scan {
+                    read_folder_config(&current_path)
+                } else {
+                    None
+                },
             });
         } else if show_image_counts
             && file_type.is_file()
@@ -931,6 +2266,7 @@ fn get_folder_tree_sync(
         is_dir: true,
         image_count: own_count + children_sum,
         has_subdirs,
+        folder_config: read_folder_config(root_path),
     })
 }
 
@@ -958,14 +2294,160 @@ pub async fn get_folder_children(
     }
 }
 
-#[tauri::command]
-pub async fn get_folder_tree(
-    path: String,
-    expanded_folders: Vec<String>,
-    show_image_counts: bool,
-) -> Result<FolderNode, String> {
-    match tauri::async_runtime::spawn_blocking(move || {
-        get_folder_tree_sync(path, expanded_folders, show_image_counts)
+const THUMBNAIL_GEOMETRY_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(30).unwrap();
+
+/// Warped, downscaled thumbnail bases, one per path along with the geometry hash it was
+/// built for, evicting the least recently used path once full. Inserting a new geometry
+/// for a path replaces the old one, so editing one image can't push out the others.
+pub struct ThumbnailGeometryCache {
+    entries: LruCache<String, (u64, (DynamicImage, f32))>,
+}
+
+impl Default for ThumbnailGeometryCache {
+    fn default() -> Self {
+        Self {
+            entries: LruCache::new(THUMBNAIL_GEOMETRY_CACHE_CAPACITY),
+        }
+    }
+}
+
+impl ThumbnailGeometryCache {
+    pub fn get(&mut self, path: &str, geometry_hash: u64) -> Option<&(DynamicImage, f32)> {
+        self.entries
+            .get(path)
+            .filter(|(hash, _)| *hash == geometry_hash)
+            .map(|(_, base)| base)
+    }
+
+    pub fn insert(&mut self, path: String, geometry_hash: u64, image: DynamicImage, scale: f32) {
+        self.entries.put(path, (geometry_hash, (image, scale)));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Folder tree node whose `image_count` stays `None` until the frontend fills it
+/// in through `count_folder_images`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShallowFolderNode {
+    pub name: String,
+    pub path: String,
+    pub children: Vec<ShallowFolderNode>,
+    pub is_dir: bool,
+    pub image_count: Option<usize>,
+    pub has_subdirs: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_config: Option<FolderConfig>,
+}
+
+fn scan_dir_shallow(path: &Path, depth: u32) -> Vec<ShallowFolderNode> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Could not scan directory '{}': {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut children: Vec<ShallowFolderNode> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| {
+            let child_path = entry.path();
+            let expand = depth > 1;
+            let grand_children = if expand {
+                scan_dir_shallow(&child_path, depth - 1)
+            } else {
+                Vec::new()
+            };
+            ShallowFolderNode {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: child_path.to_string_lossy().into_owned(),
+                has_subdirs: if expand {
+                    !grand_children.is_empty()
+                } else {
+                    has_subdirs(&child_path)
+                },
+                children: grand_children,
+                is_dir: true,
+                image_count: None,
+                folder_config: if expand {
+                    read_folder_config(&child_path)
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+
+    children.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    children
+}
+
+#[tauri::command]
+pub async fn get_folder_tree_shallow(
+    path: String,
+    depth: u32,
+) -> Result<ShallowFolderNode, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root_path = Path::new(&path);
+        if !root_path.is_dir() {
+            return Err(format!("Directory does not exist: {}", path));
+        }
+
+        let children = scan_dir_shallow(root_path, depth.max(1));
+        Ok(ShallowFolderNode {
+            name: root_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            has_subdirs: !children.is_empty(),
+            path: path.clone(),
+            children,
+            is_dir: true,
+            image_count: None,
+            folder_config: read_folder_config(root_path),
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to execute folder tree task: {}", e))?
+}
+
+/// Counts supported images under `path`, skipping dot-directories like the tree scan does.
+#[tauri::command]
+pub async fn count_folder_images(path: String) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root_path = Path::new(&path);
+        if !root_path.is_dir() {
+            return Err(format!("Directory does not exist: {}", path));
+        }
+
+        Ok(WalkDir::new(root_path)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(Result::ok)
+            .filter(|e| {
+                e.file_type().is_file() && crate::formats::is_supported_image_file(e.path())
+            })
+            .count())
+    })
+    .await
+    .map_err(|e| format!("Failed to count folder images: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_folder_tree(
+    path: String,
+    expanded_folders: Vec<String>,
+    show_image_counts: bool,
+) -> Result<FolderNode, String> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        get_folder_tree_sync(path, expanded_folders, show_image_counts)
     })
     .await
     {
@@ -1036,6 +2518,7 @@ pub fn generate_thumbnail_data(
     app_handle: &AppHandle,
 ) -> anyhow::Result<DynamicImage> {
     let (source_path, sidecar_path) = parse_virtual_path(path_str);
+    let sidecar_path = resolve_sidecar_path(app_handle, &sidecar_path);
     let source_path_str = source_path.to_string_lossy().to_string();
     let is_raw = is_raw_file(&source_path_str);
 
@@ -1047,7 +2530,7 @@ pub fn generate_thumbnail_data(
         .as_ref()
         .map_or(serde_json::Value::Null, |m| m.adjustments.clone());
 
-    if let (Some(context), Some(meta)) = (gpu_context, metadata)
+    if let Some(meta) = metadata
         && !meta.adjustments.is_null()
     {
         let state = app_handle.state::<AppState>();
@@ -1060,8 +2543,8 @@ pub fn generate_thumbnail_data(
         let crop_data: Option<Crop> = serde_json::from_value(meta.adjustments["crop"].clone()).ok();
 
         let cached_base: Option<(DynamicImage, f32)> = {
-            let cache = state.thumbnail_geometry_cache.lock().unwrap();
-            if let Some((cached_hash, img, scale)) = cache.get(path_str) {
+            let mut cache = state.thumbnail_geometry_cache.lock().unwrap();
+            if let Some((img, scale)) = cache.get(path_str, geometry_hash) {
                 let mut sufficient_resolution = true;
                 if let Some(c) = &crop_data
                     && c.width > 0.0
@@ -1074,7 +2557,7 @@ pub fn generate_thumbnail_data(
                     }
                 }
 
-                if *cached_hash == geometry_hash && sufficient_resolution {
+                if sufficient_resolution {
                     Some((img.clone(), *scale))
                 } else {
                     None
@@ -1140,7 +2623,7 @@ pub fn generate_thumbnail_data(
                 img
             };
 
-            let warped_image = apply_geometry_warp(&composite_image, &meta.adjustments);
+            let warped_image = apply_geometry_warp_owned(composite_image, &meta.adjustments);
             let orientation_steps =
                 meta.adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
             let coarse_rotated_image = apply_coarse_rotation(warped_image, orientation_steps);
@@ -1179,13 +2662,11 @@ pub fn generate_thumbnail_data(
 
             let total_scale = gpu_scale * raw_scale_factor;
 
-            let mut cache = state.thumbnail_geometry_cache.lock().unwrap();
-            if cache.len() > 30 {
-                cache.clear();
-            }
-            cache.insert(
+            state.thumbnail_geometry_cache.lock().unwrap().insert(
                 path_str.to_string(),
-                (geometry_hash, base.clone(), total_scale),
+                geometry_hash,
+                base.clone(),
+                total_scale,
             );
 
             (base, total_scale)
@@ -1206,6 +2687,7 @@ pub fn generate_thumbnail_data(
                 y: c.y * total_scale as f64,
                 width: c.width * total_scale as f64,
                 height: c.height * total_scale as f64,
+                canvas_color: c.canvas_color,
             })
             .unwrap_or(serde_json::Value::Null)
         } else {
@@ -1241,28 +2723,27 @@ pub fn generate_thumbnail_data(
             .collect();
 
         let gpu_adjustments = get_all_adjustments_from_json(&meta.adjustments, is_raw);
-        let lut_path = meta.adjustments["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| {
-            let mut cache = state.lut_cache.lock().unwrap();
-            if let Some(cached_lut) = cache.get(p) {
-                return Some(cached_lut.clone());
-            }
-            if let Ok(loaded_lut) = crate::lut_processing::parse_lut_file(p) {
-                let arc_lut = Arc::new(loaded_lut);
-                cache.insert(p.to_string(), arc_lut.clone());
-                return Some(arc_lut);
-            }
-            None
-        });
+        let lut = crate::get_adjustments_lut(&state, &meta.adjustments)
+            .map_err(|e| anyhow::anyhow!(e))?;
 
         let mut hasher = DefaultHasher::new();
         path_str.hash(&mut hasher);
         meta.adjustments.to_string().hash(&mut hasher);
         let unique_hash = hasher.finish();
 
-        if let Ok(processed_image) = gpu_processing::process_and_get_dynamic_image(
+        let Some(context) = gpu_context else {
+            return Ok(process_image_cpu(
+                &cropped_preview,
+                &gpu_adjustments,
+                &mask_bitmaps,
+                lut.as_deref(),
+            ));
+        };
+
+        match gpu_processing::process_and_get_dynamic_image(
             context,
             &state,
+            app_handle,
             &cropped_preview,
             unique_hash,
             gpu_processing::RenderRequest {
@@ -1273,9 +2754,20 @@ pub fn generate_thumbnail_data(
             },
             "generate_thumbnail_data",
         ) {
-            return Ok(processed_image);
-        } else {
-            return Ok(cropped_preview);
+            Ok(processed_image) => return Ok(processed_image),
+            Err(e) => {
+                log::warn!(
+                    "GPU thumbnail processing failed for {}: {}. Using CPU fallback.",
+                    path_str,
+                    e
+                );
+                return Ok(process_image_cpu(
+                    &cropped_preview,
+                    &gpu_adjustments,
+                    &mask_bitmaps,
+                    lut.as_deref(),
+                ));
+            }
         }
     }
 
@@ -1312,25 +2804,85 @@ pub fn generate_thumbnail_data(
         }
     };
 
-    if is_raw && adjustments.is_null() {
+    if is_raw {
         apply_cpu_default_raw_processing(&mut final_image);
     }
 
-    let fallback_orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
-    Ok(apply_coarse_rotation(
-        final_image,
-        fallback_orientation_steps,
-    ))
+    Ok(final_image)
+}
+
+const DEFAULT_THUMBNAIL_QUALITY: u8 = 75;
+
+fn thumbnail_quality(settings: &AppSettings) -> u8 {
+    settings
+        .thumbnail_quality
+        .unwrap_or(DEFAULT_THUMBNAIL_QUALITY)
+        .clamp(50, 95)
+}
+
+pub const THUMBNAIL_PROTOCOL: &str = "thumbnail";
+
+fn uses_thumbnail_protocol(settings: &AppSettings) -> bool {
+    settings.thumbnail_delivery.as_deref() == Some("protocol")
+}
+
+fn thumbnail_protocol_url(cache_filename: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", THUMBNAIL_PROTOCOL, cache_filename)
+    } else {
+        format!("{}://localhost/{}", THUMBNAIL_PROTOCOL, cache_filename)
+    }
+}
+
+/// Resolves a `thumbnail://` request path to a file in the thumbnail cache. Only bare
+/// cache filenames are accepted so the protocol cannot reach outside the cache.
+pub fn resolve_thumbnail_protocol_path(
+    app_handle: &AppHandle,
+    request_path: &str,
+) -> Option<PathBuf> {
+    let filename = request_path.trim_start_matches('/');
+    let stem = filename.strip_suffix(".jpg")?;
+    if stem.is_empty() || !stem.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let path = resolve_thumbnail_cache_dir(app_handle).ok()?.join(filename);
+    path.is_file().then_some(path)
 }
 
-fn encode_thumbnail(image: &DynamicImage, target_width: u32) -> Result<Vec<u8>> {
+fn encode_thumbnail(image: &DynamicImage, target_width: u32, quality: u8) -> Result<Vec<u8>> {
     let thumbnail = crate::image_processing::downscale_f32_image(image, target_width, target_width);
     let mut buf = Cursor::new(Vec::new());
-    let mut encoder = JpegEncoder::new_with_quality(&mut buf, 75);
+    let mut encoder = JpegEncoder::new_with_quality(&mut buf, quality);
     encoder.encode_image(&thumbnail.to_rgb8())?;
     Ok(buf.into_inner())
 }
 
+const PLACEHOLDER_THUMBNAIL_SIZE: u32 = 64;
+const PLACEHOLDER_THUMBNAIL_QUALITY: u8 = 40;
+
+/// Tiny, unadjusted JPEG shown while the real thumbnail renders, built from a RAW's
+/// embedded preview. Other files would need the same full decode as the real
+/// thumbnail, so they get no placeholder. Nothing is written to the cache.
+fn generate_placeholder_thumbnail(path_str: &str) -> Option<String> {
+    let (source_path, _) = parse_virtual_path(path_str);
+    if !is_raw_file(&source_path.to_string_lossy()) {
+        return None;
+    }
+    let bytes = read_file_mapped(&source_path).ok()?;
+    let image = crate::raw_processing::extract_embedded_preview(&bytes).ok()?;
+
+    let data = encode_thumbnail(
+        &image,
+        PLACEHOLDER_THUMBNAIL_SIZE,
+        PLACEHOLDER_THUMBNAIL_QUALITY,
+    )
+    .ok()?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        general_purpose::STANDARD.encode(&data)
+    ))
+}
+
 fn generate_single_thumbnail_and_cache(
     path_str: &str,
     thumb_cache_dir: &Path,
@@ -1338,63 +2890,194 @@ fn generate_single_thumbnail_and_cache(
     preloaded_image: Option<&DynamicImage>,
     force_regenerate: bool,
     app_handle: &AppHandle,
+    settings: &AppSettings,
 ) -> Option<(String, u8)> {
-    let (source_path, sidecar_path) = parse_virtual_path(path_str);
+    let (_, sidecar_path) = parse_virtual_path(path_str);
+    let sidecar_path = resolve_sidecar_path(app_handle, &sidecar_path);
+    let quality = thumbnail_quality(settings);
 
-    let img_mod_time = fs::metadata(source_path)
-        .ok()?
-        .modified()
-        .ok()?
-        .duration_since(std::time::UNIX_EPOCH)
-        .ok()?
-        .as_secs();
+    let cache_filename = format!("{}.jpg", get_cache_key_hash(app_handle, path_str, quality)?);
+    let rating = fs::read_to_string(&sidecar_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ImageMetadata>(&content).ok())
+        .map(|m| m.rating)
+        .unwrap_or(0);
+    let cache_path = thumb_cache_dir.join(&cache_filename);
 
-    let (sidecar_mod_time, rating) = if let Ok(content) = fs::read_to_string(&sidecar_path) {
-        let mod_time = fs::metadata(&sidecar_path)
-            .ok()
-            .and_then(|m| m.modified().ok())
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        let rating_val = serde_json::from_str::<ImageMetadata>(&content)
-            .ok()
-            .map(|m| m.rating)
-            .unwrap_or(0);
-        (mod_time, rating_val)
-    } else {
-        (0, 0)
-    };
+    let state = app_handle.state::<AppState>();
+    let is_indexed = state
+        .thumbnail_cache_index
+        .lock()
+        .unwrap()
+        .contains(&cache_filename);
+    // The file can be removed behind the app's back (e.g. by an OS cache cleaner); a stale
+    // index entry would otherwise hand out protocol URLs that 404.
+    let is_cached = cache_path.exists();
+    if is_indexed && !is_cached {
+        state
+            .thumbnail_cache_index
+            .lock()
+            .unwrap()
+            .remove(&cache_filename);
+    }
 
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(path_str.as_bytes());
-    hasher.update(&img_mod_time.to_le_bytes());
-    hasher.update(&sidecar_mod_time.to_le_bytes());
-    let hash = hasher.finalize();
-    let cache_filename = format!("{}.jpg", hash.to_hex());
-    let cache_path = thumb_cache_dir.join(cache_filename);
+    let as_protocol_url = uses_thumbnail_protocol(settings);
 
-    if !force_regenerate
-        && cache_path.exists()
-        && let Ok(data) = fs::read(&cache_path)
-    {
-        let base64_str = general_purpose::STANDARD.encode(&data);
-        return Some((format!("data:image/jpeg;base64,{}", base64_str), rating));
+    if !force_regenerate && is_cached {
+        let payload = if as_protocol_url {
+            Some(thumbnail_protocol_url(&cache_filename))
+        } else {
+            fs::read(&cache_path).ok().map(|data| {
+                format!(
+                    "data:image/jpeg;base64,{}",
+                    general_purpose::STANDARD.encode(&data)
+                )
+            })
+        };
+        if let Some(payload) = payload {
+            if !is_indexed {
+                state
+                    .thumbnail_cache_index
+                    .lock()
+                    .unwrap()
+                    .insert(cache_filename);
+            }
+            return Some((payload, rating));
+        }
     }
 
-    let settings = crate::file_management::load_settings(app_handle.clone()).unwrap_or_default();
     let target_width = settings.thumbnail_resolution.unwrap_or(720);
 
     if let Ok(thumb_image) =
         generate_thumbnail_data(path_str, gpu_context, preloaded_image, app_handle)
-        && let Ok(thumb_data) = encode_thumbnail(&thumb_image, target_width)
+        && let Ok(thumb_data) = encode_thumbnail(&thumb_image, target_width, quality)
     {
-        let _ = fs::write(&cache_path, &thumb_data);
+        let written = write_cached_thumbnail(
+            &state,
+            thumb_cache_dir,
+            &cache_filename,
+            &thumb_data,
+            settings.thumbnail_cache_max_mb,
+        )
+        .is_ok();
+        if as_protocol_url && written {
+            return Some((thumbnail_protocol_url(&cache_filename), rating));
+        }
         let base64_str = general_purpose::STANDARD.encode(&thumb_data);
         return Some((format!("data:image/jpeg;base64,{}", base64_str), rating));
     }
     None
 }
 
+fn jpeg_cache_entries(cache_dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("jpg"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let last_used = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), metadata.len(), last_used))
+        })
+        .collect()
+}
+
+/// Writes a thumbnail into the cache, indexes it and evicts old entries when the
+/// cache grows past `max_mb`.
+fn write_cached_thumbnail(
+    state: &AppState,
+    thumb_cache_dir: &Path,
+    cache_filename: &str,
+    data: &[u8],
+    max_mb: Option<u32>,
+) -> std::io::Result<()> {
+    let cache_path = thumb_cache_dir.join(cache_filename);
+    let replaced_bytes = fs::metadata(&cache_path).map_or(0, |m| m.len());
+    fs::write(&cache_path, data)?;
+    state
+        .thumbnail_cache_index
+        .lock()
+        .unwrap()
+        .insert(cache_filename.to_string());
+    enforce_thumbnail_cache_limit(
+        state,
+        thumb_cache_dir,
+        data.len() as u64,
+        replaced_bytes,
+        max_mb,
+    );
+    Ok(())
+}
+
+fn enforce_thumbnail_cache_limit(
+    state: &AppState,
+    thumb_cache_dir: &Path,
+    written_bytes: u64,
+    replaced_bytes: u64,
+    max_mb: Option<u32>,
+) {
+    let Some(max_mb) = max_mb.filter(|mb| *mb > 0) else {
+        return;
+    };
+    let budget = max_mb as u64 * 1024 * 1024;
+
+    let mut size_lock = state.thumbnail_cache_size.lock().unwrap();
+    let mut total = match *size_lock {
+        Some(size) => (size + written_bytes).saturating_sub(replaced_bytes),
+        None => jpeg_cache_entries(thumb_cache_dir)
+            .iter()
+            .map(|(_, size, _)| size)
+            .sum(),
+    };
+
+    if total > budget {
+        let mut entries = jpeg_cache_entries(thumb_cache_dir);
+        total = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(_, _, last_used)| *last_used);
+
+        let mut evicted = 0;
+        for (path, size, _) in entries {
+            if total <= budget {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(size);
+                evicted += 1;
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    state.thumbnail_cache_index.lock().unwrap().remove(name);
+                }
+            }
+        }
+        log::info!(
+            "Evicted {} thumbnails to keep the cache under {} MB",
+            evicted,
+            max_mb
+        );
+    }
+
+    *size_lock = Some(total);
+}
+
+#[tauri::command]
+pub fn get_thumbnail_cache_size(app_handle: AppHandle) -> Result<u64, String> {
+    let thumb_cache_dir = resolve_thumbnail_cache_dir(&app_handle)?;
+    let total: u64 = jpeg_cache_entries(&thumb_cache_dir)
+        .iter()
+        .map(|(_, size, _)| size)
+        .sum();
+    *app_handle
+        .state::<AppState>()
+        .thumbnail_cache_size
+        .lock()
+        .unwrap() = Some(total);
+    Ok(total)
+}
+
 #[tauri::command]
 pub async fn generate_thumbnails(
     paths: Vec<String>,
@@ -1402,17 +3085,11 @@ pub async fn generate_thumbnails(
 ) -> Result<HashMap<String, String>, String> {
     let app_handle_clone = app_handle.clone();
     tauri::async_runtime::spawn_blocking(move || {
-        let cache_dir = app_handle_clone
-            .path()
-            .app_cache_dir()
-            .map_err(|e| e.to_string())?;
-        let thumb_cache_dir = cache_dir.join("thumbnails");
-        if !thumb_cache_dir.exists() {
-            fs::create_dir_all(&thumb_cache_dir).map_err(|e| e.to_string())?;
-        }
+        let thumb_cache_dir = resolve_thumbnail_cache_dir(&app_handle_clone)?;
 
         let state = app_handle_clone.state::<AppState>();
-        let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle_clone).ok();
+        let settings = load_settings(app_handle_clone.clone()).unwrap_or_default();
 
         let thumbnails: HashMap<String, String> = paths
             .par_iter()
@@ -1424,6 +3101,7 @@ pub async fn generate_thumbnails(
                     None,
                     false,
                     &app_handle_clone,
+                    &settings,
                 )
                 .map(|(data, _rating)| (path_str.clone(), data))
             })
@@ -1435,6 +3113,27 @@ pub async fn generate_thumbnails(
     .map_err(|e| e.to_string())?
 }
 
+/// Blocks thumbnail workers while generation is paused instead of polling.
+#[derive(Default)]
+pub struct ThumbnailPauseGate {
+    paused: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl ThumbnailPauseGate {
+    pub fn set_paused(&self, paused: bool) {
+        *self.paused.lock().unwrap() = paused;
+        self.changed.notify_all();
+    }
+
+    fn wait_while_paused(&self, cancellation_token: &AtomicBool) {
+        let mut paused = self.paused.lock().unwrap();
+        while *paused && !cancellation_token.load(Ordering::Relaxed) {
+            paused = self.changed.wait(paused).unwrap();
+        }
+    }
+}
+
 #[tauri::command]
 pub fn generate_thumbnails_progressive(
     paths: Vec<String>,
@@ -1448,6 +3147,8 @@ pub fn generate_thumbnails_progressive(
         .thumbnail_cancellation_token
         .store(false, Ordering::SeqCst);
     let cancellation_token = state.thumbnail_cancellation_token.clone();
+    let pause_gate = state.thumbnail_pause_gate.clone();
+    state.thumbnail_canceled_paths.lock().unwrap().clear();
 
     const MAX_THUMBNAIL_THREADS: usize = 6;
     let num_threads = (num_cpus::get_physical().saturating_sub(1)).clamp(1, MAX_THUMBNAIL_THREADS);
@@ -1456,25 +3157,39 @@ pub fn generate_thumbnails_progressive(
         .num_threads(num_threads)
         .build()
         .unwrap();
-    let cache_dir = app_handle
-        .path()
-        .app_cache_dir()
-        .map_err(|e| e.to_string())?;
-    let thumb_cache_dir = cache_dir.join("thumbnails");
-    if !thumb_cache_dir.exists() {
-        fs::create_dir_all(&thumb_cache_dir).map_err(|e| e.to_string())?;
-    }
+    let thumb_cache_dir = resolve_thumbnail_cache_dir(&app_handle)?;
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let progressive = settings.enable_progressive_thumbnails.unwrap_or(false);
+    let quality = thumbnail_quality(&settings);
 
     let app_handle_clone = app_handle.clone();
 
     pool.spawn(move || {
         let state = app_handle_clone.state::<AppState>();
-        let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle_clone).ok();
 
         let _ = paths.par_iter().try_for_each(|path_str| -> Result<(), ()> {
+            pause_gate.wait_while_paused(&cancellation_token);
             if cancellation_token.load(Ordering::Relaxed) {
                 return Err(());
             }
+            if state.thumbnail_canceled_paths.lock().unwrap().remove(path_str) {
+                increment_thumbnail_progress(&state, &app_handle_clone);
+                return Ok(());
+            }
+
+            if progressive {
+                let is_cached = get_cache_key_hash(&app_handle_clone, path_str, quality)
+                    .is_some_and(|hash| thumb_cache_dir.join(format!("{}.jpg", hash)).exists());
+                if !is_cached
+                    && let Some(placeholder) = generate_placeholder_thumbnail(path_str)
+                {
+                    let _ = app_handle_clone.emit(
+                        "thumbnail-generated",
+                        serde_json::json!({ "path": path_str, "data": placeholder, "quality": "low" }),
+                    );
+                }
+            }
 
             let result = generate_single_thumbnail_and_cache(
                 path_str,
@@ -1483,6 +3198,7 @@ pub fn generate_thumbnails_progressive(
                 None,
                 false,
                 &app_handle_clone,
+                &settings,
             );
 
             if let Some((thumbnail_data, rating)) = result {
@@ -1491,7 +3207,7 @@ pub fn generate_thumbnails_progressive(
                 }
                 let _ = app_handle_clone.emit(
                     "thumbnail-generated",
-                    serde_json::json!({ "path": path_str, "data": thumbnail_data, "rating": rating }),
+                    serde_json::json!({ "path": path_str, "data": thumbnail_data, "rating": rating, "quality": "high" }),
                 );
             }
 
@@ -1544,12 +3260,24 @@ pub fn increment_thumbnail_progress(state: &AppState, app_handle: &AppHandle) {
     }
 }
 
+fn is_case_insensitive_fs(dir: &Path) -> bool {
+    let probe_name = format!(".rapidraw_case_probe_{}", Uuid::new_v4().simple());
+    let probe_path = dir.join(&probe_name);
+    if fs::write(&probe_path, []).is_err() {
+        return cfg!(any(target_os = "windows", target_os = "macos"));
+    }
+    let is_insensitive = dir.join(probe_name.to_uppercase()).exists();
+    let _ = fs::remove_file(&probe_path);
+    is_insensitive
+}
+
 #[tauri::command]
 pub fn create_folder(path: String) -> Result<(), String> {
     let path_obj = Path::new(&path);
     if let (Some(parent), Some(new_folder_name_os)) = (path_obj.parent(), path_obj.file_name())
         && let Some(new_folder_name) = new_folder_name_os.to_str()
         && parent.exists()
+        && is_case_insensitive_fs(parent)
     {
         for entry in fs::read_dir(parent).map_err(|e| e.to_string())? {
             if let Ok(entry) = entry
@@ -1560,6 +3288,9 @@ pub fn create_folder(path: String) -> Result<(), String> {
             }
         }
     }
+    if path_obj.exists() {
+        return Err("A folder with that name already exists.".to_string());
+    }
     fs::create_dir_all(&path).map_err(|e| e.to_string())
 }
 
@@ -1570,13 +3301,17 @@ pub fn rename_folder(path: String, new_name: String) -> Result<(), String> {
         return Err("Path is not a directory.".to_string());
     }
     if let Some(parent) = p.parent() {
-        for entry in fs::read_dir(parent).map_err(|e| e.to_string())? {
-            if let Ok(entry) = entry
-                && entry.file_name().to_string_lossy().to_lowercase() == new_name.to_lowercase()
-                && entry.path() != p
-            {
-                return Err("A folder with that name already exists.".to_string());
+        if is_case_insensitive_fs(parent) {
+            for entry in fs::read_dir(parent).map_err(|e| e.to_string())? {
+                if let Ok(entry) = entry
+                    && entry.file_name().to_string_lossy().to_lowercase() == new_name.to_lowercase()
+                    && entry.path() != p
+                {
+                    return Err("A folder with that name already exists.".to_string());
+                }
             }
+        } else if parent.join(&new_name).exists() {
+            return Err("A folder with that name already exists.".to_string());
         }
         let new_path = parent.join(&new_name);
         fs::rename(p, new_path).map_err(|e| e.to_string())
@@ -1585,47 +3320,192 @@ pub fn rename_folder(path: String, new_name: String) -> Result<(), String> {
     }
 }
 
-#[tauri::command]
-pub fn delete_folder(path: String) -> Result<(), String> {
-    if let Err(trash_error) = trash::delete(&path) {
-        log::warn!(
-            "Failed to move folder to trash: {}. Falling back to permanent delete.",
-            trash_error
-        );
-        fs::remove_dir_all(&path).map_err(|e| e.to_string())
-    } else {
-        Ok(())
-    }
+/// A file a move wrote at its destination, removed again once its original is restored.
+#[derive(Debug, Clone)]
+pub struct MovedCopy {
+    pub original: PathBuf,
+    pub copy: PathBuf,
+    pub modified: Option<SystemTime>,
 }
 
-#[tauri::command]
-pub fn duplicate_file(path: String) -> Result<(), String> {
-    let (source_path, source_sidecar_path) = parse_virtual_path(&path);
-    if !source_path.is_file() {
-        return Err("Source path is not a file.".to_string());
+impl MovedCopy {
+    fn new(original: &Path, copy: &Path) -> Self {
+        Self {
+            original: original.to_path_buf(),
+            copy: copy.to_path_buf(),
+            modified: fs::metadata(copy).and_then(|m| m.modified()).ok(),
+        }
     }
 
-    let parent = source_path
-        .parent()
-        .ok_or("Could not get parent directory")?;
-    let stem = source_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or("Could not get file stem")?;
-    let extension = source_path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
+    /// Whether the copy was changed after the move, e.g. by editing the moved image.
+    fn edited_since_move(&self) -> bool {
+        let current = fs::metadata(&self.copy).and_then(|m| m.modified()).ok();
+        self.modified.is_none() || current != self.modified
+    }
+}
 
-    let mut counter = 1;
-    let mut dest_path;
-    loop {
-        let new_stem = if counter == 1 {
-            format!("{}_copy", stem)
-        } else {
-            format!("{}_copy_{}", stem, counter - 1)
-        };
-        dest_path = parent.join(format!("{}.{}", new_stem, extension));
+/// The most recent batch sent to the OS trash, kept so it can be undone.
+#[derive(Debug, Clone, Default)]
+pub struct TrashOperation {
+    pub trashed: Vec<PathBuf>,
+    pub created: Vec<MovedCopy>,
+}
+
+fn canonical_or_self(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn trash_and_record(
+    state: &AppState,
+    paths: &[PathBuf],
+    created: Vec<MovedCopy>,
+) -> Result<(), trash::Error> {
+    invalidate_tag_index(state);
+    let trashed: Vec<PathBuf> = paths.iter().map(|p| canonical_or_self(p)).collect();
+    let created = created
+        .into_iter()
+        .map(|moved| MovedCopy {
+            original: canonical_or_self(&moved.original),
+            ..moved
+        })
+        .collect();
+    trash::delete_all(paths)?;
+    *state.last_trash_operation.lock().unwrap() = Some(TrashOperation { trashed, created });
+    Ok(())
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn restore_from_trash(paths: &[PathBuf]) -> Result<Vec<String>, String> {
+    let wanted: HashSet<PathBuf> = paths.iter().cloned().collect();
+    let mut items: Vec<trash::TrashItem> = trash::os_limited::list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| wanted.contains(&item.original_path()))
+        .collect();
+
+    items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(item.original_path()));
+    if items.is_empty() {
+        return Err("The trashed files are no longer in the system trash.".to_string());
+    }
+    if items.len() < wanted.len() {
+        return Err(format!(
+            "Only {} of {} trashed files are still in the system trash, nothing was restored.",
+            items.len(),
+            wanted.len()
+        ));
+    }
+
+    let restored = items
+        .iter()
+        .map(|item| item.original_path().to_string_lossy().into_owned())
+        .collect();
+    trash::os_limited::restore_all(items).map_err(|e| e.to_string())?;
+    Ok(restored)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn restore_from_trash(_paths: &[PathBuf]) -> Result<Vec<String>, String> {
+    Err("Restoring from the trash is not supported on this platform.".to_string())
+}
+
+#[tauri::command]
+pub fn restore_last_trash_operation(state: tauri::State<AppState>) -> Result<Vec<String>, String> {
+    let operation = state
+        .last_trash_operation
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("There is no file operation to undo.")?;
+
+    let restored = restore_from_trash(&operation.trashed)?;
+    invalidate_tag_index(&state);
+
+    // A copy is only redundant once its original is back; anything else may be the
+    // user's only copy of the file.
+    let (restored_copies, pending_copies): (Vec<MovedCopy>, Vec<MovedCopy>) = operation
+        .created
+        .into_iter()
+        .partition(|moved| moved.original.exists());
+    for moved in restored_copies {
+        if moved.edited_since_move() {
+            log::warn!(
+                "Keeping moved copy {} while undoing, it was changed after the move",
+                moved.copy.display()
+            );
+        } else if let Err(e) = fs::remove_file(&moved.copy) {
+            log::warn!(
+                "Could not remove moved copy {} while undoing: {}",
+                moved.copy.display(),
+                e
+            );
+        }
+    }
+
+    let missing: Vec<PathBuf> = operation
+        .trashed
+        .into_iter()
+        .filter(|path| !path.exists())
+        .collect();
+    if !missing.is_empty() {
+        let message = format!(
+            "{} files could not be restored from the trash, kept the undo record.",
+            missing.len()
+        );
+        *state.last_trash_operation.lock().unwrap() = Some(TrashOperation {
+            trashed: missing,
+            created: pending_copies,
+        });
+        return Err(message);
+    }
+
+    *state.last_trash_operation.lock().unwrap() = None;
+    Ok(restored)
+}
+
+#[tauri::command]
+pub fn delete_folder(path: String, state: tauri::State<AppState>) -> Result<(), String> {
+    if let Err(trash_error) = trash_and_record(&state, &[PathBuf::from(&path)], Vec::new()) {
+        log::warn!(
+            "Failed to move folder to trash: {}. Falling back to permanent delete.",
+            trash_error
+        );
+        fs::remove_dir_all(&path).map_err(|e| e.to_string())
+    } else {
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn duplicate_file(path: String, app_handle: AppHandle) -> Result<(), String> {
+    let (source_path, source_sidecar_path) = parse_virtual_path(&path);
+    let source_sidecar_path = resolve_sidecar_path(&app_handle, &source_sidecar_path);
+    if !source_path.is_file() {
+        return Err("Source path is not a file.".to_string());
+    }
+
+    let parent = source_path
+        .parent()
+        .ok_or("Could not get parent directory")?;
+    let stem = source_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Could not get file stem")?;
+    let extension = source_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+
+    let mut counter = 1;
+    let mut dest_path;
+    loop {
+        let new_stem = if counter == 1 {
+            format!("{}_copy", stem)
+        } else {
+            format!("{}_copy_{}", stem, counter - 1)
+        };
+        dest_path = parent.join(format!("{}.{}", new_stem, extension));
         if !dest_path.exists() {
             break;
         }
@@ -1638,13 +3518,30 @@ pub fn duplicate_file(path: String) -> Result<(), String> {
         && let Some(dest_str) = dest_path.to_str()
     {
         let (_, dest_sidecar_path) = parse_virtual_path(dest_str);
-        fs::copy(&source_sidecar_path, &dest_sidecar_path).map_err(|e| e.to_string())?;
+        let contents = fs::read_to_string(&source_sidecar_path).map_err(|e| e.to_string())?;
+        write_sidecar_with_fallback(&app_handle, &dest_sidecar_path, &contents)?;
     }
 
     Ok(())
 }
 
-fn find_all_associated_files(source_image_path: &Path) -> Result<Vec<PathBuf>, String> {
+fn has_associated_extension(file_name: &str, extensions: &HashSet<String>) -> bool {
+    Path::new(file_name)
+        .extension()
+        .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+}
+
+/// Companions are `<image name>.*.<ext>` (sidecars, virtual copies, `IMG.CR2.xmp`)
+/// or `<image stem>.<ext>` (`IMG.xmp`, `IMG.wav`) for any configured extension.
+///
+/// A stem companion is shared by every image with that stem (a RAW+JPEG pair shares
+/// `IMG.xmp`), so it only goes along when all of those images are in `batch`, and then
+/// only with the first of them.
+fn find_all_associated_files(
+    source_image_path: &Path,
+    extensions: &HashSet<String>,
+    batch: &HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, String> {
     let mut associated_files = vec![source_image_path.to_path_buf()];
 
     let parent_dir = source_image_path
@@ -1654,34 +3551,74 @@ fn find_all_associated_files(source_image_path: &Path) -> Result<Vec<PathBuf>, S
         .file_name()
         .ok_or("Could not get source filename")?
         .to_string_lossy();
+    let source_stem = source_image_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
 
-    let primary_sidecar_name = format!("{}.rrdata", source_filename);
-    let virtual_copy_prefix = format!("{}.", source_filename);
+    let full_name_prefix = format!("{}.", source_filename);
+    let mut stem_companions = Vec::new();
+    let mut owns_stem_companions = true;
 
     if let Ok(entries) = fs::read_dir(parent_dir) {
         for entry in entries.filter_map(Result::ok) {
             let entry_path = entry.path();
-            if !entry_path.is_file() {
+            if !entry_path.is_file() || entry_path == source_image_path {
                 continue;
             }
 
             let entry_os_filename = entry.file_name();
             let entry_filename = entry_os_filename.to_string_lossy();
+            let shares_stem = entry_path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy() == source_stem);
 
-            if entry_filename == primary_sidecar_name
-                || (entry_filename.starts_with(&virtual_copy_prefix)
-                    && entry_filename.ends_with(".rrdata"))
-            {
+            if !has_associated_extension(&entry_filename, extensions) {
+                if shares_stem && is_supported_image_file(&entry_path) {
+                    owns_stem_companions &=
+                        batch.contains(&entry_path) && source_image_path < entry_path.as_path();
+                }
+                continue;
+            }
+
+            if entry_filename.starts_with(&full_name_prefix) {
                 associated_files.push(entry_path);
+            } else if shares_stem {
+                stem_companions.push(entry_path);
             }
         }
     }
 
+    if owns_stem_companions {
+        associated_files.extend(stem_companions);
+    }
     Ok(associated_files)
 }
 
+/// Renames a companion of `source_name` so it follows the image to `new_name`,
+/// whether it is keyed on the full file name or only on the stem.
+fn companion_file_name(file_name: &str, source_name: &str, new_name: &str) -> String {
+    if let Some(rest) = file_name.strip_prefix(source_name) {
+        return format!("{}{}", new_name, rest);
+    }
+    let stem = |name: &str| {
+        Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    match file_name.strip_prefix(&stem(source_name)) {
+        Some(rest) => format!("{}{}", stem(new_name), rest),
+        None => file_name.to_string(),
+    }
+}
+
 #[tauri::command]
-pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Result<(), String> {
+pub fn copy_files(
+    source_paths: Vec<String>,
+    destination_folder: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
     let dest_path = Path::new(&destination_folder);
     if !dest_path.is_dir() {
         return Err(format!(
@@ -1695,8 +3632,11 @@ pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Resu
         .map(|p| parse_virtual_path(p).0)
         .collect();
 
-    for source_image_path in unique_source_images {
-        let all_files_to_copy = find_all_associated_files(&source_image_path)?;
+    let extensions = associated_extensions(&app_handle);
+    invalidate_tag_index(&app_handle.state::<AppState>());
+    for source_image_path in &unique_source_images {
+        let all_files_to_copy =
+            find_all_associated_files(source_image_path, &extensions, &unique_source_images)?;
 
         let source_parent = source_image_path
             .parent()
@@ -1725,17 +3665,22 @@ pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Resu
             for original_file in all_files_to_copy {
                 let original_full_filename = original_file.file_name().unwrap().to_string_lossy();
                 let source_base_filename = source_image_path.file_name().unwrap().to_string_lossy();
-                let new_dest_filename =
-                    original_full_filename.replacen(&*source_base_filename, &new_filename, 1);
+                let new_dest_filename = companion_file_name(
+                    &original_full_filename,
+                    &source_base_filename,
+                    &new_filename,
+                );
                 let final_dest_path = dest_path.join(new_dest_filename);
 
-                fs::copy(&original_file, &final_dest_path).map_err(|e| e.to_string())?;
+                copy_preserving_timestamps(&original_file, &final_dest_path, None)
+                    .map_err(|e| e.to_string())?;
             }
         } else {
             for file_to_copy in all_files_to_copy {
                 if let Some(file_name) = file_to_copy.file_name() {
                     let dest_file_path = dest_path.join(file_name);
-                    fs::copy(&file_to_copy, &dest_file_path).map_err(|e| e.to_string())?;
+                    copy_preserving_timestamps(&file_to_copy, &dest_file_path, None)
+                        .map_err(|e| e.to_string())?;
                 }
             }
         }
@@ -1743,8 +3688,105 @@ pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Resu
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MoveConflictPolicy {
+    Skip,
+    Rename,
+    Error,
+}
+
+impl MoveConflictPolicy {
+    fn from_name(name: Option<&str>) -> Result<Self, String> {
+        match name.unwrap_or("error") {
+            "skip" => Ok(Self::Skip),
+            "rename" => Ok(Self::Rename),
+            "error" => Ok(Self::Error),
+            other => Err(format!("Unknown conflict policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedFile {
+    pub source: String,
+    pub destination: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveFilesReport {
+    pub moved: Vec<MovedFile>,
+    pub renamed: Vec<MovedFile>,
+    pub skipped: Vec<String>,
+}
+
+/// Maps every associated file onto `dest_dir`, swapping the image's file name for
+/// `new_filename` so sidecars and virtual copies follow a renamed image.
+fn destination_paths(
+    source_image_path: &Path,
+    files: &[PathBuf],
+    dest_dir: &Path,
+    new_filename: &str,
+) -> Vec<PathBuf> {
+    let source_filename = source_image_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    files
+        .iter()
+        .filter_map(|file| {
+            let file_name = file.file_name()?.to_string_lossy();
+            Some(dest_dir.join(companion_file_name(
+                &file_name,
+                &source_filename,
+                new_filename,
+            )))
+        })
+        .collect()
+}
+
+fn first_free_renamed_destination(
+    source_image_path: &Path,
+    files: &[PathBuf],
+    dest_dir: &Path,
+    planned: &HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, String> {
+    let stem = source_image_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Could not get file stem")?;
+    let extension = source_image_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+
+    let mut counter = 1;
+    loop {
+        let new_filename = format!("{}_{}.{}", stem, counter, extension);
+        let destinations = destination_paths(source_image_path, files, dest_dir, &new_filename);
+        if !destinations.iter().any(|p| destination_taken(p, planned)) {
+            return Ok(destinations);
+        }
+        counter += 1;
+    }
+}
+
+/// A destination is taken when it exists on disk or another file in the same batch is
+/// already headed there.
+fn destination_taken(path: &Path, planned: &HashSet<PathBuf>) -> bool {
+    planned.contains(path) || path.exists()
+}
+
 #[tauri::command]
-pub fn move_files(source_paths: Vec<String>, destination_folder: String) -> Result<(), String> {
+pub fn move_files(
+    source_paths: Vec<String>,
+    destination_folder: String,
+    on_conflict: Option<String>,
+    state: tauri::State<AppState>,
+    app_handle: AppHandle,
+) -> Result<MoveFilesReport, String> {
+    let policy = MoveConflictPolicy::from_name(on_conflict.as_deref())?;
     let dest_path = Path::new(&destination_folder);
     if !dest_path.is_dir() {
         return Err(format!(
@@ -1758,9 +3800,10 @@ pub fn move_files(source_paths: Vec<String>, destination_folder: String) -> Resu
         .map(|p| parse_virtual_path(p).0)
         .collect();
 
-    let mut all_files_to_trash = Vec::new();
-
-    for source_image_path in unique_source_images {
+    let extensions = associated_extensions(&app_handle);
+    let mut planned_moves = Vec::new();
+    let mut planned_destinations: HashSet<PathBuf> = HashSet::new();
+    for source_image_path in unique_source_images.iter().cloned() {
         let source_parent = source_image_path
             .parent()
             .ok_or("Could not get parent directory")?;
@@ -1768,31 +3811,78 @@ pub fn move_files(source_paths: Vec<String>, destination_folder: String) -> Resu
             return Err("Cannot move files into the same folder they are already in.".to_string());
         }
 
-        let files_to_move = find_all_associated_files(&source_image_path)?;
+        let files_to_move =
+            find_all_associated_files(&source_image_path, &extensions, &unique_source_images)?;
+        let file_name = source_image_path
+            .file_name()
+            .ok_or("Could not get source filename")?
+            .to_string_lossy()
+            .into_owned();
+        let destinations =
+            destination_paths(&source_image_path, &files_to_move, dest_path, &file_name);
 
-        for file_to_move in &files_to_move {
-            if let Some(file_name) = file_to_move.file_name() {
-                let dest_file_path = dest_path.join(file_name);
-                if dest_file_path.exists() {
+        if let Some(existing) = destinations
+            .iter()
+            .find(|p| destination_taken(p, &planned_destinations))
+        {
+            match policy {
+                MoveConflictPolicy::Error => {
                     return Err(format!(
                         "File already exists at destination: {}",
-                        dest_file_path.display()
+                        existing.display()
                     ));
                 }
+                MoveConflictPolicy::Skip => {
+                    planned_moves.push((source_image_path, files_to_move, None, false));
+                }
+                MoveConflictPolicy::Rename => {
+                    let renamed = first_free_renamed_destination(
+                        &source_image_path,
+                        &files_to_move,
+                        dest_path,
+                        &planned_destinations,
+                    )?;
+                    planned_destinations.extend(renamed.iter().cloned());
+                    planned_moves.push((source_image_path, files_to_move, Some(renamed), true));
+                }
             }
+        } else {
+            planned_destinations.extend(destinations.iter().cloned());
+            planned_moves.push((source_image_path, files_to_move, Some(destinations), false));
         }
+    }
 
-        for file_to_move in &files_to_move {
-            if let Some(file_name) = file_to_move.file_name() {
-                let dest_file_path = dest_path.join(file_name);
-                fs::copy(file_to_move, &dest_file_path).map_err(|e| e.to_string())?;
-            }
+    let mut report = MoveFilesReport::default();
+    let mut all_files_to_trash = Vec::new();
+    let mut created_files = Vec::new();
+
+    for (source_image_path, files_to_move, destinations, was_renamed) in planned_moves {
+        let source = source_image_path.to_string_lossy().into_owned();
+        let Some(destinations) = destinations else {
+            report.skipped.push(source);
+            continue;
+        };
+
+        for (file_to_move, dest_file_path) in files_to_move.iter().zip(&destinations) {
+            copy_preserving_timestamps(file_to_move, dest_file_path, None)
+                .map_err(|e| e.to_string())?;
+            created_files.push(MovedCopy::new(file_to_move, dest_file_path));
+        }
+
+        let moved = MovedFile {
+            source,
+            destination: destinations[0].to_string_lossy().into_owned(),
+        };
+        if was_renamed {
+            report.renamed.push(moved);
+        } else {
+            report.moved.push(moved);
         }
         all_files_to_trash.extend(files_to_move);
     }
 
     if !all_files_to_trash.is_empty()
-        && let Err(trash_error) = trash::delete_all(&all_files_to_trash)
+        && let Err(trash_error) = trash_and_record(&state, &all_files_to_trash, created_files)
     {
         log::warn!(
             "Failed to move source files to trash: {}. Falling back to permanent delete.",
@@ -1807,7 +3897,7 @@ pub fn move_files(source_paths: Vec<String>, destination_folder: String) -> Resu
         }
     }
 
-    Ok(())
+    Ok(report)
 }
 
 #[tauri::command]
@@ -1817,10 +3907,11 @@ pub fn save_metadata_and_update_thumbnail(
     app_handle: AppHandle,
     state: tauri::State<AppState>,
 ) -> Result<(), String> {
-    let (source_path, sidecar_path) = parse_virtual_path(&path);
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
 
-    let mut metadata: ImageMetadata = if sidecar_path.exists() {
-        fs::read_to_string(&sidecar_path)
+    let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+        fs::read_to_string(&existing_sidecar_path)
             .ok()
             .and_then(|content| serde_json::from_str(&content).ok())
             .unwrap_or_default()
@@ -1829,99 +3920,267 @@ pub fn save_metadata_and_update_thumbnail(
     };
 
     metadata.rating = adjustments["rating"].as_u64().unwrap_or(0) as u8;
+    metadata.record_history(&adjustments);
     metadata.adjustments = adjustments;
 
-    let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
-    std::fs::write(&sidecar_path, json_string).map_err(|e| e.to_string())?;
+    persist_edited_metadata(&path, &metadata, &app_handle, &state)
+}
+
+pub fn persist_edited_metadata(
+    path: &str,
+    metadata: &ImageMetadata,
+    app_handle: &AppHandle,
+    state: &tauri::State<AppState>,
+) -> Result<(), String> {
+    let (source_path, sidecar_path) = parse_virtual_path(path);
+    let json_string = serde_json::to_string_pretty(metadata).map_err(|e| e.to_string())?;
+    write_sidecar_with_fallback(app_handle, &sidecar_path, &json_string)?;
 
     if let Ok(settings) = load_settings(app_handle.clone())
         && settings.enable_xmp_sync.unwrap_or(false)
     {
         let create_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
-        sync_metadata_to_xmp(&source_path, &metadata, create_if_missing);
+        let sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+        if let Err(e) =
+            sync_metadata_to_xmp(&source_path, metadata, create_if_missing, sync_develop)
+        {
+            log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+        }
     }
 
-    let loaded_image_lock = state.original_image.lock().unwrap();
-    let preloaded_image_option = if let Some(loaded_image) = loaded_image_lock.as_ref() {
-        if loaded_image.path == path {
-            Some(loaded_image.image.clone())
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-    drop(loaded_image_lock);
+    add_to_thumbnail_queue(state, 1, app_handle);
+    let sent = state
+        .thumbnail_regen_tx
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|tx| tx.send(path.to_string()).is_ok());
+    if !sent {
+        increment_thumbnail_progress(state, app_handle);
+    }
 
-    let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
-    let app_handle_clone = app_handle.clone();
-    let path_clone = path.clone();
+    Ok(())
+}
 
-    add_to_thumbnail_queue(&state, 1, &app_handle);
+const THUMBNAIL_REGEN_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(250);
 
-    thread::spawn(move || {
-        let state = app_handle_clone.state::<AppState>();
+/// Regenerates edited thumbnails on one thread. A path is rendered once it has gone
+/// `THUMBNAIL_REGEN_DEBOUNCE` without another save, so rapid saves collapse into a
+/// single render of the latest sidecar and renders never overlap.
+pub fn start_thumbnail_regen_worker(app_handle: AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let (tx, rx) = mpsc::channel::<String>();
+    *state.thumbnail_regen_tx.lock().unwrap() = Some(tx);
 
-        let thumb_cache_dir = match resolve_thumbnail_cache_dir(&app_handle_clone) {
-            Ok(dir) => dir,
-            Err(e) => {
-                log::warn!(
-                    "Unable to initialize thumbnail cache directory for '{}': {}",
-                    path_clone,
-                    e
-                );
-                emit_thumbnail_cache_setup_error(&app_handle_clone, &path_clone, &e);
-                increment_thumbnail_progress(&state, &app_handle_clone);
-                return;
+    thread::spawn(move || {
+        let state = app_handle.state::<AppState>();
+        let mut pending: HashMap<String, Instant> = HashMap::new();
+        loop {
+            let next_due = pending.values().min().copied();
+            let received = match next_due {
+                None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+                Some(due) => rx.recv_timeout(due.saturating_duration_since(Instant::now())),
+            };
+            match received {
+                Ok(path) => {
+                    let due = Instant::now() + THUMBNAIL_REGEN_DEBOUNCE;
+                    if pending.insert(path, due).is_some() {
+                        increment_thumbnail_progress(&state, &app_handle);
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    let due_paths: Vec<String> = pending
+                        .iter()
+                        .filter(|(_, due)| **due <= now)
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in due_paths {
+                        pending.remove(&path);
+                        regenerate_edited_thumbnail(&state, &app_handle, &path);
+                        increment_thumbnail_progress(&state, &app_handle);
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
             }
-        };
-
-        let result = generate_single_thumbnail_and_cache(
-            &path_clone,
-            &thumb_cache_dir,
-            gpu_context.as_ref(),
-            preloaded_image_option.as_deref(),
-            true,
-            &app_handle_clone,
-        );
+        }
+    });
+}
 
-        if let Some((thumbnail_data, rating)) = result {
-            let _ = app_handle_clone.emit(
-                "thumbnail-generated",
-                serde_json::json!({ "path": path_clone, "data": thumbnail_data, "rating": rating }),
+fn regenerate_edited_thumbnail(state: &tauri::State<AppState>, app_handle: &AppHandle, path: &str) {
+    let thumb_cache_dir = match resolve_thumbnail_cache_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!(
+                "Unable to initialize thumbnail cache directory for '{}': {}",
+                path,
+                e
             );
+            emit_thumbnail_cache_setup_error(app_handle, path, &e);
+            return;
         }
+    };
 
-        increment_thumbnail_progress(&state, &app_handle_clone);
-    });
+    let preloaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|loaded| loaded.path == path)
+        .map(|loaded| loaded.image.clone());
+    let gpu_context = gpu_processing::get_or_init_gpu_context(state, app_handle).ok();
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
-    Ok(())
+    if let Some((thumbnail_data, rating)) = generate_single_thumbnail_and_cache(
+        path,
+        &thumb_cache_dir,
+        gpu_context.as_ref(),
+        preloaded_image.as_deref(),
+        true,
+        app_handle,
+        &settings,
+    ) {
+        let _ = app_handle.emit(
+            "thumbnail-generated",
+            serde_json::json!({ "path": path, "data": thumbnail_data, "rating": rating }),
+        );
+    }
 }
 
-#[tauri::command]
-pub async fn apply_adjustments_to_paths(
-    paths: Vec<String>,
-    adjustments: Value,
+pub fn read_sidecar_metadata(path: &str, app_handle: &AppHandle) -> Result<ImageMetadata, String> {
+    let (_, sidecar_path) = parse_virtual_path(path);
+    let existing_sidecar_path = resolve_sidecar_path(app_handle, &sidecar_path);
+    if !existing_sidecar_path.exists() {
+        return Ok(ImageMetadata::default());
+    }
+    let content = fs::read_to_string(&existing_sidecar_path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn step_edit_history(
+    path: String,
+    delta: isize,
     app_handle: AppHandle,
-) -> Result<(), String> {
-    let state = app_handle.state::<AppState>();
-    add_to_thumbnail_queue(&state, paths.len(), &app_handle);
+    state: tauri::State<AppState>,
+) -> Result<Option<Value>, String> {
+    let mut metadata = read_sidecar_metadata(&path, &app_handle)?;
+    if !metadata.step_history(delta) {
+        return Ok(None);
+    }
+    persist_edited_metadata(&path, &metadata, &app_handle, &state)?;
+    Ok(Some(metadata.adjustments))
+}
 
-    tauri::async_runtime::spawn_blocking(move || {
-        let settings = load_settings(app_handle.clone()).unwrap_or_default();
-        let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
-        let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+#[tauri::command]
+pub fn undo_edit(
+    path: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Option<Value>, String> {
+    step_edit_history(path, -1, app_handle, state)
+}
 
-        paths.par_iter().for_each(|path| {
-            let (_, sidecar_path) = parse_virtual_path(path);
+#[tauri::command]
+pub fn redo_edit(
+    path: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Option<Value>, String> {
+    step_edit_history(path, 1, app_handle, state)
+}
 
-            let mut existing_metadata: ImageMetadata = if sidecar_path.exists() {
-                fs::read_to_string(&sidecar_path)
-                    .ok()
-                    .and_then(|content| serde_json::from_str(&content).ok())
-                    .unwrap_or_default()
-            } else {
-                ImageMetadata::default()
+#[tauri::command]
+pub fn create_snapshot(
+    path: String,
+    name: String,
+    app_handle: AppHandle,
+) -> Result<Snapshot, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Snapshot name cannot be empty".to_string());
+    }
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let mut metadata = read_sidecar_metadata(&path, &app_handle)?;
+    let snapshot = Snapshot {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        adjustments: metadata.adjustments.clone(),
+    };
+    metadata.snapshots.push(snapshot.clone());
+
+    let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+    write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub fn apply_snapshot(
+    path: String,
+    snapshot_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Value, String> {
+    let mut metadata = read_sidecar_metadata(&path, &app_handle)?;
+    let adjustments = metadata
+        .snapshots
+        .iter()
+        .find(|s| s.id == snapshot_id)
+        .map(|s| s.adjustments.clone())
+        .ok_or_else(|| format!("Snapshot {} not found", snapshot_id))?;
+
+    metadata.rating = adjustments["rating"].as_u64().unwrap_or(0) as u8;
+    metadata.record_history(&adjustments);
+    metadata.adjustments = adjustments;
+
+    persist_edited_metadata(&path, &metadata, &app_handle, &state)?;
+    Ok(metadata.adjustments)
+}
+
+#[tauri::command]
+pub fn delete_snapshot(
+    path: String,
+    snapshot_id: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let mut metadata = read_sidecar_metadata(&path, &app_handle)?;
+    let before = metadata.snapshots.len();
+    metadata.snapshots.retain(|s| s.id != snapshot_id);
+    if metadata.snapshots.len() == before {
+        return Err(format!("Snapshot {} not found", snapshot_id));
+    }
+
+    let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+    write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string)
+}
+
+#[tauri::command]
+pub async fn apply_adjustments_to_paths(
+    paths: Vec<String>,
+    adjustments: Value,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    add_to_thumbnail_queue(&state, paths.len(), &app_handle);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+        let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+        let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+        let copy_paste_settings = settings.copy_paste_settings;
+
+        paths.par_iter().for_each(|path| {
+            let (_, sidecar_path) = parse_virtual_path(path);
+            let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+
+            let mut existing_metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                fs::read_to_string(&existing_sidecar_path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok())
+                    .unwrap_or_default()
+            } else {
+                ImageMetadata::default()
             };
 
             let mut new_adjustments = existing_metadata.adjustments;
@@ -1932,21 +4191,27 @@ pub async fn apply_adjustments_to_paths(
             if let (Some(new_map), Some(pasted_map)) =
                 (new_adjustments.as_object_mut(), adjustments.as_object())
             {
-                for (k, v) in pasted_map {
-                    new_map.insert(k.clone(), v.clone());
-                }
+                copy_paste_settings.paste_into(new_map, pasted_map);
             }
 
             existing_metadata.rating = new_adjustments["rating"].as_u64().unwrap_or(0) as u8;
+            existing_metadata.record_history(&new_adjustments);
             existing_metadata.adjustments = new_adjustments;
 
             if let Ok(json_string) = serde_json::to_string_pretty(&existing_metadata) {
-                let _ = std::fs::write(&sidecar_path, json_string);
+                let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string);
             }
 
             if enable_xmp_sync {
                 let source_path = parse_virtual_path(path).0;
-                sync_metadata_to_xmp(&source_path, &existing_metadata, create_xmp_if_missing);
+                if let Err(e) = sync_metadata_to_xmp(
+                    &source_path,
+                    &existing_metadata,
+                    create_xmp_if_missing,
+                    xmp_sync_develop,
+                ) {
+                    log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                }
             }
         });
 
@@ -1965,7 +4230,8 @@ pub async fn apply_adjustments_to_paths(
             }
         };
 
-        let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
         paths.par_iter().for_each(|path_str| {
             let result = generate_single_thumbnail_and_cache(
@@ -1975,6 +4241,7 @@ pub async fn apply_adjustments_to_paths(
                 None,
                 true,
                 &app_handle,
+                &settings,
             );
 
             if let Some((thumbnail_data, rating)) = result {
@@ -1991,11 +4258,99 @@ pub async fn apply_adjustments_to_paths(
     Ok(())
 }
 
+/// Pastes the saved edits of `source` onto `targets` using the copy/paste settings,
+/// so the same key filtering and paste mode apply as for a clipboard paste.
+#[tauri::command]
+pub async fn copy_adjustments_from_to(
+    source: String,
+    targets: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let adjustments = read_sidecar_metadata(&source, &app_handle)?.adjustments;
+    if !adjustments.is_object() {
+        return Err(format!("{} has no saved adjustments to copy", source));
+    }
+
+    let targets: Vec<String> = targets.into_iter().filter(|t| *t != source).collect();
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    apply_adjustments_to_paths(targets, adjustments, app_handle).await
+}
+
 #[tauri::command]
 pub async fn reset_adjustments_for_paths(
     paths: Vec<String>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
+    update_adjustments_for_paths(paths, app_handle, |metadata| {
+        let reset = serde_json::json!({
+            "rating": metadata.rating
+        });
+        metadata.record_history(&reset);
+        metadata.adjustments = reset;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn reset_geometry_for_paths(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    update_adjustments_for_paths(paths, app_handle, |metadata| {
+        let mut reset = metadata.adjustments.clone();
+        if let Some(obj) = reset.as_object_mut() {
+            for key in adjustment_registry::geometry_keys() {
+                obj.remove(key);
+            }
+        }
+        metadata.record_history(&reset);
+        metadata.adjustments = reset;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn reset_section_for_paths(
+    paths: Vec<String>,
+    section: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    if !adjustment_registry::is_known_section(&section) {
+        return Err(format!("Unknown adjustment section: {}", section));
+    }
+    update_adjustments_for_paths(paths, app_handle, move |metadata| {
+        let mut reset = metadata.adjustments.clone();
+        adjustment_registry::reset_section(&mut reset, &section);
+        metadata.record_history(&reset);
+        metadata.adjustments = reset;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn reset_masks_for_paths(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    update_adjustments_for_paths(paths, app_handle, |metadata| {
+        let mut reset = metadata.adjustments.clone();
+        if let Some(obj) = reset.as_object_mut() {
+            obj.remove("masks");
+            obj.remove("aiPatches");
+        }
+        metadata.record_history(&reset);
+        metadata.adjustments = reset;
+    });
+    Ok(())
+}
+
+fn update_adjustments_for_paths<F>(paths: Vec<String>, app_handle: AppHandle, update: F)
+where
+    F: Fn(&mut ImageMetadata) + Send + Sync + 'static,
+{
     let state = app_handle.state::<AppState>();
     add_to_thumbnail_queue(&state, paths.len(), &app_handle);
 
@@ -2003,12 +4358,14 @@ pub async fn reset_adjustments_for_paths(
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
         let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
         let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+        let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
 
         paths.par_iter().for_each(|path| {
             let (_, sidecar_path) = parse_virtual_path(path);
+            let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
 
-            let mut existing_metadata: ImageMetadata = if sidecar_path.exists() {
-                fs::read_to_string(&sidecar_path)
+            let mut existing_metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                fs::read_to_string(&existing_sidecar_path)
                     .ok()
                     .and_then(|content| serde_json::from_str(&content).ok())
                     .unwrap_or_default()
@@ -2016,19 +4373,22 @@ pub async fn reset_adjustments_for_paths(
                 ImageMetadata::default()
             };
 
-            let new_adjustments = serde_json::json!({
-                "rating": existing_metadata.rating
-            });
-
-            existing_metadata.adjustments = new_adjustments;
+            update(&mut existing_metadata);
 
             if let Ok(json_string) = serde_json::to_string_pretty(&existing_metadata) {
-                let _ = std::fs::write(&sidecar_path, json_string);
+                let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string);
             }
 
             if enable_xmp_sync {
                 let source_path = parse_virtual_path(path).0;
-                sync_metadata_to_xmp(&source_path, &existing_metadata, create_xmp_if_missing);
+                if let Err(e) = sync_metadata_to_xmp(
+                    &source_path,
+                    &existing_metadata,
+                    create_xmp_if_missing,
+                    xmp_sync_develop,
+                ) {
+                    log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                }
             }
         });
 
@@ -2047,7 +4407,8 @@ pub async fn reset_adjustments_for_paths(
             }
         };
 
-        let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
         paths.par_iter().for_each(|path_str| {
             let result = generate_single_thumbnail_and_cache(
@@ -2057,6 +4418,7 @@ pub async fn reset_adjustments_for_paths(
                 None,
                 true,
                 &app_handle,
+                &settings,
             );
 
             if let Some((thumbnail_data, rating)) = result {
@@ -2069,46 +4431,65 @@ pub async fn reset_adjustments_for_paths(
             increment_thumbnail_progress(&state, &app_handle);
         });
     });
-
-    Ok(())
 }
 
 #[tauri::command]
 pub async fn apply_auto_adjustments_to_paths(
     paths: Vec<String>,
+    include_white_balance: Option<bool>,
+    auto_settings: Option<AutoSettings>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
     let state = app_handle.state::<AppState>();
     add_to_thumbnail_queue(&state, paths.len(), &app_handle);
 
+    let include_white_balance = include_white_balance.unwrap_or(false);
+    let auto_settings = auto_settings.unwrap_or_default();
+    let loaded_original = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|loaded| (parse_virtual_path(&loaded.path).0, loaded.image.clone()));
+
     tauri::async_runtime::spawn_blocking(move || {
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
         let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
         let linear_mode = settings.linear_raw_mode;
         let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
         let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+        let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
 
         paths.par_iter().for_each(|path| {
             let result: Result<(), String> = (|| {
                 let (source_path, sidecar_path) = parse_virtual_path(path);
-                let source_path_str = source_path.to_string_lossy().to_string();
 
-                let file_bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
-                let image = image_loader::load_base_image_from_bytes(
-                    &file_bytes,
-                    &source_path_str,
-                    false,
-                    highlight_compression,
-                    linear_mode.clone(),
-                    None,
-                )
-                .map_err(|e| e.to_string())?;
+                let image = match &loaded_original {
+                    Some((loaded_path, image)) if *loaded_path == source_path => image.clone(),
+                    _ => {
+                        let source_path_str = source_path.to_string_lossy().to_string();
+                        let file_bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+                        Arc::new(
+                            image_loader::load_base_image_from_bytes(
+                                &file_bytes,
+                                &source_path_str,
+                                false,
+                                highlight_compression,
+                                linear_mode.clone(),
+                                None,
+                            )
+                            .map_err(|e| e.to_string())?,
+                        )
+                    }
+                };
 
-                let auto_results = perform_auto_analysis(&image);
-                let auto_adjustments_json = auto_results_to_json(&auto_results);
+                let auto_results = perform_auto_analysis(&image, &auto_settings);
+                let auto_adjustments_json =
+                    auto_results_to_json(&auto_results, include_white_balance);
 
-                let mut existing_metadata: ImageMetadata = if sidecar_path.exists() {
-                    fs::read_to_string(&sidecar_path)
+                let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+                let mut existing_metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                    fs::read_to_string(&existing_sidecar_path)
                         .ok()
                         .and_then(|content| serde_json::from_str(&content).ok())
                         .unwrap_or_default()
@@ -2148,11 +4529,18 @@ pub async fn apply_auto_adjustments_to_paths(
                     .unwrap_or(0) as u8;
 
                 if let Ok(json_string) = serde_json::to_string_pretty(&existing_metadata) {
-                    let _ = std::fs::write(&sidecar_path, json_string);
+                    let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string);
                 }
 
                 if enable_xmp_sync {
-                    sync_metadata_to_xmp(&source_path, &existing_metadata, create_xmp_if_missing);
+                    if let Err(e) = sync_metadata_to_xmp(
+                        &source_path,
+                        &existing_metadata,
+                        create_xmp_if_missing,
+                        xmp_sync_develop,
+                    ) {
+                        log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+                    }
                 }
                 Ok(())
             })();
@@ -2176,7 +4564,8 @@ pub async fn apply_auto_adjustments_to_paths(
             }
         };
 
-        let gpu_context = gpu_processing::get_or_init_gpu_context(&state).ok();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
         paths.par_iter().for_each(|path_str| {
             let result = generate_single_thumbnail_and_cache(
@@ -2186,6 +4575,7 @@ pub async fn apply_auto_adjustments_to_paths(
                 None,
                 true,
                 &app_handle,
+                &settings,
             );
 
             if let Some((thumbnail_data, rating)) = result {
@@ -2211,12 +4601,14 @@ pub fn set_color_label_for_paths(
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
     let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
 
     paths.par_iter().for_each(|path| {
         let (_, sidecar_path) = parse_virtual_path(path);
+        let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
 
-        let mut metadata: ImageMetadata = if sidecar_path.exists() {
-            fs::read_to_string(&sidecar_path)
+        let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+            fs::read_to_string(&existing_sidecar_path)
                 .ok()
                 .and_then(|content| serde_json::from_str(&content).ok())
                 .unwrap_or_default()
@@ -2240,12 +4632,65 @@ pub fn set_color_label_for_paths(
         }
 
         if let Ok(json_string) = serde_json::to_string_pretty(&metadata) {
-            let _ = std::fs::write(&sidecar_path, json_string);
+            let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string);
+        }
+
+        if enable_xmp_sync {
+            let source_path = parse_virtual_path(path).0;
+            if let Err(e) = sync_metadata_to_xmp(
+                &source_path,
+                &metadata,
+                create_xmp_if_missing,
+                xmp_sync_develop,
+            ) {
+                log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_hidden_for_paths(
+    paths: Vec<String>,
+    hidden: bool,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
+
+    paths.par_iter().for_each(|path| {
+        let (_, sidecar_path) = parse_virtual_path(path);
+        let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+
+        let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+            fs::read_to_string(&existing_sidecar_path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+                .unwrap_or_default()
+        } else {
+            ImageMetadata::default()
+        };
+
+        metadata.hidden = hidden;
+
+        if let Ok(json_string) = serde_json::to_string_pretty(&metadata) {
+            let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json_string);
         }
 
         if enable_xmp_sync {
             let source_path = parse_virtual_path(path).0;
-            sync_metadata_to_xmp(&source_path, &metadata, create_xmp_if_missing);
+            if let Err(e) = sync_metadata_to_xmp(
+                &source_path,
+                &metadata,
+                create_xmp_if_missing,
+                xmp_sync_develop,
+            ) {
+                log::warn!("Failed to sync XMP for {}: {}", source_path.display(), e);
+            }
         }
     });
 
@@ -2254,33 +4699,32 @@ pub fn set_color_label_for_paths(
 
 #[tauri::command]
 pub fn load_metadata(path: String, app_handle: AppHandle) -> Result<ImageMetadata, String> {
-    let settings = load_settings(app_handle).unwrap_or_default();
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let xmp_sync_develop = settings.xmp_sync_develop_settings.unwrap_or(false);
 
     let (source_path, sidecar_path) = parse_virtual_path(&path);
-    let mut metadata: ImageMetadata = if sidecar_path.exists() {
-        let file_content = fs::read_to_string(&sidecar_path).map_err(|e| e.to_string())?;
+    let existing_sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+    let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+        let file_content = fs::read_to_string(&existing_sidecar_path).map_err(|e| e.to_string())?;
         serde_json::from_str(&file_content).unwrap_or_default()
     } else {
         ImageMetadata::default()
     };
+    adjustment_registry::sanitize_adjustments(&mut metadata.adjustments);
 
     if enable_xmp_sync
-        && sync_metadata_from_xmp(&source_path, &mut metadata)
+        && sync_metadata_from_xmp(&source_path, &mut metadata, xmp_sync_develop, true)
         && let Ok(json) = serde_json::to_string_pretty(&metadata)
     {
-        let _ = fs::write(&sidecar_path, json);
+        let _ = write_sidecar_with_fallback(&app_handle, &sidecar_path, &json);
     }
 
     Ok(metadata)
 }
 
 fn get_presets_path(app_handle: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let presets_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())?
-        .join("presets");
+    let presets_dir = resolve_app_data_dir(app_handle)?.join("presets");
 
     if !presets_dir.exists() {
         fs::create_dir_all(&presets_dir).map_err(|e| e.to_string())?;
@@ -2330,43 +4774,26 @@ pub fn load_settings(app_handle: AppHandle) -> Result<AppSettings, String> {
         AppSettings::default()
     };
 
-    let all_current_keys = default_included_adjustments();
-    let mut settings_modified = false;
-
-    let is_first_migration = settings.copy_paste_settings.known_adjustments.is_empty();
-
-    if is_first_migration {
-        settings.copy_paste_settings.included_adjustments = all_current_keys.clone();
-        settings.copy_paste_settings.known_adjustments = all_current_keys;
-        settings_modified = true;
-    } else {
-        let new_features: Vec<String> = all_current_keys
-            .difference(&settings.copy_paste_settings.known_adjustments)
-            .cloned()
-            .collect();
-
-        if !new_features.is_empty() {
-            settings
-                .copy_paste_settings
-                .included_adjustments
-                .extend(new_features);
-            settings.copy_paste_settings.known_adjustments = all_current_keys;
-            settings_modified = true;
-        }
-    }
-
-    if settings_modified && let Ok(json_string) = serde_json::to_string_pretty(&settings) {
-        let _ = fs::write(&path, json_string);
-    }
+    settings.copy_paste_settings.apply_exclusions();
 
     Ok(settings)
 }
 
 #[tauri::command]
-pub fn save_settings(settings: AppSettings, app_handle: AppHandle) -> Result<(), String> {
+pub fn save_settings(mut settings: AppSettings, app_handle: AppHandle) -> Result<(), String> {
+    validate_directory_override(&settings.cache_directory_override, "Cache directory")?;
+    validate_directory_override(&settings.data_directory_override, "Data directory")?;
+
+    settings.copy_paste_settings.record_exclusions();
     let path = get_settings_path(&app_handle)?;
     let json_string = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
-    fs::write(path, json_string).map_err(|e| e.to_string())
+    fs::write(path, json_string).map_err(|e| e.to_string())?;
+
+    if let Some(state) = app_handle.try_state::<AppState>() {
+        *state.app_cache_dir.lock().unwrap() = None;
+        *state.app_data_dir.lock().unwrap() = None;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -2380,7 +4807,13 @@ pub fn handle_import_presets_from_file(
         .map_err(|e| format!("Failed to parse preset file: {}", e))?;
 
     let mut current_presets = load_presets(app_handle.clone())?;
+    merge_imported_presets(&mut current_presets, imported_preset_file.presets);
+
+    save_presets(current_presets.clone(), app_handle)?;
+    Ok(current_presets)
+}
 
+fn merge_imported_presets(current_presets: &mut Vec<PresetItem>, imported: Vec<PresetItem>) {
     let mut current_names: HashSet<String> = current_presets
         .iter()
         .map(|item| match item {
@@ -2389,7 +4822,7 @@ pub fn handle_import_presets_from_file(
         })
         .collect();
 
-    for mut imported_item in imported_preset_file.presets {
+    for mut imported_item in imported {
         let (current_name, _new_id) = match &mut imported_item {
             PresetItem::Preset(p) => {
                 p.id = Uuid::new_v4().to_string();
@@ -2419,9 +4852,6 @@ pub fn handle_import_presets_from_file(
         current_names.insert(new_name);
         current_presets.push(imported_item);
     }
-
-    save_presets(current_presets.clone(), app_handle)?;
-    Ok(current_presets)
 }
 
 #[tauri::command]
@@ -2432,7 +4862,23 @@ pub fn handle_import_legacy_presets_from_file(
     let content = fs::read_to_string(&file_path)
         .map_err(|e| format!("Failed to read legacy preset file: {}", e))?;
 
-    let xmp_content = if file_path.to_lowercase().ends_with(".lrtemplate") {
+    let converted_preset =
+        preset_converter::convert_xmp_to_preset(&extract_legacy_xmp(&file_path, content))?;
+
+    let mut current_presets = load_presets(app_handle.clone())?;
+    let current_names = collect_preset_names(&current_presets);
+
+    let mut final_preset = converted_preset;
+    final_preset.name = unique_preset_name(&final_preset.name, &current_names);
+
+    current_presets.push(PresetItem::Preset(final_preset));
+
+    save_presets(current_presets.clone(), app_handle)?;
+    Ok(current_presets)
+}
+
+fn extract_legacy_xmp(file_path: &str, content: String) -> String {
+    if file_path.to_lowercase().ends_with(".lrtemplate") {
         let re = Regex::new(r#"(?s)s.xmp = "(.*)""#).unwrap();
         if let Some(caps) = re.captures(&content) {
             caps.get(1)
@@ -2443,13 +4889,11 @@ pub fn handle_import_legacy_presets_from_file(
         }
     } else {
         content
-    };
-
-    let converted_preset = preset_converter::convert_xmp_to_preset(&xmp_content)?;
-
-    let mut current_presets = load_presets(app_handle.clone())?;
+    }
+}
 
-    let current_names: HashSet<String> = current_presets
+fn collect_preset_names(presets: &[PresetItem]) -> HashSet<String> {
+    presets
         .iter()
         .flat_map(|item| match item {
             PresetItem::Preset(p) => vec![p.name.clone()],
@@ -2459,22 +4903,101 @@ pub fn handle_import_legacy_presets_from_file(
                 names
             }
         })
-        .collect();
+        .collect()
+}
 
-    let mut new_name = converted_preset.name.clone();
+fn unique_preset_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut new_name = name.to_string();
     let mut counter = 1;
-    while current_names.contains(&new_name) {
-        new_name = format!("{} ({})", converted_preset.name, counter);
+    while taken.contains(&new_name) {
+        new_name = format!("{} ({})", name, counter);
         counter += 1;
     }
+    new_name
+}
 
-    let mut final_preset = converted_preset;
-    final_preset.name = new_name;
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedPresetImport {
+    pub path: String,
+    pub error: String,
+}
 
-    current_presets.push(PresetItem::Preset(final_preset));
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyPresetFolderImport {
+    pub presets: Vec<PresetItem>,
+    pub imported: usize,
+    pub failed: Vec<FailedPresetImport>,
+}
 
-    save_presets(current_presets.clone(), app_handle)?;
-    Ok(current_presets)
+#[tauri::command]
+pub fn handle_import_legacy_presets_from_folder(
+    dir: String,
+    app_handle: AppHandle,
+) -> Result<LegacyPresetFolderImport, String> {
+    let dir_path = Path::new(&dir);
+    if !dir_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", dir));
+    }
+
+    let mut files: Vec<PathBuf> = WalkDir::new(dir_path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+                e.eq_ignore_ascii_case("xmp") || e.eq_ignore_ascii_case("lrtemplate")
+            })
+        })
+        .collect();
+    files.sort();
+
+    let mut current_presets = load_presets(app_handle.clone())?;
+    let mut taken_names = collect_preset_names(&current_presets);
+
+    let mut children = Vec::new();
+    let mut failed = Vec::new();
+    for file in files {
+        let file_str = file.to_string_lossy().into_owned();
+        let converted = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read legacy preset file: {}", e))
+            .and_then(|content| {
+                preset_converter::convert_xmp_to_preset(&extract_legacy_xmp(&file_str, content))
+            });
+        match converted {
+            Ok(mut preset) => {
+                preset.name = unique_preset_name(&preset.name, &taken_names);
+                taken_names.insert(preset.name.clone());
+                children.push(preset);
+            }
+            Err(error) => failed.push(FailedPresetImport {
+                path: file_str,
+                error,
+            }),
+        }
+    }
+
+    let imported = children.len();
+    if imported > 0 {
+        let folder_name = dir_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Imported Presets".to_string());
+        current_presets.push(PresetItem::Folder(PresetFolder {
+            id: Uuid::new_v4().to_string(),
+            name: unique_preset_name(&folder_name, &taken_names),
+            children,
+        }));
+        save_presets(current_presets.clone(), app_handle)?;
+    }
+
+    Ok(LegacyPresetFolderImport {
+        presets: current_presets,
+        imported,
+        failed,
+    })
 }
 
 #[tauri::command]
@@ -2492,29 +5015,189 @@ pub fn handle_export_presets_to_file(
     fs::write(file_path, json_string).map_err(|e| format!("Failed to write preset file: {}", e))
 }
 
+const PRESET_BUNDLE_MANIFEST: &str = "presets.json";
+const PRESET_BUNDLE_LUT_DIR: &str = "luts";
+
+fn presets_mut(items: &mut [PresetItem]) -> impl Iterator<Item = &mut Preset> {
+    items.iter_mut().flat_map(|item| match item {
+        PresetItem::Preset(p) => std::slice::from_mut(p).iter_mut(),
+        PresetItem::Folder(f) => f.children.iter_mut(),
+    })
+}
+
 #[tauri::command]
-pub fn save_community_preset(
-    name: String,
-    adjustments: Value,
-    app_handle: AppHandle,
-) -> Result<(), String> {
-    let mut current_presets = load_presets(app_handle.clone())?;
+pub fn handle_export_presets_bundle(presets: Vec<PresetItem>, dest: String) -> Result<(), String> {
+    let mut presets = presets;
+    let mut bundled_luts: HashMap<String, String> = HashMap::new();
+    let mut used_names: HashSet<String> = HashSet::new();
 
-    let community_folder_name = "Community";
-    let community_folder_id = match current_presets.iter_mut().find(|item| {
-        if let PresetItem::Folder(f) = item {
-            f.name == community_folder_name
-        } else {
-            false
-        }
-    }) {
-        Some(PresetItem::Folder(folder)) => folder.id.clone(),
-        _ => {
-            let new_folder_id = Uuid::new_v4().to_string();
-            let new_folder = PresetItem::Folder(PresetFolder {
-                id: new_folder_id.clone(),
-                name: community_folder_name.to_string(),
-                children: Vec::new(),
+    for preset in presets_mut(&mut presets) {
+        let Some(lut_path) = preset.adjustments["lutPath"].as_str().map(str::to_string) else {
+            continue;
+        };
+        let entry_name = match bundled_luts.get(&lut_path) {
+            Some(name) => name.clone(),
+            None => {
+                let source = Path::new(&lut_path);
+                if !source.is_file() {
+                    return Err(format!(
+                        "LUT file for preset '{}' not found: {}",
+                        preset.name, lut_path
+                    ));
+                }
+                let stem = source
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "lut".to_string());
+                let ext = source
+                    .extension()
+                    .map(|e| format!(".{}", e.to_string_lossy()))
+                    .unwrap_or_default();
+                let mut file_name = format!("{}{}", stem, ext);
+                let mut counter = 1;
+                while used_names.contains(&file_name) {
+                    file_name = format!("{} ({}){}", stem, counter, ext);
+                    counter += 1;
+                }
+                used_names.insert(file_name.clone());
+                let name = format!("{}/{}", PRESET_BUNDLE_LUT_DIR, file_name);
+                bundled_luts.insert(lut_path, name.clone());
+                name
+            }
+        };
+        preset.adjustments["lutPath"] = Value::String(entry_name);
+    }
+
+    let preset_file = ExportPresetFile {
+        creator: "Anonymous",
+        presets: &presets,
+    };
+    let json_string = serde_json::to_string_pretty(&preset_file)
+        .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+
+    let file = fs::File::create(&dest).map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file(PRESET_BUNDLE_MANIFEST, options)
+        .map_err(|e| e.to_string())?;
+    std::io::Write::write_all(&mut zip, json_string.as_bytes()).map_err(|e| e.to_string())?;
+
+    for (source, entry_name) in &bundled_luts {
+        let bytes =
+            fs::read(source).map_err(|e| format!("Failed to read LUT {}: {}", source, e))?;
+        zip.start_file(entry_name.as_str(), options)
+            .map_err(|e| e.to_string())?;
+        std::io::Write::write_all(&mut zip, &bytes).map_err(|e| e.to_string())?;
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn handle_import_presets_bundle(
+    file_path: String,
+    app_handle: AppHandle,
+) -> Result<Vec<PresetItem>, String> {
+    let file =
+        fs::File::open(&file_path).map_err(|e| format!("Failed to open preset bundle: {}", e))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read preset bundle: {}", e))?;
+
+    let manifest = {
+        let mut entry = archive
+            .by_name(PRESET_BUNDLE_MANIFEST)
+            .map_err(|_| "Preset bundle is missing presets.json".to_string())?;
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut content).map_err(|e| e.to_string())?;
+        content
+    };
+    let mut imported: PresetFile = serde_json::from_str(&manifest)
+        .map_err(|e| format!("Failed to parse preset bundle: {}", e))?;
+
+    let lut_dir = resolve_app_data_dir(&app_handle)?.join(PRESET_BUNDLE_LUT_DIR);
+    let mut extracted: HashMap<String, String> = HashMap::new();
+
+    for preset in presets_mut(&mut imported.presets) {
+        let Some(entry_name) = preset.adjustments["lutPath"].as_str().map(str::to_string) else {
+            continue;
+        };
+        if let Some(local) = extracted.get(&entry_name) {
+            preset.adjustments["lutPath"] = Value::String(local.clone());
+            continue;
+        }
+
+        let Ok(mut entry) = archive.by_name(&entry_name) else {
+            log::warn!(
+                "LUT '{}' referenced by preset '{}' is missing from the bundle",
+                entry_name,
+                preset.name
+            );
+            preset.adjustments["lutPath"] = Value::Null;
+            continue;
+        };
+        let file_name = Path::new(&entry_name)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("Invalid LUT entry in bundle: {}", entry_name))?;
+
+        fs::create_dir_all(&lut_dir).map_err(|e| e.to_string())?;
+        let mut target = lut_dir.join(&file_name);
+        let mut counter = 1;
+        while target.exists() {
+            let stem = Path::new(&file_name)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let ext = Path::new(&file_name)
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            target = lut_dir.join(format!("{} ({}){}", stem, counter, ext));
+            counter += 1;
+        }
+
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut entry, &mut bytes).map_err(|e| e.to_string())?;
+        fs::write(&target, bytes).map_err(|e| format!("Failed to extract LUT: {}", e))?;
+
+        let local = target.to_string_lossy().into_owned();
+        extracted.insert(entry_name, local.clone());
+        preset.adjustments["lutPath"] = Value::String(local);
+    }
+
+    let mut current_presets = load_presets(app_handle.clone())?;
+    merge_imported_presets(&mut current_presets, imported.presets);
+
+    save_presets(current_presets.clone(), app_handle)?;
+    Ok(current_presets)
+}
+
+#[tauri::command]
+pub fn save_community_preset(
+    name: String,
+    adjustments: Value,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let mut current_presets = load_presets(app_handle.clone())?;
+
+    let community_folder_name = "Community";
+    let community_folder_id = match current_presets.iter_mut().find(|item| {
+        if let PresetItem::Folder(f) = item {
+            f.name == community_folder_name
+        } else {
+            false
+        }
+    }) {
+        Some(PresetItem::Folder(folder)) => folder.id.clone(),
+        _ => {
+            let new_folder_id = Uuid::new_v4().to_string();
+            let new_folder = PresetItem::Folder(PresetFolder {
+                id: new_folder_id.clone(),
+                name: community_folder_name.to_string(),
+                children: Vec::new(),
             });
             current_presets.insert(0, new_folder);
             new_folder_id
@@ -2542,7 +5225,10 @@ pub fn save_community_preset(
 }
 
 #[tauri::command]
-pub fn clear_all_sidecars(root_path: String) -> Result<usize, String> {
+pub fn clear_all_sidecars(
+    root_path: String,
+    state: tauri::State<AppState>,
+) -> Result<usize, String> {
     if !Path::new(&root_path).exists() {
         return Err(format!("Root path does not exist: {}", root_path));
     }
@@ -2564,15 +5250,13 @@ pub fn clear_all_sidecars(root_path: String) -> Result<usize, String> {
         }
     }
 
+    invalidate_tag_index(&state);
     Ok(deleted_count)
 }
 
 #[tauri::command]
 pub fn clear_thumbnail_cache(app_handle: AppHandle) -> Result<(), String> {
-    let cache_dir = app_handle
-        .path()
-        .app_cache_dir()
-        .map_err(|e| e.to_string())?;
+    let cache_dir = resolve_app_cache_dir(&app_handle)?;
     let thumb_cache_dir = cache_dir.join("thumbnails");
 
     if thumb_cache_dir.exists() {
@@ -2583,6 +5267,10 @@ pub fn clear_thumbnail_cache(app_handle: AppHandle) -> Result<(), String> {
     fs::create_dir_all(&thumb_cache_dir)
         .map_err(|e| format!("Failed to recreate thumbnail cache directory: {}", e))?;
 
+    let state = app_handle.state::<AppState>();
+    state.thumbnail_cache_index.lock().unwrap().clear();
+    *state.thumbnail_cache_size.lock().unwrap() = Some(0);
+
     Ok(())
 }
 
@@ -2623,8 +5311,18 @@ pub fn show_in_finder(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn delete_files_from_disk(paths: Vec<String>) -> Result<(), String> {
+pub fn delete_files_from_disk(
+    paths: Vec<String>,
+    state: tauri::State<AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let extensions = associated_extensions(&app_handle);
     let mut files_to_trash = HashSet::new();
+    let batch: HashSet<PathBuf> = paths
+        .iter()
+        .filter(|path_str| !path_str.contains("?vc="))
+        .map(|path_str| parse_virtual_path(path_str).0)
+        .collect();
 
     for path_str in paths {
         let (source_path, sidecar_path) = parse_virtual_path(&path_str);
@@ -2635,7 +5333,7 @@ pub fn delete_files_from_disk(paths: Vec<String>) -> Result<(), String> {
             }
         } else {
             if source_path.exists() {
-                match find_all_associated_files(&source_path) {
+                match find_all_associated_files(&source_path, &extensions, &batch) {
                     Ok(associated_files) => {
                         for file in associated_files {
                             files_to_trash.insert(file);
@@ -2658,7 +5356,7 @@ pub fn delete_files_from_disk(paths: Vec<String>) -> Result<(), String> {
     }
 
     let final_paths_to_delete: Vec<PathBuf> = files_to_trash.into_iter().collect();
-    if let Err(trash_error) = trash::delete_all(&final_paths_to_delete) {
+    if let Err(trash_error) = trash_and_record(&state, &final_paths_to_delete, Vec::new()) {
         log::warn!(
             "Failed to move files to trash: {}. Falling back to permanent delete.",
             trash_error
@@ -2677,7 +5375,11 @@ pub fn delete_files_from_disk(paths: Vec<String>) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn delete_files_with_associated(paths: Vec<String>) -> Result<(), String> {
+pub fn delete_files_with_associated(
+    paths: Vec<String>,
+    state: tauri::State<AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
     if paths.is_empty() {
         return Ok(());
     }
@@ -2701,6 +5403,7 @@ pub fn delete_files_with_associated(paths: Vec<String>) -> Result<(), String> {
         return Ok(());
     }
 
+    let extensions = associated_extensions(&app_handle);
     let mut files_to_trash = HashSet::new();
 
     for parent_dir in parent_dirs {
@@ -2717,7 +5420,7 @@ pub fn delete_files_with_associated(paths: Vec<String>) -> Result<(), String> {
                 if let Some(base_stem) = entry_filename_str.split('.').next()
                     && stems_to_delete.contains(base_stem)
                     && (is_supported_image_file(entry_filename_str.as_ref())
-                        || entry_filename_str.ends_with(".rrdata"))
+                        || has_associated_extension(&entry_filename_str, &extensions))
                 {
                     files_to_trash.insert(entry_path);
                 }
@@ -2730,7 +5433,7 @@ pub fn delete_files_with_associated(paths: Vec<String>) -> Result<(), String> {
     }
 
     let final_paths_to_delete: Vec<PathBuf> = files_to_trash.into_iter().collect();
-    if let Err(trash_error) = trash::delete_all(&final_paths_to_delete) {
+    if let Err(trash_error) = trash_and_record(&state, &final_paths_to_delete, Vec::new()) {
         log::warn!(
             "Failed to move files to trash: {}. Falling back to permanent delete.",
             trash_error
@@ -2746,20 +5449,13 @@ pub fn delete_files_with_associated(paths: Vec<String>) -> Result<(), String> {
 }
 
 pub fn get_thumb_cache_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
-    let cache_dir = app_handle
-        .path()
-        .app_cache_dir()
-        .map_err(|e| e.to_string())?;
-    let thumb_cache_dir = cache_dir.join("thumbnails");
-    if !thumb_cache_dir.exists() {
-        fs::create_dir_all(&thumb_cache_dir).map_err(|e| e.to_string())?;
-    }
-    Ok(thumb_cache_dir)
+    resolve_thumbnail_cache_dir(app_handle)
 }
 
-pub fn get_cache_key_hash(path_str: &str) -> Option<String> {
-    let (source_path, sidecar_path) = parse_virtual_path(path_str);
-
+/// Hashes the image path and modification time, the part of every cache key that
+/// identifies the source file.
+fn source_cache_hasher(path_str: &str) -> Option<blake3::Hasher> {
+    let (source_path, _) = parse_virtual_path(path_str);
     let img_mod_time = fs::metadata(source_path)
         .ok()?
         .modified()
@@ -2768,6 +5464,17 @@ pub fn get_cache_key_hash(path_str: &str) -> Option<String> {
         .ok()?
         .as_secs();
 
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(path_str.as_bytes());
+    hasher.update(&img_mod_time.to_le_bytes());
+    Some(hasher)
+}
+
+pub fn get_cache_key_hash(app_handle: &AppHandle, path_str: &str, quality: u8) -> Option<String> {
+    let (_, sidecar_path) = parse_virtual_path(path_str);
+    let sidecar_path = resolve_sidecar_path(app_handle, &sidecar_path);
+    let mut hasher = source_cache_hasher(path_str)?;
+
     let sidecar_mod_time = if let Ok(meta) = fs::metadata(&sidecar_path) {
         meta.modified()
             .ok()
@@ -2778,14 +5485,105 @@ pub fn get_cache_key_hash(path_str: &str) -> Option<String> {
         0
     };
 
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(path_str.as_bytes());
-    hasher.update(&img_mod_time.to_le_bytes());
     hasher.update(&sidecar_mod_time.to_le_bytes());
+    hasher.update(&[quality]);
     let hash = hasher.finalize();
     Some(hash.to_hex().to_string())
 }
 
+fn get_processed_preview_cache_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let preview_cache_dir = resolve_app_cache_dir(app_handle)?.join("previews");
+    if !preview_cache_dir.exists() {
+        fs::create_dir_all(&preview_cache_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(preview_cache_dir)
+}
+
+const PROCESSED_PREVIEW_CACHE_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Returns the cache file name for an image rendered with `adjustments`, and the
+/// prefix that every cached render of the same source image shares.
+fn processed_preview_cache_filename(
+    path_str: &str,
+    adjustments: &Value,
+) -> Option<(String, String)> {
+    let image_hash = source_cache_hasher(path_str)?.finalize().to_hex();
+    let adjustments_hash = blake3::hash(adjustments.to_string().as_bytes()).to_hex();
+    let prefix = format!("{}_", image_hash);
+    Some((format!("{}{}.jpg", prefix, adjustments_hash), prefix))
+}
+
+/// Evicts the least recently used previews until the directory fits the cache budget.
+fn enforce_processed_preview_cache_limit(preview_cache_dir: &Path) {
+    let mut entries = jpeg_cache_entries(preview_cache_dir);
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    if total <= PROCESSED_PREVIEW_CACHE_MAX_BYTES {
+        return;
+    }
+    entries.sort_by_key(|(_, _, last_used)| *last_used);
+    for (path, size, _) in entries {
+        if total <= PROCESSED_PREVIEW_CACHE_MAX_BYTES {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(size);
+        }
+    }
+}
+
+pub fn store_processed_preview(
+    app_handle: &AppHandle,
+    path_str: &str,
+    adjustments: &Value,
+    jpeg_bytes: &[u8],
+) -> Result<(), String> {
+    let (cache_filename, stale_prefix) = processed_preview_cache_filename(path_str, adjustments)
+        .ok_or("Could not compute preview cache key")?;
+    let preview_cache_dir = get_processed_preview_cache_dir(app_handle)?;
+
+    if let Ok(entries) = fs::read_dir(&preview_cache_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&stale_prefix) && name != cache_filename {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    fs::write(preview_cache_dir.join(cache_filename), jpeg_bytes).map_err(|e| e.to_string())?;
+    enforce_processed_preview_cache_limit(&preview_cache_dir);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_cached_processed_preview(
+    path: String,
+    app_handle: AppHandle,
+) -> Result<Option<String>, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
+    let Some(metadata) = fs::read_to_string(&sidecar_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ImageMetadata>(&content).ok())
+    else {
+        return Ok(None);
+    };
+
+    let Some((cache_filename, _)) = processed_preview_cache_filename(&path, &metadata.adjustments)
+    else {
+        return Ok(None);
+    };
+    let cache_path = get_processed_preview_cache_dir(&app_handle)?.join(cache_filename);
+
+    match fs::read(&cache_path) {
+        Ok(bytes) => Ok(Some(format!(
+            "data:image/jpeg;base64,{}",
+            general_purpose::STANDARD.encode(bytes)
+        ))),
+        Err(_) => Ok(None),
+    }
+}
+
 pub fn get_cached_or_generate_thumbnail_image(
     path_str: &str,
     app_handle: &AppHandle,
@@ -2794,10 +5592,11 @@ pub fn get_cached_or_generate_thumbnail_image(
     let thumb_cache_dir = get_thumb_cache_dir(app_handle).map_err(|e| anyhow::anyhow!(e))?;
     let settings = crate::file_management::load_settings(app_handle.clone()).unwrap_or_default();
     let target_width = settings.thumbnail_resolution.unwrap_or(720);
+    let quality = thumbnail_quality(&settings);
 
-    if let Some(cache_hash) = get_cache_key_hash(path_str) {
+    if let Some(cache_hash) = get_cache_key_hash(app_handle, path_str, quality) {
         let cache_filename = format!("{}.jpg", cache_hash);
-        let cache_path = thumb_cache_dir.join(cache_filename);
+        let cache_path = thumb_cache_dir.join(&cache_filename);
 
         if cache_path.exists() {
             if let Ok(image) = image::open(&cache_path) {
@@ -2810,8 +5609,14 @@ pub fn get_cached_or_generate_thumbnail_image(
         }
 
         let thumb_image = generate_thumbnail_data(path_str, gpu_context, None, app_handle)?;
-        let thumb_data = encode_thumbnail(&thumb_image, target_width)?;
-        fs::write(&cache_path, &thumb_data)?;
+        let thumb_data = encode_thumbnail(&thumb_image, target_width, quality)?;
+        write_cached_thumbnail(
+            &app_handle.state::<AppState>(),
+            &thumb_cache_dir,
+            &cache_filename,
+            &thumb_data,
+            settings.thumbnail_cache_max_mb,
+        )?;
 
         Ok(thumb_image)
     } else {
@@ -2830,6 +5635,7 @@ pub async fn import_files(
     let _ = app_handle.emit("import-start", serde_json::json!({ "total": total_files }));
 
     tokio::spawn(async move {
+        let mut duplicate_index = ImportDuplicateIndex::default();
         for (i, source_path_str) in source_paths.iter().enumerate() {
             let _ = app_handle.emit(
                 "import-progress",
@@ -2858,6 +5664,20 @@ pub async fn import_files(
                 fs::create_dir_all(&final_dest_folder)
                     .map_err(|e| format!("Failed to create destination folder: {}", e))?;
 
+                if settings.skip_duplicates
+                    && let Some(existing) =
+                        duplicate_index.find_duplicate(&final_dest_folder, &source_path)
+                {
+                    let _ = app_handle.emit(
+                        "import-skip",
+                        serde_json::json!({
+                            "path": source_path_str,
+                            "existingPath": existing.to_string_lossy(),
+                        }),
+                    );
+                    return Ok(());
+                }
+
                 let new_stem = generate_filename_from_template(
                     &settings.filename_template,
                     &source_path,
@@ -2879,12 +5699,29 @@ pub async fn import_files(
                     ));
                 }
 
-                fs::copy(&source_path, &dest_file_path).map_err(|e| e.to_string())?;
+                let copy = |from: &Path, to: &Path, modified: Option<SystemTime>| {
+                    let result = if settings.preserve_timestamps {
+                        copy_preserving_timestamps(from, to, modified)
+                    } else {
+                        fs::copy(from, to).map(|_| ())
+                    };
+                    result.map_err(|e| e.to_string())
+                };
+                let capture_time = if settings.organize_by_date {
+                    exif_processing::read_capture_date(&source_path).map(SystemTime::from)
+                } else {
+                    None
+                };
+
+                copy(&source_path, &dest_file_path, capture_time)?;
+                if settings.skip_duplicates {
+                    duplicate_index.record(&final_dest_folder, &dest_file_path);
+                }
                 if source_sidecar.exists()
                     && let Some(dest_str) = dest_file_path.to_str()
                 {
                     let (_, dest_sidecar) = parse_virtual_path(dest_str);
-                    fs::copy(&source_sidecar, &dest_sidecar).map_err(|e| e.to_string())?;
+                    copy(&source_sidecar, &dest_sidecar, None)?;
                 }
 
                 if settings.delete_after_import {
@@ -2922,6 +5759,7 @@ pub async fn import_files(
             "import-progress",
             serde_json::json!({ "current": total_files, "total": total_files, "path": "" }),
         );
+        invalidate_tag_index(&app_handle.state::<AppState>());
         let _ = app_handle.emit("import-complete", ());
     });
 
@@ -2946,7 +5784,15 @@ pub fn generate_filename_from_template(
     );
     let local_date = file_date.with_timezone(&chrono::Local);
 
+    // Camera tokens go first so collapsing around missing values only touches
+    // separators written in the template, never the substituted file name.
     let mut result = template.to_string();
+    if CAMERA_TEMPLATE_TOKENS
+        .iter()
+        .any(|(token, _)| result.contains(token))
+    {
+        result = apply_camera_template_tokens(result, original_path);
+    }
     result = result.replace("{original_filename}", stem);
     result = result.replace("{sequence}", &sequence_str);
     result = result.replace("{YYYY}", &local_date.format("%Y").to_string());
@@ -2958,8 +5804,89 @@ pub fn generate_filename_from_template(
     result
 }
 
+/// Stands in for a camera token with no value until its separators are collapsed.
+const MISSING_TOKEN_MARKER: char = '\u{0}';
+
+/// A run of missing tokens together with the template separators on either side.
+static MISSING_TOKEN_SEPARATORS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?P<before>[_\-. ]*)\x00(?:[_\-. ]*\x00)*(?P<after>[_\-. ]*)").unwrap()
+});
+
+const CAMERA_TEMPLATE_TOKENS: &[(&str, &str)] = &[
+    ("{camera_make}", "Make"),
+    ("{camera_model}", "Model"),
+    ("{iso}", "PhotographicSensitivity"),
+    ("{focal_length}", "FocalLength"),
+    ("{lens}", "LensModel"),
+];
+
+fn apply_camera_template_tokens(template: String, original_path: &Path) -> String {
+    let exif = if let Ok(mmap) = read_file_mapped(original_path) {
+        exif_processing::extract_metadata(&mmap)
+    } else {
+        fs::read(original_path)
+            .ok()
+            .and_then(|bytes| exif_processing::extract_metadata(&bytes))
+    }
+    .unwrap_or_default();
+
+    let mut result = template;
+    let mut has_missing = false;
+    for (token, tag) in CAMERA_TEMPLATE_TOKENS {
+        if !result.contains(token) {
+            continue;
+        }
+        let value: String = exif
+            .get(*tag)
+            .map(|v| v.trim().trim_matches('"').trim())
+            .unwrap_or("")
+            .chars()
+            .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+            .collect();
+        let value = match *token {
+            "{focal_length}" if !value.is_empty() => format!("{}mm", value),
+            _ => value,
+        };
+        if value.is_empty() {
+            has_missing = true;
+            result = result.replace(token, &MISSING_TOKEN_MARKER.to_string());
+        } else {
+            result = result.replace(token, &value);
+        }
+    }
+
+    if has_missing {
+        result = collapse_missing_tokens(&result);
+    }
+
+    result
+}
+
+/// Removes missing-token markers, keeping a single separator between the
+/// surrounding parts and none at either end of the name.
+fn collapse_missing_tokens(template: &str) -> String {
+    MISSING_TOKEN_SEPARATORS
+        .replace_all(template, |caps: &regex::Captures| {
+            let whole = caps.get(0).unwrap();
+            if whole.start() == 0 || whole.end() == template.len() {
+                return String::new();
+            }
+            let before = &caps["before"];
+            if before.is_empty() {
+                caps["after"].to_string()
+            } else {
+                before.to_string()
+            }
+        })
+        .into_owned()
+}
+
 #[tauri::command]
-pub fn rename_files(paths: Vec<String>, name_template: String) -> Result<Vec<String>, String> {
+pub fn rename_files(
+    paths: Vec<String>,
+    name_template: String,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, String> {
     if paths.is_empty() {
         return Ok(Vec::new());
     }
@@ -3003,6 +5930,8 @@ pub fn rename_files(paths: Vec<String>, name_template: String) -> Result<Vec<Str
         operations.insert(original_path, new_path);
     }
 
+    let extensions = associated_extensions(&app_handle);
+    let batch: HashSet<PathBuf> = operations.keys().cloned().collect();
     let mut sidecar_operations: HashMap<PathBuf, PathBuf> = HashMap::new();
     for (original_path, new_path) in &operations {
         let parent = original_path
@@ -3011,28 +5940,28 @@ pub fn rename_files(paths: Vec<String>, name_template: String) -> Result<Vec<Str
         let original_filename_str = original_path.file_name().unwrap().to_string_lossy();
         let new_filename_str = new_path.file_name().unwrap().to_string_lossy();
 
-        if let Ok(entries) = fs::read_dir(parent) {
-            for entry in entries.filter_map(Result::ok) {
-                let entry_path = entry.path();
-                let entry_os_filename = entry.file_name();
-                let entry_filename = entry_os_filename.to_string_lossy();
-
-                if entry_filename.starts_with(&format!("{}.", original_filename_str))
-                    && entry_filename.ends_with(".rrdata")
-                {
-                    let new_sidecar_filename =
-                        entry_filename.replacen(&*original_filename_str, &new_filename_str, 1);
-                    let new_sidecar_path = parent.join(new_sidecar_filename);
-                    sidecar_operations.insert(entry_path, new_sidecar_path);
-                } else if entry_filename == format!("{}.rrdata", original_filename_str) {
-                    let new_sidecar_path = new_path.with_extension("rrdata");
-                    sidecar_operations.insert(entry_path, new_sidecar_path);
-                }
+        for companion in find_all_associated_files(original_path, &extensions, &batch)?
+            .into_iter()
+            .skip(1)
+        {
+            let companion_name = companion.file_name().unwrap().to_string_lossy();
+            let new_companion_path = parent.join(companion_file_name(
+                &companion_name,
+                &original_filename_str,
+                &new_filename_str,
+            ));
+            if new_companion_path != companion && new_companion_path.exists() {
+                return Err(format!(
+                    "A file with the name {} already exists.",
+                    new_companion_path.display()
+                ));
             }
+            sidecar_operations.insert(companion, new_companion_path);
         }
     }
     operations.extend(sidecar_operations);
 
+    invalidate_tag_index(&app_handle.state::<AppState>());
     for (old_path, new_path) in operations {
         fs::rename(&old_path, &new_path).map_err(|e| {
             format!(
@@ -3051,22 +5980,24 @@ pub fn rename_files(paths: Vec<String>, name_template: String) -> Result<Vec<Str
 }
 
 #[tauri::command]
-pub fn create_virtual_copy(source_virtual_path: String) -> Result<String, String> {
+pub fn create_virtual_copy(
+    source_virtual_path: String,
+    app_handle: AppHandle,
+) -> Result<String, String> {
     let (source_path, source_sidecar_path) = parse_virtual_path(&source_virtual_path);
+    let source_sidecar_path = resolve_sidecar_path(&app_handle, &source_sidecar_path);
 
     let new_copy_id = Uuid::new_v4().to_string()[..6].to_string();
     let new_virtual_path = format!("{}?vc={}", source_path.to_string_lossy(), new_copy_id);
     let (_, new_sidecar_path) = parse_virtual_path(&new_virtual_path);
 
-    if source_sidecar_path.exists() {
-        fs::copy(&source_sidecar_path, &new_sidecar_path)
-            .map_err(|e| format!("Failed to copy sidecar file: {}", e))?;
+    let json_string = if source_sidecar_path.exists() {
+        fs::read_to_string(&source_sidecar_path)
+            .map_err(|e| format!("Failed to copy sidecar file: {}", e))?
     } else {
-        let default_metadata = ImageMetadata::default();
-        let json_string =
-            serde_json::to_string_pretty(&default_metadata).map_err(|e| e.to_string())?;
-        fs::write(new_sidecar_path, json_string).map_err(|e| e.to_string())?;
-    }
+        serde_json::to_string_pretty(&ImageMetadata::default()).map_err(|e| e.to_string())?
+    };
+    write_sidecar_with_fallback(&app_handle, &new_sidecar_path, &json_string)?;
 
     Ok(new_virtual_path)
 }
@@ -3099,27 +6030,189 @@ pub fn extract_xmp_label(content: &str) -> Option<String> {
     None
 }
 
-pub fn extract_xmp_tags(content: &str) -> Vec<String> {
-    let mut tags = Vec::new();
-    if let Some(start_idx) = content.find("<dc:subject>")
-        && let Some(end_idx) = content[start_idx..].find("</dc:subject>")
-    {
-        let subject_block = &content[start_idx..start_idx + end_idx];
-        let mut current_idx = 0;
-        while let Some(li_start) = subject_block[current_idx..].find("<rdf:li>") {
-            let val_start = current_idx + li_start + 8;
-            if let Some(li_end) = subject_block[val_start..].find("</rdf:li>") {
-                tags.push(subject_block[val_start..val_start + li_end].to_string());
-                current_idx = val_start + li_end + 9;
-            } else {
-                break;
-            }
+/// Reads keywords from `dc:subject`, preferring the full `|`-delimited paths from
+/// `lr:hierarchicalSubject` over the flattened keywords they produce.
+fn extract_xmp_tags(values: &XmpValues) -> Vec<String> {
+    let hierarchical = values.array(XMP_LR_NS, "hierarchicalSubject");
+    let covered: HashSet<&str> = hierarchical.iter().flat_map(|t| t.split('|')).collect();
+
+    let mut tags: Vec<String> = values
+        .array(XMP_DC_NS, "subject")
+        .iter()
+        .filter(|t| !covered.contains(t.as_str()))
+        .cloned()
+        .collect();
+    for tag in hierarchical {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
         }
     }
     tags
 }
 
-pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata) -> bool {
+const XMP_RAPIDRAW_NS: &str = "https://github.com/CyberTimon/RapidRAW/ns/1.0/";
+
+fn extract_xmp_hidden(values: &XmpValues) -> Option<bool> {
+    values
+        .get(XMP_RAPIDRAW_NS, "Hidden")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+}
+
+fn extract_xmp_develop_settings(values: &XmpValues) -> Map<String, Value> {
+    let mut adjustments = Map::new();
+    for (crs_key, key) in preset_converter::CRS_DEVELOP_SETTINGS {
+        if let Some(raw) = values.get(XMP_CRS_NS, crs_key)
+            && let Some(value) = preset_converter::crs_to_adjustment(crs_key, raw)
+            && let Some(def) = adjustment_registry::find(key)
+        {
+            adjustments.insert(key.to_string(), Value::from(def.clamp(value)));
+        }
+    }
+    adjustments
+}
+
+fn extract_xmp_crs_f64(values: &XmpValues, local_name: &str) -> Option<f64> {
+    values
+        .get(XMP_CRS_NS, local_name)?
+        .trim_start_matches('+')
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+}
+
+fn xmp_has_crop(values: &XmpValues) -> bool {
+    values
+        .get(XMP_CRS_NS, "HasCrop")
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+fn orientation_to_steps(orientation: u16) -> Option<u8> {
+    match orientation {
+        1 => Some(0),
+        6 => Some(1),
+        3 => Some(2),
+        8 => Some(3),
+        _ => None,
+    }
+}
+
+/// Size of the source image as loaded by the editor (after EXIF orientation),
+/// together with that EXIF orientation.
+fn oriented_source_dimensions(source_path: &Path) -> Option<(u32, u32, u16)> {
+    let bytes = read_file_mapped(source_path).ok()?;
+    let (width, height, orientation) = if is_raw_file(&source_path.to_string_lossy()) {
+        crate::raw_processing::get_raw_dimensions(&bytes)?
+    } else {
+        let (width, height) = image::ImageReader::new(Cursor::new(&bytes[..]))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()?;
+        let orientation = exif_processing::read_exif(&bytes)
+            .and_then(|exif| {
+                exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                    .and_then(|f| f.value.get_uint(0))
+            })
+            .unwrap_or(1) as u16;
+        (width, height, orientation)
+    };
+    Some(if (5..=8).contains(&orientation) {
+        (height, width, orientation)
+    } else {
+        (width, height, orientation)
+    })
+}
+
+/// Converts Lightroom's normalized, pre-rotation crop and the XMP orientation
+/// into our `crop`, `rotation` and `orientationSteps` adjustments, given the
+/// oriented source size from [`oriented_source_dimensions`]. Returns an empty
+/// map when the XMP has no crop.
+fn extract_xmp_geometry(
+    values: &XmpValues,
+    (width, height, file_orientation): (u32, u32, u16),
+) -> Map<String, Value> {
+    let mut geometry = Map::new();
+    if !xmp_has_crop(values) {
+        return geometry;
+    }
+    let xmp_orientation = values
+        .get(XMP_TIFF_NS, "Orientation")
+        .and_then(|v| v.parse::<u16>().ok())
+        .and_then(orientation_to_steps);
+
+    let steps = match (xmp_orientation, orientation_to_steps(file_orientation)) {
+        (Some(xmp), Some(file)) => (xmp + 4 - file) % 4,
+        _ => 0,
+    };
+    if steps != 0 {
+        geometry.insert("orientationSteps".to_string(), Value::from(steps));
+    }
+
+    let (w, h) = if steps % 2 == 1 {
+        (height as f64, width as f64)
+    } else {
+        (width as f64, height as f64)
+    };
+    let left = extract_xmp_crs_f64(values, "CropLeft")
+        .unwrap_or(0.0)
+        .clamp(0.0, 1.0);
+    let top = extract_xmp_crs_f64(values, "CropTop")
+        .unwrap_or(0.0)
+        .clamp(0.0, 1.0);
+    let right = extract_xmp_crs_f64(values, "CropRight")
+        .unwrap_or(1.0)
+        .clamp(0.0, 1.0);
+    let bottom = extract_xmp_crs_f64(values, "CropBottom")
+        .unwrap_or(1.0)
+        .clamp(0.0, 1.0);
+    let angle = extract_xmp_crs_f64(values, "CropAngle").unwrap_or(0.0);
+    if right <= left || bottom <= top {
+        return geometry;
+    }
+
+    let crop_width = (right - left) * w;
+    let crop_height = (bottom - top) * h;
+    let (theta_sin, theta_cos) = angle.to_radians().sin_cos();
+    let (dx, dy) = (
+        (left + right) / 2.0 * w - w / 2.0,
+        (top + bottom) / 2.0 * h - h / 2.0,
+    );
+    let center_x = w / 2.0 + dx * theta_cos - dy * theta_sin;
+    let center_y = h / 2.0 + dx * theta_sin + dy * theta_cos;
+
+    let x = (center_x - crop_width / 2.0).clamp(0.0, w - crop_width.min(w));
+    let y = (center_y - crop_height / 2.0).clamp(0.0, h - crop_height.min(h));
+    let crop = Crop {
+        x,
+        y,
+        width: crop_width.min(w),
+        height: crop_height.min(h),
+        canvas_color: None,
+    };
+    if let Ok(crop) = serde_json::to_value(crop) {
+        geometry.insert("crop".to_string(), crop);
+    }
+    if angle != 0.0 {
+        geometry.insert("rotation".to_string(), Value::from(angle));
+    }
+    geometry
+}
+
+fn has_adjustments(adjustments: &Value) -> bool {
+    adjustments
+        .as_object()
+        .is_some_and(|a| a.keys().any(|k| k != "rating"))
+}
+
+/// Imports XMP sidecar values into `metadata`. Lightroom crops need the source
+/// dimensions, so they are only imported with `import_geometry`, which folder
+/// listings leave off to avoid reading every RAW header.
+pub fn sync_metadata_from_xmp(
+    source_path: &Path,
+    metadata: &mut ImageMetadata,
+    import_develop: bool,
+    import_geometry: bool,
+) -> bool {
     let xmp_path = source_path.with_extension("xmp");
     let xmp_path_upper = source_path.with_extension("XMP");
     let actual_xmp = if xmp_path.exists() {
@@ -3148,17 +6241,62 @@ pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata)
             changed = true;
         }
 
-        let xmp_label = extract_xmp_label(&content);
-        let xmp_tags = extract_xmp_tags(&content);
-
-        let mut current_tags = metadata.tags.clone().unwrap_or_default();
-        let original_len = current_tags.len();
-        let had_no_tags = metadata.tags.is_none();
+        let values = XmpValues::parse(&content);
 
-        for tag in xmp_tags {
-            if !current_tags.contains(&tag) {
-                current_tags.push(tag);
-            }
+        if let Some(hidden) = extract_xmp_hidden(&values)
+            && hidden != metadata.hidden
+        {
+            metadata.hidden = hidden;
+            changed = true;
+        }
+
+        if import_develop && !has_adjustments(&metadata.adjustments) {
+            let develop = extract_xmp_develop_settings(&values);
+            if !develop.is_empty() {
+                if !metadata.adjustments.is_object() {
+                    metadata.adjustments = Value::Object(Map::new());
+                }
+                if let Some(obj) = metadata.adjustments.as_object_mut() {
+                    obj.extend(develop);
+                }
+                changed = true;
+            }
+        }
+
+        // Adjustments saved by the editor always carry a `crop` key, even after
+        // a reset, so only sidecars that were never edited here take the crop.
+        let has_local_geometry = metadata.adjustments.get("crop").is_some();
+        if import_geometry
+            && !has_local_geometry
+            && xmp_has_crop(&values)
+            && let Some(dimensions) = oriented_source_dimensions(source_path)
+        {
+            let geometry = extract_xmp_geometry(&values, dimensions);
+            if !geometry.is_empty() {
+                if !metadata.adjustments.is_object() {
+                    metadata.adjustments = Value::Object(Map::new());
+                }
+                if let Some(obj) = metadata.adjustments.as_object_mut() {
+                    obj.extend(geometry);
+                }
+                changed = true;
+            }
+        }
+
+        let xmp_label = extract_xmp_label(&content);
+        let xmp_tags = extract_xmp_tags(&values);
+
+        let mut current_tags = metadata.tags.clone().unwrap_or_default();
+        let original_len = current_tags.len();
+        let had_no_tags = metadata.tags.is_none();
+
+        for tag in xmp_tags {
+            let present = current_tags
+                .iter()
+                .any(|t| t.strip_prefix(USER_TAG_PREFIX).unwrap_or(t) == tag);
+            if !present {
+                current_tags.push(tag);
+            }
         }
 
         if let Some(label) = xmp_label {
@@ -3177,117 +6315,1250 @@ pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata)
     changed
 }
 
-pub fn sync_metadata_to_xmp(source_path: &Path, metadata: &ImageMetadata, create_if_missing: bool) {
-    let xmp_path = source_path.with_extension("xmp");
-    let xmp_path_upper = source_path.with_extension("XMP");
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+const XMP_DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XMP_EXIF_NS: &str = "http://ns.adobe.com/exif/1.0/";
+const XMP_CRS_NS: &str = "http://ns.adobe.com/camera-raw-settings/1.0/";
+const XMP_LR_NS: &str = "http://ns.adobe.com/lightroom/1.0/";
+const XMP_TIFF_NS: &str = "http://ns.adobe.com/tiff/1.0/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum XmpProperty {
+    Rating,
+    Label,
+    Hidden,
+    Subject,
+    HierarchicalSubject,
+    GpsLatitude,
+    GpsLongitude,
+    GpsAltitude,
+    GpsAltitudeRef,
+    /// Index into `preset_converter::CRS_DEVELOP_SETTINGS`.
+    Develop(usize),
+}
 
-    let mut actual_xmp = if xmp_path.exists() {
-        Some(xmp_path.clone())
-    } else if xmp_path_upper.exists() {
-        Some(xmp_path_upper.clone())
-    } else {
-        None
-    };
+impl XmpProperty {
+    const ALL: [XmpProperty; 5] = [
+        XmpProperty::Rating,
+        XmpProperty::Label,
+        XmpProperty::Hidden,
+        XmpProperty::Subject,
+        XmpProperty::HierarchicalSubject,
+    ];
+
+    const GPS: [XmpProperty; 4] = [
+        XmpProperty::GpsLatitude,
+        XmpProperty::GpsLongitude,
+        XmpProperty::GpsAltitude,
+        XmpProperty::GpsAltitudeRef,
+    ];
+
+    fn namespace(self) -> &'static str {
+        match self {
+            XmpProperty::Rating | XmpProperty::Label => XMP_NS,
+            XmpProperty::Hidden => XMP_RAPIDRAW_NS,
+            XmpProperty::Subject => XMP_DC_NS,
+            XmpProperty::HierarchicalSubject => XMP_LR_NS,
+            XmpProperty::GpsLatitude
+            | XmpProperty::GpsLongitude
+            | XmpProperty::GpsAltitude
+            | XmpProperty::GpsAltitudeRef => XMP_EXIF_NS,
+            XmpProperty::Develop(_) => XMP_CRS_NS,
+        }
+    }
 
-    if actual_xmp.is_none() {
-        if !create_if_missing {
-            return;
+    fn local_name(self) -> &'static str {
+        match self {
+            XmpProperty::Rating => "Rating",
+            XmpProperty::Label => "Label",
+            XmpProperty::Hidden => "Hidden",
+            XmpProperty::Subject => "subject",
+            XmpProperty::HierarchicalSubject => "hierarchicalSubject",
+            XmpProperty::GpsLatitude => "GPSLatitude",
+            XmpProperty::GpsLongitude => "GPSLongitude",
+            XmpProperty::GpsAltitude => "GPSAltitude",
+            XmpProperty::GpsAltitudeRef => "GPSAltitudeRef",
+            XmpProperty::Develop(i) => preset_converter::CRS_DEVELOP_SETTINGS[i].0,
         }
-        let skeleton = r#"<?xml version="1.0" encoding="UTF-8"?>
-<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
- <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
-  <rdf:Description rdf:about=""
-    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
-    xmlns:dc="http://purl.org/dc/elements/1.1/">
-  </rdf:Description>
- </rdf:RDF>
-</x:xmpmeta>"#;
-        if let Err(e) = fs::write(&xmp_path, skeleton) {
-            log::error!("Failed to create skeleton XMP: {}", e);
-            return;
+    }
+
+    fn default_prefix(self) -> &'static str {
+        match self {
+            XmpProperty::Rating | XmpProperty::Label => "xmp",
+            XmpProperty::Hidden => "rapidraw",
+            XmpProperty::Subject => "dc",
+            XmpProperty::HierarchicalSubject => "lr",
+            XmpProperty::GpsLatitude
+            | XmpProperty::GpsLongitude
+            | XmpProperty::GpsAltitude
+            | XmpProperty::GpsAltitudeRef => "exif",
+            XmpProperty::Develop(_) => "crs",
         }
-        actual_xmp = Some(xmp_path);
     }
 
-    if let Some(xmp_file) = actual_xmp
-        && let Ok(mut content) = fs::read_to_string(&xmp_file)
-    {
-        let rating_str = metadata.rating.to_string();
-        let re_rating_attr = Regex::new(r#"xmp:Rating\s*=\s*"[^"]*""#).unwrap();
-        let re_rating_tag = Regex::new(r#"<xmp:Rating\s*>[^<]*</xmp:Rating>"#).unwrap();
-
-        if re_rating_attr.is_match(&content) {
-            content = re_rating_attr
-                .replace(&content, format!("xmp:Rating=\"{}\"", rating_str))
-                .to_string();
-        } else if re_rating_tag.is_match(&content) {
-            content = re_rating_tag
-                .replace(&content, format!("<xmp:Rating>{}</xmp:Rating>", rating_str))
-                .to_string();
-        } else if let Some(last_index) = content.rfind("</rdf:Description>") {
-            let (start, end) = content.split_at(last_index);
-            content = format!("{} <xmp:Rating>{}</xmp:Rating>\n{}", start, rating_str, end);
-        }
-
-        let current_tags = metadata.tags.clone().unwrap_or_default();
+    fn is_bag(self) -> bool {
+        matches!(
+            self,
+            XmpProperty::Subject | XmpProperty::HierarchicalSubject
+        )
+    }
+}
+
+struct XmpUpdate {
+    rating: String,
+    label: Option<String>,
+    hidden: bool,
+    subjects: Vec<String>,
+    /// Full `|`-delimited keyword paths for `lr:hierarchicalSubject`.
+    hierarchical_subjects: Vec<String>,
+    /// Formatted `crs:` values indexed like `CRS_DEVELOP_SETTINGS`. `None`
+    /// leaves that setting untouched, and the list is empty when develop
+    /// sync is off.
+    develop: Vec<Option<String>>,
+    /// Formatted `exif:` GPS values, or `None` to leave any existing location untouched.
+    gps: Option<[Option<String>; 4]>,
+}
+
+impl XmpUpdate {
+    fn from_metadata(metadata: &ImageMetadata, sync_develop: bool) -> Self {
         let mut label = None;
-        let mut normal_tags = Vec::new();
+        let mut subjects = Vec::new();
+        let mut hierarchical_subjects = Vec::new();
 
-        for t in current_tags {
+        for t in metadata.tags.clone().unwrap_or_default() {
             if let Some(color) = t.strip_prefix(COLOR_TAG_PREFIX) {
                 let mut c = color.chars();
-                let cap_color = match c.next() {
+                label = Some(match c.next() {
                     None => String::new(),
                     Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-                };
-                label = Some(cap_color);
-            } else {
-                normal_tags.push(t);
+                });
+            } else if let Some((_, leaf)) = t.rsplit_once('|') {
+                if !subjects.iter().any(|s| s == leaf) {
+                    subjects.push(leaf.to_string());
+                }
+                let path = t.strip_prefix(USER_TAG_PREFIX).unwrap_or(&t);
+                hierarchical_subjects.push(path.to_string());
+            } else if !subjects.contains(&t) {
+                subjects.push(t);
             }
         }
 
-        if let Some(lbl) = label {
-            let re_label_attr = Regex::new(r#"xmp:Label\s*=\s*"[^"]*""#).unwrap();
-            let re_label_tag = Regex::new(r#"<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
+        let develop = if sync_develop {
+            preset_converter::CRS_DEVELOP_SETTINGS
+                .iter()
+                .map(|(crs_key, key)| {
+                    let value = metadata.adjustments.get(*key)?.as_f64()?;
+                    preset_converter::adjustment_to_crs(crs_key, value)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let gps = metadata.gps.map(|gps| {
+            [
+                Some(exif_processing::format_xmp_gps_coord(gps.lat, 'N', 'S')),
+                Some(exif_processing::format_xmp_gps_coord(gps.lon, 'E', 'W')),
+                gps.altitude
+                    .map(|alt| format!("{}/100", (alt.abs() * 100.0).round() as u32)),
+                gps.altitude
+                    .map(|alt| if alt < 0.0 { "1" } else { "0" }.to_string()),
+            ]
+        });
+
+        Self {
+            rating: metadata.rating.to_string(),
+            label,
+            hidden: metadata.hidden,
+            subjects,
+            hierarchical_subjects,
+            develop,
+            gps,
+        }
+    }
+
+    fn properties(&self) -> impl Iterator<Item = XmpProperty> + '_ {
+        XmpProperty::ALL
+            .into_iter()
+            .chain(self.gps.iter().flat_map(|_| XmpProperty::GPS))
+            .chain(
+                self.develop
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| value.is_some())
+                    .map(|(i, _)| XmpProperty::Develop(i)),
+            )
+    }
 
-            if re_label_attr.is_match(&content) {
-                content = re_label_attr
-                    .replace(&content, format!("xmp:Label=\"{}\"", lbl))
-                    .to_string();
-            } else if re_label_tag.is_match(&content) {
-                content = re_label_tag
-                    .replace(&content, format!("<xmp:Label>{}</xmp:Label>", lbl))
-                    .to_string();
-            } else if let Some(last_index) = content.rfind("</rdf:Description>") {
-                let (start, end) = content.split_at(last_index);
-                content = format!("{} <xmp:Label>{}</xmp:Label>\n{}", start, lbl, end);
+    fn resolve(&self, namespace: &str, local_name: &str) -> Option<XmpProperty> {
+        self.properties()
+            .find(|p| p.namespace() == namespace && p.local_name() == local_name)
+    }
+
+    /// An existing `Hidden` flag is rewritten as `False` rather than dropped, so
+    /// other libraries syncing the same XMP pick up the unhide.
+    fn keeps_existing(&self, property: XmpProperty) -> bool {
+        property == XmpProperty::Hidden || self.is_set(property)
+    }
+
+    fn is_set(&self, property: XmpProperty) -> bool {
+        match property {
+            XmpProperty::Subject | XmpProperty::HierarchicalSubject => {
+                !self.bag_values(property).is_empty()
             }
-        } else {
-            let re_label_attr = Regex::new(r#"\s*xmp:Label\s*=\s*"[^"]*""#).unwrap();
-            let re_label_tag = Regex::new(r#"\s*<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
-            content = re_label_attr.replace_all(&content, "").to_string();
-            content = re_label_tag.replace_all(&content, "").to_string();
+            XmpProperty::Hidden => self.hidden,
+            _ => self.simple_value(property).is_some(),
         }
+    }
 
-        let re_subject =
-            Regex::new(r#"(?s)<dc:subject>\s*<rdf:Bag>.*?</rdf:Bag>\s*</dc:subject>"#).unwrap();
-        if normal_tags.is_empty() {
-            content = re_subject.replace_all(&content, "").to_string();
-        } else {
-            let mut bag = String::from("<dc:subject>\n    <rdf:Bag>\n");
-            for t in normal_tags {
-                bag.push_str(&format!("     <rdf:li>{}</rdf:li>\n", t));
+    fn simple_value(&self, property: XmpProperty) -> Option<&str> {
+        match property {
+            XmpProperty::Rating => Some(&self.rating),
+            XmpProperty::Label => self.label.as_deref(),
+            XmpProperty::Hidden => Some(if self.hidden { "True" } else { "False" }),
+            XmpProperty::Subject | XmpProperty::HierarchicalSubject => None,
+            XmpProperty::GpsLatitude => self.gps_value(0),
+            XmpProperty::GpsLongitude => self.gps_value(1),
+            XmpProperty::GpsAltitude => self.gps_value(2),
+            XmpProperty::GpsAltitudeRef => self.gps_value(3),
+            XmpProperty::Develop(i) => self.develop[i].as_deref(),
+        }
+    }
+}
+
+impl XmpUpdate {
+    fn bag_values(&self, property: XmpProperty) -> &[String] {
+        match property {
+            XmpProperty::Subject => &self.subjects,
+            XmpProperty::HierarchicalSubject => &self.hierarchical_subjects,
+            _ => &[],
+        }
+    }
+
+    fn gps_value(&self, index: usize) -> Option<&str> {
+        self.gps
+            .as_ref()
+            .and_then(|values| values[index].as_deref())
+    }
+}
+
+/// Tracks `xmlns` bindings so properties are matched by namespace URI rather
+/// than by whatever prefix the writing application chose.
+#[derive(Default)]
+struct XmpNamespaces {
+    scopes: Vec<Vec<(String, String)>>,
+}
+
+impl XmpNamespaces {
+    fn push(&mut self, start: &BytesStart) {
+        let bindings = start
+            .attributes()
+            .with_checks(false)
+            .filter_map(|a| a.ok())
+            .filter_map(|a| {
+                let key = std::str::from_utf8(a.key.as_ref()).ok()?;
+                let prefix = key.strip_prefix("xmlns:")?;
+                Some((
+                    prefix.to_string(),
+                    String::from_utf8_lossy(&a.value).into_owned(),
+                ))
+            })
+            .collect();
+        self.scopes.push(bindings);
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn uri_for_prefix(&self, prefix: &str) -> Option<&str> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter())
+            .find(|(p, _)| p == prefix)
+            .map(|(_, uri)| uri.as_str())
+    }
+
+    fn prefix_for_uri(&self, uri: &str) -> Option<&str> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter())
+            .find(|(p, u)| u == uri && self.uri_for_prefix(p) == Some(uri))
+            .map(|(p, _)| p.as_str())
+    }
+
+    fn resolve<'n>(&self, qname: &'n [u8]) -> Option<(&str, &'n str)> {
+        let qname = std::str::from_utf8(qname).ok()?;
+        let (prefix, local) = qname.split_once(':')?;
+        Some((self.uri_for_prefix(prefix)?, local))
+    }
+}
+
+/// Simple and array property values from every `rdf:Description` of an XMP packet,
+/// keyed by namespace URI and local name so any prefix binding is understood.
+#[derive(Default)]
+struct XmpValues {
+    simple: HashMap<(String, String), String>,
+    arrays: HashMap<(String, String), Vec<String>>,
+}
+
+impl XmpValues {
+    fn parse(content: &str) -> Self {
+        let mut values = XmpValues::default();
+        let mut reader = Reader::from_str(content);
+        let mut namespaces = XmpNamespaces::default();
+        let mut in_description = false;
+        // Resolved names of the elements open inside the current description.
+        let mut path: Vec<(String, String)> = Vec::new();
+        let mut text = String::new();
+
+        while let Ok(event) = reader.read_event() {
+            match event {
+                Event::Start(ref start) | Event::Empty(ref start) => {
+                    let is_empty = matches!(event, Event::Empty(_));
+                    namespaces.push(start);
+                    let name = namespaces
+                        .resolve(start.name().as_ref())
+                        .map(|(ns, local)| (ns.to_string(), local.to_string()))
+                        .unwrap_or_default();
+                    if !in_description {
+                        if name.0 == XMP_RDF_NS && name.1 == "Description" {
+                            values.read_attributes(&namespaces, start);
+                            in_description = !is_empty;
+                        }
+                    } else if !is_empty {
+                        path.push(name);
+                    }
+                    text.clear();
+                    if is_empty {
+                        namespaces.pop();
+                    }
+                }
+                Event::Text(t) => text.push_str(&t.decode().unwrap_or_default()),
+                Event::GeneralRef(r) => {
+                    let resolved = match r.resolve_char_ref() {
+                        Ok(Some(c)) => Some(c.to_string()),
+                        _ => r.decode().ok().and_then(|name| {
+                            quick_xml::escape::resolve_predefined_entity(&name).map(str::to_string)
+                        }),
+                    };
+                    text.push_str(&resolved.unwrap_or_default());
+                }
+                Event::End(_) => {
+                    namespaces.pop();
+                    if !in_description {
+                        continue;
+                    }
+                    let Some(name) = path.pop() else {
+                        in_description = false;
+                        continue;
+                    };
+                    let value = text.trim().to_string();
+                    text.clear();
+                    if path.is_empty() && !value.is_empty() {
+                        values.simple.insert(name, value);
+                    } else if path.len() == 2 && name.0 == XMP_RDF_NS && name.1 == "li" {
+                        values
+                            .arrays
+                            .entry(path[0].clone())
+                            .or_default()
+                            .push(value);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        values
+    }
+
+    fn read_attributes(&mut self, namespaces: &XmpNamespaces, start: &BytesStart) {
+        for attr in start.attributes().with_checks(false).filter_map(|a| a.ok()) {
+            if let Some((ns, local)) = namespaces.resolve(attr.key.as_ref())
+                && ns != XMP_RDF_NS
+                && let Ok(value) = attr.unescape_value()
+            {
+                self.simple.insert(
+                    (ns.to_string(), local.to_string()),
+                    value.trim().to_string(),
+                );
+            }
+        }
+    }
+
+    fn get(&self, namespace: &str, local_name: &str) -> Option<&str> {
+        self.simple
+            .get(&(namespace.to_string(), local_name.to_string()))
+            .map(String::as_str)
+    }
+
+    fn array(&self, namespace: &str, local_name: &str) -> &[String] {
+        self.arrays
+            .get(&(namespace.to_string(), local_name.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+fn is_xml_whitespace(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| b.is_ascii_whitespace())
+}
+
+fn write_xmp_event(writer: &mut Writer<Vec<u8>>, event: Event) -> Result<(), String> {
+    writer.write_event(event).map_err(|e| e.to_string())
+}
+
+fn write_xmp_property(
+    writer: &mut Writer<Vec<u8>>,
+    update: &XmpUpdate,
+    property: XmpProperty,
+    qname: &str,
+    rdf_prefix: &str,
+    indent: &str,
+) -> Result<(), String> {
+    write_xmp_event(writer, Event::Start(BytesStart::new(qname)))?;
+    if property.is_bag() {
+        let bag = format!("{}:Bag", rdf_prefix);
+        let li = format!("{}:li", rdf_prefix);
+        write_xmp_event(
+            writer,
+            Event::Text(BytesText::from_escaped(format!("{} ", indent))),
+        )?;
+        write_xmp_event(writer, Event::Start(BytesStart::new(bag.as_str())))?;
+        for subject in update.bag_values(property) {
+            write_xmp_event(
+                writer,
+                Event::Text(BytesText::from_escaped(format!("{}  ", indent))),
+            )?;
+            write_xmp_event(writer, Event::Start(BytesStart::new(li.as_str())))?;
+            write_xmp_event(writer, Event::Text(BytesText::new(subject)))?;
+            write_xmp_event(writer, Event::End(BytesEnd::new(li.as_str())))?;
+        }
+        write_xmp_event(
+            writer,
+            Event::Text(BytesText::from_escaped(format!("{} ", indent))),
+        )?;
+        write_xmp_event(writer, Event::End(BytesEnd::new(bag.as_str())))?;
+        write_xmp_event(
+            writer,
+            Event::Text(BytesText::from_escaped(indent.to_string())),
+        )?;
+    } else if let Some(value) = update.simple_value(property) {
+        write_xmp_event(writer, Event::Text(BytesText::new(value)))?;
+    }
+    write_xmp_event(writer, Event::End(BytesEnd::new(qname)))
+}
+
+const XMP_SKELETON: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+/// Rewrites the managed properties of an XMP packet in a single streaming
+/// pass, leaving every other node and its formatting untouched.
+fn rewrite_xmp(content: &str, update: &XmpUpdate) -> Result<String, String> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(false);
+    let mut writer = Writer::new(Vec::with_capacity(content.len() + 256));
+
+    let mut namespaces = XmpNamespaces::default();
+    let mut written: HashSet<XmpProperty> = HashSet::new();
+    let mut insert_prefixes: HashMap<XmpProperty, String> = HashMap::new();
+    let mut rdf_prefix = "rdf".to_string();
+
+    let mut depth = 0usize;
+    let mut description_depth: Option<usize> = None;
+    let mut in_first_description = false;
+    let mut seen_description = false;
+    let mut child_indent: Option<String> = None;
+    let mut pending_whitespace: Option<String> = None;
+
+    let mut skip_depth = 0usize;
+
+    loop {
+        let event = reader.read_event().map_err(|e| e.to_string())?;
+
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
             }
-            bag.push_str("    </rdf:Bag>\n   </dc:subject>");
+            continue;
+        }
 
-            if re_subject.is_match(&content) {
-                content = re_subject.replace(&content, bag).to_string();
-            } else if let Some(last_index) = content.rfind("</rdf:Description>") {
-                let (start, end) = content.split_at(last_index);
-                content = format!("{} {}\n  {}", start, bag, end);
+        if let Event::Text(text) = &event
+            && is_xml_whitespace(text)
+        {
+            if let Some(ws) = pending_whitespace.take() {
+                write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
             }
+            pending_whitespace = Some(String::from_utf8_lossy(text).into_owned());
+            continue;
         }
 
-        let _ = fs::write(&xmp_file, content);
+        match event {
+            Event::Start(ref start) | Event::Empty(ref start)
+                if description_depth.is_some_and(|d| d == depth) =>
+            {
+                let is_empty = matches!(event, Event::Empty(_));
+                let property = namespaces
+                    .resolve(start.name().as_ref())
+                    .and_then(|(ns, local)| update.resolve(ns, local));
+
+                let Some(property) = property else {
+                    if in_first_description {
+                        child_indent = pending_whitespace.clone().or(child_indent);
+                    }
+                    if let Some(ws) = pending_whitespace.take() {
+                        write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
+                    }
+                    if !is_empty {
+                        namespaces.push(start);
+                        depth += 1;
+                    }
+                    write_xmp_event(&mut writer, event.borrow())?;
+                    continue;
+                };
+
+                let keep = update.keeps_existing(property) && !written.contains(&property);
+                if keep {
+                    let ws = pending_whitespace.take().unwrap_or_default();
+                    if in_first_description {
+                        child_indent = Some(ws.clone());
+                    }
+                    write_xmp_event(
+                        &mut writer,
+                        Event::Text(BytesText::from_escaped(ws.clone())),
+                    )?;
+                    let qname = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                    write_xmp_property(&mut writer, update, property, &qname, &rdf_prefix, &ws)?;
+                    written.insert(property);
+                } else {
+                    pending_whitespace = None;
+                }
+                if !is_empty {
+                    skip_depth = 1;
+                }
+            }
+            Event::Start(ref start) | Event::Empty(ref start) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                namespaces.push(start);
+                let is_description = description_depth.is_none()
+                    && namespaces
+                        .resolve(start.name().as_ref())
+                        .is_some_and(|(ns, local)| ns == XMP_RDF_NS && local == "Description");
+
+                if let Some(ws) = pending_whitespace.take() {
+                    write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
+                }
+
+                if !is_description {
+                    if is_empty {
+                        namespaces.pop();
+                    } else {
+                        depth += 1;
+                    }
+                    write_xmp_event(&mut writer, event.borrow())?;
+                    continue;
+                }
+
+                let is_first = !seen_description;
+                seen_description = true;
+                let qname = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                if let Some((prefix, _)) = qname.split_once(':') {
+                    rdf_prefix = prefix.to_string();
+                }
+
+                let mut rebuilt = BytesStart::new(qname.clone());
+                let mut changed = false;
+                for attr in start.attributes().with_checks(false).filter_map(|a| a.ok()) {
+                    let property = namespaces
+                        .resolve(attr.key.as_ref())
+                        .and_then(|(ns, local)| update.resolve(ns, local));
+                    match property.filter(|p| !p.is_bag()) {
+                        Some(p) => match update.simple_value(p) {
+                            Some(value) if !written.contains(&p) => {
+                                changed |= attr.value.as_ref() != value.as_bytes();
+                                let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+                                rebuilt.push_attribute((key.as_str(), value));
+                                written.insert(p);
+                            }
+                            _ => changed = true,
+                        },
+                        None => rebuilt.push_attribute(attr),
+                    }
+                }
+
+                if is_first {
+                    for property in update.properties() {
+                        if !update.is_set(property) || written.contains(&property) {
+                            continue;
+                        }
+                        let prefix = match namespaces.prefix_for_uri(property.namespace()) {
+                            Some(prefix) => prefix.to_string(),
+                            None => {
+                                let prefix = property.default_prefix();
+                                rebuilt.push_attribute((
+                                    format!("xmlns:{}", prefix).as_str(),
+                                    property.namespace(),
+                                ));
+                                namespaces
+                                    .scopes
+                                    .last_mut()
+                                    .unwrap()
+                                    .push((prefix.to_string(), property.namespace().to_string()));
+                                changed = true;
+                                prefix.to_string()
+                            }
+                        };
+                        insert_prefixes.insert(property, prefix);
+                    }
+                }
+
+                let needs_children = is_first && !insert_prefixes.is_empty();
+                if is_empty && needs_children {
+                    write_xmp_event(&mut writer, Event::Start(rebuilt))?;
+                    let indent = "\n   ".to_string();
+                    for property in update.properties() {
+                        if let Some(prefix) = insert_prefixes.get(&property)
+                            && !written.contains(&property)
+                        {
+                            let child = format!("{}:{}", prefix, property.local_name());
+                            write_xmp_event(
+                                &mut writer,
+                                Event::Text(BytesText::from_escaped(indent.clone())),
+                            )?;
+                            write_xmp_property(
+                                &mut writer,
+                                update,
+                                property,
+                                &child,
+                                &rdf_prefix,
+                                &indent,
+                            )?;
+                            written.insert(property);
+                        }
+                    }
+                    write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped("\n  ")))?;
+                    write_xmp_event(&mut writer, Event::End(BytesEnd::new(qname)))?;
+                    namespaces.pop();
+                } else if is_empty {
+                    if changed {
+                        write_xmp_event(&mut writer, Event::Empty(rebuilt))?;
+                    } else {
+                        write_xmp_event(&mut writer, event.borrow())?;
+                    }
+                    namespaces.pop();
+                } else {
+                    if changed {
+                        write_xmp_event(&mut writer, Event::Start(rebuilt))?;
+                    } else {
+                        write_xmp_event(&mut writer, event.borrow())?;
+                    }
+                    depth += 1;
+                    description_depth = Some(depth);
+                    in_first_description = is_first;
+                }
+            }
+            Event::End(end) => {
+                if description_depth == Some(depth) {
+                    if in_first_description {
+                        let closing = pending_whitespace.take();
+                        let indent = child_indent.clone().unwrap_or_else(|| {
+                            format!("{} ", closing.as_deref().unwrap_or("\n  "))
+                        });
+                        for property in update.properties() {
+                            if let Some(prefix) = insert_prefixes.get(&property)
+                                && !written.contains(&property)
+                            {
+                                let child = format!("{}:{}", prefix, property.local_name());
+                                write_xmp_event(
+                                    &mut writer,
+                                    Event::Text(BytesText::from_escaped(indent.clone())),
+                                )?;
+                                write_xmp_property(
+                                    &mut writer,
+                                    update,
+                                    property,
+                                    &child,
+                                    &rdf_prefix,
+                                    &indent,
+                                )?;
+                                written.insert(property);
+                            }
+                        }
+                        pending_whitespace = closing;
+                    }
+                    description_depth = None;
+                    in_first_description = false;
+                }
+                if let Some(ws) = pending_whitespace.take() {
+                    write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
+                }
+                namespaces.pop();
+                depth = depth.saturating_sub(1);
+                write_xmp_event(&mut writer, Event::End(end))?;
+            }
+            Event::Eof => break,
+            other => {
+                if let Some(ws) = pending_whitespace.take() {
+                    write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
+                }
+                write_xmp_event(&mut writer, other)?;
+            }
+        }
+    }
+
+    if let Some(ws) = pending_whitespace.take() {
+        write_xmp_event(&mut writer, Event::Text(BytesText::from_escaped(ws)))?;
+    }
+
+    String::from_utf8(writer.into_inner()).map_err(|e| e.to_string())
+}
+
+/// Returns whether the XMP file was written, which is `false` when there is none to
+/// update or it already matches.
+pub fn sync_metadata_to_xmp(
+    source_path: &Path,
+    metadata: &ImageMetadata,
+    create_if_missing: bool,
+    sync_develop: bool,
+) -> Result<bool, String> {
+    let xmp_path = source_path.with_extension("xmp");
+    let xmp_path_upper = source_path.with_extension("XMP");
+
+    let xmp_file = if xmp_path.exists() {
+        xmp_path
+    } else if xmp_path_upper.exists() {
+        xmp_path_upper
+    } else if create_if_missing {
+        fs::write(&xmp_path, XMP_SKELETON)
+            .map_err(|e| format!("Failed to create skeleton XMP: {}", e))?;
+        xmp_path
+    } else {
+        return Ok(false);
+    };
+
+    let content = fs::read_to_string(&xmp_file)
+        .map_err(|e| format!("Failed to read XMP {}: {}", xmp_file.display(), e))?;
+    let updated = rewrite_xmp(&content, &XmpUpdate::from_metadata(metadata, sync_develop))?;
+    if updated == content {
+        return Ok(false);
+    }
+    fs::write(&xmp_file, updated)
+        .map_err(|e| format!("Failed to write XMP {}: {}", xmp_file.display(), e))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(path: &str, capture_time: Option<i64>) -> (ImageFile, Option<i64>) {
+        (
+            ImageFile {
+                path: path.to_string(),
+                modified: 0,
+                is_edited: false,
+                rating: 0,
+                tags: None,
+                exif: None,
+                is_virtual_copy: false,
+                group_id: None,
+                is_hidden: false,
+                capture_time,
+            },
+            capture_time,
+        )
+    }
+
+    #[test]
+    fn bursts_need_exif_time_and_consecutive_frames() {
+        let mut entries = vec![
+            listed("/shoot/IMG_0001.CR2", Some(100)),
+            listed("/shoot/IMG_0001.JPG", Some(100)),
+            listed("/shoot/IMG_0002.CR2", Some(101)),
+            listed("/shoot/IMG_0003.CR2", Some(110)),
+            listed("/shoot/DSC_0005.ARW", Some(101)),
+            listed("/shoot/IMG_0004.CR2", None),
+            listed("/shoot/IMG_0005.CR2", None),
+        ];
+        assign_burst_groups(&mut entries);
+
+        let groups: Vec<Option<&str>> = entries
+            .iter()
+            .map(|(image, _)| image.group_id.as_deref())
+            .collect();
+        let burst = Some("/shoot/IMG_0001.CR2");
+        assert_eq!(groups, vec![burst, burst, burst, None, None, None, None]);
+    }
+
+    #[test]
+    fn case_probe_matches_filesystem_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("probe.txt"), []).unwrap();
+        let expected = dir.path().join("PROBE.TXT").exists();
+
+        assert_eq!(is_case_insensitive_fs(dir.path()), expected);
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != "probe.txt")
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn folder_case_collisions_follow_the_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let insensitive = is_case_insensitive_fs(dir.path());
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+        create_folder(path("Photos")).unwrap();
+        assert_eq!(create_folder(path("photos")).is_err(), insensitive);
+
+        create_folder(path("Archive")).unwrap();
+        assert_eq!(
+            rename_folder(path("Archive"), "PHOTOS".to_string()).is_err(),
+            insensitive
+        );
+    }
+
+    #[test]
+    fn renamed_destinations_skip_paths_planned_in_the_same_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = Path::new("/elsewhere/IMG_0001.jpg");
+        let files = vec![source.to_path_buf()];
+        fs::write(dir.path().join("IMG_0001.jpg"), b"").unwrap();
+
+        let mut planned = HashSet::new();
+        planned.insert(dir.path().join("IMG_0001_1.jpg"));
+        let renamed = first_free_renamed_destination(source, &files, dir.path(), &planned).unwrap();
+
+        assert_eq!(renamed, vec![dir.path().join("IMG_0001_2.jpg")]);
+        assert!(destination_taken(
+            &dir.path().join("IMG_0001.jpg"),
+            &HashSet::new()
+        ));
+        assert!(!destination_taken(&renamed[0], &planned));
+    }
+
+    #[test]
+    fn shared_stem_companions_stay_with_the_remaining_image() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "IMG_0001.CR2",
+            "IMG_0001.JPG",
+            "IMG_0001.xmp",
+            "IMG_0001.JPG.rrdata",
+        ] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let raw = dir.path().join("IMG_0001.CR2");
+        let jpg = dir.path().join("IMG_0001.JPG");
+        let extensions: HashSet<String> = default_associated_extensions().into_iter().collect();
+        let sorted = |mut files: Vec<PathBuf>| {
+            files.sort();
+            files
+        };
+
+        let jpg_only = HashSet::from([jpg.clone()]);
+        assert_eq!(
+            sorted(find_all_associated_files(&jpg, &extensions, &jpg_only).unwrap()),
+            vec![jpg.clone(), dir.path().join("IMG_0001.JPG.rrdata")]
+        );
+
+        let pair = HashSet::from([raw.clone(), jpg.clone()]);
+        assert_eq!(
+            sorted(find_all_associated_files(&raw, &extensions, &pair).unwrap()),
+            vec![raw.clone(), dir.path().join("IMG_0001.xmp")]
+        );
+        assert!(
+            !find_all_associated_files(&jpg, &extensions, &pair)
+                .unwrap()
+                .contains(&dir.path().join("IMG_0001.xmp"))
+        );
+    }
+
+    #[test]
+    fn thumbnail_geometry_cache_keeps_one_base_per_path() {
+        let mut cache = ThumbnailGeometryCache::default();
+        let base = || DynamicImage::new_rgb8(1, 1);
+        cache.insert("other.jpg".to_string(), 1, base(), 1.0);
+        for geometry_hash in 0..100 {
+            cache.insert("edited.jpg".to_string(), geometry_hash, base(), 1.0);
+        }
+
+        assert!(cache.get("other.jpg", 1).is_some());
+        assert!(cache.get("edited.jpg", 98).is_none());
+        assert!(cache.get("edited.jpg", 99).is_some());
+    }
+
+    #[test]
+    fn moved_copies_edited_after_the_move_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let copy = dir.path().join("IMG_0001.jpg.rrdata");
+        fs::write(&copy, b"{}").unwrap();
+
+        let moved = MovedCopy::new(&dir.path().join("original"), &copy);
+        assert!(!moved.edited_since_move());
+
+        let later = moved.modified.unwrap() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&copy)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(moved.edited_since_move());
+    }
+
+    #[test]
+    fn xmp_sync_reports_whether_it_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("IMG_0001.CR2");
+        let metadata = ImageMetadata {
+            rating: 3,
+            ..ImageMetadata::default()
+        };
+
+        assert_eq!(
+            sync_metadata_to_xmp(&source, &metadata, false, false),
+            Ok(false)
+        );
+        assert_eq!(
+            sync_metadata_to_xmp(&source, &metadata, true, false),
+            Ok(true)
+        );
+        assert_eq!(
+            sync_metadata_to_xmp(&source, &metadata, true, false),
+            Ok(false)
+        );
+
+        fs::write(source.with_extension("xmp"), "not xmp <").unwrap();
+        assert!(sync_metadata_to_xmp(&source, &metadata, true, false).is_err());
+    }
+
+    fn hidden_update(hidden: bool) -> XmpUpdate {
+        let metadata = ImageMetadata {
+            hidden,
+            ..Default::default()
+        };
+        XmpUpdate::from_metadata(&metadata, false)
+    }
+
+    #[test]
+    fn hidden_flag_round_trips_through_xmp() {
+        let hidden = rewrite_xmp(XMP_SKELETON, &hidden_update(true)).unwrap();
+        assert_eq!(extract_xmp_hidden(&XmpValues::parse(&hidden)), Some(true));
+
+        let unhidden = rewrite_xmp(&hidden, &hidden_update(false)).unwrap();
+        assert_eq!(
+            extract_xmp_hidden(&XmpValues::parse(&unhidden)),
+            Some(false)
+        );
+
+        let untouched = rewrite_xmp(XMP_SKELETON, &hidden_update(false)).unwrap();
+        assert_eq!(extract_xmp_hidden(&XmpValues::parse(&untouched)), None);
+    }
+
+    #[test]
+    fn hidden_flag_is_read_from_any_prefix() {
+        let content = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <r:RDF xmlns:r="{}">
+  <r:Description r:about="" xmlns:rr="{}" rr:Hidden="True"/>
+ </r:RDF>
+</x:xmpmeta>"#,
+            XMP_RDF_NS, XMP_RAPIDRAW_NS
+        );
+        assert_eq!(extract_xmp_hidden(&XmpValues::parse(&content)), Some(true));
+    }
+
+    #[test]
+    fn hierarchical_keywords_round_trip_without_the_user_prefix() {
+        let metadata = ImageMetadata {
+            tags: Some(vec![
+                "user:Animals|Birds|Owl".to_string(),
+                "landscape".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let update = XmpUpdate::from_metadata(&metadata, false);
+        assert_eq!(update.hierarchical_subjects, vec!["Animals|Birds|Owl"]);
+        assert_eq!(update.subjects, vec!["Owl", "landscape"]);
+
+        let rewritten = rewrite_xmp(XMP_SKELETON, &update).unwrap();
+        assert_eq!(
+            extract_xmp_tags(&XmpValues::parse(&rewritten)),
+            vec!["landscape", "Animals|Birds|Owl"]
+        );
+    }
+
+    #[test]
+    fn keywords_are_read_from_any_prefix() {
+        let content = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <r:RDF xmlns:r="{rdf}">
+  <r:Description r:about="" xmlns:d="{dc}" xmlns:l="{lr}">
+   <d:subject><r:Bag><r:li>Owl</r:li><r:li>night</r:li></r:Bag></d:subject>
+   <l:hierarchicalSubject><r:Bag><r:li>Animals|Owl</r:li></r:Bag></l:hierarchicalSubject>
+  </r:Description>
+ </r:RDF>
+</x:xmpmeta>"#,
+            rdf = XMP_RDF_NS,
+            dc = XMP_DC_NS,
+            lr = XMP_LR_NS
+        );
+        assert_eq!(
+            extract_xmp_tags(&XmpValues::parse(&content)),
+            vec!["night", "Animals|Owl"]
+        );
+    }
+
+    #[test]
+    fn missing_camera_tokens_collapse_only_template_separators() {
+        assert_eq!(collapse_missing_tokens("a_\u{0}_b"), "a_b");
+        assert_eq!(collapse_missing_tokens("a\u{0}-b"), "a-b");
+        assert_eq!(collapse_missing_tokens("a_\u{0} \u{0}.b"), "a_b");
+        assert_eq!(collapse_missing_tokens("\u{0}_a"), "a");
+        assert_eq!(collapse_missing_tokens("a - \u{0}"), "a");
+
+        let name = generate_filename_from_template(
+            "{original_filename}_{camera_model}",
+            Path::new("/missing/IMG__001.jpg"),
+            1,
+            1,
+            &Utc::now(),
+        );
+        assert_eq!(name, "IMG__001");
+    }
+
+    fn tagged_update(rating: u8, tags: &[&str]) -> XmpUpdate {
+        let metadata = ImageMetadata {
+            rating,
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            ..Default::default()
+        };
+        XmpUpdate::from_metadata(&metadata, false)
+    }
+
+    const LIGHTROOM_XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="Adobe XMP Core 7.0">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:tiff="http://ns.adobe.com/tiff/1.0/"
+    xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/"
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+    xmp:Rating="2"
+    xmp:Label="Blue"
+    tiff:Make="Canon"
+    crs:Exposure2012="+0.50">
+   <photoshop:DateCreated>2024-05-01T10:00:00</photoshop:DateCreated>
+   <crs:ToneCurvePV2012>
+    <rdf:Seq>
+     <rdf:li>0, 0</rdf:li>
+     <rdf:li>255, 255</rdf:li>
+    </rdf:Seq>
+   </crs:ToneCurvePV2012>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    #[test]
+    fn rewrite_updates_attributes_in_place_and_keeps_other_namespaces() {
+        let update = tagged_update(4, &["color:red", "travel", "places|Lisbon"]);
+        let rewritten = rewrite_xmp(LIGHTROOM_XMP, &update).unwrap();
+        let values = XmpValues::parse(&rewritten);
+
+        assert_eq!(values.get(XMP_NS, "Rating"), Some("4"));
+        assert_eq!(values.get(XMP_NS, "Label"), Some("Red"));
+        assert_eq!(rewritten.matches("Rating").count(), 1);
+        assert!(rewritten.contains(r#"xmp:Rating="4""#));
+
+        assert_eq!(values.get(XMP_TIFF_NS, "Make"), Some("Canon"));
+        assert_eq!(values.get(XMP_CRS_NS, "Exposure2012"), Some("+0.50"));
+        assert_eq!(
+            values.get("http://ns.adobe.com/photoshop/1.0/", "DateCreated"),
+            Some("2024-05-01T10:00:00")
+        );
+        assert_eq!(
+            values.array(XMP_CRS_NS, "ToneCurvePV2012"),
+            ["0, 0", "255, 255"]
+        );
+        assert_eq!(values.array(XMP_DC_NS, "subject"), ["travel", "Lisbon"]);
+
+        assert_eq!(rewrite_xmp(&rewritten, &update).unwrap(), rewritten);
+    }
+
+    #[test]
+    fn rewrite_matches_properties_by_namespace_not_prefix() {
+        let content = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <r:RDF xmlns:r="{rdf}">
+  <r:Description r:about="" xmlns:xap="{xmp}" xmlns:d="{dc}">
+   <xap:Rating>1</xap:Rating>
+   <d:subject>
+    <r:Bag>
+     <r:li>old</r:li>
+    </r:Bag>
+   </d:subject>
+  </r:Description>
+ </r:RDF>
+</x:xmpmeta>"#,
+            rdf = XMP_RDF_NS,
+            xmp = XMP_NS,
+            dc = XMP_DC_NS
+        );
+        let rewritten = rewrite_xmp(&content, &tagged_update(3, &["new"])).unwrap();
+        let values = XmpValues::parse(&rewritten);
+
+        assert_eq!(values.get(XMP_NS, "Rating"), Some("3"));
+        assert_eq!(values.array(XMP_DC_NS, "subject"), ["new"]);
+        assert!(rewritten.contains("<xap:Rating>3</xap:Rating>"));
+        assert!(rewritten.contains("<r:li>new</r:li>"));
+        assert!(!rewritten.contains("xmlns:xmp="));
+        assert!(!rewritten.contains("xmlns:dc="));
+    }
+
+    #[test]
+    fn rewrite_drops_cleared_properties() {
+        let update = tagged_update(0, &[]);
+        let rewritten = rewrite_xmp(LIGHTROOM_XMP, &update).unwrap();
+        let values = XmpValues::parse(&rewritten);
+
+        assert_eq!(values.get(XMP_NS, "Rating"), Some("0"));
+        assert_eq!(values.get(XMP_NS, "Label"), None);
+        assert!(values.array(XMP_DC_NS, "subject").is_empty());
+        assert_eq!(values.get(XMP_TIFF_NS, "Make"), Some("Canon"));
+    }
+
+    #[test]
+    fn develop_sync_writes_only_adjustments_that_are_set() {
+        let metadata = ImageMetadata {
+            rating: 2,
+            adjustments: serde_json::json!({ "exposure": 1.0, "temperature": 40.0 }),
+            ..Default::default()
+        };
+        let update = XmpUpdate::from_metadata(&metadata, true);
+        let rewritten = rewrite_xmp(LIGHTROOM_XMP, &update).unwrap();
+        let values = XmpValues::parse(&rewritten);
+
+        assert_eq!(values.get(XMP_CRS_NS, "Exposure2012"), Some("+1.00"));
+        assert_eq!(values.get(XMP_CRS_NS, "Contrast2012"), None);
+        assert_eq!(values.get(XMP_CRS_NS, "Temperature"), None);
+        assert_eq!(
+            values.array(XMP_CRS_NS, "ToneCurvePV2012"),
+            ["0, 0", "255, 255"]
+        );
+    }
+
+    const LIGHTROOM_CROP_XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:tiff="http://ns.adobe.com/tiff/1.0/"
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+    tiff:Orientation="6"
+    crs:CropTop="0.2"
+    crs:CropLeft="0.1"
+    crs:CropBottom="0.7"
+    crs:CropRight="0.6"
+    crs:CropAngle="0">
+   <crs:HasCrop>True</crs:HasCrop>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    fn crop_pixels(geometry: &Map<String, Value>) -> [i64; 4] {
+        ["x", "y", "width", "height"].map(|k| geometry["crop"][k].as_f64().unwrap().round() as i64)
+    }
+
+    #[test]
+    fn lightroom_crop_is_converted_to_pixels() {
+        let values = XmpValues::parse(LIGHTROOM_CROP_XMP);
+
+        let geometry = extract_xmp_geometry(&values, (6000, 4000, 6));
+        assert_eq!(geometry.get("orientationSteps"), None);
+        assert_eq!(geometry.get("rotation"), None);
+        assert_eq!(crop_pixels(&geometry), [600, 800, 3000, 2000]);
+
+        let geometry = extract_xmp_geometry(&values, (6000, 4000, 1));
+        assert_eq!(geometry["orientationSteps"].as_u64(), Some(1));
+        assert_eq!(crop_pixels(&geometry), [400, 1200, 2000, 3000]);
+
+        let uncropped = LIGHTROOM_CROP_XMP.replace("True", "False");
+        assert!(extract_xmp_geometry(&XmpValues::parse(&uncropped), (6000, 4000, 1)).is_empty());
+    }
+
+    fn paste_settings(mode: PasteMode, included: &[&str]) -> CopyPasteSettings {
+        CopyPasteSettings {
+            mode,
+            included_adjustments: included.iter().map(|key| key.to_string()).collect(),
+            excluded_adjustments: None,
+        }
+    }
+
+    fn edited_target() -> Map<String, Value> {
+        serde_json::from_value(serde_json::json!({
+            "exposure": 1.0,
+            "contrast": 20,
+            "curves": { "luma": [] },
+            "rating": 4,
+        }))
+        .unwrap()
+    }
+
+    fn pasted_adjustments() -> Map<String, Value> {
+        serde_json::from_value(serde_json::json!({
+            "exposure": 2.0,
+            "contrast": 50,
+            "saturation": 10,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_paste_only_touches_included_keys() {
+        let mut target = edited_target();
+        paste_settings(PasteMode::Merge, &["exposure"])
+            .paste_into(&mut target, &pasted_adjustments());
+
+        assert_eq!(target["exposure"], 2.0);
+        assert_eq!(target["contrast"], 20);
+        assert!(!target.contains_key("saturation"));
+        assert!(target.contains_key("curves"));
+        assert_eq!(target["rating"], 4);
+    }
+
+    #[test]
+    fn merge_paste_includes_keys_through_their_section() {
+        let mut target = edited_target();
+        paste_settings(PasteMode::Merge, &["basic"]).paste_into(&mut target, &pasted_adjustments());
+
+        assert_eq!(target["exposure"], 2.0);
+        assert_eq!(target["contrast"], 50);
+        assert!(!target.contains_key("saturation"));
+    }
+
+    #[test]
+    fn replace_paste_resets_everything_not_included() {
+        let mut target = edited_target();
+        paste_settings(PasteMode::Replace, &["exposure"])
+            .paste_into(&mut target, &pasted_adjustments());
+
+        assert_eq!(target["exposure"], 2.0);
+        assert_eq!(target["contrast"], 0);
+        assert_eq!(target["saturation"], 0);
+        assert!(!target.contains_key("curves"));
+        assert_eq!(target["rating"], 4);
     }
 }
@@ -0,0 +1,198 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::AppState;
+use crate::formats::is_supported_image_file;
+
+const FOLDER_EVENT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Created,
+    Removed,
+    Modified,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct FolderChangedPayload {
+    root: String,
+    created: Vec<String>,
+    removed: Vec<String>,
+    modified: Vec<String>,
+}
+
+fn is_relevant_path(root: &Path, path: &Path) -> bool {
+    let relative = path
+        .strip_prefix(root)
+        .ok()
+        .or_else(|| path.file_name().map(Path::new))
+        .unwrap_or(path);
+    if relative
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    {
+        return false;
+    }
+    is_supported_image_file(path)
+        || path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("rrdata"))
+}
+
+/// Folds a new change into whatever is already pending for the same path so a
+/// burst of events collapses into the net effect.
+fn merge_change(pending: &mut HashMap<PathBuf, Change>, path: PathBuf, change: Change) {
+    match (pending.get(&path).copied(), change) {
+        (Some(Change::Created), Change::Removed) => {
+            pending.remove(&path);
+        }
+        (Some(Change::Removed), Change::Created) => {
+            pending.insert(path, Change::Modified);
+        }
+        (Some(Change::Created), Change::Modified) => {}
+        _ => {
+            pending.insert(path, change);
+        }
+    }
+}
+
+fn classify_event(event: Event) -> Vec<(PathBuf, Change)> {
+    match event.kind {
+        EventKind::Create(_) => event
+            .paths
+            .into_iter()
+            .map(|p| (p, Change::Created))
+            .collect(),
+        EventKind::Remove(_) => event
+            .paths
+            .into_iter()
+            .map(|p| (p, Change::Removed))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => event
+            .paths
+            .into_iter()
+            .map(|p| (p, Change::Removed))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => event
+            .paths
+            .into_iter()
+            .map(|p| (p, Change::Created))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let mut paths = event.paths.into_iter();
+            paths
+                .next()
+                .map(|p| (p, Change::Removed))
+                .into_iter()
+                .chain(paths.map(|p| (p, Change::Created)))
+                .collect()
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .into_iter()
+            .map(|p| {
+                let change = if p.exists() {
+                    Change::Created
+                } else {
+                    Change::Removed
+                };
+                (p, change)
+            })
+            .collect(),
+        EventKind::Modify(_) => event
+            .paths
+            .into_iter()
+            .map(|p| (p, Change::Modified))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn emit_pending(app_handle: &AppHandle, root: &str, pending: &mut HashMap<PathBuf, Change>) {
+    let mut payload = FolderChangedPayload {
+        root: root.to_string(),
+        ..Default::default()
+    };
+    for (path, change) in pending.drain() {
+        let path = path.to_string_lossy().into_owned();
+        match change {
+            Change::Created => payload.created.push(path),
+            Change::Removed => payload.removed.push(path),
+            Change::Modified => payload.modified.push(path),
+        }
+    }
+    let _ = app_handle.emit("folder-changed", payload);
+}
+
+/// Collects raw watcher events and emits one `folder-changed` event once the
+/// folder has been quiet for `FOLDER_EVENT_DEBOUNCE`. Exits when the watcher is dropped.
+fn run_debouncer(app_handle: AppHandle, root: PathBuf, rx: mpsc::Receiver<notify::Result<Event>>) {
+    let root_str = root.to_string_lossy().into_owned();
+    let mut pending: HashMap<PathBuf, Change> = HashMap::new();
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(FOLDER_EVENT_DEBOUNCE)
+        };
+        match received {
+            Ok(Ok(event)) => {
+                for (path, change) in classify_event(event) {
+                    if is_relevant_path(&root, &path) {
+                        merge_change(&mut pending, path, change);
+                    }
+                }
+            }
+            Ok(Err(e)) => log::warn!("Folder watcher error for '{}': {}", root_str, e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                emit_pending(&app_handle, &root_str, &mut pending);
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn watch_folder(
+    path: String,
+    recursive: Option<bool>,
+    state: tauri::State<AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mode = if recursive.unwrap_or(false) {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(&root, mode).map_err(|e| e.to_string())?;
+
+    thread::spawn(move || run_debouncer(app_handle, root, rx));
+
+    state.folder_watchers.lock().unwrap().insert(path, watcher);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_folder(path: String, state: tauri::State<AppState>) -> Result<(), String> {
+    state.folder_watchers.lock().unwrap().remove(&path);
+    Ok(())
+}
@@ -78,6 +78,24 @@ pub const NON_RAW_EXTENSIONS: &[&str] = &[
     "pnm", "pbm", "pgm", "ppm", "pam", // Netpbm family
 ];
 
+/// Only decodable when built with the `heif` feature.
+pub const HEIF_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+pub fn supported_non_raw_extensions() -> Vec<&'static str> {
+    let mut extensions = NON_RAW_EXTENSIONS.to_vec();
+    if cfg!(feature = "heif") {
+        extensions.extend_from_slice(HEIF_EXTENSIONS);
+    }
+    extensions
+}
+
+pub fn is_heif_file<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| HEIF_EXTENSIONS.iter().any(|h| h.eq_ignore_ascii_case(ext)))
+}
+
 pub fn is_raw_file<P: AsRef<Path>>(path: P) -> bool {
     let ext = match path.as_ref().extension().and_then(|s| s.to_str()) {
         Some(e) => e,
@@ -104,6 +122,10 @@ pub fn is_supported_image_file<P: AsRef<Path>>(path: P) -> bool {
         return true;
     }
 
+    if cfg!(feature = "heif") && is_heif_file(path) {
+        return true;
+    }
+
     NON_RAW_EXTENSIONS
         .iter()
         .any(|non_raw_ext| non_raw_ext.eq_ignore_ascii_case(ext))
@@ -1,11 +1,13 @@
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
 
 use half::f16;
 use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgba};
+use tauri::Emitter;
 use wgpu::util::{DeviceExt, TextureDataOrder};
 
-use crate::image_processing::{AllAdjustments, GpuContext};
+use crate::image_processing::{AllAdjustments, GpuContext, MAX_MASK_COUNT, process_image_cpu};
 use crate::lut_processing::Lut;
 use crate::{AppState, GpuImageCache};
 
@@ -17,6 +19,7 @@ pub struct Roi {
     pub height: u32,
 }
 
+#[derive(Clone)]
 pub struct RenderRequest<'a> {
     pub adjustments: AllAdjustments,
     pub mask_bitmaps: &'a [ImageBuffer<Luma<u8>, Vec<u8>>],
@@ -24,17 +27,96 @@ pub struct RenderRequest<'a> {
     pub roi: Option<Roi>,
 }
 
-pub fn get_or_init_gpu_context(state: &tauri::State<AppState>) -> Result<GpuContext, String> {
-    let mut context_lock = state.gpu_context.lock().unwrap();
-    if let Some(context) = &*context_lock {
-        return Ok(context.clone());
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuAdapterInfo {
+    pub name: String,
+    pub backend: String,
+    pub device_type: String,
+}
+
+/// Timing and memory figures of the most recent GPU render, kept for diagnostics.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingStats {
+    pub caller: String,
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: f64,
+    pub tiles: u32,
+    pub gpu_backend: String,
+    pub estimated_vram_mb: u64,
+}
+
+fn default_backends() -> wgpu::Backends {
+    if cfg!(target_os = "windows") {
+        wgpu::Backends::PRIMARY
+    } else {
+        wgpu::Backends::all()
+    }
+}
+
+/// Maps a `processing_backend` setting to the backends to request. `"auto"` resolves to
+/// the platform default rather than `None`, so it also overrides a startup `WGPU_BACKEND`.
+pub fn backends_from_name(name: &str) -> Result<wgpu::Backends, String> {
+    match name.to_lowercase().as_str() {
+        "auto" => Ok(default_backends()),
+        "vulkan" => Ok(wgpu::Backends::VULKAN),
+        "dx12" => Ok(wgpu::Backends::DX12),
+        "metal" => Ok(wgpu::Backends::METAL),
+        "gl" => Ok(wgpu::Backends::GL),
+        other => Err(format!("Unknown processing backend: {}", other)),
     }
-    #[allow(unused_mut)]
+}
+
+fn create_wgpu_instance(backends: Option<wgpu::Backends>) -> wgpu::Instance {
     let mut instance_desc = wgpu::InstanceDescriptor::from_env_or_default();
 
-    #[cfg(target_os = "windows")]
-    if std::env::var("WGPU_BACKEND").is_err() {
-        instance_desc.backends = wgpu::Backends::PRIMARY;
+    if let Some(backends) = backends {
+        instance_desc.backends = backends;
+    } else if std::env::var("WGPU_BACKEND").is_err() {
+        instance_desc.backends = default_backends();
+    }
+
+    wgpu::Instance::new(&instance_desc)
+}
+
+pub fn list_gpu_adapters() -> Vec<GpuAdapterInfo> {
+    let instance = create_wgpu_instance(None);
+    pollster::block_on(instance.enumerate_adapters(wgpu::Backends::all()))
+        .into_iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+            GpuAdapterInfo {
+                name: info.name,
+                backend: format!("{:?}", info.backend),
+                device_type: format!("{:?}", info.device_type),
+            }
+        })
+        .collect()
+}
+
+fn find_preferred_adapter(instance: &wgpu::Instance, name: &str) -> Option<wgpu::Adapter> {
+    pollster::block_on(instance.enumerate_adapters(wgpu::Backends::all()))
+        .into_iter()
+        .find(|adapter| adapter.get_info().name == name)
+}
+
+pub fn get_or_init_gpu_context(
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+) -> Result<GpuContext, String> {
+    let mut context_lock = state.gpu_context.lock().unwrap();
+    let recovering = match &*context_lock {
+        Some(context) if !context.lost.load(Ordering::SeqCst) => return Ok(context.clone()),
+        Some(_) => true,
+        None => false,
+    };
+    if recovering {
+        log::warn!("GPU device was lost. Recreating the GPU context.");
+        *context_lock = None;
+        *state.gpu_processor.lock().unwrap() = None;
+        *state.gpu_image_cache.lock().unwrap() = None;
     }
 
     let flag_path = state.gpu_crash_flag_path.lock().unwrap().clone();
@@ -45,17 +127,33 @@ pub fn get_or_init_gpu_context(state: &tauri::State<AppState>) -> Result<GpuCont
         let _ = std::fs::write(p, "initializing_gpu");
     }
 
-    let instance = wgpu::Instance::new(&instance_desc);
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        ..Default::default()
-    }))
-    .map_err(|e| {
-        if let Some(p) = &flag_path {
-            let _ = std::fs::remove_file(p);
+    let instance = create_wgpu_instance(*state.gpu_backends.lock().unwrap());
+    let preferred_name = state.preferred_gpu_adapter.lock().unwrap().clone();
+    let preferred_adapter = preferred_name.as_deref().and_then(|name| {
+        let adapter = find_preferred_adapter(&instance, name);
+        if adapter.is_none() {
+            log::warn!(
+                "Preferred GPU adapter '{}' is not available. Falling back to the default adapter.",
+                name
+            );
         }
-        format!("Failed to find a wgpu adapter: {}", e)
-    })?;
+        adapter
+    });
+
+    let adapter = match preferred_adapter {
+        Some(adapter) => adapter,
+        None => pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| {
+            if let Some(p) = &flag_path {
+                let _ = std::fs::remove_file(p);
+            }
+            format!("Failed to find a wgpu adapter: {}", e)
+        })?,
+    };
+    log::info!("Using GPU adapter '{}'", adapter.get_info().name);
 
     let mut required_features = wgpu::Features::empty();
     if adapter
@@ -86,15 +184,36 @@ pub fn get_or_init_gpu_context(state: &tauri::State<AppState>) -> Result<GpuCont
         let _ = std::fs::remove_file(p);
     }
 
+    let lost = Arc::new(AtomicBool::new(false));
+    let lost_flag = lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        if !matches!(reason, wgpu::DeviceLostReason::Destroyed) {
+            log::error!("GPU device lost ({:?}): {}", reason, message);
+            lost_flag.store(true, Ordering::SeqCst);
+        }
+    });
+
     let new_context = GpuContext {
         device: Arc::new(device),
         queue: Arc::new(queue),
         limits,
+        backend: format!("{:?}", adapter.get_info().backend),
+        lost,
     };
     *context_lock = Some(new_context.clone());
+
+    if recovering {
+        let _ = app_handle.emit(
+            "gpu-recovered",
+            serde_json::json!({ "backend": new_context.backend }),
+        );
+    }
     Ok(new_context)
 }
 
+const GPU_READBACK_FAILED: &str = "Failed to map GPU buffer";
+const GPU_CONTEXT_LOST: &str = "GPU context was lost or replaced";
+
 fn read_texture_data(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
@@ -144,11 +263,11 @@ fn read_texture_data(
             submission_index: None,
             timeout: Some(std::time::Duration::from_secs(60)),
         })
-        .map_err(|e| format!("Failed while polling mapped GPU buffer: {}", e))?;
+        .map_err(|e| format!("{}: polling failed: {}", GPU_READBACK_FAILED, e))?;
     let map_result = rx
         .recv()
-        .map_err(|e| format!("Failed receiving GPU map result: {}", e))?;
-    map_result.map_err(|e| e.to_string())?;
+        .map_err(|e| format!("{}: no map result: {}", GPU_READBACK_FAILED, e))?;
+    map_result.map_err(|e| format!("{}: {}", GPU_READBACK_FAILED, e))?;
 
     let padded_data = buffer_slice.get_mapped_range().to_vec();
     output_buffer.unmap();
@@ -217,6 +336,7 @@ pub struct GpuProcessor {
     adjustments_buffer: wgpu::Buffer,
     dummy_blur_view: wgpu::TextureView,
     dummy_mask_view: wgpu::TextureView,
+    dummy_mask_atlas_view: wgpu::TextureView,
     dummy_lut_view: wgpu::TextureView,
     dummy_lut_sampler: wgpu::Sampler,
     ping_pong_view: wgpu::TextureView,
@@ -224,16 +344,135 @@ pub struct GpuProcessor {
     tonal_blur_view: wgpu::TextureView,
     clarity_blur_view: wgpu::TextureView,
     structure_blur_view: wgpu::TextureView,
+    glow_blur_view: OnceLock<wgpu::TextureView>,
+    halation_blur_view: OnceLock<wgpu::TextureView>,
     output_texture: wgpu::Texture,
     output_texture_view: wgpu::TextureView,
+    texture_extent: (u32, u32),
+    tile_size: u32,
+    tile_overlap: u32,
 }
 
 const FLARE_MAP_SIZE: u32 = 512;
+const DEFAULT_TILE_SIZE: u32 = 2048;
+const MIN_TILE_SIZE: u32 = 256;
+const TILE_OVERLAP: u32 = 128;
+
+/// Picks the tile edge and overlap used by `GpuProcessor::run`. A tile plus its overlap on
+/// both sides has to fit in a single texture, so the size is capped by the device limit.
+fn resolve_tile_layout(max_texture_dimension: u32, requested: Option<u32>) -> (u32, u32) {
+    let max_tile = max_texture_dimension
+        .saturating_sub(2 * TILE_OVERLAP)
+        .max(MIN_TILE_SIZE);
+    let tile_size = match requested {
+        Some(size) if size < MIN_TILE_SIZE || size > max_tile => {
+            log::warn!(
+                "Requested GPU tile size {} is outside {}..={}, clamping.",
+                size,
+                MIN_TILE_SIZE,
+                max_tile
+            );
+            size.clamp(MIN_TILE_SIZE, max_tile)
+        }
+        Some(size) => size,
+        None => DEFAULT_TILE_SIZE.min(max_tile),
+    };
+    (tile_size, TILE_OVERLAP.min(tile_size / 2))
+}
+
+/// Size of the per-tile working textures: one tile plus its overlap on both sides,
+/// never larger than the image itself.
+fn tile_texture_extent(
+    max_width: u32,
+    max_height: u32,
+    tile_size: u32,
+    tile_overlap: u32,
+) -> (u32, u32) {
+    let footprint = tile_size + 2 * tile_overlap;
+    (max_width.min(footprint), max_height.min(footprint))
+}
+
+/// Tiles have to overlap by the widest blur radius or neighbouring tiles see different
+/// context and leave seams. Wide glow and halation radii grow the overlap inside the same
+/// textures at the cost of tile area, keeping at least half the footprint for the tile.
+fn blur_tile_layout(tile_size: u32, tile_overlap: u32, max_blur_radius: u32) -> (u32, u32) {
+    let footprint = tile_size + 2 * tile_overlap;
+    let overlap = max_blur_radius.clamp(tile_overlap, tile_overlap.max(footprint / 4));
+    (footprint - 2 * overlap, overlap)
+}
+
+/// Masks with a binding of their own; the rest go into the mask atlas array texture.
+pub const MAX_MASKS: u32 = 8;
+
+/// Binding of `mask_atlas` in shader.wgsl, which hard-codes it as `@binding(21)`.
+const MASK_ATLAS_BINDING: u32 = 13 + MAX_MASKS;
+const _: () = assert!(
+    MASK_ATLAS_BINDING == 21,
+    "update @binding(21) of mask_atlas in shader.wgsl"
+);
+
+/// `mask_count` is the number of full-resolution masks the render uploads.
+pub fn estimate_gpu_memory_bytes(
+    width: u32,
+    height: u32,
+    tile_size: Option<u32>,
+    mask_count: usize,
+) -> u64 {
+    let image_pixels = width as u64 * height as u64;
+    let (tile_size, tile_overlap) = resolve_tile_layout(u32::MAX, tile_size);
+    let (extent_width, extent_height) = tile_texture_extent(
+        (width + 255) & !255,
+        (height + 255) & !255,
+        tile_size,
+        tile_overlap,
+    );
+    let processor_bytes = processor_texture_bytes(extent_width, extent_height);
+    let input_bytes = image_pixels * 8;
+    let mask_bytes = image_pixels * mask_count.min(MAX_MASK_COUNT) as u64;
+    let readback_bytes = image_pixels * 4;
+
+    processor_bytes + input_bytes + mask_bytes + readback_bytes
+}
+
+/// Bytes held by the reusable per-tile textures `GpuProcessor::new` allocates for the given
+/// tile extent: the ping-pong, four detail blur bands and the glow and halation bands as
+/// Rgba16Float plus the Rgba8Unorm output. The glow and halation bands are only created the
+/// first time those effects are used, so this is an upper bound.
+fn processor_texture_bytes(extent_width: u32, extent_height: u32) -> u64 {
+    extent_width as u64 * extent_height as u64 * (7 * 8 + 4)
+}
+
+pub fn check_gpu_memory_budget(
+    width: u32,
+    height: u32,
+    tile_size: Option<u32>,
+    mask_count: usize,
+    budget_mb: Option<u32>,
+) -> Result<(), String> {
+    let Some(budget_mb) = budget_mb else {
+        return Ok(());
+    };
+    let required_mb =
+        estimate_gpu_memory_bytes(width, height, tile_size, mask_count).div_ceil(1024 * 1024);
+    if required_mb > budget_mb as u64 {
+        return Err(format!(
+            "Processing a {}x{} image needs about {} MB of GPU memory, which exceeds the configured budget of {} MB.",
+            width, height, required_mb, budget_mb
+        ));
+    }
+    Ok(())
+}
 
 impl GpuProcessor {
-    pub fn new(context: GpuContext, max_width: u32, max_height: u32) -> Result<Self, String> {
+    pub fn new(
+        context: GpuContext,
+        max_width: u32,
+        max_height: u32,
+        tile_size_override: Option<u32>,
+    ) -> Result<Self, String> {
         let device = &context.device;
-        const MAX_MASKS: u32 = 8;
+        let (tile_size, tile_overlap) =
+            resolve_tile_layout(context.limits.max_texture_dimension_2d, tile_size_override);
 
         let blur_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Blur Shader"),
@@ -577,6 +816,29 @@ impl GpuProcessor {
             count: None,
         });
 
+        for binding in [11 + MAX_MASKS, 12 + MAX_MASKS] {
+            bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
+        bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: MASK_ATLAS_BINDING,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            },
+            count: None,
+        });
+
         let main_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Main BGL"),
             entries: &bind_group_layout_entries,
@@ -626,6 +888,10 @@ impl GpuProcessor {
             ..dummy_texture_desc
         });
         let dummy_mask_view = dummy_mask_texture.create_view(&Default::default());
+        let dummy_mask_atlas_view = dummy_mask_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
 
         let dummy_lut_texture = device.create_texture(&wgpu::TextureDescriptor {
             dimension: wgpu::TextureDimension::D3,
@@ -634,9 +900,11 @@ impl GpuProcessor {
         let dummy_lut_view = dummy_lut_texture.create_view(&Default::default());
         let dummy_lut_sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
 
+        let (extent_width, extent_height) =
+            tile_texture_extent(max_width, max_height, tile_size, tile_overlap);
         let max_tile_size = wgpu::Extent3d {
-            width: max_width,
-            height: max_height,
+            width: extent_width,
+            height: extent_height,
             depth_or_array_layers: 1,
         };
 
@@ -713,6 +981,7 @@ impl GpuProcessor {
             adjustments_buffer,
             dummy_blur_view,
             dummy_mask_view,
+            dummy_mask_atlas_view,
             dummy_lut_view,
             dummy_lut_sampler,
             ping_pong_view,
@@ -720,11 +989,52 @@ impl GpuProcessor {
             tonal_blur_view,
             clarity_blur_view,
             structure_blur_view,
+            glow_blur_view: OnceLock::new(),
+            halation_blur_view: OnceLock::new(),
             output_texture,
             output_texture_view,
+            texture_extent: (extent_width, extent_height),
+            tile_size,
+            tile_overlap,
+        })
+    }
+
+    fn lazy_blur_view<'a>(
+        &self,
+        slot: &'a OnceLock<wgpu::TextureView>,
+        label: &str,
+    ) -> &'a wgpu::TextureView {
+        slot.get_or_init(|| {
+            let texture = self
+                .context
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: self.texture_extent.0,
+                        height: self.texture_extent.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                    view_formats: &[],
+                });
+            texture.create_view(&Default::default())
         })
     }
 
+    fn tile_count(&self, bounds: Roi) -> u32 {
+        let tiles_x =
+            (bounds.x + bounds.width).div_ceil(self.tile_size) - bounds.x / self.tile_size;
+        let tiles_y =
+            (bounds.y + bounds.height).div_ceil(self.tile_size) - bounds.y / self.tile_size;
+        tiles_x * tiles_y
+    }
+
     pub fn run(
         &self,
         input_texture_view: &wgpu::TextureView,
@@ -735,7 +1045,6 @@ impl GpuProcessor {
         let device = &self.context.device;
         let queue = &self.context.queue;
         let scale = (width.min(height) as f32) / 1080.0;
-        const MAX_MASKS: u32 = 8;
 
         let bounds = request.roi.unwrap_or(Roi {
             x: 0,
@@ -754,6 +1063,7 @@ impl GpuProcessor {
         let mask_views: Vec<wgpu::TextureView> = request
             .mask_bitmaps
             .iter()
+            .take(MAX_MASKS as usize)
             .map(|mask_bitmap| {
                 let mask_texture = device.create_texture_with_data(
                     queue,
@@ -774,6 +1084,39 @@ impl GpuProcessor {
             })
             .collect();
 
+        let atlas_bitmaps = request
+            .mask_bitmaps
+            .get(MAX_MASKS as usize..MAX_MASK_COUNT.min(request.mask_bitmaps.len()))
+            .unwrap_or(&[]);
+        let mask_atlas_view = (!atlas_bitmaps.is_empty()).then(|| {
+            let atlas_data: Vec<u8> = atlas_bitmaps
+                .iter()
+                .flat_map(|bitmap| bitmap.as_raw().iter().copied())
+                .collect();
+            let atlas_texture = device.create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("Mask Atlas Texture"),
+                    size: wgpu::Extent3d {
+                        depth_or_array_layers: atlas_bitmaps.len() as u32,
+                        ..full_texture_size
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::R8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                },
+                TextureDataOrder::LayerMajor,
+                &atlas_data,
+            );
+            atlas_texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                ..Default::default()
+            })
+        });
+
         let (lut_texture_view, lut_sampler) = if let Some(lut_arc) = &request.lut {
             let lut_data = &lut_arc.data;
             let size = lut_arc.size;
@@ -818,6 +1161,11 @@ impl GpuProcessor {
         };
 
         let adjustments = request.adjustments;
+        let active_masks = &adjustments.mask_adjustments[..adjustments.mask_count as usize];
+        let uses_glow = adjustments.global.glow_amount > 0.0
+            || active_masks.iter().any(|m| m.glow_amount != 0.0);
+        let uses_halation = adjustments.global.halation_amount > 0.0
+            || active_masks.iter().any(|m| m.halation_amount != 0.0);
         if adjustments.global.flare_amount > 0.0 {
             let mut encoder = device.create_command_encoder(&Default::default());
 
@@ -985,37 +1333,51 @@ impl GpuProcessor {
             queue.submit(Some(blur_encoder.finish()));
         }
 
-        const TILE_SIZE: u32 = 2048;
-        const TILE_OVERLAP: u32 = 128;
+        let mut max_blur_radius = 40.0f32;
+        if uses_glow {
+            max_blur_radius = max_blur_radius.max(adjustments.global.glow_radius);
+        }
+        if uses_halation {
+            max_blur_radius = max_blur_radius.max(adjustments.global.halation_radius);
+        }
+        let (tile_size, tile_overlap) = blur_tile_layout(
+            self.tile_size,
+            self.tile_overlap,
+            (max_blur_radius * scale).ceil() as u32,
+        );
+        let glow_blur_view =
+            uses_glow.then(|| self.lazy_blur_view(&self.glow_blur_view, "Glow Blur Texture"));
+        let halation_blur_view = uses_halation
+            .then(|| self.lazy_blur_view(&self.halation_blur_view, "Halation Blur Texture"));
 
         let mut final_pixels = vec![0u8; (out_width * out_height * 4) as usize];
 
-        let start_tile_x = bounds.x / TILE_SIZE;
-        let start_tile_y = bounds.y / TILE_SIZE;
-        let end_tile_x = (bounds.x + bounds.width).div_ceil(TILE_SIZE);
-        let end_tile_y = (bounds.y + bounds.height).div_ceil(TILE_SIZE);
+        let start_tile_x = bounds.x / tile_size;
+        let start_tile_y = bounds.y / tile_size;
+        let end_tile_x = (bounds.x + bounds.width).div_ceil(tile_size);
+        let end_tile_y = (bounds.y + bounds.height).div_ceil(tile_size);
 
         for tile_y in start_tile_y..end_tile_y {
             for tile_x in start_tile_x..end_tile_x {
-                let x_start_unclamped = tile_x * TILE_SIZE;
-                let y_start_unclamped = tile_y * TILE_SIZE;
+                let x_start_unclamped = tile_x * tile_size;
+                let y_start_unclamped = tile_y * tile_size;
 
                 let x_start = x_start_unclamped.max(bounds.x);
                 let y_start = y_start_unclamped.max(bounds.y);
-                let x_end = (x_start_unclamped + TILE_SIZE)
+                let x_end = (x_start_unclamped + tile_size)
                     .min(bounds.x + bounds.width)
                     .min(width);
-                let y_end = (y_start_unclamped + TILE_SIZE)
+                let y_end = (y_start_unclamped + tile_size)
                     .min(bounds.y + bounds.height)
                     .min(height);
 
                 let tile_width = x_end - x_start;
                 let tile_height = y_end - y_start;
 
-                let input_x_start = (x_start as i32 - TILE_OVERLAP as i32).max(0) as u32;
-                let input_y_start = (y_start as i32 - TILE_OVERLAP as i32).max(0) as u32;
-                let input_x_end = (x_end + TILE_OVERLAP).min(width);
-                let input_y_end = (y_end + TILE_OVERLAP).min(height);
+                let input_x_start = (x_start as i32 - tile_overlap as i32).max(0) as u32;
+                let input_y_start = (y_start as i32 - tile_overlap as i32).max(0) as u32;
+                let input_x_end = (x_end + tile_overlap).min(width);
+                let input_y_end = (y_end + tile_overlap).min(height);
                 let input_width = input_x_end - input_x_start;
                 let input_height = input_y_end - input_y_start;
 
@@ -1026,7 +1388,7 @@ impl GpuProcessor {
                 };
 
                 let run_blur = |base_radius: f32, output_view: &wgpu::TextureView| -> bool {
-                    let radius = (base_radius * scale).ceil().max(1.0) as u32;
+                    let radius = ((base_radius * scale).ceil().max(1.0) as u32).min(tile_overlap);
                     if radius == 0 {
                         return false;
                     }
@@ -1105,6 +1467,10 @@ impl GpuProcessor {
                 let did_create_tonal_blur = run_blur(3.5, &self.tonal_blur_view);
                 let did_create_clarity_blur = run_blur(8.0, &self.clarity_blur_view);
                 let did_create_structure_blur = run_blur(40.0, &self.structure_blur_view);
+                let glow_blur =
+                    glow_blur_view.filter(|&view| run_blur(adjustments.global.glow_radius, view));
+                let halation_blur = halation_blur_view
+                    .filter(|&view| run_blur(adjustments.global.halation_radius, view));
 
                 let mut main_encoder = device.create_command_encoder(&Default::default());
 
@@ -1193,6 +1559,26 @@ impl GpuProcessor {
                     binding: 10 + MAX_MASKS,
                     resource: wgpu::BindingResource::Sampler(&self.flare_sampler),
                 });
+                bind_group_entries.push(wgpu::BindGroupEntry {
+                    binding: 11 + MAX_MASKS,
+                    resource: wgpu::BindingResource::TextureView(
+                        glow_blur.unwrap_or(&self.dummy_blur_view),
+                    ),
+                });
+                bind_group_entries.push(wgpu::BindGroupEntry {
+                    binding: 12 + MAX_MASKS,
+                    resource: wgpu::BindingResource::TextureView(
+                        halation_blur.unwrap_or(&self.dummy_blur_view),
+                    ),
+                });
+                bind_group_entries.push(wgpu::BindGroupEntry {
+                    binding: MASK_ATLAS_BINDING,
+                    resource: wgpu::BindingResource::TextureView(
+                        mask_atlas_view
+                            .as_ref()
+                            .unwrap_or(&self.dummy_mask_atlas_view),
+                    ),
+                });
 
                 let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
                     label: Some("Tile Bind Group"),
@@ -1237,7 +1623,133 @@ impl GpuProcessor {
     }
 }
 
+/// Runs a render on the GPU. If the device was lost or a readback failed, the render is
+/// retried once on a freshly created context; any other error is returned as is.
 pub fn process_and_get_dynamic_image(
+    context: &GpuContext,
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+    base_image: &DynamicImage,
+    transform_hash: u64,
+    request: RenderRequest,
+    caller_id: &str,
+) -> Result<DynamicImage, String> {
+    let context = if context.lost.load(Ordering::SeqCst) {
+        get_or_init_gpu_context(state, app_handle)?
+    } else {
+        context.clone()
+    };
+
+    match render_on_gpu(
+        &context,
+        state,
+        base_image,
+        transform_hash,
+        request.clone(),
+        caller_id,
+    ) {
+        Ok(image) => Ok(image),
+        Err(e) => {
+            if !context.lost.load(Ordering::SeqCst) && !e.starts_with(GPU_READBACK_FAILED) {
+                return Err(e);
+            }
+            log::warn!(
+                "[{}] GPU processing failed, retrying once: {}",
+                caller_id,
+                e
+            );
+            // A failed readback can leave the device unusable without the lost callback
+            // having fired yet, so the context is always rebuilt before retrying.
+            context.lost.store(true, Ordering::SeqCst);
+            let retry_context = get_or_init_gpu_context(state, app_handle)?;
+            render_on_gpu(
+                &retry_context,
+                state,
+                base_image,
+                transform_hash,
+                request,
+                caller_id,
+            )
+        }
+    }
+}
+
+/// Creates the processor, halving the tile size for as long as allocating its working
+/// textures runs out of GPU memory.
+fn create_processor_with_fallback(
+    context: &GpuContext,
+    max_width: u32,
+    max_height: u32,
+    tile_size_override: Option<u32>,
+) -> Result<GpuProcessor, String> {
+    let mut tile_size = tile_size_override;
+    loop {
+        let scope = context
+            .device
+            .push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        let processor = GpuProcessor::new(context.clone(), max_width, max_height, tile_size)?;
+        let Some(error) = pollster::block_on(scope.pop()) else {
+            return Ok(processor);
+        };
+        if processor.tile_size <= MIN_TILE_SIZE {
+            return Err(format!(
+                "Not enough GPU memory to process images up to {}x{}: {}",
+                max_width, max_height, error
+            ));
+        }
+        let smaller = (processor.tile_size / 2).max(MIN_TILE_SIZE);
+        log::warn!(
+            "Out of GPU memory with {}px tiles ({}). Retrying with {}px tiles.",
+            processor.tile_size,
+            error,
+            smaller
+        );
+        tile_size = Some(smaller);
+    }
+}
+
+fn create_input_texture(context: &GpuContext, image: &DynamicImage) -> wgpu::Texture {
+    let (width, height) = image.dimensions();
+    let img_rgba_f16 = to_rgba_f16(image);
+    context.device.create_texture_with_data(
+        &context.queue,
+        &wgpu::TextureDescriptor {
+            label: Some("Input Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::MipMajor,
+        bytemuck::cast_slice(&img_rgba_f16),
+    )
+}
+
+/// Renders with a processor and input texture of its own, leaving the editor's cached
+/// processor, input texture and stats untouched.
+pub fn render_uncached(
+    context: &GpuContext,
+    base_image: &DynamicImage,
+    request: RenderRequest,
+) -> Result<DynamicImage, String> {
+    let (width, height) = base_image.dimensions();
+    let processor = create_processor_with_fallback(context, width, height, None)?;
+    let texture = create_input_texture(context, base_image);
+    let view = texture.create_view(&Default::default());
+    let (pixels, out_w, out_h) = processor.run(&view, width, height, request)?;
+    let img_buf = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(out_w, out_h, pixels)
+        .ok_or("Failed to create image buffer from GPU data")?;
+    Ok(DynamicImage::ImageRgba8(img_buf))
+}
+
+fn render_on_gpu(
     context: &GpuContext,
     state: &tauri::State<AppState>,
     base_image: &DynamicImage,
@@ -1247,21 +1759,36 @@ pub fn process_and_get_dynamic_image(
 ) -> Result<DynamicImage, String> {
     let start_time = Instant::now();
     let (width, height) = base_image.dimensions();
-    let device = &context.device;
-    let queue = &context.queue;
 
     let max_dim = context.limits.max_texture_dimension_2d;
     if width > max_dim || height > max_dim {
         log::warn!(
-            "Image dimensions ({}x{}) exceed GPU limits ({}). Bypassing GPU processing and returning unprocessed image to prevent a crash. Try upgrading your GPU :)",
+            "Image dimensions ({}x{}) exceed GPU limits ({}). Falling back to CPU processing to prevent a crash. Try upgrading your GPU :)",
             width,
             height,
             max_dim
         );
-        return Ok(base_image.clone());
+        return Ok(process_image_cpu(
+            base_image,
+            &request.adjustments,
+            request.mask_bitmaps,
+            request.lut.as_deref(),
+        ));
+    }
+
+    if context.lost.load(Ordering::SeqCst) {
+        return Err(GPU_CONTEXT_LOST.to_string());
     }
 
     let mut processor_lock = state.gpu_processor.lock().unwrap();
+    if processor_lock
+        .as_ref()
+        .is_some_and(|p| !Arc::ptr_eq(&p.processor.context.device, &context.device))
+    {
+        // Built on a context that has since been replaced; none of its resources are usable.
+        *processor_lock = None;
+        *state.gpu_image_cache.lock().unwrap() = None;
+    }
     if processor_lock.is_none()
         || processor_lock.as_ref().unwrap().width < width
         || processor_lock.as_ref().unwrap().height < height
@@ -1273,7 +1800,9 @@ pub fn process_and_get_dynamic_image(
             new_width,
             new_height
         );
-        let processor = GpuProcessor::new(context.clone(), new_width, new_height)?;
+        let tile_size_override = *state.gpu_tile_size.lock().unwrap();
+        let processor =
+            create_processor_with_fallback(context, new_width, new_height, tile_size_override)?;
         *processor_lock = Some(crate::GpuProcessorState {
             processor,
             width: new_width,
@@ -1293,27 +1822,7 @@ pub fn process_and_get_dynamic_image(
     }
 
     if cache_lock.is_none() {
-        let img_rgba_f16 = to_rgba_f16(base_image);
-        let texture_size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-        let texture = device.create_texture_with_data(
-            queue,
-            &wgpu::TextureDescriptor {
-                label: Some("Input Texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba16Float,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            },
-            TextureDataOrder::MipMajor,
-            bytemuck::cast_slice(&img_rgba_f16),
-        );
+        let texture = create_input_texture(context, base_image);
         let texture_view = texture.create_view(&Default::default());
 
         *cache_lock = Some(GpuImageCache {
@@ -1327,10 +1836,29 @@ pub fn process_and_get_dynamic_image(
 
     let cache = cache_lock.as_ref().unwrap();
 
+    let tiles = processor.tile_count(request.roi.unwrap_or(Roi {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    }));
+    let estimated_vram_bytes =
+        processor_texture_bytes(processor.texture_extent.0, processor.texture_extent.1)
+            + width as u64 * height as u64 * 8;
+
     let (processed_pixels, out_w, out_h) =
         processor.run(&cache.texture_view, cache.width, cache.height, request)?;
 
     let duration = start_time.elapsed();
+    *state.last_processing_stats.lock().unwrap() = Some(ProcessingStats {
+        caller: caller_id.to_string(),
+        width,
+        height,
+        duration_ms: duration.as_secs_f64() * 1000.0,
+        tiles,
+        gpu_backend: context.backend.clone(),
+        estimated_vram_mb: estimated_vram_bytes.div_ceil(1024 * 1024),
+    });
     let fps = 1.0 / duration.as_secs_f64();
     log::info!(
         "[{}] {}x{} processed (ROI: {}x{}) on GPU in {:?} ({:.2} FPS)",
@@ -1347,3 +1875,169 @@ pub fn process_and_get_dynamic_image(
         .ok_or("Failed to create image buffer from GPU data")?;
     Ok(DynamicImage::ImageRgba8(img_buf))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_context() -> Option<GpuContext> {
+        let instance = create_wgpu_instance(None);
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok()?;
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("Test Device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: limits.clone(),
+            experimental_features: wgpu::ExperimentalFeatures::default(),
+            memory_hints: wgpu::MemoryHints::Performance,
+            trace: wgpu::Trace::Off,
+        }))
+        .ok()?;
+        Some(GpuContext {
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+            limits,
+            backend: format!("{:?}", adapter.get_info().backend),
+            lost: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb32F(image::Rgb32FImage::from_fn(width, height, |x, y| {
+            let fx = x as f32 / width as f32;
+            let fy = y as f32 / height as f32;
+            image::Rgb([fx, fy, (fx + fy) * 0.5])
+        }))
+    }
+
+    #[test]
+    fn wide_blurs_grow_the_overlap_within_the_footprint() {
+        assert_eq!(blur_tile_layout(2048, 128, 40), (2048, 128));
+
+        let (tile_size, overlap) = blur_tile_layout(2048, 128, 400);
+        assert_eq!(overlap, 400);
+        assert_eq!(tile_size + 2 * overlap, 2048 + 2 * 128);
+
+        let (tile_size, overlap) = blur_tile_layout(2048, 128, 5000);
+        assert_eq!(overlap, (2048 + 2 * 128) / 4);
+        assert!(tile_size >= (2048 + 2 * 128) / 2);
+    }
+
+    #[test]
+    fn memory_estimate_counts_every_uploaded_mask() {
+        let bytes = |masks| estimate_gpu_memory_bytes(1000, 1000, Some(512), masks);
+        assert_eq!(
+            bytes(MAX_MASK_COUNT) - bytes(0),
+            1000 * 1000 * MAX_MASK_COUNT as u64
+        );
+        assert_eq!(bytes(MAX_MASK_COUNT + 4), bytes(MAX_MASK_COUNT));
+    }
+
+    #[test]
+    fn tile_size_does_not_change_output() {
+        let Some(context) = test_context() else {
+            eprintln!("No GPU adapter available, skipping.");
+            return;
+        };
+        let (width, height) = (700, 600);
+        let texture = create_input_texture(&context, &gradient(width, height));
+        let view = texture.create_view(&Default::default());
+
+        let mut adjustments = AllAdjustments::default();
+        adjustments.global.exposure = 0.3;
+        adjustments.global.sharpness = 40.0;
+        adjustments.global.clarity = 30.0;
+        adjustments.global.structure = 25.0;
+
+        let render = |tile_size: u32| {
+            let processor =
+                GpuProcessor::new(context.clone(), width, height, Some(tile_size)).unwrap();
+            processor
+                .run(
+                    &view,
+                    width,
+                    height,
+                    RenderRequest {
+                        adjustments,
+                        mask_bitmaps: &[],
+                        lut: None,
+                        roi: None,
+                    },
+                )
+                .unwrap()
+                .0
+        };
+
+        let reference = render(2048);
+        for tile_size in [256, 384, 512] {
+            assert!(
+                render(tile_size) == reference,
+                "tile size {} produced different output",
+                tile_size
+            );
+        }
+    }
+
+    #[test]
+    fn grain_matches_between_preview_and_export_sizes() {
+        let Some(context) = test_context() else {
+            eprintln!("No GPU adapter available, skipping.");
+            return;
+        };
+
+        let mut adjustments = AllAdjustments::default();
+        adjustments.global.grain_amount = 0.5;
+        adjustments.global.grain_size = 0.5;
+        adjustments.global.grain_roughness = 0.5;
+
+        let render = |width: u32, height: u32| {
+            let flat = DynamicImage::ImageRgb32F(image::Rgb32FImage::from_pixel(
+                width,
+                height,
+                image::Rgb([0.2, 0.2, 0.2]),
+            ));
+            let texture = create_input_texture(&context, &flat);
+            let view = texture.create_view(&Default::default());
+            let processor = GpuProcessor::new(context.clone(), width, height, None).unwrap();
+            processor
+                .run(
+                    &view,
+                    width,
+                    height,
+                    RenderRequest {
+                        adjustments,
+                        mask_bitmaps: &[],
+                        lut: None,
+                        roi: None,
+                    },
+                )
+                .unwrap()
+                .0
+        };
+
+        // 300 px keeps the grain frequency off the noise lattice at both sizes.
+        let (width, height) = (400, 300);
+        let preview = render(width, height);
+        let export = render(width * 2, height * 2);
+
+        let (mut darkest, mut brightest, mut max_difference) = (u8::MAX, u8::MIN, 0);
+        for y in 0..height {
+            for x in 0..width {
+                let p = preview[((y * width + x) * 4) as usize];
+                let e = export[((2 * y * width * 2 + 2 * x) * 4) as usize];
+                darkest = darkest.min(p);
+                brightest = brightest.max(p);
+                max_difference = max_difference.max(p.abs_diff(e));
+            }
+        }
+
+        assert!(brightest - darkest > 4, "grain should be visible");
+        assert!(
+            max_difference <= 2,
+            "grain differs by {} between preview and export",
+            max_difference
+        );
+    }
+}
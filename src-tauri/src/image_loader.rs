@@ -1,12 +1,12 @@
 use crate::Cursor;
-use crate::formats::is_raw_file;
+use crate::formats::{is_heif_file, is_raw_file};
 use crate::image_processing::{apply_orientation, remove_raw_artifacts_and_enhance};
-use crate::mask_generation::{MaskDefinition, SubMask, generate_mask_bitmap};
+use crate::mask_generation::{MaskBlendMode, MaskDefinition, SubMask, generate_mask_bitmap};
 use crate::raw_processing::develop_raw_image;
 use anyhow::{Context, Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
 use exif::{Reader as ExifReader, Tag};
-use image::{DynamicImage, GenericImageView, ImageReader, imageops};
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageReader, imageops};
 use rawler::Orientation;
 use rayon::prelude::*;
 use serde::Deserialize;
@@ -56,19 +56,45 @@ pub fn load_base_image_from_bytes(
     highlight_compression: f32,
     linear_mode: String,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
+) -> Result<DynamicImage> {
+    load_base_image_from_bytes_with_progress(
+        bytes,
+        path_for_ext_check,
+        use_fast_raw_dev,
+        highlight_compression,
+        linear_mode,
+        cancel_token,
+        None,
+    )
+}
+
+pub fn load_base_image_from_bytes_with_progress(
+    bytes: &[u8],
+    path_for_ext_check: &str,
+    use_fast_raw_dev: bool,
+    highlight_compression: f32,
+    linear_mode: String,
+    cancel_token: Option<(Arc<AtomicUsize>, usize)>,
+    on_stage: Option<&dyn Fn(&str)>,
 ) -> Result<DynamicImage> {
     if is_raw_file(path_for_ext_check) {
-        match panic::catch_unwind(move || {
+        match panic::catch_unwind(panic::AssertUnwindSafe(move || {
             develop_raw_image(
                 bytes,
                 use_fast_raw_dev,
                 highlight_compression,
                 linear_mode,
                 cancel_token,
+                on_stage,
             )
-        }) {
-            Ok(Ok(mut image)) => {
-                if !use_fast_raw_dev {
+        })) {
+            Ok(Ok((mut image, is_linear_format))) => {
+                if is_linear_format {
+                    log::info!(
+                        "Skipping raw enhancing for linear DNG '{}'",
+                        path_for_ext_check
+                    );
+                } else if !use_fast_raw_dev {
                     let start = Instant::now();
                     remove_raw_artifacts_and_enhance(&mut image);
                     let duration = start.elapsed();
@@ -77,6 +103,9 @@ pub fn load_base_image_from_bytes(
                         path_for_ext_check,
                         duration
                     );
+                    if let Some(callback) = on_stage {
+                        callback("enhanced");
+                    }
                 }
                 Ok(image)
             }
@@ -97,11 +126,96 @@ pub fn load_base_image_from_bytes(
                 ))
             }
         }
+    } else if is_heif_file(path_for_ext_check) {
+        load_heif_image(bytes)
     } else {
         load_image_with_orientation(bytes, cancel_token)
     }
 }
 
+#[cfg(feature = "heif")]
+fn load_heif_image(bytes: &[u8]) -> Result<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let lib_heif = LibHeif::new();
+    let context = HeifContext::read_from_bytes(bytes).context("Failed to read HEIF container")?;
+    let handle = context
+        .primary_image_handle()
+        .context("HEIF file has no primary image")?;
+    let bit_depth = handle.luma_bits_per_pixel();
+    let chroma = if bit_depth > 8 {
+        RgbChroma::HdrRgbLe
+    } else {
+        RgbChroma::Rgb
+    };
+    // libheif applies the container's irot/imir transforms, which HEIF treats as
+    // authoritative over the (usually duplicated) EXIF orientation tag.
+    let decoded = lib_heif
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .context("Failed to decode HEIF image")?;
+
+    let planes = decoded.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| anyhow!("HEIF decode returned no interleaved plane"))?;
+    let image = if bit_depth > 8 {
+        let bits = if plane.bits_per_pixel > 8 {
+            plane.bits_per_pixel
+        } else {
+            bit_depth
+        };
+        rgb16le_plane_to_rgb32f(plane.data, plane.width, plane.height, plane.stride, bits)
+    } else {
+        rgb8_plane_to_rgb32f(plane.data, plane.width, plane.height, plane.stride)
+    };
+    image
+        .map(DynamicImage::ImageRgb32F)
+        .ok_or_else(|| anyhow!("HEIF plane size does not match its dimensions"))
+}
+
+#[cfg_attr(not(feature = "heif"), allow(dead_code))]
+fn rgb8_plane_to_rgb32f(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> Option<image::Rgb32FImage> {
+    let row_bytes = width as usize * 3;
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        pixels.extend(row.get(..row_bytes)?.iter().map(|&v| v as f32 / 255.0));
+    }
+    image::Rgb32FImage::from_raw(width, height, pixels)
+}
+
+/// Converts an interleaved little-endian 16-bit RGB plane holding `bits`-bit
+/// samples, as libheif returns for 10/12-bit HEIC, without dropping to 8 bits.
+#[cfg_attr(not(feature = "heif"), allow(dead_code))]
+fn rgb16le_plane_to_rgb32f(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    bits: u8,
+) -> Option<image::Rgb32FImage> {
+    let max = ((1u32 << bits.clamp(1, 16)) - 1) as f32;
+    let row_bytes = width as usize * 6;
+    let mut pixels = Vec::with_capacity(width as usize * 3 * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        pixels.extend(
+            row.get(..row_bytes)?
+                .chunks_exact(2)
+                .map(|v| u16::from_le_bytes([v[0], v[1]]) as f32 / max),
+        );
+    }
+    image::Rgb32FImage::from_raw(width, height, pixels)
+}
+
+#[cfg(not(feature = "heif"))]
+fn load_heif_image(_bytes: &[u8]) -> Result<DynamicImage> {
+    Err(anyhow!("HEIC/HEIF support is not enabled in this build"))
+}
+
 fn classify_raw_develop_error(path: &str, err: anyhow::Error) -> anyhow::Error {
     let error_text = err.to_string();
     let lowered = error_text.to_ascii_lowercase();
@@ -141,24 +255,31 @@ pub fn load_image_with_orientation(
 
     check_cancel()?;
 
-    let image = reader.decode().context("Failed to decode image")?;
+    let mut decoder = reader
+        .into_decoder()
+        .context("Failed to create image decoder")?;
+    let decoder_orientation = decoder.orientation().ok();
+    let image = DynamicImage::from_decoder(decoder).context("Failed to decode image")?;
     check_cancel()?;
 
-    let oriented_image = {
-        let exif_reader = ExifReader::new();
-        if let Ok(exif) = exif_reader.read_from_container(&mut cursor.clone()) {
-            if let Some(orientation) = exif
-                .get_field(Tag::Orientation, exif::In::PRIMARY)
+    let exif_orientation = ExifReader::new()
+        .read_from_container(&mut cursor.clone())
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, exif::In::PRIMARY)
                 .and_then(|f| f.value.get_uint(0))
-            {
-                check_cancel()?;
-                apply_orientation(image, Orientation::from_u16(orientation as u16))
-            } else {
-                image
-            }
-        } else {
-            image
-        }
+        });
+
+    let oriented_image = if let Some(orientation) = exif_orientation {
+        check_cancel()?;
+        apply_orientation(image, Orientation::from_u16(orientation as u16))
+    } else if let Some(orientation) = decoder_orientation {
+        check_cancel()?;
+        let mut image = image;
+        image.apply_orientation(orientation);
+        image
+    } else {
+        image
     };
 
     Ok(DynamicImage::ImageRgb32F(oriented_image.to_rgb32f()))
@@ -228,6 +349,7 @@ pub fn composite_patches_on_image(
                 visible: true,
                 invert: patch_info.invert,
                 opacity: 100.0,
+                blend_mode: MaskBlendMode::Normal,
                 adjustments: Value::Null,
                 sub_masks: patch_info.sub_masks,
             };
@@ -283,3 +405,81 @@ pub fn composite_patches_on_image(
 
     Ok(DynamicImage::ImageRgba32F(composited_rgba))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{Rgb, RgbImage};
+
+    fn exif_orientation_segment(orientation: u16) -> Vec<u8> {
+        let mut tiff = Vec::new();
+        tiff.extend_from_slice(b"II*\0");
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x0112u16.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        segment.extend_from_slice(b"Exif\0\0");
+        segment.extend_from_slice(&tiff);
+        segment
+    }
+
+    #[test]
+    fn orientation_6_jpeg_is_rotated_clockwise() {
+        let source = RgbImage::from_fn(32, 16, |x, _| {
+            if x < 16 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 95)
+            .encode_image(&source)
+            .unwrap();
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend(exif_orientation_segment(6));
+        bytes.extend_from_slice(&jpeg[2..]);
+
+        let image = load_image_with_orientation(&bytes, None).unwrap();
+        assert_eq!(image.dimensions(), (16, 32));
+
+        let rgb = image.to_rgb8();
+        let top = rgb.get_pixel(8, 4);
+        let bottom = rgb.get_pixel(8, 27);
+        assert!(top[0] > 200 && top[2] < 60, "top should be red: {:?}", top);
+        assert!(
+            bottom[2] > 200 && bottom[0] < 60,
+            "bottom should be blue: {:?}",
+            bottom
+        );
+    }
+
+    #[test]
+    fn high_bit_depth_heif_planes_keep_full_precision() {
+        // Two 10-bit pixels per row with two bytes of row padding.
+        let samples: [u16; 6] = [0, 1, 2, 511, 1022, 1023];
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            for v in samples {
+                data.extend_from_slice(&v.to_le_bytes());
+            }
+            data.extend_from_slice(&[0, 0]);
+        }
+
+        let image = rgb16le_plane_to_rgb32f(&data, 2, 2, 14, 10).unwrap();
+        let expected = samples.map(|v| v as f32 / 1023.0);
+        assert_eq!(image.get_pixel(0, 1).0, expected[..3]);
+        assert_eq!(image.get_pixel(1, 1).0, expected[3..]);
+        assert_ne!(image.get_pixel(0, 0)[1], image.get_pixel(0, 0)[2]);
+
+        assert!(rgb16le_plane_to_rgb32f(&data[..20], 2, 2, 14, 10).is_none());
+    }
+}
@@ -1,22 +1,51 @@
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat3, Vec2, Vec3};
-use image::{DynamicImage, GenericImageView, Rgb32FImage, Rgba};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb32FImage, Rgba};
 use imageproc::geometric_transformations::{Interpolation, rotate_about_center};
 use nalgebra::{Matrix3 as NaMatrix3, Vector3 as NaVector3};
 use rawler::decoders::Orientation;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use serde_json::json;
+use serde_json::{Map, Value};
 use std::f32::consts::PI;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
+use crate::adjustment_registry;
+use crate::color_management;
 pub use crate::gpu_processing::{
     RenderRequest, get_or_init_gpu_context, process_and_get_dynamic_image,
 };
+use crate::lut_processing::Lut;
 use crate::{AppState, mask_generation::MaskDefinition};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
+pub const MAX_EDIT_HISTORY: usize = 50;
+/// Upper bound on the serialized size of the history kept in one sidecar.
+pub const MAX_EDIT_HISTORY_BYTES: usize = 256 * 1024;
+/// Adjustment keys that don't change the rendered image, so they never create
+/// history entries and are left alone by undo and redo.
+const HISTORY_IGNORED_KEYS: &[&str] = &["rating", "showClipping"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Adjustment keys changed by this entry. The oldest entry holds the full
+    /// adjustments the history starts from.
+    #[serde(default)]
+    pub changes: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub name: String,
+    pub adjustments: Value,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageMetadata {
     pub version: u32,
@@ -24,6 +53,18 @@ pub struct ImageMetadata {
     pub adjustments: Value,
     #[serde(default)]
     pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    /// Index into `history` of the entry matching `adjustments`.
+    #[serde(default)]
+    pub history_cursor: Option<usize>,
+    #[serde(default)]
+    pub snapshots: Vec<Snapshot>,
+    /// Location set by the user for files whose EXIF is not rewritten in place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps: Option<crate::exif_processing::GpsCoordinates>,
 }
 
 impl Default for ImageMetadata {
@@ -33,7 +74,102 @@ impl Default for ImageMetadata {
             rating: 0,
             adjustments: Value::Null,
             tags: None,
+            hidden: false,
+            history: Vec::new(),
+            history_cursor: None,
+            snapshots: Vec::new(),
+            gps: None,
+        }
+    }
+}
+
+fn history_changes(from: &Map<String, Value>, to: &Value) -> Map<String, Value> {
+    let empty = Map::new();
+    let to = to.as_object().unwrap_or(&empty);
+    let added = to
+        .iter()
+        .filter(|(key, value)| from.get(*key).unwrap_or(&Value::Null) != *value);
+    let removed = from
+        .iter()
+        .filter(|(key, value)| !value.is_null() && !to.contains_key(*key))
+        .map(|(key, _)| (key, &Value::Null));
+    added
+        .chain(removed)
+        .filter(|(key, _)| !HISTORY_IGNORED_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+impl ImageMetadata {
+    /// Records `adjustments` as the newest history entry, discarding any redo
+    /// branch past the cursor. Changes that don't affect the image are not
+    /// recorded, and the oldest entries are folded into the first one once
+    /// the history exceeds `MAX_EDIT_HISTORY` entries or
+    /// `MAX_EDIT_HISTORY_BYTES`.
+    pub fn record_history(&mut self, adjustments: &Value) {
+        if self.history.is_empty() {
+            self.history.push(HistoryEntry {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                changes: history_changes(&Map::new(), &self.adjustments),
+            });
+        } else if let Some(cursor) = self.history_cursor {
+            self.history.truncate(cursor + 1);
+        }
+
+        let current = self.history_state(self.history.len() - 1);
+        let changes = history_changes(&current, adjustments);
+        if !changes.is_empty() {
+            self.history.push(HistoryEntry {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                changes,
+            });
+        }
+        while self.history.len() > MAX_EDIT_HISTORY
+            || (self.history.len() > 1 && self.history_bytes() > MAX_EDIT_HISTORY_BYTES)
+        {
+            let oldest = self.history.remove(0);
+            let mut base = oldest.changes;
+            base.append(&mut self.history[0].changes);
+            self.history[0].changes = base;
+        }
+        self.history_cursor = Some(self.history.len() - 1);
+    }
+
+    /// Moves the history cursor by `delta` and restores that state, returning
+    /// `false` when already at the oldest or newest entry.
+    pub fn step_history(&mut self, delta: isize) -> bool {
+        let current = self
+            .history_cursor
+            .unwrap_or(self.history.len().saturating_sub(1));
+        let Some(target) = current.checked_add_signed(delta) else {
+            return false;
+        };
+        if target >= self.history.len() {
+            return false;
+        }
+        let mut restored = self.history_state(target);
+        for key in HISTORY_IGNORED_KEYS {
+            match self.adjustments.get(*key) {
+                Some(value) => restored.insert(key.to_string(), value.clone()),
+                None => restored.remove(*key),
+            };
+        }
+        self.adjustments = Value::Object(restored);
+        self.history_cursor = Some(target);
+        true
+    }
+
+    /// The adjustments at history entry `index`, rebuilt from the changes up to it.
+    fn history_state(&self, index: usize) -> Map<String, Value> {
+        let mut state = Map::new();
+        for entry in &self.history[..=index] {
+            state.extend(entry.changes.clone());
         }
+        state
+    }
+
+    fn history_bytes(&self) -> usize {
+        serde_json::to_vec(&self.history).map_or(0, |bytes| bytes.len())
     }
 }
 
@@ -43,6 +179,12 @@ pub struct Crop {
     pub y: f64,
     pub width: f64,
     pub height: f64,
+    #[serde(
+        default,
+        rename = "canvasColor",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub canvas_color: Option<[f32; 4]>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -872,6 +1014,10 @@ pub fn apply_crop(mut image: DynamicImage, crop_value: &Value) -> DynamicImage {
         return image;
     }
     if let Ok(crop) = serde_json::from_value::<Crop>(crop_value.clone()) {
+        if let Some(canvas_color) = crop.canvas_color {
+            return apply_canvas_crop(&image, &crop, canvas_color);
+        }
+
         let x = crop.x.round() as u32;
         let y = crop.y.round() as u32;
         let width = crop.width.round() as u32;
@@ -891,6 +1037,28 @@ pub fn apply_crop(mut image: DynamicImage, crop_value: &Value) -> DynamicImage {
     image
 }
 
+fn apply_canvas_crop(image: &DynamicImage, crop: &Crop, canvas_color: [f32; 4]) -> DynamicImage {
+    let x = crop.x.round() as i64;
+    let y = crop.y.round() as i64;
+    let width = crop.width.round().max(0.0) as u32;
+    let height = crop.height.round().max(0.0) as u32;
+    if width == 0 || height == 0 {
+        return image.clone();
+    }
+
+    let (img_w, img_h) = image.dimensions();
+    let fully_inside =
+        x >= 0 && y >= 0 && x + width as i64 <= img_w as i64 && y + height as i64 <= img_h as i64;
+    if fully_inside {
+        return image.crop_imm(x as u32, y as u32, width, height);
+    }
+
+    let mut canvas = image::ImageBuffer::from_pixel(width, height, Rgba(canvas_color));
+    let source = image.to_rgba32f();
+    image::imageops::overlay(&mut canvas, &source, -x, -y);
+    DynamicImage::ImageRgba32F(canvas)
+}
+
 pub fn is_geometry_identity(params: &GeometryParams) -> bool {
     let dist_identity = !params.lens_distortion_enabled
         || ((params.lens_distortion_amount - 1.0).abs() < 1e-4
@@ -931,6 +1099,20 @@ pub fn apply_geometry_warp(image: &DynamicImage, adjustments: &serde_json::Value
     }
 }
 
+/// Owned variant of `apply_geometry_warp` that hands the input back untouched when the
+/// lens and transform parameters are identity, avoiding a full-resolution copy.
+pub fn apply_geometry_warp_owned(
+    image: DynamicImage,
+    adjustments: &serde_json::Value,
+) -> DynamicImage {
+    let params = get_geometry_params_from_json(adjustments);
+    if is_geometry_identity(&params) {
+        image
+    } else {
+        warp_image_geometry(&image, params)
+    }
+}
+
 pub fn apply_unwarp_geometry(
     image: &DynamicImage,
     adjustments: &serde_json::Value,
@@ -1010,6 +1192,46 @@ pub struct ColorCalibrationSettings {
     _pad1: f32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[repr(C)]
+pub struct SplitToning {
+    pub shadow_hue: f32,
+    pub shadow_saturation: f32,
+    pub highlight_hue: f32,
+    pub highlight_saturation: f32,
+    pub balance: f32,
+    _pad1: f32,
+    _pad2: f32,
+    _pad3: f32,
+}
+
+/// Each output row holds the red, green and blue input weights plus a constant offset.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct ChannelMixer {
+    pub red: [f32; 4],
+    pub green: [f32; 4],
+    pub blue: [f32; 4],
+    pub monochrome: u32,
+    pub strength: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+impl Default for ChannelMixer {
+    fn default() -> Self {
+        Self {
+            red: [1.0, 0.0, 0.0, 0.0],
+            green: [0.0, 1.0, 0.0, 0.0],
+            blue: [0.0, 0.0, 1.0, 0.0],
+            monochrome: 0,
+            strength: 0.0,
+            _pad1: 0.0,
+            _pad2: 0.0,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct GpuMat3 {
@@ -1111,6 +1333,41 @@ pub struct GlobalAdjustments {
     pub flare_amount: f32,
 
     _pad_creative_1: f32,
+
+    pub channel_mixer: ChannelMixer,
+    pub split_toning: SplitToning,
+
+    pub defringe_purple_amount: f32,
+    pub defringe_green_amount: f32,
+    pub defringe_purple_hue_min: f32,
+    pub defringe_purple_hue_max: f32,
+    pub defringe_green_hue_min: f32,
+    pub defringe_green_hue_max: f32,
+    _pad_defringe1: f32,
+    _pad_defringe2: f32,
+
+    pub grain_color: u32,
+    pub grain_highlight_rolloff: f32,
+    pub glow_radius: f32,
+    pub halation_radius: f32,
+
+    pub invert: u32,
+    pub film_base_r: f32,
+    pub film_base_g: f32,
+    pub film_base_b: f32,
+    pub negative_black_r: f32,
+    pub negative_black_g: f32,
+    pub negative_black_b: f32,
+    _pad_negative1: f32,
+    pub negative_white_r: f32,
+    pub negative_white_g: f32,
+    pub negative_white_b: f32,
+    _pad_negative2: f32,
+
+    pub output_primaries: u32,
+    _pad_output1: f32,
+    _pad_output2: f32,
+    _pad_output3: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
@@ -1138,10 +1395,10 @@ pub struct MaskAdjustments {
     pub glow_amount: f32,
     pub halation_amount: f32,
     pub flare_amount: f32,
-    _pad1: f32,
+    pub grain_amount: f32,
 
-    _pad_cg1: f32,
-    _pad_cg2: f32,
+    pub grain_size: f32,
+    pub grain_roughness: f32,
     _pad_cg3: f32,
     pub color_grading_shadows: ColorGradeSettings,
     pub color_grading_midtones: ColorGradeSettings,
@@ -1160,17 +1417,30 @@ pub struct MaskAdjustments {
     pub red_curve_count: u32,
     pub green_curve_count: u32,
     pub blue_curve_count: u32,
-    _pad_end4: f32,
-    _pad_end5: f32,
-    _pad_end6: f32,
+    pub blend_mode: u32,
+    pub grain_color: u32,
+    pub grain_highlight_rolloff: f32,
     _pad_end7: f32,
+
+    pub channel_mixer: ChannelMixer,
 }
 
+// `ChannelMixer` is three vec4s in WGSL, so it must stay 64 bytes and start on a 16-byte
+// boundary in both uniform structs.
+const _: () = {
+    assert!(std::mem::size_of::<ChannelMixer>() == 64);
+    assert!(std::mem::offset_of!(GlobalAdjustments, channel_mixer) % 16 == 0);
+    assert!(std::mem::offset_of!(MaskAdjustments, channel_mixer) % 16 == 0);
+};
+
+/// Masks beyond the first `gpu_processing::MAX_MASKS` are packed into the mask atlas.
+pub const MAX_MASK_COUNT: usize = 16;
+
 #[derive(Debug, Clone, Copy, Pod, Zeroable, Default)]
 #[repr(C)]
 pub struct AllAdjustments {
     pub global: GlobalAdjustments,
-    pub mask_adjustments: [MaskAdjustments; 8],
+    pub mask_adjustments: [MaskAdjustments; MAX_MASK_COUNT],
     pub mask_count: u32,
     pub tile_offset_x: u32,
     pub tile_offset_y: u32,
@@ -1178,36 +1448,6 @@ pub struct AllAdjustments {
 }
 
 struct AdjustmentScales {
-    exposure: f32,
-    brightness: f32,
-    contrast: f32,
-    highlights: f32,
-    shadows: f32,
-    whites: f32,
-    blacks: f32,
-    saturation: f32,
-    temperature: f32,
-    tint: f32,
-    vibrance: f32,
-
-    sharpness: f32,
-    luma_noise_reduction: f32,
-    color_noise_reduction: f32,
-    clarity: f32,
-    dehaze: f32,
-    structure: f32,
-    centré: f32,
-
-    vignette_amount: f32,
-    vignette_midpoint: f32,
-    vignette_roundness: f32,
-    vignette_feather: f32,
-    grain_amount: f32,
-    grain_size: f32,
-    grain_roughness: f32,
-
-    chromatic_aberration: f32,
-
     hsl_hue_multiplier: f32,
     hsl_saturation: f32,
     hsl_luminance: f32,
@@ -1220,42 +1460,13 @@ struct AdjustmentScales {
     color_calibration_hue: f32,
     color_calibration_saturation: f32,
 
-    glow: f32,
-    halation: f32,
-    flares: f32,
+    channel_mixer: f32,
+
+    split_toning_saturation: f32,
+    split_toning_balance: f32,
 }
 
 const SCALES: AdjustmentScales = AdjustmentScales {
-    exposure: 0.8,
-    brightness: 0.8,
-    contrast: 100.0,
-    highlights: 120.0,
-    shadows: 120.0,
-    whites: 30.0,
-    blacks: 70.0,
-    saturation: 100.0,
-    temperature: 25.0,
-    tint: 100.0,
-    vibrance: 100.0,
-
-    sharpness: 40.0,
-    luma_noise_reduction: 100.0,
-    color_noise_reduction: 100.0,
-    clarity: 200.0,
-    dehaze: 750.0,
-    structure: 200.0,
-    centré: 250.0,
-
-    vignette_amount: 100.0,
-    vignette_midpoint: 100.0,
-    vignette_roundness: 100.0,
-    vignette_feather: 100.0,
-    grain_amount: 200.0,
-    grain_size: 50.0,
-    grain_roughness: 100.0,
-
-    chromatic_aberration: 10000.0,
-
     hsl_hue_multiplier: 0.3,
     hsl_saturation: 100.0,
     hsl_luminance: 100.0,
@@ -1268,9 +1479,10 @@ const SCALES: AdjustmentScales = AdjustmentScales {
     color_calibration_hue: 400.0,
     color_calibration_saturation: 120.0,
 
-    glow: 100.0,
-    halation: 100.0,
-    flares: 100.0,
+    channel_mixer: 100.0,
+
+    split_toning_saturation: 100.0,
+    split_toning_balance: 100.0,
 };
 
 fn parse_hsl_adjustments(js_hsl: &serde_json::Value) -> [HslColor; 8] {
@@ -1317,6 +1529,136 @@ fn parse_color_grade_settings(js_cg: &serde_json::Value) -> ColorGradeSettings {
     }
 }
 
+fn parse_channel_mixer(js_mixer: &serde_json::Value) -> ChannelMixer {
+    if !js_mixer.is_object() {
+        return ChannelMixer::default();
+    }
+    let identity = ChannelMixer::default();
+    let parse_row = |name: &str, default: [f32; 4]| -> [f32; 4] {
+        let row = &js_mixer[name];
+        let get = |key: &str, fallback: f32| {
+            row[key]
+                .as_f64()
+                .map_or(fallback, |v| v as f32 / SCALES.channel_mixer)
+        };
+        [
+            get("red", default[0]),
+            get("green", default[1]),
+            get("blue", default[2]),
+            get("constant", default[3]),
+        ]
+    };
+
+    ChannelMixer {
+        red: parse_row("red", identity.red),
+        green: parse_row("green", identity.green),
+        blue: parse_row("blue", identity.blue),
+        monochrome: if js_mixer["monochrome"].as_bool().unwrap_or(false) {
+            1
+        } else {
+            0
+        },
+        strength: 1.0,
+        _pad1: 0.0,
+        _pad2: 0.0,
+    }
+}
+
+fn parse_split_toning(js_st: &serde_json::Value) -> SplitToning {
+    if !js_st.is_object() {
+        return SplitToning::default();
+    }
+    SplitToning {
+        shadow_hue: js_st["shadowHue"].as_f64().unwrap_or(0.0) as f32,
+        shadow_saturation: js_st["shadowSaturation"].as_f64().unwrap_or(0.0) as f32
+            / SCALES.split_toning_saturation,
+        highlight_hue: js_st["highlightHue"].as_f64().unwrap_or(0.0) as f32,
+        highlight_saturation: js_st["highlightSaturation"].as_f64().unwrap_or(0.0) as f32
+            / SCALES.split_toning_saturation,
+        balance: js_st["balance"].as_f64().unwrap_or(0.0) as f32 / SCALES.split_toning_balance,
+        _pad1: 0.0,
+        _pad2: 0.0,
+        _pad3: 0.0,
+    }
+}
+
+const PARAMETRIC_CURVE_SAMPLES: usize = 15;
+const PARAMETRIC_REGION_SHIFT: f32 = 64.0;
+const PARAMETRIC_MIN_POINT_GAP: f32 = 8.0;
+const PARAMETRIC_REGIONS: [(&str, f32); 4] = [
+    ("shadows", 0.125),
+    ("darks", 0.375),
+    ("lights", 0.625),
+    ("highlights", 0.875),
+];
+
+/// Bakes the parametric region sliders into the luma curve. The user's points are kept
+/// and shifted by their region weights, free slots are filled with samples of the point
+/// curve so the regions have somewhere to bend, and the result is kept monotonic.
+fn apply_parametric_curve(
+    points: Vec<serde_json::Value>,
+    js_parametric: &serde_json::Value,
+) -> Vec<serde_json::Value> {
+    let amounts: Vec<(f32, f32)> = PARAMETRIC_REGIONS
+        .iter()
+        .map(|(key, center)| {
+            let amount = js_parametric[*key]
+                .as_f64()
+                .unwrap_or(0.0)
+                .clamp(-100.0, 100.0);
+            (*center, amount as f32 / 100.0)
+        })
+        .collect();
+    if amounts.iter().all(|(_, amount)| *amount == 0.0) {
+        return points;
+    }
+
+    let count = points.len().min(16);
+    let aligned = convert_points_to_aligned(points);
+    let mut anchors: Vec<(f32, Option<f32>)> = if count >= 2 {
+        aligned[..count].iter().map(|p| (p.x, Some(p.y))).collect()
+    } else {
+        vec![(0.0, Some(0.0)), (255.0, Some(255.0))]
+    };
+    let samples = PARAMETRIC_REGIONS
+        .iter()
+        .map(|(_, center)| center * 255.0)
+        .chain(
+            (0..PARAMETRIC_CURVE_SAMPLES)
+                .map(|i| i as f32 / (PARAMETRIC_CURVE_SAMPLES - 1) as f32 * 255.0),
+        );
+    for x in samples {
+        if anchors.len() >= 16 {
+            break;
+        }
+        if anchors
+            .iter()
+            .all(|(existing, _)| (existing - x).abs() >= PARAMETRIC_MIN_POINT_GAP)
+        {
+            anchors.push((x, None));
+        }
+    }
+    anchors.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut previous_y = 0.0f32;
+    anchors
+        .into_iter()
+        .map(|(x, user_y)| {
+            let t = (x / 255.0).clamp(0.0, 1.0);
+            let base = user_y.unwrap_or_else(|| cpu_apply_curve(t, &aligned, count as u32) * 255.0);
+            let shift: f32 = amounts
+                .iter()
+                .map(|(center, amount)| amount * (1.0 - (t - center).abs() / 0.25).max(0.0))
+                .sum();
+            let endpoint_falloff = (t * 8.0).min(1.0) * ((1.0 - t) * 8.0).min(1.0);
+            let y = (base + shift * PARAMETRIC_REGION_SHIFT * endpoint_falloff)
+                .clamp(previous_y, 255.0);
+            previous_y = y;
+            json!({ "x": x, "y": y })
+        })
+        .collect()
+}
+
 fn convert_points_to_aligned(frontend_points: Vec<serde_json::Value>) -> [Point; 16] {
     let mut aligned_points = [Point::default(); 16];
     for (i, point) in frontend_points.iter().enumerate().take(16) {
@@ -1332,19 +1674,19 @@ fn convert_points_to_aligned(frontend_points: Vec<serde_json::Value>) -> [Point;
     aligned_points
 }
 
-const WP_D65: Vec2 = Vec2::new(0.3127, 0.3290);
-const PRIMARIES_SRGB: [Vec2; 3] = [
+pub const WP_D65: Vec2 = Vec2::new(0.3127, 0.3290);
+pub const PRIMARIES_SRGB: [Vec2; 3] = [
     Vec2::new(0.64, 0.33),
     Vec2::new(0.30, 0.60),
     Vec2::new(0.15, 0.06),
 ];
-const PRIMARIES_REC2020: [Vec2; 3] = [
+pub const PRIMARIES_REC2020: [Vec2; 3] = [
     Vec2::new(0.708, 0.292),
     Vec2::new(0.170, 0.797),
     Vec2::new(0.131, 0.046),
 ];
 
-fn xy_to_xyz(xy: Vec2) -> Vec3 {
+pub fn xy_to_xyz(xy: Vec2) -> Vec3 {
     if xy.y < 1e-6 {
         Vec3::ZERO
     } else {
@@ -1352,7 +1694,7 @@ fn xy_to_xyz(xy: Vec2) -> Vec3 {
     }
 }
 
-fn primaries_to_xyz_matrix(primaries: &[Vec2; 3], white_point: Vec2) -> Mat3 {
+pub fn primaries_to_xyz_matrix(primaries: &[Vec2; 3], white_point: Vec2) -> Mat3 {
     let r_xyz = xy_to_xyz(primaries[0]);
     let g_xyz = xy_to_xyz(primaries[1]);
     let b_xyz = xy_to_xyz(primaries[2]);
@@ -1362,6 +1704,63 @@ fn primaries_to_xyz_matrix(primaries: &[Vec2; 3], white_point: Vec2) -> Mat3 {
     Mat3::from_cols(r_xyz * s.x, g_xyz * s.y, b_xyz * s.z)
 }
 
+const WB_MAX_MIRED_SHIFT: f32 = 150.0;
+const WB_MAX_DUV_SHIFT: f32 = 0.05;
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct WhiteBalanceSample {
+    pub temperature: f64,
+    pub tint: f64,
+}
+
+fn xy_to_uv(xy: Vec2) -> Vec2 {
+    let denom = -2.0 * xy.x + 12.0 * xy.y + 3.0;
+    Vec2::new(4.0 * xy.x / denom, 6.0 * xy.y / denom)
+}
+
+/// Correlated colour temperature (McCamy) and signed distance from the
+/// Planckian locus in CIE 1960 uv (positive is greener).
+fn cct_and_duv(xy: Vec2) -> (f32, f32) {
+    let n = (xy.x - 0.3320) / (0.1858 - xy.y);
+    let cct =
+        (449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33).clamp(1000.0, 25000.0);
+
+    let t = cct as f64;
+    let locus = Vec2::new(
+        ((0.860117757 + 1.54118254e-4 * t + 1.28641212e-7 * t * t)
+            / (1.0 + 8.42420235e-4 * t + 7.08145163e-7 * t * t)) as f32,
+        ((0.317398726 + 4.22806245e-5 * t + 4.20481691e-8 * t * t)
+            / (1.0 - 2.89741816e-5 * t + 1.61456053e-7 * t * t)) as f32,
+    );
+    let uv = xy_to_uv(xy);
+    let duv = uv.distance(locus) * (uv.y - locus.y).signum();
+    (cct, duv)
+}
+
+/// Solves the temperature/tint slider offsets that would render the linear
+/// `color` neutral, measured against the D65 working white point.
+pub fn solve_white_balance(color: Vec3) -> WhiteBalanceSample {
+    let xyz = primaries_to_xyz_matrix(&PRIMARIES_SRGB, WP_D65) * color.max(Vec3::ZERO);
+    let sum = xyz.x + xyz.y + xyz.z;
+    if sum < 1e-6 {
+        return WhiteBalanceSample {
+            temperature: 0.0,
+            tint: 0.0,
+        };
+    }
+
+    let white = xy_to_xyz(WP_D65);
+    let white_xy = Vec2::new(white.x, white.y) / (white.x + white.y + white.z);
+    let (ref_cct, ref_duv) = cct_and_duv(white_xy);
+    let (cct, duv) = cct_and_duv(Vec2::new(xyz.x, xyz.y) / sum);
+
+    let mired_delta = 1_000_000.0 / cct - 1_000_000.0 / ref_cct;
+    WhiteBalanceSample {
+        temperature: (-mired_delta / WB_MAX_MIRED_SHIFT * 100.0) as f64,
+        tint: ((duv - ref_duv) / WB_MAX_DUV_SHIFT * 100.0) as f64,
+    }
+}
+
 fn rotate_and_scale_primary(primary: Vec2, white_point: Vec2, scale: f32, rotation: f32) -> Vec2 {
     let p_rel = primary - white_point;
     let p_scaled = p_rel * scale;
@@ -1424,6 +1823,16 @@ fn calculate_agx_matrices() -> (GpuMat3, GpuMat3) {
     )
 }
 
+pub fn parse_hex_color(hex: &str) -> Option<Rgba<u8>> {
+    let hex = hex.trim().trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(Rgba([channel(0)?, channel(2)?, channel(4)?, 255])),
+        8 => Some(Rgba([channel(0)?, channel(2)?, channel(4)?, channel(6)?])),
+        _ => None,
+    }
+}
+
 fn get_global_adjustments_from_json(
     js_adjustments: &serde_json::Value,
     is_raw: bool,
@@ -1436,24 +1845,15 @@ fn get_global_adjustments_from_json(
             .unwrap_or(true)
     };
 
-    let get_val = |section: &str, key: &str, scale: f32, default: Option<f64>| -> f32 {
-        if is_visible(section) {
-            js_adjustments[key]
-                .as_f64()
-                .unwrap_or(default.unwrap_or(0.0)) as f32
-                / scale
-        } else {
-            if let Some(d) = default {
-                d as f32 / scale
-            } else {
-                0.0
-            }
-        }
-    };
+    let get_val =
+        |key: &str| -> f32 { adjustment_registry::scaled_value(js_adjustments, key, &is_visible) };
 
     let curves_obj = js_adjustments.get("curves").cloned().unwrap_or_default();
     let luma_points: Vec<serde_json::Value> = if is_visible("curves") {
-        curves_obj["luma"].as_array().cloned().unwrap_or_default()
+        apply_parametric_curve(
+            curves_obj["luma"].as_array().cloned().unwrap_or_default(),
+            &js_adjustments["parametricCurve"],
+        )
     } else {
         Vec::new()
     };
@@ -1505,81 +1905,48 @@ fn get_global_adjustments_from_json(
         ColorCalibrationSettings::default()
     };
 
+    let invert =
+        (is_visible("negative") && js_adjustments["invert"].as_bool() == Some(true)) as u32;
+    let film_base = js_adjustments["filmBaseColor"]
+        .as_str()
+        .and_then(parse_hex_color)
+        .map_or([1.0; 3], |c| [c[0], c[1], c[2]].map(|v| v as f32 / 255.0));
+
     let tone_mapper = js_adjustments["toneMapper"].as_str().unwrap_or("basic");
     let (pipe_to_rendering, rendering_to_pipe) = calculate_agx_matrices();
 
     GlobalAdjustments {
-        exposure: get_val("basic", "exposure", SCALES.exposure, None),
-        brightness: get_val("basic", "brightness", SCALES.brightness, None),
-        contrast: get_val("basic", "contrast", SCALES.contrast, None),
-        highlights: get_val("basic", "highlights", SCALES.highlights, None),
-        shadows: get_val("basic", "shadows", SCALES.shadows, None),
-        whites: get_val("basic", "whites", SCALES.whites, None),
-        blacks: get_val("basic", "blacks", SCALES.blacks, None),
-
-        saturation: get_val("color", "saturation", SCALES.saturation, None),
-        temperature: get_val("color", "temperature", SCALES.temperature, None),
-        tint: get_val("color", "tint", SCALES.tint, None),
-        vibrance: get_val("color", "vibrance", SCALES.vibrance, None),
-
-        sharpness: get_val("details", "sharpness", SCALES.sharpness, None),
-        luma_noise_reduction: get_val(
-            "details",
-            "lumaNoiseReduction",
-            SCALES.luma_noise_reduction,
-            None,
-        ),
-        color_noise_reduction: get_val(
-            "details",
-            "colorNoiseReduction",
-            SCALES.color_noise_reduction,
-            None,
-        ),
-
-        clarity: get_val("effects", "clarity", SCALES.clarity, None),
-        dehaze: get_val("effects", "dehaze", SCALES.dehaze, None),
-        structure: get_val("effects", "structure", SCALES.structure, None),
-        centré: get_val("effects", "centré", SCALES.centré, None),
-        vignette_amount: get_val("effects", "vignetteAmount", SCALES.vignette_amount, None),
-        vignette_midpoint: get_val(
-            "effects",
-            "vignetteMidpoint",
-            SCALES.vignette_midpoint,
-            Some(50.0),
-        ),
-        vignette_roundness: get_val(
-            "effects",
-            "vignetteRoundness",
-            SCALES.vignette_roundness,
-            Some(0.0),
-        ),
-        vignette_feather: get_val(
-            "effects",
-            "vignetteFeather",
-            SCALES.vignette_feather,
-            Some(50.0),
-        ),
-        grain_amount: get_val("effects", "grainAmount", SCALES.grain_amount, None),
-        grain_size: get_val("effects", "grainSize", SCALES.grain_size, Some(25.0)),
-        grain_roughness: get_val(
-            "effects",
-            "grainRoughness",
-            SCALES.grain_roughness,
-            Some(50.0),
-        ),
-
-        chromatic_aberration_red_cyan: get_val(
-            "details",
-            "chromaticAberrationRedCyan",
-            SCALES.chromatic_aberration,
-            None,
-        ),
-        chromatic_aberration_blue_yellow: get_val(
-            "details",
-            "chromaticAberrationBlueYellow",
-            SCALES.chromatic_aberration,
-            None,
-        ),
+        exposure: get_val("exposure"),
+        brightness: get_val("brightness"),
+        contrast: get_val("contrast"),
+        highlights: get_val("highlights"),
+        shadows: get_val("shadows"),
+        whites: get_val("whites"),
+        blacks: get_val("blacks"),
+
+        saturation: get_val("saturation"),
+        temperature: get_val("temperature"),
+        tint: get_val("tint"),
+        vibrance: get_val("vibrance"),
+
+        sharpness: get_val("sharpness"),
+        luma_noise_reduction: get_val("lumaNoiseReduction"),
+        color_noise_reduction: get_val("colorNoiseReduction"),
+
+        clarity: get_val("clarity"),
+        dehaze: get_val("dehaze"),
+        structure: get_val("structure"),
+        centré: get_val("centré"),
+        vignette_amount: get_val("vignetteAmount"),
+        vignette_midpoint: get_val("vignetteMidpoint"),
+        vignette_roundness: get_val("vignetteRoundness"),
+        vignette_feather: get_val("vignetteFeather"),
+        grain_amount: get_val("grainAmount"),
+        grain_size: get_val("grainSize"),
+        grain_roughness: get_val("grainRoughness"),
+
+        chromatic_aberration_red_cyan: get_val("chromaticAberrationRedCyan"),
+        chromatic_aberration_blue_yellow: get_val("chromaticAberrationBlueYellow"),
         show_clipping: if js_adjustments["showClipping"].as_bool().unwrap_or(false) {
             1
         } else {
@@ -1594,7 +1961,12 @@ fn get_global_adjustments_from_json(
             0
         },
         lut_intensity: js_adjustments["lutIntensity"].as_f64().unwrap_or(100.0) as f32 / 100.0,
-        tonemapper_mode: if tone_mapper == "agx" { 1 } else { 0 },
+        tonemapper_mode: match tone_mapper {
+            "agx" => 1,
+            "reinhard" => 2,
+            "filmic" => 3,
+            _ => 0,
+        },
         _pad_lut2: 0.0,
         _pad_lut3: 0.0,
         _pad_lut4: 0.0,
@@ -1658,11 +2030,55 @@ fn get_global_adjustments_from_json(
         _pad_end3: 0.0,
         _pad_end4: 0.0,
 
-        glow_amount: get_val("effects", "glowAmount", SCALES.glow, None),
-        halation_amount: get_val("effects", "halationAmount", SCALES.halation, None),
-        flare_amount: get_val("effects", "flareAmount", SCALES.flares, None),
+        glow_amount: get_val("glowAmount"),
+        halation_amount: get_val("halationAmount"),
+        flare_amount: get_val("flareAmount"),
 
         _pad_creative_1: 0.0,
+
+        channel_mixer: if is_visible("color") {
+            parse_channel_mixer(&js_adjustments["channelMixer"])
+        } else {
+            ChannelMixer::default()
+        },
+        split_toning: if is_visible("color") {
+            parse_split_toning(&js_adjustments["splitToning"])
+        } else {
+            SplitToning::default()
+        },
+
+        defringe_purple_amount: get_val("defringePurpleAmount"),
+        defringe_green_amount: get_val("defringeGreenAmount"),
+        defringe_purple_hue_min: get_val("defringePurpleHueMin"),
+        defringe_purple_hue_max: get_val("defringePurpleHueMax"),
+        defringe_green_hue_min: get_val("defringeGreenHueMin"),
+        defringe_green_hue_max: get_val("defringeGreenHueMax"),
+        _pad_defringe1: 0.0,
+        _pad_defringe2: 0.0,
+
+        grain_color: (is_visible("effects") && js_adjustments["grainColor"].as_bool() == Some(true))
+            as u32,
+        grain_highlight_rolloff: get_val("grainHighlightRolloff"),
+        glow_radius: get_val("glowRadius"),
+        halation_radius: get_val("halationRadius"),
+
+        invert,
+        film_base_r: film_base[0],
+        film_base_g: film_base[1],
+        film_base_b: film_base[2],
+        negative_black_r: get_val("negativeBlackRed"),
+        negative_black_g: get_val("negativeBlackGreen"),
+        negative_black_b: get_val("negativeBlackBlue"),
+        _pad_negative1: 0.0,
+        negative_white_r: get_val("negativeWhiteRed"),
+        negative_white_g: get_val("negativeWhiteGreen"),
+        negative_white_b: get_val("negativeWhiteBlue"),
+        _pad_negative2: 0.0,
+
+        output_primaries: color_management::OUTPUT_PRIMARIES_SRGB,
+        _pad_output1: 0.0,
+        _pad_output2: 0.0,
+        _pad_output3: 0.0,
     }
 }
 
@@ -1679,17 +2095,14 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
             .unwrap_or(true)
     };
 
-    let get_val = |section: &str, key: &str, scale: f32| -> f32 {
-        if is_visible(section) {
-            adj[key].as_f64().unwrap_or(0.0) as f32 / scale
-        } else {
-            0.0
-        }
-    };
+    let get_val = |key: &str| -> f32 { adjustment_registry::scaled_value(adj, key, &is_visible) };
 
     let curves_obj = adj.get("curves").cloned().unwrap_or_default();
     let luma_points: Vec<serde_json::Value> = if is_visible("curves") {
-        curves_obj["luma"].as_array().cloned().unwrap_or_default()
+        apply_parametric_curve(
+            curves_obj["luma"].as_array().cloned().unwrap_or_default(),
+            &adj["parametricCurve"],
+        )
     } else {
         Vec::new()
     };
@@ -1711,38 +2124,34 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
     let cg_obj = adj.get("colorGrading").cloned().unwrap_or_default();
 
     MaskAdjustments {
-        exposure: get_val("basic", "exposure", SCALES.exposure),
-        brightness: get_val("basic", "brightness", SCALES.brightness),
-        contrast: get_val("basic", "contrast", SCALES.contrast),
-        highlights: get_val("basic", "highlights", SCALES.highlights),
-        shadows: get_val("basic", "shadows", SCALES.shadows),
-        whites: get_val("basic", "whites", SCALES.whites),
-        blacks: get_val("basic", "blacks", SCALES.blacks),
-
-        saturation: get_val("color", "saturation", SCALES.saturation),
-        temperature: get_val("color", "temperature", SCALES.temperature),
-        tint: get_val("color", "tint", SCALES.tint),
-        vibrance: get_val("color", "vibrance", SCALES.vibrance),
-
-        sharpness: get_val("details", "sharpness", SCALES.sharpness),
-        luma_noise_reduction: get_val("details", "lumaNoiseReduction", SCALES.luma_noise_reduction),
-        color_noise_reduction: get_val(
-            "details",
-            "colorNoiseReduction",
-            SCALES.color_noise_reduction,
-        ),
-
-        clarity: get_val("effects", "clarity", SCALES.clarity),
-        dehaze: get_val("effects", "dehaze", SCALES.dehaze),
-        structure: get_val("effects", "structure", SCALES.structure),
-
-        glow_amount: get_val("effects", "glowAmount", SCALES.glow),
-        halation_amount: get_val("effects", "halationAmount", SCALES.halation),
-        flare_amount: get_val("effects", "flareAmount", SCALES.flares),
-        _pad1: 0.0,
-
-        _pad_cg1: 0.0,
-        _pad_cg2: 0.0,
+        exposure: get_val("exposure"),
+        brightness: get_val("brightness"),
+        contrast: get_val("contrast"),
+        highlights: get_val("highlights"),
+        shadows: get_val("shadows"),
+        whites: get_val("whites"),
+        blacks: get_val("blacks"),
+
+        saturation: get_val("saturation"),
+        temperature: get_val("temperature"),
+        tint: get_val("tint"),
+        vibrance: get_val("vibrance"),
+
+        sharpness: get_val("sharpness"),
+        luma_noise_reduction: get_val("lumaNoiseReduction"),
+        color_noise_reduction: get_val("colorNoiseReduction"),
+
+        clarity: get_val("clarity"),
+        dehaze: get_val("dehaze"),
+        structure: get_val("structure"),
+
+        glow_amount: get_val("glowAmount"),
+        halation_amount: get_val("halationAmount"),
+        flare_amount: get_val("flareAmount"),
+        grain_amount: get_val("grainAmount"),
+
+        grain_size: get_val("grainSize"),
+        grain_roughness: get_val("grainRoughness"),
         _pad_cg3: 0.0,
         color_grading_shadows: if is_visible("color") {
             parse_color_grade_settings(&cg_obj["shadows"])
@@ -1785,10 +2194,16 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
         red_curve_count: red_points.len() as u32,
         green_curve_count: green_points.len() as u32,
         blue_curve_count: blue_points.len() as u32,
-        _pad_end4: 0.0,
-        _pad_end5: 0.0,
-        _pad_end6: 0.0,
+        blend_mode: 0,
+        grain_color: (is_visible("effects") && adj["grainColor"].as_bool() == Some(true)) as u32,
+        grain_highlight_rolloff: get_val("grainHighlightRolloff"),
         _pad_end7: 0.0,
+
+        channel_mixer: if is_visible("color") {
+            parse_channel_mixer(&adj["channelMixer"])
+        } else {
+            ChannelMixer::default()
+        },
     }
 }
 
@@ -1797,7 +2212,7 @@ pub fn get_all_adjustments_from_json(
     is_raw: bool,
 ) -> AllAdjustments {
     let global = get_global_adjustments_from_json(js_adjustments, is_raw);
-    let mut mask_adjustments = [MaskAdjustments::default(); 8];
+    let mut mask_adjustments = [MaskAdjustments::default(); MAX_MASK_COUNT];
     let mut mask_count = 0;
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -1809,9 +2224,10 @@ pub fn get_all_adjustments_from_json(
         .iter()
         .filter(|m| m.visible)
         .enumerate()
-        .take(8)
+        .take(MAX_MASK_COUNT)
     {
         mask_adjustments[i] = get_mask_adjustments_from_json(&mask_def.adjustments);
+        mask_adjustments[i].blend_mode = mask_def.blend_mode.shader_index();
         mask_count += 1;
     }
 
@@ -1825,69 +2241,982 @@ pub fn get_all_adjustments_from_json(
     }
 }
 
-#[derive(Clone)]
-pub struct GpuContext {
-    pub device: Arc<wgpu::Device>,
-    pub queue: Arc<wgpu::Queue>,
-    pub limits: wgpu::Limits,
+const CPU_LUMA_COEFF: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+const CPU_HSL_RANGES: [(f32, f32); 8] = [
+    (358.0, 35.0),
+    (25.0, 45.0),
+    (60.0, 40.0),
+    (115.0, 90.0),
+    (180.0, 60.0),
+    (225.0, 60.0),
+    (280.0, 55.0),
+    (330.0, 50.0),
+];
+
+#[inline(always)]
+fn cpu_luma(c: Vec3) -> f32 {
+    c.dot(CPU_LUMA_COEFF)
 }
 
 #[inline(always)]
-fn rgb_to_yc_only(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
-    let y = 0.299 * r + 0.587 * g + 0.114 * b;
-    let cb = -0.168736 * r - 0.331264 * g + 0.5 * b;
-    let cr = 0.5 * r - 0.418688 * g - 0.081312 * b;
-    (y, cb, cr)
+fn cpu_smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
 }
 
 #[inline(always)]
-fn yc_to_rgb(y: f32, cb: f32, cr: f32) -> (f32, f32, f32) {
-    let r = y + 1.402 * cr;
-    let g = y - 0.344136 * cb - 0.714136 * cr;
-    let b = y + 1.772 * cb;
-    (r, g, b)
+fn cpu_mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
 }
 
-pub fn remove_raw_artifacts_and_enhance(image: &mut DynamicImage) {
-    let mut buffer = image.to_rgb32f();
-    let w = buffer.width() as usize;
-    let h = buffer.height() as usize;
+pub fn cpu_srgb_to_linear(c: Vec3) -> Vec3 {
+    let f = |v: f32| {
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Vec3::new(f(c.x), f(c.y), f(c.z))
+}
 
-    let mut ycbcr_buffer = vec![0.0f32; w * h * 3];
+pub fn cpu_linear_to_srgb(c: Vec3) -> Vec3 {
+    let f = |v: f32| {
+        let v = v.clamp(0.0, 1.0);
+        if v <= 0.0031308 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    Vec3::new(f(c.x), f(c.y), f(c.z))
+}
 
-    let src = buffer.as_raw();
+fn cpu_rgb_to_hsv(c: Vec3) -> Vec3 {
+    let c_max = c.max_element();
+    let c_min = c.min_element();
+    let delta = c_max - c_min;
+    let mut h = 0.0;
+    if delta > 0.0 {
+        if c_max == c.x {
+            h = 60.0 * (((c.y - c.z) / delta) % 6.0);
+        } else if c_max == c.y {
+            h = 60.0 * (((c.z - c.x) / delta) + 2.0);
+        } else {
+            h = 60.0 * (((c.x - c.y) / delta) + 4.0);
+        }
+    }
+    if h < 0.0 {
+        h += 360.0;
+    }
+    let s = if c_max > 0.0 { delta / c_max } else { 0.0 };
+    Vec3::new(h, s, c_max)
+}
 
-    ycbcr_buffer
-        .par_chunks_mut(3)
-        .zip(src.par_chunks(3))
-        .for_each(|(dest, pixel)| {
-            let (y, cb, cr) = rgb_to_yc_only(pixel[0], pixel[1], pixel[2]);
-            dest[0] = y;
-            dest[1] = cb;
-            dest[2] = cr;
-        });
+fn cpu_hsv_to_rgb(c: Vec3) -> Vec3 {
+    let (h, s, v) = (c.x, c.y, c.z);
+    let chroma = v * s;
+    let x = chroma * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - chroma;
+    let rgb_prime = if h < 60.0 {
+        Vec3::new(chroma, x, 0.0)
+    } else if h < 120.0 {
+        Vec3::new(x, chroma, 0.0)
+    } else if h < 180.0 {
+        Vec3::new(0.0, chroma, x)
+    } else if h < 240.0 {
+        Vec3::new(0.0, x, chroma)
+    } else if h < 300.0 {
+        Vec3::new(x, 0.0, chroma)
+    } else {
+        Vec3::new(chroma, 0.0, x)
+    };
+    rgb_prime + Vec3::splat(m)
+}
 
-    const BASE_INV_SIGMA: f32 = 14.0;
-    const OFFSETS: [isize; 3] = [-5, -1, 3];
-    const OFFSET_SQUARES: [f32; 3] = [25.0, 1.0, 9.0];
+fn cpu_shadow_mult(luma: f32, sh: f32, bl: f32) -> f32 {
+    let mut mult = 1.0;
+    let safe_luma = luma.max(0.0001);
+    if bl != 0.0 && safe_luma < 0.05 {
+        let x = safe_luma / 0.05;
+        let mask = (1.0 - x) * (1.0 - x);
+        mult *= cpu_mix(1.0, (bl * 0.75).exp2().min(3.9), mask);
+    }
+    if sh != 0.0 && safe_luma < 0.1 {
+        let x = safe_luma / 0.1;
+        let mask = (1.0 - x) * (1.0 - x);
+        mult *= cpu_mix(1.0, (sh * 1.5).exp2().min(3.9), mask);
+    }
+    mult
+}
 
-    buffer
-        .par_chunks_mut(w * 3)
-        .enumerate()
-        .for_each(|(y, row)| {
-            let row_offset = y * w;
-            let h_isize = h as isize;
-            let w_isize = w as isize;
-            let y_isize = y as isize;
+fn cpu_apply_tonal_adjustments(color: Vec3, con: f32, sh: f32, wh: f32, bl: f32) -> Vec3 {
+    let mut rgb = color;
+    if wh != 0.0 {
+        let white_level = 1.0 - wh * 0.25;
+        rgb *= 1.0 / white_level.max(0.01);
+    }
+    if sh != 0.0 || bl != 0.0 {
+        rgb *= cpu_shadow_mult(cpu_luma(rgb.max(Vec3::ZERO)), sh, bl);
+    }
+    if con != 0.0 {
+        let g = 2.2;
+        let strength = 2.0f32.powf(con * 1.25);
+        let curve = |v: f32| {
+            let safe = v.max(0.0);
+            let perceptual = safe.powf(1.0 / g).clamp(0.0, 1.0);
+            let curved = if perceptual < 0.5 {
+                0.5 * (2.0 * perceptual).powf(strength)
+            } else {
+                1.0 - 0.5 * (2.0 * (1.0 - perceptual)).powf(strength)
+            };
+            cpu_mix(curved.powf(g), v, cpu_smoothstep(1.0, 1.01, safe))
+        };
+        rgb = Vec3::new(curve(rgb.x), curve(rgb.y), curve(rgb.z));
+    }
+    rgb
+}
 
-            for x in 0..w {
-                let center_idx = (row_offset + x) * 3;
+fn cpu_apply_highlights_adjustment(color: Vec3, highlights_adj: f32) -> Vec3 {
+    if highlights_adj == 0.0 {
+        return color;
+    }
+    let luma = cpu_luma(color.max(Vec3::ZERO));
+    let highlight_mask = cpu_smoothstep(0.3, 0.95, (luma.max(0.0001) * 1.5).tanh());
+    if highlight_mask < 0.001 {
+        return color;
+    }
+    let adjusted = if highlights_adj < 0.0 {
+        let new_luma = if luma <= 1.0 {
+            luma.powf(1.0 - highlights_adj * 1.75)
+        } else {
+            let luma_excess = luma - 1.0;
+            let compression_strength = -highlights_adj * 6.0;
+            1.0 + luma_excess / (1.0 + luma_excess * compression_strength)
+        };
+        let tonally_adjusted = color * (new_luma / luma.max(0.0001));
+        tonally_adjusted.lerp(Vec3::splat(new_luma), cpu_smoothstep(1.0, 10.0, luma))
+    } else {
+        color * 2.0f32.powf(highlights_adj * 1.75)
+    };
+    color.lerp(adjusted, highlight_mask)
+}
 
-                let cy = ycbcr_buffer[center_idx];
-                let ccb = ycbcr_buffer[center_idx + 1];
-                let ccr = ycbcr_buffer[center_idx + 2];
+fn cpu_apply_filmic_exposure(color: Vec3, brightness_adj: f32) -> Vec3 {
+    if brightness_adj == 0.0 {
+        return color;
+    }
+    const RATIONAL_CURVE_MIX: f32 = 0.95;
+    const MIDTONE_STRENGTH: f32 = 1.2;
+    let original_luma = cpu_luma(color);
+    if original_luma.abs() < 0.00001 {
+        return color;
+    }
+    let scale = (brightness_adj * (1.0 - RATIONAL_CURVE_MIX)).exp2();
+    let k = (-brightness_adj * RATIONAL_CURVE_MIX * MIDTONE_STRENGTH).exp2();
+    let luma_abs = original_luma.abs();
+    let luma_floor = luma_abs.floor();
+    let luma_fract = luma_abs - luma_floor;
+    let shaped_fract = luma_fract / (luma_fract + (1.0 - luma_fract) * k);
+    let new_luma = original_luma.signum() * (luma_floor + shaped_fract) * scale;
+    let chroma = color - Vec3::splat(original_luma);
+    let chroma_scale = (new_luma / original_luma).powf(0.8);
+    Vec3::splat(new_luma) + chroma * chroma_scale
+}
 
-                let mut cb_sum = 0.0;
+fn cpu_apply_white_balance(color: Vec3, temp: f32, tint: f32) -> Vec3 {
+    let temp_mult = Vec3::new(1.0 + temp * 0.2, 1.0 + temp * 0.05, 1.0 - temp * 0.2);
+    let tint_mult = Vec3::new(1.0 + tint * 0.25, 1.0 - tint * 0.25, 1.0 + tint * 0.25);
+    color * temp_mult * tint_mult
+}
+
+fn cpu_apply_color_calibration(color: Vec3, cal: &ColorCalibrationSettings) -> Vec3 {
+    let (h_r, h_g, h_b) = (cal.red_hue, cal.green_hue, cal.blue_hue);
+    let hue_matrix = Mat3::from_cols(
+        Vec3::new(1.0 - h_r.abs(), h_r.max(0.0), (-h_r).max(0.0)),
+        Vec3::new((-h_g).max(0.0), 1.0 - h_g.abs(), h_g.max(0.0)),
+        Vec3::new(h_b.max(0.0), (-h_b).max(0.0), 1.0 - h_b.abs()),
+    );
+    let mut c = hue_matrix * color;
+
+    let sat_vector = c - Vec3::splat(cpu_luma(c.max(Vec3::ZERO)));
+    let color_sum = c.x + c.y + c.z;
+    let masks = if color_sum > 0.001 {
+        c / color_sum
+    } else {
+        Vec3::ZERO
+    };
+    let total_sat_adjustment = masks.x * cal.red_saturation
+        + masks.y * cal.green_saturation
+        + masks.z * cal.blue_saturation;
+    c += sat_vector * total_sat_adjustment;
+
+    let st = cal.shadows_tint;
+    if st.abs() > 0.001 {
+        let mask = 1.0 - cpu_smoothstep(0.0, 0.3, cpu_luma(c.max(Vec3::ZERO)));
+        let tint_mult = Vec3::new(1.0 + st * 0.25, 1.0 - st * 0.25, 1.0 + st * 0.25);
+        c = c.lerp(c * tint_mult, mask);
+    }
+    c
+}
+
+fn cpu_apply_channel_mixer(color: Vec3, mixer: &ChannelMixer) -> Vec3 {
+    if mixer.strength <= 0.0 {
+        return color;
+    }
+    let row = |r: &[f32; 4]| color.dot(Vec3::new(r[0], r[1], r[2])) + r[3];
+    let mut mixed = Vec3::new(row(&mixer.red), row(&mixer.green), row(&mixer.blue));
+    if mixer.monochrome == 1 {
+        mixed = Vec3::splat(cpu_luma(mixed));
+    }
+    color.lerp(mixed, mixer.strength)
+}
+
+fn cpu_apply_creative_color(color: Vec3, sat: f32, vib: f32) -> Vec3 {
+    let luma = cpu_luma(color);
+    let mut processed = color;
+    if sat != 0.0 {
+        processed = Vec3::splat(luma).lerp(processed, 1.0 + sat);
+    }
+    if vib == 0.0 {
+        return processed;
+    }
+    let c_max = processed.max_element();
+    let delta = c_max - processed.min_element();
+    if delta < 0.02 {
+        return processed;
+    }
+    let current_sat = delta / c_max.max(0.001);
+    let amount = if vib > 0.0 {
+        let sat_mask = 1.0 - cpu_smoothstep(0.4, 0.9, current_sat);
+        let hue = cpu_rgb_to_hsv(processed).x;
+        let skin_center = 25.0;
+        let hue_dist = (hue - skin_center)
+            .abs()
+            .min(360.0 - (hue - skin_center).abs());
+        let skin_dampener = cpu_mix(1.0, 0.6, cpu_smoothstep(35.0, 10.0, hue_dist));
+        vib * sat_mask * skin_dampener * 3.0
+    } else {
+        vib * (1.0 - cpu_smoothstep(0.2, 0.8, current_sat))
+    };
+    Vec3::splat(luma).lerp(processed, 1.0 + amount)
+}
+
+fn cpu_apply_hsl_panel(color: Vec3, hsl: &[HslColor; 8]) -> Vec3 {
+    let safe_color = color.max(Vec3::ZERO);
+    if (safe_color.x - safe_color.y).abs() < 0.001 && (safe_color.y - safe_color.z).abs() < 0.001 {
+        return safe_color;
+    }
+    let original_hsv = cpu_rgb_to_hsv(safe_color);
+    let original_luma = cpu_luma(safe_color);
+
+    let saturation_mask = cpu_smoothstep(0.05, 0.20, original_hsv.y);
+    let luminance_weight = cpu_smoothstep(0.0, 1.0, original_hsv.y);
+    if saturation_mask < 0.001 && luminance_weight < 0.001 {
+        return safe_color;
+    }
+
+    let mut raw_influences = [0.0f32; 8];
+    let mut total_raw_influence = 0.0;
+    for (i, (center, width)) in CPU_HSL_RANGES.iter().enumerate() {
+        let diff = (original_hsv.x - center).abs();
+        let falloff = diff.min(360.0 - diff) / (width * 0.5);
+        raw_influences[i] = (-1.5 * falloff * falloff).exp();
+        total_raw_influence += raw_influences[i];
+    }
+
+    let mut total_hue_shift = 0.0;
+    let mut total_sat_multiplier = 0.0;
+    let mut total_lum_adjust = 0.0;
+    for (influence, adj) in raw_influences.iter().zip(hsl.iter()) {
+        let normalized = influence / total_raw_influence;
+        total_hue_shift += adj.hue * 2.0 * normalized * saturation_mask;
+        total_sat_multiplier += adj.saturation * normalized * saturation_mask;
+        total_lum_adjust += adj.luminance * normalized * luminance_weight;
+    }
+
+    let target_luma = original_luma * (1.0 + total_lum_adjust);
+    if original_hsv.y * (1.0 + total_sat_multiplier) < 0.0001 {
+        return Vec3::splat(target_luma);
+    }
+    let hue = (original_hsv.x + total_hue_shift + 360.0) % 360.0;
+    let sat = (original_hsv.y * (1.0 + total_sat_multiplier)).clamp(0.0, 1.0);
+    let shifted = cpu_hsv_to_rgb(Vec3::new(hue, sat, original_hsv.z));
+    let new_luma = cpu_luma(shifted);
+    if new_luma < 0.0001 {
+        return Vec3::splat(target_luma.max(0.0));
+    }
+    shifted * (target_luma / new_luma)
+}
+
+fn cpu_apply_split_toning(color: Vec3, st: &SplitToning) -> Vec3 {
+    if st.shadow_saturation <= 0.001 && st.highlight_saturation <= 0.001 {
+        return color;
+    }
+    let luma = cpu_luma(color.max(Vec3::ZERO));
+    let pivot = (0.5 - st.balance * 0.4).clamp(0.1, 0.9);
+    let highlight_weight = cpu_smoothstep(pivot - 0.35, pivot + 0.35, luma);
+    let shadow_weight = 1.0 - highlight_weight;
+
+    let mut toned = color;
+    for (hue, saturation, weight) in [
+        (st.shadow_hue, st.shadow_saturation, shadow_weight),
+        (st.highlight_hue, st.highlight_saturation, highlight_weight),
+    ] {
+        if saturation > 0.001 {
+            let tint_rgb = cpu_hsv_to_rgb(Vec3::new(hue, 1.0, 1.0));
+            let tint_direction = tint_rgb - Vec3::splat(cpu_luma(tint_rgb));
+            toned += tint_direction * luma * saturation * weight;
+        }
+    }
+    toned
+}
+
+fn cpu_apply_color_grading(
+    color: Vec3,
+    grades: [&ColorGradeSettings; 3],
+    blending: f32,
+    balance: f32,
+) -> Vec3 {
+    let luma = cpu_luma(color.max(Vec3::ZERO));
+    let shadow_crossover = 0.1 + (-balance).max(0.0) * 0.5;
+    let highlight_crossover = 0.5 - balance.max(0.0) * 0.5;
+    let feather = 0.2 * blending;
+    let final_shadow_crossover = shadow_crossover.min(highlight_crossover - 0.01);
+    let shadow_mask = 1.0
+        - cpu_smoothstep(
+            final_shadow_crossover - feather,
+            final_shadow_crossover + feather,
+            luma,
+        );
+    let highlight_mask = cpu_smoothstep(
+        highlight_crossover - feather,
+        highlight_crossover + feather,
+        luma,
+    );
+    let midtone_mask = (1.0 - shadow_mask - highlight_mask).max(0.0);
+
+    let mut graded = color;
+    for (settings, mask, sat_strength, lum_strength) in [
+        (grades[0], shadow_mask, 0.3, 0.5),
+        (grades[1], midtone_mask, 0.6, 0.8),
+        (grades[2], highlight_mask, 0.8, 1.0),
+    ] {
+        if settings.saturation > 0.001 {
+            let tint_rgb = cpu_hsv_to_rgb(Vec3::new(settings.hue, 1.0, 1.0));
+            graded += (tint_rgb - 0.5) * settings.saturation * mask * sat_strength;
+        }
+        graded += Vec3::splat(settings.luminance * mask * lum_strength);
+    }
+    graded
+}
+
+fn cpu_gpu_mat3_to_mat3(m: &GpuMat3) -> Mat3 {
+    Mat3::from_cols(
+        Vec3::new(m.col0[0], m.col0[1], m.col0[2]),
+        Vec3::new(m.col1[0], m.col1[1], m.col1[2]),
+        Vec3::new(m.col2[0], m.col2[1], m.col2[2]),
+    )
+}
+
+fn cpu_agx_curve_channel(x: f32) -> f32 {
+    const SLOPE: f32 = 2.3843;
+    const TRANSITION_X: f32 = 0.6060606;
+    const TRANSITION_Y: f32 = 0.43446;
+    let scaled_sigmoid = |x: f32, scale: f32| {
+        let v = SLOPE * (x - TRANSITION_X) / scale;
+        scale * (v / (1.0 + v.powf(1.5)).powf(1.0 / 1.5)) + TRANSITION_Y
+    };
+    // The shader's toe and shoulder share one transition point, so its linear
+    // segment between them is empty.
+    let result = if x < TRANSITION_X {
+        scaled_sigmoid(x, -1.0359)
+    } else {
+        scaled_sigmoid(x, 1.3475)
+    };
+    result.clamp(0.0, 1.0)
+}
+
+fn cpu_agx_full_transform(color: Vec3, adj: &GlobalAdjustments) -> Vec3 {
+    let min_c = color.min_element();
+    let compressed = if min_c < 0.0 { color - min_c } else { color };
+    let agx_space = cpu_gpu_mat3_to_mat3(&adj.agx_pipe_to_rendering_matrix) * compressed;
+    let curve = |v: f32| {
+        let log_encoded = ((v / 0.18).max(1.0e-6).log2() + 15.2) / 20.2;
+        cpu_agx_curve_channel(log_encoded.clamp(0.0, 1.0))
+            .max(0.0)
+            .powf(2.4)
+    };
+    let tonemapped = Vec3::new(curve(agx_space.x), curve(agx_space.y), curve(agx_space.z));
+    cpu_gpu_mat3_to_mat3(&adj.agx_rendering_to_pipe_matrix) * tonemapped
+}
+
+fn cpu_reinhard_tonemap(color: Vec3) -> Vec3 {
+    const WHITE_SQ: f32 = 16.0;
+    let x = color.max(Vec3::ZERO);
+    let luma = cpu_luma(x);
+    if luma <= 0.0 {
+        return Vec3::ZERO;
+    }
+    let mapped_luma = luma * (1.0 + luma / WHITE_SQ) / (1.0 + luma);
+    (x * (mapped_luma / luma)).clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+fn cpu_filmic_tonemap(color: Vec3) -> Vec3 {
+    const EXPOSURE_BIAS: f32 = 2.0;
+    const WHITE_POINT: f32 = 11.2;
+    let hable = |x: f32| {
+        const A: f32 = 0.15;
+        const B: f32 = 0.50;
+        const C: f32 = 0.10;
+        const D: f32 = 0.20;
+        const E: f32 = 0.02;
+        const F: f32 = 0.30;
+        ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+    };
+    let white_scale = 1.0 / hable(WHITE_POINT);
+    let x = color.max(Vec3::ZERO) * EXPOSURE_BIAS;
+    (Vec3::new(hable(x.x), hable(x.y), hable(x.z)) * white_scale).clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+fn cpu_apply_curve(val: f32, points: &[Point; 16], count: u32) -> f32 {
+    if count < 2 {
+        return val;
+    }
+    let count = count.min(16) as usize;
+    let x = val * 255.0;
+    if x <= points[0].x {
+        return points[0].y / 255.0;
+    }
+    if x >= points[count - 1].x {
+        return points[count - 1].y / 255.0;
+    }
+    for i in 0..count - 1 {
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        if x > p2.x {
+            continue;
+        }
+        let p0 = points[i.saturating_sub(1)];
+        let p3 = points[(i + 2).min(count - 1)];
+        let delta_before = (p1.y - p0.y) / (p1.x - p0.x).max(0.001);
+        let delta_current = (p2.y - p1.y) / (p2.x - p1.x).max(0.001);
+        let delta_after = (p3.y - p2.y) / (p3.x - p2.x).max(0.001);
+        let mut tangent_p1 = if i == 0 {
+            delta_current
+        } else if delta_before * delta_current <= 0.0 {
+            0.0
+        } else {
+            (delta_before + delta_current) / 2.0
+        };
+        let mut tangent_p2 = if i + 1 == count - 1 {
+            delta_current
+        } else if delta_current * delta_after <= 0.0 {
+            0.0
+        } else {
+            (delta_current + delta_after) / 2.0
+        };
+        if delta_current != 0.0 {
+            let alpha = tangent_p1 / delta_current;
+            let beta = tangent_p2 / delta_current;
+            if alpha * alpha + beta * beta > 9.0 {
+                let tau = 3.0 / (alpha * alpha + beta * beta).sqrt();
+                tangent_p1 *= tau;
+                tangent_p2 *= tau;
+            }
+        }
+        let dx = p2.x - p1.x;
+        if dx <= 0.0 {
+            return (p1.y / 255.0).clamp(0.0, 1.0);
+        }
+        let t = (x - p1.x) / dx;
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let result_y = (2.0 * t3 - 3.0 * t2 + 1.0) * p1.y
+            + (t3 - 2.0 * t2 + t) * tangent_p1 * dx
+            + (-2.0 * t3 + 3.0 * t2) * p2.y
+            + (t3 - t2) * tangent_p2 * dx;
+        return (result_y / 255.0).clamp(0.0, 1.0);
+    }
+    points[count - 1].y / 255.0
+}
+
+fn cpu_is_default_curve(points: &[Point; 16], count: u32) -> bool {
+    count == 2
+        && points[0].x.abs() < 0.1
+        && points[0].y.abs() < 0.1
+        && (points[1].x - 255.0).abs() < 0.1
+        && (points[1].y - 255.0).abs() < 0.1
+}
+
+/// A curve's points and how many of them are in use.
+type CpuCurve<'a> = (&'a [Point; 16], u32);
+
+fn cpu_apply_all_curves(color: Vec3, curves: [CpuCurve; 4]) -> Vec3 {
+    let [
+        (luma_points, luma_count),
+        (red_points, red_count),
+        (green_points, green_count),
+        (blue_points, blue_count),
+    ] = curves;
+    let rgb_curves_are_active = !cpu_is_default_curve(red_points, red_count)
+        || !cpu_is_default_curve(green_points, green_count)
+        || !cpu_is_default_curve(blue_points, blue_count);
+
+    if !rgb_curves_are_active {
+        let luma_curve = |v: f32| cpu_apply_curve(v, luma_points, luma_count);
+        return Vec3::new(
+            luma_curve(color.x),
+            luma_curve(color.y),
+            luma_curve(color.z),
+        );
+    }
+
+    let color_graded = Vec3::new(
+        cpu_apply_curve(color.x, red_points, red_count),
+        cpu_apply_curve(color.y, green_points, green_count),
+        cpu_apply_curve(color.z, blue_points, blue_count),
+    );
+    let luma_target = cpu_apply_curve(cpu_luma(color), luma_points, luma_count);
+    let luma_graded = cpu_luma(color_graded);
+    let mut final_color = if luma_graded > 0.001 {
+        color_graded * (luma_target / luma_graded)
+    } else {
+        Vec3::splat(luma_target)
+    };
+    let max_comp = final_color.max_element();
+    if max_comp > 1.0 {
+        final_color /= max_comp;
+    }
+    final_color
+}
+
+fn cpu_apply_negative_inversion(rgb: Vec3, adj: &GlobalAdjustments) -> Vec3 {
+    if adj.invert == 0 {
+        return rgb;
+    }
+    let base = cpu_srgb_to_linear(Vec3::new(adj.film_base_r, adj.film_base_g, adj.film_base_b))
+        .max(Vec3::splat(0.0001));
+    let positive = (base - rgb).max(Vec3::ZERO) / base;
+    let black = Vec3::new(
+        adj.negative_black_r,
+        adj.negative_black_g,
+        adj.negative_black_b,
+    );
+    let white = Vec3::new(
+        adj.negative_white_r,
+        adj.negative_white_g,
+        adj.negative_white_b,
+    );
+    ((positive - black) / (white - black).max(Vec3::splat(0.0001))).max(Vec3::ZERO)
+}
+
+fn cpu_apply_mask_adjustments(color: Vec3, adj: &MaskAdjustments) -> Vec3 {
+    let mut rgb = color;
+    if adj.exposure != 0.0 {
+        rgb *= adj.exposure.exp2();
+    }
+    rgb = cpu_apply_white_balance(rgb, adj.temperature, adj.tint);
+    rgb = cpu_apply_filmic_exposure(rgb, adj.brightness);
+    rgb = cpu_apply_highlights_adjustment(rgb, adj.highlights);
+    rgb = cpu_apply_tonal_adjustments(rgb, adj.contrast, adj.shadows, adj.whites, adj.blacks);
+    rgb = cpu_apply_hsl_panel(rgb, &adj.hsl);
+    rgb = cpu_apply_channel_mixer(rgb, &adj.channel_mixer);
+    rgb = cpu_apply_color_grading(
+        rgb,
+        [
+            &adj.color_grading_shadows,
+            &adj.color_grading_midtones,
+            &adj.color_grading_highlights,
+        ],
+        adj.color_grading_blending,
+        adj.color_grading_balance,
+    );
+    cpu_apply_creative_color(rgb, adj.saturation, adj.vibrance)
+}
+
+fn cpu_scale_mask_adjustments(adj: &MaskAdjustments, influence: f32) -> MaskAdjustments {
+    let mut scaled = *adj;
+    for value in [
+        &mut scaled.exposure,
+        &mut scaled.brightness,
+        &mut scaled.contrast,
+        &mut scaled.highlights,
+        &mut scaled.shadows,
+        &mut scaled.whites,
+        &mut scaled.blacks,
+        &mut scaled.saturation,
+        &mut scaled.temperature,
+        &mut scaled.tint,
+        &mut scaled.vibrance,
+        &mut scaled.color_grading_shadows.saturation,
+        &mut scaled.color_grading_shadows.luminance,
+        &mut scaled.color_grading_midtones.saturation,
+        &mut scaled.color_grading_midtones.luminance,
+        &mut scaled.color_grading_highlights.saturation,
+        &mut scaled.color_grading_highlights.luminance,
+        &mut scaled.channel_mixer.strength,
+    ] {
+        *value *= influence;
+    }
+    for hsl in scaled.hsl.iter_mut() {
+        hsl.hue *= influence;
+        hsl.saturation *= influence;
+        hsl.luminance *= influence;
+    }
+    scaled
+}
+
+/// Blends in sRGB-encoded space without the upper clamp of `cpu_linear_to_srgb`, so
+/// scene-referred highlights above 1.0 are not clipped before tone mapping.
+fn cpu_blend_mask_layer(base_linear: Vec3, layer_linear: Vec3, mode: u32) -> Vec3 {
+    let encode = |v: f32| {
+        let v = v.max(0.0);
+        if v <= 0.0031308 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    // Above 1.0, 1 - (1 - a)(1 - b) would darken, so take the brighter input instead.
+    let screen = |a: f32, b: f32| {
+        if a > 1.0 || b > 1.0 {
+            a.max(b)
+        } else {
+            1.0 - (1.0 - a) * (1.0 - b)
+        }
+    };
+    let blend = |b: f32, l: f32| match mode {
+        1 => b * l,
+        2 => screen(b, l),
+        3 if b < 0.5 => 2.0 * b * l,
+        3 => screen(2.0 * b - 1.0, l),
+        _ => l,
+    };
+    let (base, layer) = (base_linear, layer_linear);
+    cpu_srgb_to_linear(Vec3::new(
+        blend(encode(base.x), encode(layer.x)),
+        blend(encode(base.y), encode(layer.y)),
+        blend(encode(base.z), encode(layer.z)),
+    ))
+}
+
+fn cpu_sample_lut_tetrahedral(lut: &Lut, color: Vec3) -> Vec3 {
+    let size = lut.size as usize;
+    if size < 2 || lut.data.len() < size * size * size * 3 {
+        return color;
+    }
+    let scaled = color.clamp(Vec3::ZERO, Vec3::ONE) * (size - 1) as f32;
+    let base = scaled.floor();
+    let f = scaled - base;
+    let i0 = [base.x as usize, base.y as usize, base.z as usize];
+    let i1 = i0.map(|i| (i + 1).min(size - 1));
+    let texel = |r: usize, g: usize, b: usize| {
+        let index = ((b * size + g) * size + r) * 3;
+        Vec3::new(lut.data[index], lut.data[index + 1], lut.data[index + 2])
+    };
+    let c000 = texel(i0[0], i0[1], i0[2]);
+    let c111 = texel(i1[0], i1[1], i1[2]);
+
+    if f.x > f.y {
+        if f.y > f.z {
+            c000 * (1.0 - f.x)
+                + texel(i1[0], i0[1], i0[2]) * (f.x - f.y)
+                + texel(i1[0], i1[1], i0[2]) * (f.y - f.z)
+                + c111 * f.z
+        } else if f.x > f.z {
+            c000 * (1.0 - f.x)
+                + texel(i1[0], i0[1], i0[2]) * (f.x - f.z)
+                + texel(i1[0], i0[1], i1[2]) * (f.z - f.y)
+                + c111 * f.y
+        } else {
+            c000 * (1.0 - f.z)
+                + texel(i0[0], i0[1], i1[2]) * (f.z - f.x)
+                + texel(i1[0], i0[1], i1[2]) * (f.x - f.y)
+                + c111 * f.y
+        }
+    } else if f.z > f.y {
+        c000 * (1.0 - f.z)
+            + texel(i0[0], i0[1], i1[2]) * (f.z - f.y)
+            + texel(i0[0], i1[1], i1[2]) * (f.y - f.x)
+            + c111 * f.x
+    } else if f.z > f.x {
+        c000 * (1.0 - f.y)
+            + texel(i0[0], i1[1], i0[2]) * (f.y - f.z)
+            + texel(i0[0], i1[1], i1[2]) * (f.z - f.x)
+            + c111 * f.x
+    } else {
+        c000 * (1.0 - f.y)
+            + texel(i0[0], i1[1], i0[2]) * (f.y - f.x)
+            + texel(i1[0], i1[1], i0[2]) * (f.x - f.z)
+            + c111 * f.z
+    }
+}
+
+fn cpu_mask_curves(adj: &MaskAdjustments) -> [CpuCurve<'_>; 4] {
+    [
+        (&adj.luma_curve, adj.luma_curve_count),
+        (&adj.red_curve, adj.red_curve_count),
+        (&adj.green_curve, adj.green_curve_count),
+        (&adj.blue_curve, adj.blue_curve_count),
+    ]
+}
+
+/// A mask's adjustments paired with its influence at the pixel being rendered.
+type CpuMaskLayer<'a> = (&'a MaskAdjustments, f32);
+
+fn cpu_process_pixel(
+    color: Vec3,
+    adj: &GlobalAdjustments,
+    masks: &[CpuMaskLayer],
+    lut: Option<&Lut>,
+) -> Vec3 {
+    let is_raw = adj.is_raw_image == 1;
+    let mut rgb = if is_raw {
+        color
+    } else {
+        cpu_srgb_to_linear(color)
+    };
+    rgb = cpu_apply_negative_inversion(rgb, adj);
+
+    if adj.exposure != 0.0 {
+        rgb *= adj.exposure.exp2();
+    }
+    rgb = cpu_apply_white_balance(rgb, adj.temperature, adj.tint);
+    rgb = cpu_apply_filmic_exposure(rgb, adj.brightness);
+    rgb = cpu_apply_tonal_adjustments(rgb, adj.contrast, adj.shadows, adj.whites, adj.blacks);
+    rgb = cpu_apply_highlights_adjustment(rgb, adj.highlights);
+    rgb = cpu_apply_color_calibration(rgb, &adj.color_calibration);
+    rgb = cpu_apply_hsl_panel(rgb, &adj.hsl);
+    rgb = cpu_apply_channel_mixer(rgb, &adj.channel_mixer);
+    rgb = cpu_apply_creative_color(rgb, adj.saturation, adj.vibrance);
+
+    for &(mask_adj, influence) in masks {
+        if influence <= 0.001 {
+            continue;
+        }
+        if mask_adj.blend_mode == 0 {
+            rgb = cpu_apply_mask_adjustments(rgb, &cpu_scale_mask_adjustments(mask_adj, influence));
+        } else {
+            let layer = cpu_apply_mask_adjustments(rgb, mask_adj);
+            let blended = cpu_blend_mask_layer(rgb, layer, mask_adj.blend_mode);
+            rgb = rgb.lerp(blended, influence);
+        }
+    }
+
+    rgb = cpu_apply_split_toning(rgb, &adj.split_toning);
+    rgb = cpu_apply_color_grading(
+        rgb,
+        [
+            &adj.color_grading_shadows,
+            &adj.color_grading_midtones,
+            &adj.color_grading_highlights,
+        ],
+        adj.color_grading_blending,
+        adj.color_grading_balance,
+    );
+
+    let wide_output = adj.output_primaries != color_management::OUTPUT_PRIMARIES_SRGB;
+    if wide_output && adj.tonemapper_mode != 1 {
+        rgb = color_management::srgb_linear_to_output_primaries(rgb, adj.output_primaries);
+    }
+
+    let base_srgb = if adj.tonemapper_mode == 1 {
+        cpu_agx_full_transform(rgb, adj)
+    } else if adj.tonemapper_mode == 2 {
+        cpu_linear_to_srgb(cpu_reinhard_tonemap(rgb))
+    } else if adj.tonemapper_mode == 3 {
+        cpu_linear_to_srgb(cpu_filmic_tonemap(rgb))
+    } else if is_raw {
+        let emulated = cpu_linear_to_srgb(rgb).powf(1.0 / 1.1);
+        let contrast_curve = emulated * emulated * (Vec3::splat(3.0) - 2.0 * emulated);
+        emulated.lerp(contrast_curve, 0.75)
+    } else {
+        cpu_linear_to_srgb(rgb)
+    };
+    let base_srgb = if wide_output && adj.tonemapper_mode == 1 {
+        cpu_linear_to_srgb(color_management::srgb_linear_to_output_primaries(
+            cpu_srgb_to_linear(base_srgb),
+            adj.output_primaries,
+        ))
+    } else {
+        base_srgb
+    };
+
+    let mut final_rgb = cpu_apply_all_curves(
+        base_srgb,
+        [
+            (&adj.luma_curve, adj.luma_curve_count),
+            (&adj.red_curve, adj.red_curve_count),
+            (&adj.green_curve, adj.green_curve_count),
+            (&adj.blue_curve, adj.blue_curve_count),
+        ],
+    );
+    for &(mask_adj, influence) in masks {
+        if influence > 0.001 {
+            let curved = cpu_apply_all_curves(final_rgb, cpu_mask_curves(mask_adj));
+            final_rgb = final_rgb.lerp(curved, influence);
+        }
+    }
+
+    if adj.has_lut == 1
+        && let Some(lut) = lut
+    {
+        let lut_color = cpu_sample_lut_tetrahedral(lut, final_rgb);
+        final_rgb = final_rgb.lerp(lut_color, adj.lut_intensity);
+    }
+
+    final_rgb.clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+/// Applies the per-pixel global tonal and color adjustments on the CPU, mirroring the
+/// math in `shader.wgsl`. Spatial effects (local contrast, dehaze, vignette, grain,
+/// glow) and masks are not applied. The result is an sRGB `Rgba32F` image.
+pub fn cpu_apply_global_adjustments(
+    image: &DynamicImage,
+    adjustments: &GlobalAdjustments,
+) -> DynamicImage {
+    let mut buffer = image.to_rgba32f();
+    buffer.par_chunks_mut(4).for_each(|pixel| {
+        let result = cpu_process_pixel(
+            Vec3::new(pixel[0], pixel[1], pixel[2]),
+            adjustments,
+            &[],
+            None,
+        );
+        pixel[0] = result.x;
+        pixel[1] = result.y;
+        pixel[2] = result.z;
+    });
+    DynamicImage::ImageRgba32F(buffer)
+}
+
+/// CPU fallback for `process_and_get_dynamic_image` when no GPU is usable. Renders the
+/// per-pixel global and mask adjustments, curves and the LUT; spatial effects are
+/// skipped. Mask bitmaps must match the image size. The output matches the GPU's
+/// `Rgba8` format.
+pub fn process_image_cpu(
+    base: &DynamicImage,
+    adjustments: &AllAdjustments,
+    mask_bitmaps: &[GrayImage],
+    lut: Option<&Lut>,
+) -> DynamicImage {
+    let mask_count = (adjustments.mask_count as usize).min(mask_bitmaps.len());
+    let masks: Vec<(&MaskAdjustments, &GrayImage)> = adjustments
+        .mask_adjustments
+        .iter()
+        .zip(mask_bitmaps)
+        .take(mask_count)
+        .collect();
+
+    let mut buffer = base.to_rgba32f();
+    let row_len = buffer.width() as usize * 4;
+    buffer
+        .par_chunks_mut(row_len.max(4))
+        .enumerate()
+        .for_each(|(y, row)| {
+            let mut layers: Vec<CpuMaskLayer> = Vec::with_capacity(masks.len());
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                layers.clear();
+                layers.extend(masks.iter().map(|&(mask_adj, bitmap)| {
+                    let influence = bitmap
+                        .get_pixel_checked(x as u32, y as u32)
+                        .map_or(0.0, |p| p.0[0] as f32 / 255.0);
+                    (mask_adj, influence)
+                }));
+                let result = cpu_process_pixel(
+                    Vec3::new(pixel[0], pixel[1], pixel[2]),
+                    &adjustments.global,
+                    &layers,
+                    lut,
+                );
+                pixel[0] = result.x;
+                pixel[1] = result.y;
+                pixel[2] = result.z;
+            }
+        });
+    DynamicImage::ImageRgba8(DynamicImage::ImageRgba32F(buffer).to_rgba8())
+}
+
+#[derive(Clone)]
+pub struct GpuContext {
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    pub limits: wgpu::Limits,
+    pub backend: String,
+    /// Set by the device-lost callback or when the backend is switched; a lost context is
+    /// replaced on the next use.
+    pub lost: Arc<AtomicBool>,
+}
+
+#[inline(always)]
+fn rgb_to_yc_only(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let cb = -0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 0.5 * r - 0.418688 * g - 0.081312 * b;
+    (y, cb, cr)
+}
+
+#[inline(always)]
+fn yc_to_rgb(y: f32, cb: f32, cr: f32) -> (f32, f32, f32) {
+    let r = y + 1.402 * cr;
+    let g = y - 0.344136 * cb - 0.714136 * cr;
+    let b = y + 1.772 * cb;
+    (r, g, b)
+}
+
+pub fn remove_raw_artifacts_and_enhance(image: &mut DynamicImage) {
+    let mut buffer = image.to_rgb32f();
+    let w = buffer.width() as usize;
+    let h = buffer.height() as usize;
+
+    let mut ycbcr_buffer = vec![0.0f32; w * h * 3];
+
+    let src = buffer.as_raw();
+
+    ycbcr_buffer
+        .par_chunks_mut(3)
+        .zip(src.par_chunks(3))
+        .for_each(|(dest, pixel)| {
+            let (y, cb, cr) = rgb_to_yc_only(pixel[0], pixel[1], pixel[2]);
+            dest[0] = y;
+            dest[1] = cb;
+            dest[2] = cr;
+        });
+
+    const BASE_INV_SIGMA: f32 = 14.0;
+    const OFFSETS: [isize; 3] = [-5, -1, 3];
+    const OFFSET_SQUARES: [f32; 3] = [25.0, 1.0, 9.0];
+    const DETAIL_AMOUNT: f32 = 0.35;
+    const DETAIL_RADIUS: isize = 2;
+    const DETAIL_AREA: f32 = ((2 * DETAIL_RADIUS + 1) * (2 * DETAIL_RADIUS + 1)) as f32;
+
+    buffer.par_chunks_mut(w * 3).enumerate().for_each_init(
+        || vec![0.0f32; w],
+        |column_sums, (y, row)| {
+            let row_offset = y * w;
+            let h_isize = h as isize;
+            let w_isize = w as isize;
+            let y_isize = y as isize;
+
+            column_sums.iter_mut().for_each(|v| *v = 0.0);
+            for ky in -DETAIL_RADIUS..=DETAIL_RADIUS {
+                let sy = (y_isize + ky).clamp(0, h_isize - 1) as usize;
+                let luma_row = &ycbcr_buffer[sy * w * 3..(sy + 1) * w * 3];
+                for (sum, pixel) in column_sums.iter_mut().zip(luma_row.chunks_exact(3)) {
+                    *sum += pixel[0];
+                }
+            }
+
+            for x in 0..w {
+                let center_idx = (row_offset + x) * 3;
+
+                let cy = ycbcr_buffer[center_idx];
+                let ccb = ycbcr_buffer[center_idx + 1];
+                let ccr = ycbcr_buffer[center_idx + 2];
+
+                let mut cb_sum = 0.0;
                 let mut cr_sum = 0.0;
                 let mut w_sum = 0.0;
 
@@ -1942,119 +3271,87 @@ pub fn remove_raw_artifacts_and_enhance(image: &mut DynamicImage) {
 
                 let (r, g, b) = yc_to_rgb(cy, out_cb, out_cr);
 
-                let o = x * 3;
-                row[o] = r.clamp(0.0, 1.0);
-                row[o + 1] = g.clamp(0.0, 1.0);
-                row[o + 2] = b.clamp(0.0, 1.0);
-            }
-        });
-
-    apply_gentle_detail_enhance(&mut buffer, &ycbcr_buffer, 0.35);
-
-    *image = DynamicImage::ImageRgb32F(buffer);
-}
-
-fn apply_gentle_detail_enhance(
-    buffer: &mut image::ImageBuffer<image::Rgb<f32>, Vec<f32>>,
-    ycbcr_source: &[f32],
-    amount: f32,
-) {
-    let w = buffer.width() as usize;
-    let h = buffer.height() as usize;
-
-    let mut temp_blur = vec![0.0; w * h];
-    let radius = 2i32;
-
-    temp_blur
-        .par_chunks_mut(w)
-        .enumerate()
-        .for_each(|(y, row)| {
-            let row_offset = y * w;
-            for (x, row_val) in row.iter_mut().enumerate() {
-                let mut sum = 0.0;
-                let mut count = 0;
-                for kx in -radius..=radius {
-                    let sx = (x as i32 + kx).clamp(0, (w as i32) - 1) as usize;
-                    sum += ycbcr_source[(row_offset + sx) * 3];
-                    count += 1;
-                }
-                *row_val = sum / count as f32;
-            }
-        });
-
-    let output = buffer.as_mut();
-
-    output
-        .par_chunks_mut(w * 3)
-        .enumerate()
-        .for_each(|(y, rgb_row)| {
-            for x in 0..w {
                 let mut blur_sum = 0.0;
-                let mut count = 0;
-                for ky in -radius..=radius {
-                    let sy = (y as i32 + ky).clamp(0, (h as i32) - 1) as usize;
-                    blur_sum += temp_blur[sy * w + x];
-                    count += 1;
+                for kx in -DETAIL_RADIUS..=DETAIL_RADIUS {
+                    let sx = (x as isize + kx).clamp(0, w_isize - 1) as usize;
+                    blur_sum += column_sums[sx];
                 }
-                let blurred_val = blur_sum / count as f32;
-
-                let original_luma = ycbcr_source[(y * w + x) * 3];
+                let detail = cy - blur_sum / DETAIL_AREA;
 
-                let detail = original_luma - blurred_val;
+                let (r, g, b) = apply_detail_boost(
+                    r.clamp(0.0, 1.0),
+                    g.clamp(0.0, 1.0),
+                    b.clamp(0.0, 1.0),
+                    detail,
+                    DETAIL_AMOUNT,
+                );
 
-                let edge_strength = detail.abs();
-                let adaptive_amount = if edge_strength > 0.1 {
-                    amount * 0.3
-                } else {
-                    amount
-                };
-                let boost = detail * adaptive_amount;
+                let o = x * 3;
+                row[o] = r;
+                row[o + 1] = g;
+                row[o + 2] = b;
+            }
+        },
+    );
 
-                let r_idx = x * 3;
-                let g_idx = r_idx + 1;
-                let b_idx = r_idx + 2;
+    *image = DynamicImage::ImageRgb32F(buffer);
+}
 
-                let r = rgb_row[r_idx];
-                let g = rgb_row[g_idx];
-                let b = rgb_row[b_idx];
+#[inline(always)]
+fn apply_detail_boost(r: f32, g: f32, b: f32, detail: f32, amount: f32) -> (f32, f32, f32) {
+    let adaptive_amount = if detail.abs() > 0.1 {
+        amount * 0.3
+    } else {
+        amount
+    };
+    let boost = detail * adaptive_amount;
 
-                let new_r = r + boost;
-                let new_g = g + boost;
-                let new_b = b + boost;
+    let new_r = r + boost;
+    let new_g = g + boost;
+    let new_b = b + boost;
 
-                let max_val = new_r.max(new_g).max(new_b);
-                let min_val = new_r.min(new_g).min(new_b);
+    let max_val = new_r.max(new_g).max(new_b);
+    let min_val = new_r.min(new_g).min(new_b);
 
-                let scale = if max_val > 1.0 || min_val < 0.0 {
-                    if max_val > 1.0 && min_val < 0.0 {
-                        0.0
-                    } else if max_val > 1.0 {
-                        (1.0 - r.max(g).max(b)) / boost.max(0.001)
-                    } else {
-                        r.min(g).min(b) / (-boost).max(0.001)
-                    }
-                } else {
-                    1.0
-                };
+    let scale = if max_val > 1.0 || min_val < 0.0 {
+        if max_val > 1.0 && min_val < 0.0 {
+            0.0
+        } else if max_val > 1.0 {
+            (1.0 - r.max(g).max(b)) / boost.max(0.001)
+        } else {
+            r.min(g).min(b) / (-boost).max(0.001)
+        }
+    } else {
+        1.0
+    };
 
-                let safe_boost = boost * scale.clamp(0.0, 1.0);
+    let safe_boost = boost * scale.clamp(0.0, 1.0);
 
-                rgb_row[r_idx] = (r + safe_boost).clamp(0.0, 1.0);
-                rgb_row[g_idx] = (g + safe_boost).clamp(0.0, 1.0);
-                rgb_row[b_idx] = (b + safe_boost).clamp(0.0, 1.0);
-            }
-        });
+    (
+        (r + safe_boost).clamp(0.0, 1.0),
+        (g + safe_boost).clamp(0.0, 1.0),
+        (b + safe_boost).clamp(0.0, 1.0),
+    )
 }
 
 #[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct HistogramData {
     red: Vec<f32>,
     green: Vec<f32>,
     blue: Vec<f32>,
     luma: Vec<f32>,
+    /// Percentage of sampled pixels whose luma falls in bins 0–2.
+    clip_shadows: f32,
+    /// Percentage of sampled pixels whose luma falls in bins 253–255.
+    clip_highlights: f32,
 }
 
-pub fn calculate_histogram_from_image(image: &DynamicImage) -> Result<HistogramData, String> {
+/// `scale` is `"linear"` (default) or `"log"`, which compresses tall peaks with `ln(1 + x)`.
+pub fn calculate_histogram_from_image(
+    image: &DynamicImage,
+    scale: Option<&str>,
+) -> Result<HistogramData, String> {
     let init_hist = || ([0u32; 256], [0u32; 256], [0u32; 256], [0u32; 256]);
 
     let reduce_hist = |mut a: ([u32; 256], [u32; 256], [u32; 256], [u32; 256]),
@@ -2112,10 +3409,34 @@ pub fn calculate_histogram_from_image(image: &DynamicImage) -> Result<HistogramD
         }
     };
 
-    let mut red: Vec<f32> = r_c.into_iter().map(|c| c as f32).collect();
-    let mut green: Vec<f32> = g_c.into_iter().map(|c| c as f32).collect();
-    let mut blue: Vec<f32> = b_c.into_iter().map(|c| c as f32).collect();
-    let mut luma: Vec<f32> = l_c.into_iter().map(|c| c as f32).collect();
+    let total: u64 = l_c.iter().map(|&c| c as u64).sum();
+    let clip_percent = |bins: &[u32]| {
+        if total == 0 {
+            0.0
+        } else {
+            bins.iter().map(|&c| c as u64).sum::<u64>() as f32 * 100.0 / total as f32
+        }
+    };
+    let clip_shadows = clip_percent(&l_c[0..=2]);
+    let clip_highlights = clip_percent(&l_c[253..=255]);
+
+    let log_scale = matches!(scale, Some("log"));
+    let to_bins = |counts: [u32; 256]| -> Vec<f32> {
+        counts
+            .into_iter()
+            .map(|c| {
+                if log_scale {
+                    (c as f32).ln_1p()
+                } else {
+                    c as f32
+                }
+            })
+            .collect()
+    };
+    let mut red = to_bins(r_c);
+    let mut green = to_bins(g_c);
+    let mut blue = to_bins(b_c);
+    let mut luma = to_bins(l_c);
 
     let smoothing_sigma = 2.0;
     apply_gaussian_smoothing(&mut red, smoothing_sigma);
@@ -2133,6 +3454,8 @@ pub fn calculate_histogram_from_image(image: &DynamicImage) -> Result<HistogramD
         green,
         blue,
         luma,
+        clip_shadows,
+        clip_highlights,
     })
 }
 
@@ -2205,6 +3528,8 @@ fn normalize_histogram_range(histogram: &mut [f32], percentile_clip: f32) {
 #[derive(serde::Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WaveformData {
+    /// `"overlay"` or `"parade"`; in parade mode `rgb` holds R|G|B side by side.
+    pub mode: String,
     pub rgb: String,
     pub luma: String,
     pub parade: String,
@@ -2216,6 +3541,7 @@ pub struct WaveformData {
 pub fn calculate_waveform_from_image(
     image: &DynamicImage,
     active_channel: Option<&str>,
+    mode: Option<&str>,
 ) -> Result<WaveformData, String> {
     const W: usize = 256;
     const H: usize = 256;
@@ -2230,6 +3556,12 @@ pub fn calculate_waveform_from_image(
         active_channel.is_none() || active_channel == Some("luma") || active_channel == Some("rgb");
     let do_parade = active_channel.is_none() || active_channel == Some("parade");
     let do_vectorscope = active_channel.is_none() || active_channel == Some("vectorscope");
+    let rgb_parade = match mode.unwrap_or("overlay") {
+        "overlay" => false,
+        "parade" => true,
+        other => return Err(format!("Unknown waveform mode: {}", other)),
+    };
+    let need_parade_buckets = do_parade || (do_rgb && rgb_parade);
 
     let mut red_bins = if do_rgb { vec![0u32; W * H] } else { vec![] };
     let mut green_bins = if do_rgb { vec![0u32; W * H] } else { vec![] };
@@ -2251,7 +3583,7 @@ pub fn calculate_waveform_from_image(
 
     for x in 0..(orig_w as usize) {
         x_buckets[x] = ((x as f32 * x_scale) as usize).min(W - 1);
-        if do_parade {
+        if need_parade_buckets {
             let relative_x = x as f32 / orig_w as f32;
             x_buckets_parade_r[x] = (relative_x * 82.0) as usize % 82;
             x_buckets_parade_g[x] = 87 + (relative_x * 82.0) as usize % 82;
@@ -2261,9 +3593,18 @@ pub fn calculate_waveform_from_image(
 
     let mut process_pixel = |r: u8, g: u8, b: u8, out_x: usize, orig_x: usize| {
         if do_rgb {
-            red_bins[(255 - r as usize) * W + out_x] += 1;
-            green_bins[(255 - g as usize) * W + out_x] += 1;
-            blue_bins[(255 - b as usize) * W + out_x] += 1;
+            let (x_r, x_g, x_b) = if rgb_parade {
+                (
+                    x_buckets_parade_r[orig_x],
+                    x_buckets_parade_g[orig_x],
+                    x_buckets_parade_b[orig_x],
+                )
+            } else {
+                (out_x, out_x, out_x)
+            };
+            red_bins[(255 - r as usize) * W + x_r] += 1;
+            green_bins[(255 - g as usize) * W + x_g] += 1;
+            blue_bins[(255 - b as usize) * W + x_b] += 1;
         }
         if do_luma {
             let l = ((r as u32 * 218 + g as u32 * 732 + b as u32 * 74) >> 10).min(255) as usize;
@@ -2468,6 +3809,7 @@ pub fn calculate_waveform_from_image(
     }
 
     Ok(WaveformData {
+        mode: if rgb_parade { "parade" } else { "overlay" }.to_string(),
         rgb: if do_rgb {
             BASE64.encode(&rgba_rgb)
         } else {
@@ -2493,7 +3835,28 @@ pub fn calculate_waveform_from_image(
     })
 }
 
-pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutoSettings {
+    pub target_midtone: f64,
+    pub contrast_strength: f64,
+    pub protect_highlights: bool,
+}
+
+impl Default for AutoSettings {
+    fn default() -> Self {
+        Self {
+            target_midtone: 128.0,
+            contrast_strength: 50.0,
+            protect_highlights: false,
+        }
+    }
+}
+
+pub fn perform_auto_analysis(
+    image: &DynamicImage,
+    auto_settings: &AutoSettings,
+) -> AutoAdjustmentResults {
     let analysis_preview = downscale_f32_image(image, 1024, 1024);
     let rgb_image = analysis_preview.to_rgb8();
     let total_pixels = (rgb_image.width() * rgb_image.height()) as f64;
@@ -2556,13 +3919,20 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     let mut exposure = 0.0;
     let mut contrast = 0.0;
     if range > 20.0 {
-        exposure = (128.0 - mid_point as f64) * 0.35;
+        exposure = (auto_settings.target_midtone.clamp(0.0, 255.0) - mid_point as f64) * 0.35;
         let target_range = 250.0;
         if range < target_range {
-            contrast = (target_range / range - 1.0) * 50.0;
+            contrast = (target_range / range - 1.0) * auto_settings.contrast_strength;
         }
     }
 
+    let mut exposure = (exposure / 20.0).clamp(-5.0, 5.0);
+    if auto_settings.protect_highlights && exposure > 0.0 {
+        // Stops of headroom before the clipped white point reaches full scale (gamma ~2.2).
+        let headroom = 2.2 * (255.0 / white_point.max(1) as f64).log2();
+        exposure = exposure.min(headroom);
+    }
+
     let shadow_percent = luma_hist[0..32].iter().sum::<u32>() as f64 / total_pixels;
     let highlight_percent = luma_hist[224..256].iter().sum::<u32>() as f64 / total_pixels;
     let mut shadows = 0.0;
@@ -2656,7 +4026,7 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     }
 
     AutoAdjustmentResults {
-        exposure: (exposure / 20.0).clamp(-5.0, 5.0),
+        exposure,
         contrast: contrast.clamp(0.0, 100.0),
         highlights: highlights.clamp(-100.0, 0.0),
         shadows: shadows.clamp(0.0, 100.0),
@@ -2670,8 +4040,15 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     }
 }
 
-pub fn auto_results_to_json(results: &AutoAdjustmentResults) -> serde_json::Value {
-    json!({
+// The raw highlight-based estimate tends to overshoot, so only part of it is applied.
+const AUTO_WHITE_BALANCE_DAMPING: f64 = 0.35;
+const AUTO_WHITE_BALANCE_LIMIT: f64 = 30.0;
+
+pub fn auto_results_to_json(
+    results: &AutoAdjustmentResults,
+    include_white_balance: bool,
+) -> serde_json::Value {
+    let mut value = json!({
         "exposure": results.exposure,
         "contrast": results.contrast,
         "highlights": results.highlights,
@@ -2680,19 +4057,37 @@ pub fn auto_results_to_json(results: &AutoAdjustmentResults) -> serde_json::Valu
         "vignetteAmount": results.vignette_amount,
         "clarity": results.clarity,
         "centré": results.centre,
-        //"temperature": results.temperature,
-        //"tint": results.tint,
         "dehaze": results.dehaze,
-        "sectionVisibility": {
-            "basic": true,
-            "color": true,
-            "effects": true
-        }
-    })
+    });
+
+    if include_white_balance && let Some(map) = value.as_object_mut() {
+        let dampen = |v: f64| {
+            (v * AUTO_WHITE_BALANCE_DAMPING)
+                .clamp(-AUTO_WHITE_BALANCE_LIMIT, AUTO_WHITE_BALANCE_LIMIT)
+        };
+        map.insert(
+            "temperature".to_string(),
+            json!(dampen(results.temperature)),
+        );
+        map.insert("tint".to_string(), json!(dampen(results.tint)));
+    }
+
+    if let Some(map) = value.as_object_mut() {
+        let visibility: serde_json::Map<String, Value> = map
+            .keys()
+            .filter_map(|key| adjustment_registry::find(key))
+            .map(|def| (def.section.to_string(), Value::Bool(true)))
+            .collect();
+        map.insert("sectionVisibility".to_string(), Value::Object(visibility));
+    }
+
+    value
 }
 
 #[tauri::command]
 pub fn calculate_auto_adjustments(
+    include_white_balance: Option<bool>,
+    auto_settings: Option<AutoSettings>,
     state: tauri::State<AppState>,
 ) -> Result<serde_json::Value, String> {
     let original_image = state
@@ -2704,7 +4099,380 @@ pub fn calculate_auto_adjustments(
         .image
         .clone();
 
-    let results = perform_auto_analysis(&original_image);
+    let results = perform_auto_analysis(&original_image, &auto_settings.unwrap_or_default());
+
+    Ok(auto_results_to_json(
+        &results,
+        include_white_balance.unwrap_or(false),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(auto_results_to_json(&results))
+    fn neutral_adjustments() -> GlobalAdjustments {
+        GlobalAdjustments {
+            color_grading_blending: 0.5,
+            ..GlobalAdjustments::default()
+        }
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3, tolerance: f32) {
+        assert!(
+            (actual - expected).abs().max_element() <= tolerance,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn neutral_adjustments_keep_srgb_pixels() {
+        let adjustments = neutral_adjustments();
+        for color in [
+            Vec3::new(0.2, 0.5, 0.8),
+            Vec3::splat(0.5),
+            Vec3::new(0.9, 0.1, 0.3),
+        ] {
+            assert_close(
+                cpu_process_pixel(color, &adjustments, &[], None),
+                color,
+                1e-3,
+            );
+        }
+    }
+
+    #[test]
+    fn one_stop_of_exposure_doubles_linear_light() {
+        let mut adjustments = neutral_adjustments();
+        adjustments.exposure = 1.0;
+        let color = Vec3::new(0.2, 0.3, 0.4);
+        let result = cpu_srgb_to_linear(cpu_process_pixel(color, &adjustments, &[], None));
+        assert_close(result, cpu_srgb_to_linear(color) * 2.0, 1e-4);
+    }
+
+    #[test]
+    fn negative_inversion_stretches_each_channel_between_its_points() {
+        let adjustments = GlobalAdjustments {
+            invert: 1,
+            film_base_r: 1.0,
+            film_base_g: 1.0,
+            film_base_b: 1.0,
+            negative_black_r: 0.1,
+            negative_black_g: 0.0,
+            negative_black_b: 0.2,
+            negative_white_r: 0.9,
+            negative_white_g: 1.0,
+            negative_white_b: 0.6,
+            ..neutral_adjustments()
+        };
+
+        assert_close(
+            cpu_apply_negative_inversion(Vec3::new(0.9, 1.0, 0.8), &adjustments),
+            Vec3::ZERO,
+            1e-5,
+        );
+        assert_close(
+            cpu_apply_negative_inversion(Vec3::new(0.1, 0.5, 0.4), &adjustments),
+            Vec3::new(1.0, 0.5, 1.0),
+            1e-5,
+        );
+        assert_close(
+            cpu_apply_negative_inversion(Vec3::new(0.5, 0.5, 0.6), &adjustments),
+            Vec3::new(0.5, 0.5, 0.5),
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn contrast_spreads_tones_around_the_midpoint() {
+        let mut adjustments = neutral_adjustments();
+        adjustments.contrast = 0.5;
+        let shadow = cpu_process_pixel(Vec3::splat(0.2), &adjustments, &[], None);
+        let highlight = cpu_process_pixel(Vec3::splat(0.8), &adjustments, &[], None);
+        assert!(shadow.x < 0.2);
+        assert!(highlight.x > 0.8);
+    }
+
+    fn curve(points: &[(f32, f32)]) -> ([Point; 16], u32) {
+        let mut curve = [Point::default(); 16];
+        for (slot, &(x, y)) in curve.iter_mut().zip(points) {
+            slot.x = x;
+            slot.y = y;
+        }
+        (curve, points.len() as u32)
+    }
+
+    /// Expected `process_image_cpu` output for `render_golden_scene`, as RGB bytes.
+    const GOLDEN_SCENE: [u8; 48] = [
+        23, 11, 9, 66, 54, 46, 115, 105, 90, 163, 155, 134, 160, 152, 132, 186, 179, 157, 203, 199,
+        175, 222, 217, 190, 13, 255, 94, 13, 255, 111, 13, 247, 136, 144, 200, 147, 171, 110, 121,
+        216, 51, 125, 246, 0, 124, 255, 0, 111,
+    ];
+
+    /// An 8x2 sRGB gradient with global tone and color edits, a luma curve, a mask
+    /// over the right half and a warming LUT at half strength.
+    fn render_golden_scene() -> Vec<u8> {
+        let base = DynamicImage::ImageRgb8(image::RgbImage::from_fn(8, 2, |x, y| {
+            let t = (x * 32 + 16) as u8;
+            if y == 0 {
+                image::Rgb([t, t, t])
+            } else {
+                image::Rgb([t, 255 - t, 128])
+            }
+        }));
+
+        let mut adjustments = AllAdjustments {
+            global: neutral_adjustments(),
+            mask_count: 1,
+            ..AllAdjustments::default()
+        };
+        let global = &mut adjustments.global;
+        global.exposure = 0.5;
+        global.contrast = 0.3;
+        global.temperature = 0.2;
+        global.saturation = 0.3;
+        (global.luma_curve, global.luma_curve_count) =
+            curve(&[(0.0, 0.0), (128.0, 150.0), (255.0, 255.0)]);
+        global.has_lut = 1;
+        global.lut_intensity = 0.5;
+        let mask = &mut adjustments.mask_adjustments[0];
+        mask.exposure = -1.0;
+        mask.color_grading_blending = 0.5;
+
+        let mask_bitmap =
+            GrayImage::from_fn(8, 2, |x, _| image::Luma([if x >= 4 { 255 } else { 0 }]));
+        let mut data = Vec::new();
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    data.extend([r as f32 * 0.9 + 0.1, g as f32, b as f32 * 0.8]);
+                }
+            }
+        }
+        let lut = Lut { size: 2, data };
+
+        process_image_cpu(&base, &adjustments, &[mask_bitmap], Some(&lut))
+            .to_rgb8()
+            .into_raw()
+    }
+
+    #[test]
+    fn parametric_curve_keeps_manual_points() {
+        let points = vec![
+            json!({ "x": 0.0, "y": 0.0 }),
+            json!({ "x": 64.0, "y": 40.0 }),
+            json!({ "x": 192.0, "y": 220.0 }),
+            json!({ "x": 255.0, "y": 255.0 }),
+        ];
+        let curve = apply_parametric_curve(points, &json!({ "lights": 50 }));
+        let xy = |p: &Value| (p["x"].as_f64().unwrap(), p["y"].as_f64().unwrap());
+
+        assert!(curve.len() <= 16);
+        assert!(curve.windows(2).all(|w| xy(&w[0]).1 <= xy(&w[1]).1));
+        let at = |x: f64| curve.iter().map(xy).find(|p| p.0 == x).map(|p| p.1);
+        assert_eq!(at(64.0), Some(40.0));
+        assert!(at(192.0).unwrap() > 220.0);
+        assert_eq!(at(255.0), Some(255.0));
+    }
+
+    #[test]
+    fn masks_apply_the_parametric_curve() {
+        let adjustments = get_mask_adjustments_from_json(&json!({
+            "parametricCurve": { "shadows": 40 }
+        }));
+        assert!(adjustments.luma_curve_count > 2);
+        let lifted = cpu_apply_curve(0.125, &adjustments.luma_curve, adjustments.luma_curve_count);
+        assert!(lifted > 0.125);
+    }
+
+    #[test]
+    fn cpu_render_matches_golden_image() {
+        let rendered = render_golden_scene();
+        for (i, (&actual, &expected)) in rendered.iter().zip(GOLDEN_SCENE.iter()).enumerate() {
+            assert!(
+                actual.abs_diff(expected) <= 1,
+                "byte {i}: {actual} != {expected}\n{rendered:?}"
+            );
+        }
+        assert_eq!(rendered.len(), GOLDEN_SCENE.len());
+    }
+
+    /// Timing for a 45MP frame, run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_remove_raw_artifacts_45mp() {
+        let (width, height) = (8256, 5504);
+        let mut image = DynamicImage::ImageRgb32F(Rgb32FImage::from_fn(width, height, |x, y| {
+            let noise = ((x.wrapping_mul(7919) ^ y.wrapping_mul(104729)) & 0xff) as f32 / 255.0;
+            image::Rgb([
+                x as f32 / width as f32,
+                y as f32 / height as f32,
+                0.8 * noise + 0.1,
+            ])
+        }));
+        let start = std::time::Instant::now();
+        remove_raw_artifacts_and_enhance(&mut image);
+        println!(
+            "remove_raw_artifacts_and_enhance on {}x{}: {:?}",
+            width,
+            height,
+            start.elapsed()
+        );
+    }
+
+    /// Timing for the identity and warped geometry paths on a 24MP frame, run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_geometry_warp_identity_vs_warped() {
+        let image = DynamicImage::ImageRgb32F(Rgb32FImage::new(6000, 4000));
+        for (label, adjustments) in [
+            ("identity", json!({})),
+            ("warped", json!({ "transformDistortion": 10.0 })),
+        ] {
+            let input = image.clone();
+            let start = std::time::Instant::now();
+            let warped = apply_geometry_warp_owned(input, &adjustments);
+            println!("{} geometry on 6000x4000: {:?}", label, start.elapsed());
+            assert_eq!(warped.dimensions(), (6000, 4000));
+        }
+    }
+
+    #[test]
+    fn agx_curve_is_continuous_and_monotonic() {
+        let mut previous = cpu_agx_curve_channel(0.0);
+        for i in 1..=1000 {
+            let value = cpu_agx_curve_channel(i as f32 / 1000.0);
+            assert!(value >= previous, "curve decreases at {i}");
+            previous = value;
+        }
+        let below = cpu_agx_curve_channel(0.6060606 - 1e-4);
+        let above = cpu_agx_curve_channel(0.6060606 + 1e-4);
+        assert!((above - below).abs() < 1e-3);
+    }
+
+    #[test]
+    fn auto_adjustments_show_every_section_they_set() {
+        let results = AutoAdjustmentResults {
+            exposure: 0.3,
+            contrast: 10.0,
+            highlights: -20.0,
+            shadows: 15.0,
+            vibrancy: 12.0,
+            vignette_amount: -5.0,
+            temperature: 10.0,
+            tint: -4.0,
+            dehaze: 8.0,
+            clarity: 12.0,
+            centre: 6.0,
+        };
+        let value = auto_results_to_json(&results, true);
+        let visibility = value["sectionVisibility"].as_object().unwrap();
+        for key in value.as_object().unwrap().keys() {
+            if let Some(def) = adjustment_registry::find(key) {
+                assert_eq!(
+                    visibility.get(def.section),
+                    Some(&Value::Bool(true)),
+                    "{key}"
+                );
+            }
+        }
+        assert_eq!(visibility.get("details"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn history_stores_only_changed_keys() {
+        let mut metadata = ImageMetadata {
+            adjustments: json!({ "exposure": 0.0, "contrast": 0.0, "rating": 2 }),
+            ..ImageMetadata::default()
+        };
+        let edited = json!({ "exposure": 1.0, "contrast": 0.0, "rating": 2 });
+        metadata.record_history(&edited);
+        metadata.adjustments = edited;
+
+        assert_eq!(metadata.history.len(), 2);
+        assert_eq!(
+            Value::Object(metadata.history[0].changes.clone()),
+            json!({ "exposure": 0.0, "contrast": 0.0 })
+        );
+        assert_eq!(
+            Value::Object(metadata.history[1].changes.clone()),
+            json!({ "exposure": 1.0 })
+        );
+    }
+
+    #[test]
+    fn rating_changes_are_not_recorded_or_undone() {
+        let mut metadata = ImageMetadata::default();
+        for adjustments in [
+            json!({ "exposure": 1.0, "rating": 0 }),
+            json!({ "exposure": 1.0, "rating": 4 }),
+        ] {
+            metadata.record_history(&adjustments);
+            metadata.adjustments = adjustments;
+        }
+        assert_eq!(metadata.history.len(), 2);
+
+        assert!(metadata.step_history(-1));
+        assert_eq!(metadata.adjustments, json!({ "rating": 4 }));
+        assert!(!metadata.step_history(-1));
+        assert!(metadata.step_history(1));
+        assert_eq!(
+            metadata.adjustments,
+            json!({ "exposure": 1.0, "rating": 4 })
+        );
+        assert!(!metadata.step_history(1));
+    }
+
+    #[test]
+    fn mask_blends_keep_highlights_above_one() {
+        for mode in 1..=3 {
+            let blended = cpu_blend_mask_layer(Vec3::splat(4.0), Vec3::splat(2.0), mode);
+            assert!(blended.min_element() > 1.0, "mode {mode}: {blended:?}");
+        }
+
+        // Within [0, 1] the modes keep their usual display-space results.
+        let mid = cpu_srgb_to_linear(Vec3::splat(0.5));
+        assert_close(
+            cpu_blend_mask_layer(mid, mid, 1),
+            cpu_srgb_to_linear(Vec3::splat(0.25)),
+            1e-5,
+        );
+        assert_close(
+            cpu_blend_mask_layer(mid, mid, 2),
+            cpu_srgb_to_linear(Vec3::splat(0.75)),
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn history_is_capped_by_entries_and_bytes() {
+        let mut metadata = ImageMetadata::default();
+        for i in 0..MAX_EDIT_HISTORY * 2 {
+            let adjustments = json!({ "exposure": i, "contrast": 0 });
+            metadata.record_history(&adjustments);
+            metadata.adjustments = adjustments;
+        }
+        assert_eq!(metadata.history.len(), MAX_EDIT_HISTORY);
+        assert_eq!(
+            metadata.history_state(0),
+            json!({ "exposure": MAX_EDIT_HISTORY, "contrast": 0 })
+                .as_object()
+                .unwrap()
+                .clone()
+        );
+
+        let large = "x".repeat(MAX_EDIT_HISTORY_BYTES / 4);
+        for i in 0..8 {
+            let adjustments = json!({ "lutData": format!("{}{}", large, i) });
+            metadata.record_history(&adjustments);
+            metadata.adjustments = adjustments;
+        }
+        assert!(metadata.history_bytes() <= MAX_EDIT_HISTORY_BYTES);
+        assert!(metadata.step_history(-1));
+        assert_eq!(
+            metadata.adjustments["lutData"],
+            json!(format!("{}{}", large, 6))
+        );
+    }
 }
@@ -1,4 +1,6 @@
 use crate::AppState;
+use crate::exif_processing;
+use crate::file_management::{MyLens, load_settings, parse_virtual_path, read_file_mapped};
 use fuzzy_matcher::FuzzyMatcher;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
@@ -606,123 +608,141 @@ pub fn get_lensfun_lenses_for_maker(
     }
 }
 
-#[tauri::command]
-pub fn autodetect_lens(
-    maker: String,
-    model: String,
-    state: State<AppState>,
-) -> Result<Option<(String, String)>, String> {
-    let clean_maker = maker.trim().trim_matches('"').to_string();
-    let clean_model = model.trim().trim_matches('"').to_string();
-
-    log::info!(
-        "Attempting to autodetect lens. Cleaned Maker: '{}', Cleaned Model: '{}'",
-        clean_maker,
-        clean_model
-    );
+/// Fraction of the model's score against itself that a lens name has to reach.
+/// Scattered subsequence matches of the wrong lens land well below it.
+const MIN_LENS_MATCH_RATIO: f64 = 0.9;
 
-    let db_guard = state
-        .lens_db
-        .lock()
-        .map_err(|e| format!("Lock poisoned: {}", e))?;
-    if let Some(db) = &*db_guard {
-        let matcher = fuzzy_matcher::skim::SkimMatcherV2::default().ignore_case();
-
-        log::info!(
-            "[Attempt 1] Searching for lenses from maker: '{}'",
-            clean_maker
-        );
+fn min_lens_match_score(matcher: &fuzzy_matcher::skim::SkimMatcherV2, model: &str) -> i64 {
+    matcher
+        .fuzzy_match(model, model)
+        .map_or(i64::MAX, |s| {
+            (s as f64 * MIN_LENS_MATCH_RATIO).ceil() as i64
+        })
+        .max(1)
+}
 
-        let lenses_from_maker: Vec<&Lens> = db
-            .lenses
-            .iter()
-            .filter(|lens| lens.get_maker().eq_ignore_ascii_case(&clean_maker))
-            .collect();
+fn detect_lens(
+    db: &LensDatabase,
+    clean_maker: &str,
+    clean_model: &str,
+) -> Option<(String, String)> {
+    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default().ignore_case();
+    let min_score = min_lens_match_score(&matcher, clean_model);
 
-        if !lenses_from_maker.is_empty() {
-            let best_match = lenses_from_maker
-                .iter()
-                .filter_map(|lens| {
-                    let english_name = lens.get_full_model_name();
-                    let canonical_name = lens.get_canonical_model_name();
-
-                    let score_english = matcher
-                        .fuzzy_match(&english_name, &clean_model)
-                        .unwrap_or(0);
-                    let score_canonical = matcher
-                        .fuzzy_match(&canonical_name, &clean_model)
-                        .unwrap_or(0);
-                    let score = score_english.max(score_canonical);
-
-                    if score > 0 {
-                        let best_name = if score_canonical > score_english {
-                            &canonical_name
-                        } else {
-                            &english_name
-                        };
-                        let length_penalty =
-                            (best_name.len() as i64 - clean_model.len() as i64).max(0) / 2;
-                        let adjusted_score = score - length_penalty;
-                        Some((adjusted_score, *lens))
-                    } else {
-                        None
-                    }
-                })
-                .max_by_key(|(score, _)| *score);
-
-            if let Some((_, best_lens)) = best_match {
-                let lens_maker = best_lens.get_maker();
-                let display_name = best_lens.get_display_name(&lenses_from_maker);
-                log::info!(
-                    "[Attempt 1] Success! Found best match: '{} {}'",
-                    lens_maker,
-                    display_name
-                );
-                return Ok(Some((lens_maker, display_name)));
-            }
-        }
+    log::info!(
+        "[Attempt 1] Searching for lenses from maker: '{}'",
+        clean_maker
+    );
 
-        log::warn!(
-            "[Attempt 1] Failed. Could not find a match for model '{}' from maker '{}'.",
-            clean_model,
-            clean_maker
-        );
-        log::info!("[Attempt 2] Falling back to searching model name against ALL lens makers.");
+    let lenses_from_maker: Vec<&Lens> = db
+        .lenses
+        .iter()
+        .filter(|lens| lens.get_maker().eq_ignore_ascii_case(clean_maker))
+        .collect();
 
-        let best_match_fallback = db
-            .lenses
+    if !lenses_from_maker.is_empty() {
+        let best_match = lenses_from_maker
             .iter()
             .filter_map(|lens| {
                 let english_name = lens.get_full_model_name();
                 let canonical_name = lens.get_canonical_model_name();
 
-                let score_english = matcher
-                    .fuzzy_match(&english_name, &clean_model)
-                    .unwrap_or(0);
+                let score_english = matcher.fuzzy_match(&english_name, clean_model).unwrap_or(0);
                 let score_canonical = matcher
-                    .fuzzy_match(&canonical_name, &clean_model)
+                    .fuzzy_match(&canonical_name, clean_model)
                     .unwrap_or(0);
                 let score = score_english.max(score_canonical);
 
-                if score > 0 { Some((score, lens)) } else { None }
+                if score >= min_score {
+                    let best_name = if score_canonical > score_english {
+                        &canonical_name
+                    } else {
+                        &english_name
+                    };
+                    let length_penalty =
+                        (best_name.len() as i64 - clean_model.len() as i64).max(0) / 2;
+                    let adjusted_score = score - length_penalty;
+                    Some((adjusted_score, *lens))
+                } else {
+                    None
+                }
             })
-            .max_by_key(|(score, _): &(i64, _)| *score);
+            .max_by_key(|(score, _)| *score);
 
-        if let Some((score, best_lens)) = best_match_fallback {
+        if let Some((_, best_lens)) = best_match {
             let lens_maker = best_lens.get_maker();
-            let maker_lenses = lenses_for_maker(db, &lens_maker);
-            let display_name = best_lens.get_display_name(&maker_lenses);
+            let display_name = best_lens.get_display_name(&lenses_from_maker);
             log::info!(
-                "[Attempt 2] Found best fallback match with score {}: '{} {}'",
-                score,
+                "[Attempt 1] Success! Found best match: '{} {}'",
                 lens_maker,
                 display_name
             );
-            return Ok(Some((lens_maker, display_name)));
+            return Some((lens_maker, display_name));
         }
+    }
 
-        log::warn!("[Attempt 2] Fallback failed. No suitable lens found in the entire database.");
-        Ok(None)
+    log::warn!(
+        "[Attempt 1] Failed. Could not find a match for model '{}' from maker '{}'.",
+        clean_model,
+        clean_maker
+    );
+    log::info!("[Attempt 2] Falling back to searching model name against ALL lens makers.");
+
+    let best_match_fallback = db
+        .lenses
+        .iter()
+        .filter_map(|lens| {
+            let english_name = lens.get_full_model_name();
+            let canonical_name = lens.get_canonical_model_name();
+
+            let score_english = matcher.fuzzy_match(&english_name, clean_model).unwrap_or(0);
+            let score_canonical = matcher
+                .fuzzy_match(&canonical_name, clean_model)
+                .unwrap_or(0);
+            let score = score_english.max(score_canonical);
+
+            (score >= min_score).then_some((score, lens))
+        })
+        .max_by_key(|(score, _): &(i64, _)| *score);
+
+    if let Some((score, best_lens)) = best_match_fallback {
+        let lens_maker = best_lens.get_maker();
+        let maker_lenses = lenses_for_maker(db, &lens_maker);
+        let display_name = best_lens.get_display_name(&maker_lenses);
+        log::info!(
+            "[Attempt 2] Found best fallback match with score {}: '{} {}'",
+            score,
+            lens_maker,
+            display_name
+        );
+        return Some((lens_maker, display_name));
+    }
+
+    log::warn!("[Attempt 2] Fallback failed. No suitable lens found in the entire database.");
+    None
+}
+
+#[tauri::command]
+pub fn autodetect_lens(
+    maker: String,
+    model: String,
+    state: State<AppState>,
+) -> Result<Option<(String, String)>, String> {
+    let clean_maker = maker.trim().trim_matches('"').to_string();
+    let clean_model = model.trim().trim_matches('"').to_string();
+
+    log::info!(
+        "Attempting to autodetect lens. Cleaned Maker: '{}', Cleaned Model: '{}'",
+        clean_maker,
+        clean_model
+    );
+
+    let db_guard = state
+        .lens_db
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {}", e))?;
+    if let Some(db) = &*db_guard {
+        Ok(detect_lens(db, &clean_maker, &clean_model))
     } else {
         log::warn!("Lens database not loaded. Cannot perform autodetect.");
         Ok(None)
@@ -754,3 +774,151 @@ pub fn get_lens_distortion_params(
     }
     Ok(None)
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LensProfileMatch {
+    lens_maker: String,
+    lens_model: String,
+    lens_distortion_params: LensDistortionParams,
+}
+
+fn parse_exif_number(value: &str) -> Option<f32> {
+    let value = value
+        .trim()
+        .trim_matches('"')
+        .trim_start_matches("f/")
+        .trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    value[..end].parse().ok().filter(|v: &f32| *v > 0.0)
+}
+
+fn match_my_lens(
+    db: &LensDatabase,
+    my_lenses: &[MyLens],
+    maker: &str,
+    model: &str,
+) -> Option<(String, String)> {
+    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default().ignore_case();
+    let min_score = min_lens_match_score(&matcher, model);
+    let best = my_lenses
+        .iter()
+        .filter_map(|lens| {
+            let score = matcher.fuzzy_match(&lens.model, model).unwrap_or(0);
+            (score >= min_score).then_some((score, lens))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, lens)| lens);
+
+    let from_maker: Vec<&MyLens> = my_lenses
+        .iter()
+        .filter(|lens| lens.maker.eq_ignore_ascii_case(maker))
+        .collect();
+    let chosen = best.or(match from_maker.as_slice() {
+        [only] => Some(*only),
+        _ => None,
+    })?;
+
+    let maker_lenses = lenses_for_maker(db, &chosen.maker);
+    maker_lenses
+        .iter()
+        .any(|l| l.get_display_name(&maker_lenses) == chosen.model)
+        .then(|| (chosen.maker.clone(), chosen.model.clone()))
+}
+
+/// Reads the lens from the image's EXIF and returns the matching Lensfun
+/// profile evaluated at the shot's focal length and aperture, falling back to
+/// the user's saved lenses.
+#[tauri::command]
+pub fn match_lens_profile(
+    path: String,
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<LensProfileMatch>, String> {
+    let (source_path, _) = parse_virtual_path(&path);
+    let mmap = read_file_mapped(&source_path).map_err(|e| e.to_string())?;
+    let exif = exif_processing::read_exif_data(&source_path.to_string_lossy(), &mmap);
+    let get = |key: &str| {
+        exif.get(key)
+            .map(|v| v.trim().trim_matches('"').trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let Some(focal_length) = get("FocalLength").as_deref().and_then(parse_exif_number) else {
+        return Ok(None);
+    };
+    let aperture = get("FNumber").as_deref().and_then(parse_exif_number);
+    let maker = get("LensMake").or_else(|| get("Make")).unwrap_or_default();
+    let model = get("LensModel").unwrap_or_default();
+
+    let db_guard = state
+        .lens_db
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {}", e))?;
+    let Some(db) = &*db_guard else {
+        return Ok(None);
+    };
+
+    let detected = if model.is_empty() {
+        None
+    } else {
+        detect_lens(db, &maker, &model)
+    };
+    let Some((lens_maker, lens_model)) = detected.or_else(|| {
+        let my_lenses = load_settings(app_handle).ok()?.my_lenses?;
+        match_my_lens(db, &my_lenses, &maker, &model)
+    }) else {
+        return Ok(None);
+    };
+
+    let maker_lenses = lenses_for_maker(db, &lens_maker);
+    let params = maker_lenses
+        .iter()
+        .find(|l| l.get_display_name(&maker_lenses) == lens_model)
+        .and_then(|lens| lens.get_distortion_params(focal_length, aperture, None));
+
+    Ok(params.map(|lens_distortion_params| LensProfileMatch {
+        lens_maker,
+        lens_model,
+        lens_distortion_params,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENSES_XML: &str = r#"<lensdatabase>
+        <lens>
+            <maker>Canon</maker>
+            <model>Canon EF 24-70mm f/2.8L II USM</model>
+            <mount>Canon EF</mount>
+        </lens>
+        <lens>
+            <maker>Canon</maker>
+            <model>Canon EF-S 18-135mm f/3.5-5.6 IS STM</model>
+            <mount>Canon EF-S</mount>
+        </lens>
+        <lens>
+            <maker>Sony</maker>
+            <model>Sony FE 24-105mm F4 G OSS</model>
+            <mount>Sony E</mount>
+        </lens>
+    </lensdatabase>"#;
+
+    #[test]
+    fn detect_lens_rejects_weak_matches() {
+        let db: LensDatabase = quick_xml::de::from_str(LENSES_XML).unwrap();
+
+        let (maker, _) = detect_lens(&db, "Canon", "EF24-70mm f/2.8L II USM").unwrap();
+        assert_eq!(maker, "Canon");
+        let (maker, _) = detect_lens(&db, "", "FE 24-105mm F4 G OSS").unwrap();
+        assert_eq!(maker, "Sony");
+
+        assert_eq!(detect_lens(&db, "Canon", "E 15mm"), None);
+        assert_eq!(detect_lens(&db, "Canon", "18mm"), None);
+        assert_eq!(detect_lens(&db, "Sony", "F 2G"), None);
+    }
+}
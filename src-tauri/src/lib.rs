@@ -2,12 +2,15 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+mod adjustment_registry;
 mod ai_connector;
 mod ai_processing;
+mod color_management;
 mod culling;
 mod denoising;
 mod exif_processing;
 mod file_management;
+mod folder_watcher;
 mod formats;
 mod gpu_processing;
 mod image_loader;
@@ -23,7 +26,7 @@ mod raw_processing;
 mod tagging;
 mod tagging_utils;
 
-use std::collections::{HashMap, hash_map::DefaultHasher};
+use std::collections::{HashMap, HashSet, hash_map::DefaultHasher};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::Cursor;
@@ -37,15 +40,17 @@ use std::thread;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use ab_glyph::{FontRef, PxScale};
 use base64::{Engine as _, engine::general_purpose};
 use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
 use image::{
-    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageFormat, Luma, Rgb, RgbImage, Rgba,
-    RgbaImage, imageops,
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageEncoder, ImageFormat, Luma, Rgb,
+    RgbImage, Rgba, RgbaImage, imageops,
 };
 use image_hdr::hdr_merge_images;
 use image_hdr::input::HDRInput;
-use imageproc::drawing::draw_line_segment_mut;
+use imageproc::drawing::{draw_line_segment_mut, draw_text_mut, text_size};
 use imageproc::edges::canny;
 use imageproc::hough::{LineDetectionOptions, detect_lines};
 use jxl_encoder::{LosslessConfig, LossyConfig, PixelLayout};
@@ -64,24 +69,28 @@ use crate::ai_processing::{
     AiSubjectMaskParameters, CachedDepthMap, generate_image_embeddings, get_or_init_ai_models,
     run_depth_anything_model, run_sam_decoder, run_sky_seg_model, run_u2netp_model,
 };
+use crate::color_management::ExportColorSpace;
 use crate::exif_processing::{read_exposure_time_secs, read_iso};
 use crate::file_management::{
-    AppSettings, generate_filename_from_template, load_settings, parse_virtual_path,
-    read_file_mapped,
+    AppSettings, ExportPreset, default_export_color_space, generate_filename_from_template,
+    load_settings, parse_virtual_path, read_file_mapped, resolve_sidecar_path,
 };
 use crate::formats::is_raw_file;
 use crate::image_loader::{
     composite_patches_on_image, load_and_composite, load_base_image_from_bytes,
+    load_base_image_from_bytes_with_progress,
 };
 use crate::image_processing::{
     AllAdjustments, Crop, GeometryParams, GpuContext, ImageMetadata, RenderRequest,
     apply_coarse_rotation, apply_cpu_default_raw_processing, apply_crop, apply_flip,
-    apply_geometry_warp, apply_rotation, apply_unwarp_geometry, downscale_f32_image,
-    get_all_adjustments_from_json, get_or_init_gpu_context, process_and_get_dynamic_image,
-    warp_image_geometry,
+    apply_geometry_warp, apply_geometry_warp_owned, apply_rotation, apply_unwarp_geometry,
+    downscale_f32_image, get_all_adjustments_from_json, get_or_init_gpu_context, parse_hex_color,
+    process_and_get_dynamic_image, warp_image_geometry,
 };
 use crate::lut_processing::{Lut, convert_image_to_cube_lut, generate_identity_lut_image};
-use crate::mask_generation::{AiPatchDefinition, MaskDefinition, generate_mask_bitmap};
+use crate::mask_generation::{
+    AiPatchDefinition, MaskBlendMode, MaskDefinition, SubMask, SubMaskMode, generate_mask_bitmap,
+};
 use tagging_utils::{candidates, hierarchy};
 
 #[cfg(target_os = "macos")]
@@ -150,6 +159,8 @@ struct PreviewJob {
     roi: Option<(f32, f32, f32, f32)>,
     compute_waveform: bool,
     active_waveform_channel: Option<String>,
+    waveform_mode: Option<String>,
+    histogram_scale: Option<String>,
     responder: tokio::sync::oneshot::Sender<Vec<u8>>,
 }
 
@@ -158,6 +169,8 @@ struct AnalyticsJob {
     image: Arc<DynamicImage>,
     compute_waveform: bool,
     active_waveform_channel: Option<String>,
+    waveform_mode: Option<String>,
+    histogram_scale: Option<String>,
 }
 
 pub struct ThumbnailProgressTracker {
@@ -168,10 +181,15 @@ pub struct ThumbnailProgressTracker {
 pub struct AppState {
     window_setup_complete: AtomicBool,
     pub gpu_crash_flag_path: Mutex<Option<PathBuf>>,
+    pub preferred_gpu_adapter: Mutex<Option<String>>,
+    pub gpu_tile_size: Mutex<Option<u32>>,
+    pub gpu_backends: Mutex<Option<wgpu::Backends>>,
     original_image: Mutex<Option<LoadedImage>>,
     cached_preview: Mutex<Option<CachedPreview>>,
+    last_processed_preview: Mutex<Option<Arc<DynamicImage>>>,
     gpu_context: Mutex<Option<GpuContext>>,
     gpu_image_cache: Mutex<Option<GpuImageCache>>,
+    pub last_processing_stats: Mutex<Option<gpu_processing::ProcessingStats>>,
     gpu_processor: Mutex<Option<GpuProcessorState>>,
     ai_state: Mutex<Option<AiState>>,
     ai_init_lock: TokioMutex<()>,
@@ -183,16 +201,28 @@ pub struct AppState {
     pub lut_cache: Mutex<HashMap<String, Arc<Lut>>>,
     initial_file_path: Mutex<Option<String>>,
     pub thumbnail_cancellation_token: Arc<AtomicBool>,
+    pub thumbnail_pause_gate: Arc<file_management::ThumbnailPauseGate>,
+    pub thumbnail_canceled_paths: Mutex<HashSet<String>>,
     pub thumbnail_progress: Mutex<ThumbnailProgressTracker>,
+    pub thumbnail_cache_index: Mutex<HashSet<String>>,
+    pub thumbnail_cache_size: Mutex<Option<u64>>,
     preview_worker_tx: Mutex<Option<Sender<PreviewJob>>>,
     analytics_worker_tx: Mutex<Option<Sender<AnalyticsJob>>>,
     pub mask_cache: Mutex<HashMap<u64, GrayImage>>,
     pub patch_cache: Mutex<HashMap<String, serde_json::Value>>,
     pub geometry_cache: Mutex<HashMap<u64, DynamicImage>>,
-    pub thumbnail_geometry_cache: Mutex<HashMap<String, (u64, DynamicImage, f32)>>,
+    pub thumbnail_geometry_cache: Mutex<file_management::ThumbnailGeometryCache>,
     pub lens_db: Mutex<Option<lens_correction::LensDatabase>>,
     pub load_image_generation: Arc<AtomicUsize>,
     pub full_warped_cache: Mutex<Option<(u64, Arc<DynamicImage>)>>,
+    pub thumbnail_regen_tx: Mutex<Option<Sender<String>>>,
+    pub tag_index: Mutex<Option<tagging::TagIndex>>,
+    pub capture_time_cache: Mutex<HashMap<String, (u64, Option<i64>)>>,
+    pub last_trash_operation: Mutex<Option<file_management::TrashOperation>>,
+    pub folder_watchers: Mutex<HashMap<String, notify::RecommendedWatcher>>,
+    /// Cache and data directories with any user override applied, cleared when settings are saved.
+    pub app_cache_dir: Mutex<Option<PathBuf>>,
+    pub app_data_dir: Mutex<Option<PathBuf>>,
 }
 
 #[derive(serde::Serialize)]
@@ -232,6 +262,12 @@ struct ExportSettings {
     watermark: Option<WatermarkSettings>,
     #[serde(default)]
     export_masks: bool,
+    #[serde(default)]
+    bit_depth: Option<u8>,
+    #[serde(default)]
+    lossless: bool,
+    #[serde(default = "default_export_color_space")]
+    color_space: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -246,6 +282,14 @@ struct LutParseResult {
     size: u32,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LutValidationResult {
+    path: String,
+    size: Option<u32>,
+    error: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum WatermarkAnchor {
@@ -263,19 +307,49 @@ pub enum WatermarkAnchor {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WatermarkSettings {
-    path: String,
+    #[serde(default)]
+    path: Option<String>,
     anchor: WatermarkAnchor,
     scale: f32,
     spacing: f32,
     opacity: f32,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default = "default_watermark_font_size")]
+    font_size: u32,
+    #[serde(default)]
+    color: Option<String>,
 }
 
+fn default_watermark_font_size() -> u32 {
+    30
+}
+
+const WATERMARK_FONT: &[u8] = include_bytes!("../resources/fonts/DejaVuSans.ttf");
+
 #[derive(serde::Serialize)]
 struct ImageDimensions {
     width: u32,
     height: u32,
 }
 
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GpuMemoryEstimate {
+    required_mb: u64,
+    budget_mb: Option<u32>,
+    fits: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GpuSelfTestResult {
+    max_difference: f32,
+    mean_difference: f32,
+    threshold: f32,
+    passed: bool,
+}
+
 fn apply_all_transformations(
     image: &DynamicImage,
     adjustments: &serde_json::Value,
@@ -304,26 +378,6 @@ fn apply_all_transformations(
     (cropped_image, unscaled_crop_offset)
 }
 
-const GEOMETRY_KEYS: &[&str] = &[
-    "transformDistortion",
-    "transformVertical",
-    "transformHorizontal",
-    "transformRotate",
-    "transformAspect",
-    "transformScale",
-    "transformXOffset",
-    "transformYOffset",
-    "lensDistortionAmount",
-    "lensVignetteAmount",
-    "lensTcaAmount",
-    "lensDistortionParams",
-    "lensMaker",
-    "lensModel",
-    "lensDistortionEnabled",
-    "lensTcaEnabled",
-    "lensVignetteEnabled",
-];
-
 pub fn calculate_geometry_hash(adjustments: &serde_json::Value) -> u64 {
     let mut hasher = DefaultHasher::new();
 
@@ -332,34 +386,91 @@ pub fn calculate_geometry_hash(adjustments: &serde_json::Value) -> u64 {
     }
 
     adjustments["orientationSteps"].as_u64().hash(&mut hasher);
-
-    for key in GEOMETRY_KEYS {
-        if let Some(val) = adjustments.get(key) {
-            key.hash(&mut hasher);
-            val.to_string().hash(&mut hasher);
-        }
+    // Every identity parameter set warps to the same image, so they share one hash and
+    // toggling an inactive lens or transform setting keeps the cached bases valid.
+    let params = image_processing::get_geometry_params_from_json(adjustments);
+    let is_identity = image_processing::is_geometry_identity(&params);
+    is_identity.hash(&mut hasher);
+    if !is_identity {
+        hash_geometry_params(&params, &mut hasher);
     }
 
     hasher.finish()
 }
 
+/// Hashes the parsed warp parameters rather than the raw JSON, so defaults and
+/// absent keys hash the same and every field the warp consumes is covered.
+fn hash_geometry_params(params: &GeometryParams, hasher: &mut impl Hasher) {
+    // Destructured without `..` so a new warp parameter can't be silently left out.
+    let GeometryParams {
+        distortion,
+        vertical,
+        horizontal,
+        rotate,
+        aspect,
+        scale,
+        x_offset,
+        y_offset,
+        lens_distortion_amount,
+        lens_vignette_amount,
+        lens_tca_amount,
+        lens_distortion_enabled,
+        lens_tca_enabled,
+        lens_vignette_enabled,
+        lens_dist_k1,
+        lens_dist_k2,
+        lens_dist_k3,
+        lens_model,
+        tca_vr,
+        tca_vb,
+        vig_k1,
+        vig_k2,
+        vig_k3,
+    } = *params;
+
+    for value in [
+        distortion,
+        vertical,
+        horizontal,
+        rotate,
+        aspect,
+        scale,
+        x_offset,
+        y_offset,
+        lens_distortion_amount,
+        lens_vignette_amount,
+        lens_tca_amount,
+        lens_dist_k1,
+        lens_dist_k2,
+        lens_dist_k3,
+        tca_vr,
+        tca_vb,
+        vig_k1,
+        vig_k2,
+        vig_k3,
+    ] {
+        value.to_bits().hash(hasher);
+    }
+    [
+        lens_distortion_enabled,
+        lens_tca_enabled,
+        lens_vignette_enabled,
+    ]
+    .hash(hasher);
+    lens_model.hash(hasher);
+}
+
 fn calculate_visual_hash(path: &str, adjustments: &serde_json::Value) -> u64 {
     let mut hasher = DefaultHasher::new();
     path.hash(&mut hasher);
 
     if let Some(obj) = adjustments.as_object() {
         for (key, value) in obj {
-            if GEOMETRY_KEYS.contains(&key.as_str()) {
+            if adjustment_registry::find(key).is_some_and(|def| def.geometry) {
                 continue;
             }
-
-            match key.as_str() {
-                "crop" | "rotation" | "orientationSteps" | "flipHorizontal" | "flipVertical" => (),
-                _ => {
-                    key.hash(&mut hasher);
-                    value.to_string().hash(&mut hasher);
-                }
-            }
+            key.hash(&mut hasher);
+            value.to_string().hash(&mut hasher);
         }
     }
 
@@ -387,7 +498,7 @@ fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
         crop_val.to_string().hash(&mut hasher);
     }
 
-    for key in GEOMETRY_KEYS {
+    for key in adjustment_registry::warp_keys() {
         if let Some(val) = adjustments.get(key) {
             key.hash(&mut hasher);
             val.to_string().hash(&mut hasher);
@@ -565,12 +676,53 @@ fn get_or_load_lut(state: &tauri::State<AppState>, path: &str) -> Result<Arc<Lut
         return Ok(lut.clone());
     }
 
-    let lut = lut_processing::parse_lut_file(path).map_err(|e| e.to_string())?;
+    let lut = lut_processing::parse_lut_file(path).map_err(|e| {
+        log::warn!("Failed to load LUT '{}': {}", path, e);
+        e.to_string()
+    })?;
     let arc_lut = Arc::new(lut);
     cache.insert(path.to_string(), arc_lut.clone());
     Ok(arc_lut)
 }
 
+/// Loads the LUT named by the adjustments' `lutPath`, if any. A LUT that fails to
+/// parse is an error rather than being silently left out of the render.
+fn get_adjustments_lut(
+    state: &tauri::State<AppState>,
+    adjustments: &Value,
+) -> Result<Option<Arc<Lut>>, String> {
+    adjustments["lutPath"]
+        .as_str()
+        .map(|path| get_or_load_lut(state, path))
+        .transpose()
+}
+
+/// Number of full-resolution mask bitmaps a render of these adjustments uploads.
+fn rendered_mask_count(adjustments: &Value) -> usize {
+    adjustments
+        .get("masks")
+        .and_then(|m| serde_json::from_value::<Vec<MaskDefinition>>(m.clone()).ok())
+        .map_or(0, |masks| {
+            masks
+                .iter()
+                .filter(|mask| mask.visible && !mask.sub_masks.is_empty())
+                .count()
+        })
+}
+
+/// Drops every derived in-memory cache (warped bases, LUTs, masks, patches); they are
+/// rebuilt lazily on the next render. The loaded image and its preview are kept.
+#[tauri::command]
+fn clear_processing_caches(state: tauri::State<AppState>) -> Result<(), String> {
+    state.thumbnail_geometry_cache.lock().unwrap().clear();
+    state.geometry_cache.lock().unwrap().clear();
+    *state.full_warped_cache.lock().unwrap() = None;
+    state.lut_cache.lock().unwrap().clear();
+    state.mask_cache.lock().unwrap().clear();
+    state.patch_cache.lock().unwrap().clear();
+    Ok(())
+}
+
 #[tauri::command]
 async fn load_image(
     path: String,
@@ -584,6 +736,7 @@ async fn load_image(
     {
         *state.original_image.lock().unwrap() = None;
         *state.cached_preview.lock().unwrap() = None;
+        *state.last_processed_preview.lock().unwrap() = None;
         *state.gpu_image_cache.lock().unwrap() = None;
         *state.full_warped_cache.lock().unwrap() = None;
 
@@ -597,6 +750,7 @@ async fn load_image(
     }
 
     let (source_path, sidecar_path) = parse_virtual_path(&path);
+    let sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
     let source_path_str = source_path.to_string_lossy().to_string();
 
     let metadata: ImageMetadata = if sidecar_path.exists() {
@@ -611,12 +765,20 @@ async fn load_image(
     let linear_mode = settings.linear_raw_mode;
 
     let path_clone = source_path_str.clone();
+    let progress_handle = app_handle.clone();
 
     let (pristine_img, exif_data) = tokio::task::spawn_blocking(move || {
         if generation_tracker.load(Ordering::SeqCst) != my_generation {
             return Err("Load cancelled".to_string());
         }
 
+        let report_stage = |stage: &str| {
+            let _ = progress_handle.emit(
+                "develop-progress",
+                serde_json::json!({ "path": &path_clone, "stage": stage }),
+            );
+        };
+
         let result: Result<(DynamicImage, HashMap<String, String>), String> =
             (|| match read_file_mapped(Path::new(&path_clone)) {
                 Ok(mmap) => {
@@ -624,13 +786,14 @@ async fn load_image(
                         return Err("Load cancelled".to_string());
                     }
 
-                    let img = load_base_image_from_bytes(
+                    let img = load_base_image_from_bytes_with_progress(
                         &mmap,
                         &path_clone,
                         false,
                         highlight_compression,
                         linear_mode.clone(),
                         cancel_token.clone(),
+                        Some(&report_stage),
                     )
                     .map_err(|e| e.to_string())?;
                     let exif = exif_processing::read_exif_data(&path_clone, &mmap);
@@ -650,13 +813,14 @@ async fn load_image(
                         return Err("Load cancelled".to_string());
                     }
 
-                    let img = load_base_image_from_bytes(
+                    let img = load_base_image_from_bytes_with_progress(
                         &bytes,
                         &path_clone,
                         false,
                         highlight_compression,
                         linear_mode.clone(),
                         cancel_token.clone(),
+                        Some(&report_stage),
                     )
                     .map_err(|e| e.to_string())?;
                     let exif = exif_processing::read_exif_data(&path_clone, &bytes);
@@ -695,6 +859,218 @@ async fn load_image(
     })
 }
 
+const WHITE_BALANCE_SAMPLE_RADIUS: i64 = 2;
+
+/// Averages the neighbourhood around normalized `(x, y)` in linear light.
+/// `srgb_encoded` marks images that hold display-encoded values (non-RAW
+/// originals and processed previews), which are linearized per pixel first.
+fn average_linear_pixel(
+    image: &DynamicImage,
+    x: f64,
+    y: f64,
+    radius: i64,
+    srgb_encoded: bool,
+) -> Result<glam::Vec3, String> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err("Image has zero dimensions.".to_string());
+    }
+    let cx = (x.clamp(0.0, 1.0) * (width - 1) as f64).round() as i64;
+    let cy = (y.clamp(0.0, 1.0) * (height - 1) as f64).round() as i64;
+
+    let mut sum = glam::Vec3::ZERO;
+    let mut count = 0.0;
+    for py in (cy - radius).max(0)..=(cy + radius).min(height as i64 - 1) {
+        for px in (cx - radius).max(0)..=(cx + radius).min(width as i64 - 1) {
+            let p = match image {
+                DynamicImage::ImageRgb32F(img) => img.get_pixel(px as u32, py as u32).0,
+                DynamicImage::ImageRgba32F(img) => {
+                    let p = img.get_pixel(px as u32, py as u32).0;
+                    [p[0], p[1], p[2]]
+                }
+                other => {
+                    let p = other.get_pixel(px as u32, py as u32).0;
+                    [
+                        p[0] as f32 / 255.0,
+                        p[1] as f32 / 255.0,
+                        p[2] as f32 / 255.0,
+                    ]
+                }
+            };
+            let p = glam::Vec3::from_array(p);
+            sum += if srgb_encoded {
+                image_processing::cpu_srgb_to_linear(p)
+            } else {
+                p
+            };
+            count += 1.0;
+        }
+    }
+    Ok(sum / count)
+}
+
+fn loaded_original(state: &AppState) -> Result<(Arc<DynamicImage>, bool), String> {
+    state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|loaded| (Arc::clone(&loaded.image), loaded.is_raw))
+        .ok_or_else(|| "No original image loaded".to_string())
+}
+
+#[tauri::command]
+fn sample_white_balance(
+    x: f64,
+    y: f64,
+    state: tauri::State<AppState>,
+) -> Result<image_processing::WhiteBalanceSample, String> {
+    let (original, is_raw) = loaded_original(&state)?;
+    let image = state
+        .cached_preview
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(original, |cached| Arc::clone(&cached.image));
+    let color = average_linear_pixel(&image, x, y, WHITE_BALANCE_SAMPLE_RADIUS, !is_raw)?;
+    let mut sample = image_processing::solve_white_balance(color);
+    if let Some(def) = adjustment_registry::find("temperature") {
+        sample.temperature = def.clamp(sample.temperature);
+    }
+    if let Some(def) = adjustment_registry::find("tint") {
+        sample.tint = def.clamp(sample.tint);
+    }
+    Ok(sample)
+}
+
+#[derive(Serialize)]
+struct PixelSample {
+    r: f32,
+    g: f32,
+    b: f32,
+    h: f32,
+    s: f32,
+    l: f32,
+    luma: f32,
+}
+
+#[tauri::command]
+fn sample_pixel(
+    x: f64,
+    y: f64,
+    use_processed: bool,
+    state: tauri::State<AppState>,
+) -> Result<PixelSample, String> {
+    let (image, srgb_encoded) = if use_processed {
+        let preview = state
+            .last_processed_preview
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("No processed preview available")?;
+        (preview, true)
+    } else {
+        let (original, is_raw) = loaded_original(&state)?;
+        (original, !is_raw)
+    };
+    let linear = average_linear_pixel(&image, x, y, 0, srgb_encoded)?;
+    let c = image_processing::cpu_linear_to_srgb(linear);
+
+    let max = c.max_element();
+    let min = c.min_element();
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    let (h, s) = if delta < 1e-6 {
+        (0.0, 0.0)
+    } else {
+        let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == c.x {
+            ((c.y - c.z) / delta).rem_euclid(6.0)
+        } else if max == c.y {
+            (c.z - c.x) / delta + 2.0
+        } else {
+            (c.x - c.y) / delta + 4.0
+        };
+        (h * 60.0, s)
+    };
+
+    let rgb = c * 255.0;
+    Ok(PixelSample {
+        r: rgb.x,
+        g: rgb.y,
+        b: rgb.z,
+        h,
+        s: s * 100.0,
+        l: l * 100.0,
+        luma: (rgb.x * 218.0 + rgb.y * 732.0 + rgb.z * 74.0) / 1024.0,
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SoftProofResult {
+    image: String,
+    gamut_mask: String,
+    out_of_gamut_ratio: f32,
+}
+
+#[tauri::command]
+async fn generate_soft_proof(
+    profile_path: String,
+    js_adjustments: Value,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<SoftProofResult, String> {
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No original image loaded")?;
+    let mut adjustments = js_adjustments;
+    hydrate_adjustments(&state, &mut adjustments);
+
+    // The editor preview is already clipped to sRGB; render one with Rec. 2020 primaries
+    // so colors beyond sRGB reach the proof.
+    let preview = render_editor_preview(
+        &state,
+        &app_handle,
+        &loaded_image,
+        &adjustments,
+        color_management::OUTPUT_PRIMARIES_REC2020,
+        "generate_soft_proof",
+    )?;
+
+    tokio::task::spawn_blocking(move || {
+        let profile_bytes = fs::read(&profile_path).map_err(|e| e.to_string())?;
+        let (proofed, gamut_mask, out_of_gamut_ratio) =
+            color_management::soft_proof(&preview, &profile_bytes)?;
+
+        let mut image_buf = Cursor::new(Vec::new());
+        proofed
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut image_buf, 90))
+            .map_err(|e| e.to_string())?;
+        let mut mask_buf = Cursor::new(Vec::new());
+        gamut_mask
+            .write_to(&mut mask_buf, ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+
+        Ok(SoftProofResult {
+            image: format!(
+                "data:image/jpeg;base64,{}",
+                general_purpose::STANDARD.encode(image_buf.get_ref())
+            ),
+            gamut_mask: format!(
+                "data:image/png;base64,{}",
+                general_purpose::STANDARD.encode(mask_buf.get_ref())
+            ),
+            out_of_gamut_ratio,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn get_image_dimensions(path: String) -> Result<ImageDimensions, String> {
     let (source_path, _) = parse_virtual_path(&path);
@@ -703,6 +1079,164 @@ fn get_image_dimensions(path: String) -> Result<ImageDimensions, String> {
         .map_err(|e| e.to_string())
 }
 
+/// Without a `mask_count` the estimate assumes the most masks a render can upload.
+#[tauri::command]
+fn estimate_gpu_memory_usage(
+    width: u32,
+    height: u32,
+    mask_count: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> Result<GpuMemoryEstimate, String> {
+    let settings = load_settings(app_handle).unwrap_or_default();
+    let required_mb = gpu_processing::estimate_gpu_memory_bytes(
+        width,
+        height,
+        settings.gpu_tile_size,
+        mask_count.unwrap_or(image_processing::MAX_MASK_COUNT),
+    )
+    .div_ceil(1024 * 1024);
+    let budget_mb = settings.gpu_memory_budget_mb;
+    Ok(GpuMemoryEstimate {
+        required_mb,
+        budget_mb,
+        fits: budget_mb.is_none_or(|budget| required_mb <= budget as u64),
+    })
+}
+
+const GPU_SELF_TEST_IMAGE: &[u8] = include_bytes!("../resources/self_test/reference.png");
+const GPU_SELF_TEST_THRESHOLD: f32 = 0.05;
+
+#[tauri::command]
+fn list_gpu_adapters() -> Vec<gpu_processing::GpuAdapterInfo> {
+    gpu_processing::list_gpu_adapters()
+}
+
+/// Switches the wgpu backend without a restart and returns the backend that actually bound.
+#[tauri::command]
+fn set_processing_backend(
+    backend: String,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let backends = gpu_processing::backends_from_name(&backend)?;
+
+    let previous_backends = state.gpu_backends.lock().unwrap().replace(backends);
+    let previous_context = state.gpu_context.lock().unwrap().take();
+    *state.gpu_processor.lock().unwrap() = None;
+    *state.gpu_image_cache.lock().unwrap() = None;
+
+    let context = match get_or_init_gpu_context(&state, &app_handle) {
+        Ok(context) => context,
+        Err(e) => {
+            *state.gpu_backends.lock().unwrap() = previous_backends;
+            *state.gpu_context.lock().unwrap() = previous_context;
+            return Err(e);
+        }
+    };
+    // Renders still holding the old context see it as lost and move to the new one.
+    if let Some(previous) = previous_context {
+        previous.lost.store(true, Ordering::SeqCst);
+    }
+
+    let mut settings = load_settings(app_handle.clone()).unwrap_or_default();
+    settings.processing_backend = Some(backend.to_lowercase());
+    file_management::save_settings(settings, app_handle)?;
+
+    log::info!(
+        "Processing backend set to '{}', bound {}",
+        backend,
+        context.backend
+    );
+    Ok(context.backend)
+}
+
+#[tauri::command]
+fn get_processing_stats(state: tauri::State<AppState>) -> Option<gpu_processing::ProcessingStats> {
+    state.last_processing_stats.lock().unwrap().clone()
+}
+
+#[tauri::command]
+async fn self_test_gpu(app_handle: tauri::AppHandle) -> Result<GpuSelfTestResult, String> {
+    tauri::async_runtime::spawn_blocking(move || run_gpu_self_test(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn run_gpu_self_test(app_handle: &tauri::AppHandle) -> Result<GpuSelfTestResult, String> {
+    let state = app_handle.state::<AppState>();
+    let context = get_or_init_gpu_context(&state, app_handle)?;
+    let reference_image = image::load_from_memory(GPU_SELF_TEST_IMAGE)
+        .map_err(|e| format!("Failed to decode self-test image: {}", e))?;
+    let adjustments = serde_json::json!({
+        "exposure": 0.5,
+        "contrast": 20.0,
+        "highlights": -30.0,
+        "shadows": 25.0,
+        "whites": 10.0,
+        "blacks": -10.0,
+        "saturation": 15.0,
+        "vibrance": 10.0,
+        "temperature": 10.0,
+        "tint": -5.0,
+    });
+    let all_adjustments = get_all_adjustments_from_json(&adjustments, false);
+    let mask_bitmaps = Vec::new();
+
+    let gpu_image = gpu_processing::render_uncached(
+        &context,
+        &reference_image,
+        RenderRequest {
+            adjustments: all_adjustments,
+            mask_bitmaps: &mask_bitmaps,
+            lut: None,
+            roi: None,
+        },
+    )?
+    .to_rgba32f();
+    let cpu_image =
+        image_processing::cpu_apply_global_adjustments(&reference_image, &all_adjustments.global)
+            .to_rgba32f();
+
+    if gpu_image.dimensions() != cpu_image.dimensions() {
+        return Err(format!(
+            "GPU output size {:?} does not match reference size {:?}",
+            gpu_image.dimensions(),
+            cpu_image.dimensions()
+        ));
+    }
+
+    let (max_difference, total_difference) = gpu_image
+        .pixels()
+        .zip(cpu_image.pixels())
+        .flat_map(|(gpu, cpu)| (0..3).map(move |c| (gpu.0[c] - cpu.0[c]).abs()))
+        .fold((0.0f32, 0.0f64), |(max, total), diff| {
+            (max.max(diff), total + diff as f64)
+        });
+    let sample_count = (gpu_image.width() * gpu_image.height() * 3) as f64;
+    let passed = max_difference <= GPU_SELF_TEST_THRESHOLD;
+
+    if !passed {
+        log::warn!(
+            "GPU self-test exceeded tolerance: max difference {:.4} (threshold {:.4})",
+            max_difference,
+            GPU_SELF_TEST_THRESHOLD
+        );
+    }
+
+    Ok(GpuSelfTestResult {
+        max_difference,
+        mean_difference: (total_difference / sample_count) as f32,
+        threshold: GPU_SELF_TEST_THRESHOLD,
+        passed,
+    })
+}
+
+#[tauri::command]
+fn cancel_thumbnails(paths: Vec<String>, state: tauri::State<AppState>) -> Result<(), String> {
+    state.thumbnail_canceled_paths.lock().unwrap().extend(paths);
+    Ok(())
+}
+
 #[tauri::command]
 fn cancel_thumbnail_generation(
     state: tauri::State<AppState>,
@@ -711,6 +1245,7 @@ fn cancel_thumbnail_generation(
     state
         .thumbnail_cancellation_token
         .store(true, Ordering::SeqCst);
+    state.thumbnail_pause_gate.set_paused(false);
 
     let mut tracker = state.thumbnail_progress.lock().unwrap();
     tracker.total = 0;
@@ -724,11 +1259,58 @@ fn cancel_thumbnail_generation(
     Ok(())
 }
 
-fn apply_watermark(
+#[tauri::command]
+fn pause_thumbnail_generation(state: tauri::State<AppState>) {
+    state.thumbnail_pause_gate.set_paused(true);
+}
+
+#[tauri::command]
+fn resume_thumbnail_generation(state: tauri::State<AppState>) {
+    state.thumbnail_pause_gate.set_paused(false);
+}
+
+fn watermark_position(
+    anchor: &WatermarkAnchor,
+    base_dims: (u32, u32),
+    wm_dims: (u32, u32),
+    spacing_pixels: i64,
+) -> (i64, i64) {
+    let (base_w, base_h) = (base_dims.0 as i64, base_dims.1 as i64);
+    let (wm_w, wm_h) = (wm_dims.0 as i64, wm_dims.1 as i64);
+
+    let x = match anchor {
+        WatermarkAnchor::TopLeft | WatermarkAnchor::CenterLeft | WatermarkAnchor::BottomLeft => {
+            spacing_pixels
+        }
+        WatermarkAnchor::TopCenter | WatermarkAnchor::Center | WatermarkAnchor::BottomCenter => {
+            (base_w - wm_w) / 2
+        }
+        WatermarkAnchor::TopRight | WatermarkAnchor::CenterRight | WatermarkAnchor::BottomRight => {
+            base_w - wm_w - spacing_pixels
+        }
+    };
+
+    let y = match anchor {
+        WatermarkAnchor::TopLeft | WatermarkAnchor::TopCenter | WatermarkAnchor::TopRight => {
+            spacing_pixels
+        }
+        WatermarkAnchor::CenterLeft | WatermarkAnchor::Center | WatermarkAnchor::CenterRight => {
+            (base_h - wm_h) / 2
+        }
+        WatermarkAnchor::BottomLeft
+        | WatermarkAnchor::BottomCenter
+        | WatermarkAnchor::BottomRight => base_h - wm_h - spacing_pixels,
+    };
+
+    (x, y)
+}
+
+fn apply_image_watermark(
     base_image: &mut DynamicImage,
+    watermark_path: &str,
     watermark_settings: &WatermarkSettings,
 ) -> Result<(), String> {
-    let watermark_img = image::open(&watermark_settings.path)
+    let watermark_img = image::open(watermark_path)
         .map_err(|e| format!("Failed to open watermark image: {}", e))?;
 
     let (base_w, base_h) = base_image.dimensions();
@@ -754,57 +1336,113 @@ fn apply_watermark(
     let final_watermark = DynamicImage::ImageRgba8(scaled_watermark_rgba);
 
     let spacing_pixels = (base_min_dim * (watermark_settings.spacing / 100.0)) as i64;
-    let (wm_w, wm_h) = final_watermark.dimensions();
-
-    let x = match watermark_settings.anchor {
-        WatermarkAnchor::TopLeft | WatermarkAnchor::CenterLeft | WatermarkAnchor::BottomLeft => {
-            spacing_pixels
-        }
-        WatermarkAnchor::TopCenter | WatermarkAnchor::Center | WatermarkAnchor::BottomCenter => {
-            (base_w as i64 - wm_w as i64) / 2
-        }
-        WatermarkAnchor::TopRight | WatermarkAnchor::CenterRight | WatermarkAnchor::BottomRight => {
-            base_w as i64 - wm_w as i64 - spacing_pixels
-        }
-    };
-
-    let y = match watermark_settings.anchor {
-        WatermarkAnchor::TopLeft | WatermarkAnchor::TopCenter | WatermarkAnchor::TopRight => {
-            spacing_pixels
-        }
-        WatermarkAnchor::CenterLeft | WatermarkAnchor::Center | WatermarkAnchor::CenterRight => {
-            (base_h as i64 - wm_h as i64) / 2
-        }
-        WatermarkAnchor::BottomLeft
-        | WatermarkAnchor::BottomCenter
-        | WatermarkAnchor::BottomRight => base_h as i64 - wm_h as i64 - spacing_pixels,
-    };
+    let (x, y) = watermark_position(
+        &watermark_settings.anchor,
+        (base_w, base_h),
+        final_watermark.dimensions(),
+        spacing_pixels,
+    );
 
     image::imageops::overlay(base_image, &final_watermark, x, y);
 
     Ok(())
 }
 
-fn get_cached_full_warped_image(
-    state: &tauri::State<AppState>,
-    js_adjustments: &serde_json::Value,
-) -> Result<Arc<DynamicImage>, String> {
-    let geo_hash = calculate_geometry_hash(js_adjustments);
+fn apply_text_watermark(
+    base_image: &mut DynamicImage,
+    text: &str,
+    watermark_settings: &WatermarkSettings,
+) -> Result<(), String> {
+    let font = FontRef::try_from_slice(WATERMARK_FONT)
+        .map_err(|e| format!("Failed to load watermark font: {}", e))?;
 
-    {
-        let cache_lock = state.full_warped_cache.lock().unwrap();
-        if let Some((hash, img)) = cache_lock.as_ref()
-            && *hash == geo_hash
-        {
-            return Ok(Arc::clone(img));
-        }
-    }
+    let (base_w, base_h) = base_image.dimensions();
+    let base_min_dim = base_w.min(base_h) as f32;
+    // font_size is the pixel height at a 1000px long edge, so the stamp keeps
+    // its proportions regardless of the resize mode.
+    let long_edge = base_w.max(base_h) as f32;
+    let scale = PxScale::from((long_edge * watermark_settings.font_size as f32 / 1000.0).max(1.0));
+
+    let (text_w, text_h) = text_size(scale, &font, text);
+    if text_w == 0 || text_h == 0 {
+        return Ok(());
+    }
+
+    let mut color = watermark_settings
+        .color
+        .as_deref()
+        .and_then(parse_hex_color)
+        .unwrap_or(Rgba([255, 255, 255, 255]));
+    let opacity_factor = (watermark_settings.opacity / 100.0).clamp(0.0, 1.0);
+    color[3] = (color[3] as f32 * opacity_factor) as u8;
+
+    let mut text_layer =
+        RgbaImage::from_pixel(text_w, text_h, Rgba([color[0], color[1], color[2], 0]));
+    draw_text_mut(&mut text_layer, color, 0, 0, scale, &font, text);
+
+    let spacing_pixels = (base_min_dim * (watermark_settings.spacing / 100.0)) as i64;
+    let (x, y) = watermark_position(
+        &watermark_settings.anchor,
+        (base_w, base_h),
+        (text_w, text_h),
+        spacing_pixels,
+    );
+
+    image::imageops::overlay(base_image, &DynamicImage::ImageRgba8(text_layer), x, y);
+
+    Ok(())
+}
+
+fn apply_watermark(
+    base_image: &mut DynamicImage,
+    watermark_settings: &WatermarkSettings,
+) -> Result<(), String> {
+    if let Some(path) = watermark_settings.path.as_deref().filter(|p| !p.is_empty()) {
+        apply_image_watermark(base_image, path, watermark_settings)?;
+    }
+
+    if let Some(text) = watermark_settings
+        .text
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+    {
+        apply_text_watermark(base_image, text, watermark_settings)?;
+    }
+
+    Ok(())
+}
+
+fn get_cached_full_warped_image(
+    state: &tauri::State<AppState>,
+    js_adjustments: &serde_json::Value,
+) -> Result<Arc<DynamicImage>, String> {
+    let params = image_processing::get_geometry_params_from_json(js_adjustments);
+    if image_processing::is_geometry_identity(&params) {
+        let original_image_lock = state.original_image.lock().unwrap();
+        let loaded_image = original_image_lock
+            .as_ref()
+            .ok_or("No original image loaded")?;
+        if !loaded_image.is_raw {
+            return Ok(Arc::clone(&loaded_image.image));
+        }
+    }
+
+    let geo_hash = calculate_geometry_hash(js_adjustments);
+
+    {
+        let cache_lock = state.full_warped_cache.lock().unwrap();
+        if let Some((hash, img)) = cache_lock.as_ref()
+            && *hash == geo_hash
+        {
+            return Ok(Arc::clone(img));
+        }
+    }
 
     let (mut full_image, is_raw) = get_full_image_for_processing(state)?;
     if is_raw {
         apply_cpu_default_raw_processing(&mut full_image);
     }
-    let warped_image = apply_geometry_warp(&full_image, js_adjustments);
+    let warped_image = apply_geometry_warp_owned(full_image, js_adjustments);
     let warped_arc = Arc::new(warped_image);
 
     {
@@ -889,9 +1527,12 @@ fn process_preview_job(
     roi: Option<(f32, f32, f32, f32)>,
     compute_waveform: bool,
     active_waveform_channel: Option<&str>,
+    waveform_mode: Option<&str>,
+    histogram_scale: Option<&str>,
 ) -> Result<Vec<u8>, String> {
     let fn_start = std::time::Instant::now();
-    let context = get_or_init_gpu_context(&state)?;
+    let context = get_or_init_gpu_context(&state, app_handle)?;
+    let preview_cache_adjustments = (!is_interactive).then(|| adjustments_json.clone());
     hydrate_adjustments(&state, &mut adjustments_json);
     let adjustments_clone = adjustments_json;
 
@@ -1022,12 +1663,12 @@ fn process_preview_job(
 
     let is_raw = loaded_image.is_raw;
     let final_adjustments = get_all_adjustments_from_json(&adjustments_clone, is_raw);
-    let lut_path = adjustments_clone["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+    let lut = get_adjustments_lut(&state, &adjustments_clone)?;
 
     let final_processed_image_result = process_and_get_dynamic_image(
         &context,
         &state,
+        app_handle,
         &processing_image,
         new_transform_hash,
         RenderRequest {
@@ -1043,6 +1684,9 @@ fn process_preview_job(
         let final_processed_image = Arc::new(final_processed_image);
 
         if !(is_interactive && pixel_roi.is_some()) {
+            *state.last_processed_preview.lock().unwrap() =
+                Some(Arc::clone(&final_processed_image));
+
             let channel_filter = if is_interactive {
                 active_waveform_channel.map(|s| s.to_string())
             } else {
@@ -1054,6 +1698,8 @@ fn process_preview_job(
                 image: Arc::clone(&final_processed_image),
                 compute_waveform,
                 active_waveform_channel: channel_filter,
+                waveform_mode: waveform_mode.map(|s| s.to_string()),
+                histogram_scale: histogram_scale.map(|s| s.to_string()),
             };
 
             if let Some(tx) = state.analytics_worker_tx.lock().unwrap().as_ref() {
@@ -1118,6 +1764,25 @@ fn process_preview_job(
                         step_start.elapsed(),
                         fn_start.elapsed()
                     );
+                    if let Some(cache_adjustments) = preview_cache_adjustments {
+                        let cache_handle = app_handle.clone();
+                        let cache_path = loaded_image.path.clone();
+                        let cache_bytes = bytes.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = file_management::store_processed_preview(
+                                &cache_handle,
+                                &cache_path,
+                                &cache_adjustments,
+                                &cache_bytes,
+                            ) {
+                                log::warn!(
+                                    "Failed to cache processed preview for '{}': {}",
+                                    cache_path,
+                                    e
+                                );
+                            }
+                        });
+                    }
                     return Ok(bytes);
                 }
                 Err(e) => {
@@ -1145,8 +1810,10 @@ fn start_analytics_worker(app_handle: tauri::AppHandle) {
                 job = latest;
             }
 
-            if let Ok(histogram_data) = image_processing::calculate_histogram_from_image(&job.image)
-            {
+            if let Ok(histogram_data) = image_processing::calculate_histogram_from_image(
+                &job.image,
+                job.histogram_scale.as_deref(),
+            ) {
                 let _ = app_handle.emit(
                     "histogram-update",
                     serde_json::json!({ "path": job.path, "data": histogram_data }),
@@ -1157,6 +1824,7 @@ fn start_analytics_worker(app_handle: tauri::AppHandle) {
                 && let Ok(waveform_data) = image_processing::calculate_waveform_from_image(
                     &job.image,
                     job.active_waveform_channel.as_deref(),
+                    job.waveform_mode.as_deref(),
                 )
             {
                 let _ = app_handle.emit(
@@ -1191,6 +1859,8 @@ fn start_preview_worker(app_handle: tauri::AppHandle) {
                 job.roi,
                 job.compute_waveform,
                 job.active_waveform_channel.as_deref(),
+                job.waveform_mode.as_deref(),
+                job.histogram_scale.as_deref(),
             ) {
                 Ok(bytes) => {
                     let _ = responder.send(bytes);
@@ -1203,6 +1873,7 @@ fn start_preview_worker(app_handle: tauri::AppHandle) {
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 async fn apply_adjustments(
     js_adjustments: serde_json::Value,
@@ -1211,6 +1882,8 @@ async fn apply_adjustments(
     roi: Option<(f32, f32, f32, f32)>,
     compute_waveform: bool,
     active_waveform_channel: Option<String>,
+    waveform_mode: Option<String>,
+    histogram_scale: Option<String>,
     state: tauri::State<'_, AppState>,
 ) -> Result<Response, String> {
     let (tx, rx) = tokio::sync::oneshot::channel();
@@ -1225,6 +1898,8 @@ async fn apply_adjustments(
                 roi,
                 compute_waveform,
                 active_waveform_channel,
+                waveform_mode,
+                histogram_scale,
                 responder: tx,
             };
             worker_tx
@@ -1247,7 +1922,7 @@ fn generate_uncropped_preview(
     state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    let context = get_or_init_gpu_context(&state)?;
+    let context = get_or_init_gpu_context(&state, &app_handle)?;
     let mut adjustments_clone = js_adjustments.clone();
     hydrate_adjustments(&state, &mut adjustments_clone);
 
@@ -1272,7 +1947,7 @@ fn generate_uncropped_preview(
                 }
             };
 
-        let warped_image = apply_geometry_warp(&patched_image, &adjustments_clone);
+        let warped_image = apply_geometry_warp_owned(patched_image, &adjustments_clone);
 
         let orientation_steps = adjustments_clone["orientationSteps"].as_u64().unwrap_or(0) as u8;
         let coarse_rotated_image = apply_coarse_rotation(warped_image, orientation_steps);
@@ -1325,12 +2000,18 @@ fn generate_uncropped_preview(
             .collect();
 
         let uncropped_adjustments = get_all_adjustments_from_json(&adjustments_clone, is_raw);
-        let lut_path = adjustments_clone["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+        let lut = match get_adjustments_lut(&state, &adjustments_clone) {
+            Ok(lut) => lut,
+            Err(e) => {
+                log::error!("Skipping uncropped preview: {}", e);
+                return;
+            }
+        };
 
         if let Ok(processed_image) = process_and_get_dynamic_image(
             &context,
             &state,
+            &app_handle,
             &processing_base,
             unique_hash,
             RenderRequest {
@@ -1410,6 +2091,118 @@ fn generate_original_transformed_preview(
     Ok(format!("data:image/jpeg;base64,{}", base64_str))
 }
 
+#[tauri::command]
+async fn get_embedded_preview(path: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let (source_path, _) = parse_virtual_path(&path);
+        let source_path_str = source_path.to_string_lossy().to_string();
+        if !is_raw_file(&source_path_str) {
+            return Err("Embedded previews are only available for RAW files.".to_string());
+        }
+
+        let bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+        let preview =
+            raw_processing::extract_embedded_preview(&bytes).map_err(|e| e.to_string())?;
+
+        let (width, height) = preview.dimensions();
+        let rgb_pixels = preview.to_rgb8().into_vec();
+
+        let bytes = Encoder::new(Preset::BaselineFastest)
+            .quality(92)
+            .encode_rgb(&rgb_pixels, width, height)
+            .map_err(|e| format!("Failed to encode with mozjpeg-rs: {}", e))?;
+
+        let base64_str = general_purpose::STANDARD.encode(&bytes);
+        Ok(format!("data:image/jpeg;base64,{}", base64_str))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn load_processed_image_for_scopes(
+    path: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<DynamicImage, String> {
+    let state = app_handle.state::<AppState>();
+    let gpu_context = get_or_init_gpu_context(&state, app_handle).ok();
+    file_management::get_cached_or_generate_thumbnail_image(path, app_handle, gpu_context.as_ref())
+        .map_err(|e| e.to_string())
+}
+
+/// `stage` is `"output"` (default) for the edited preview or `"input"` for the developed
+/// original before any adjustments, bucketed on its linear values clamped to [0, 1].
+#[tauri::command]
+async fn generate_histogram(
+    stage: Option<String>,
+    scale: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<image_processing::HistogramData, String> {
+    let image = match stage.as_deref().unwrap_or("output") {
+        "output" => state
+            .last_processed_preview
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("No processed preview available")?,
+        "input" => state
+            .original_image
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|loaded| loaded.image.clone())
+            .ok_or("No image loaded")?,
+        other => return Err(format!("Unknown histogram stage: {}", other)),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let image = image_processing::downscale_f32_image(&image, 2048, 2048);
+        image_processing::calculate_histogram_from_image(&image, scale.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn generate_histogram_for_path(
+    path: String,
+    scale: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<image_processing::HistogramData, String> {
+    tokio::task::spawn_blocking(move || {
+        let image = load_processed_image_for_scopes(&path, &app_handle)?;
+        image_processing::calculate_histogram_from_image(&image, scale.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn generate_waveform_for_path(
+    path: String,
+    active_channel: Option<String>,
+    mode: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<image_processing::WaveformData, String> {
+    tokio::task::spawn_blocking(move || {
+        let image = load_processed_image_for_scopes(&path, &app_handle)?;
+        image_processing::calculate_waveform_from_image(
+            &image,
+            active_channel.as_deref(),
+            mode.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn generate_vectorscope_for_path(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<image_processing::WaveformData, String> {
+    generate_waveform_for_path(path, Some("vectorscope".to_string()), None, app_handle).await
+}
+
 #[tauri::command]
 async fn preview_geometry_transform(
     params: GeometryParams,
@@ -1437,7 +2230,7 @@ async fn preview_geometry_transform(
         if let Some(cached_image) = maybe_cached_image {
             cached_image
         } else {
-            let context = get_or_init_gpu_context(&state)?;
+            let context = get_or_init_gpu_context(&state, &app_handle)?;
 
             let original_image = {
                 let guard = state.original_image.lock().unwrap();
@@ -1460,40 +2253,23 @@ async fn preview_geometry_transform(
             hydrate_adjustments(&state, &mut temp_adjustments);
 
             if let Some(obj) = temp_adjustments.as_object_mut() {
-                obj.insert("crop".to_string(), serde_json::Value::Null);
-                obj.insert("rotation".to_string(), serde_json::json!(0.0));
-                obj.insert("orientationSteps".to_string(), serde_json::json!(0));
-                obj.insert("flipHorizontal".to_string(), serde_json::json!(false));
-                obj.insert("flipVertical".to_string(), serde_json::json!(false));
-                for key in GEOMETRY_KEYS {
-                    match *key {
-                        "transformScale"
-                        | "lensDistortionAmount"
-                        | "lensVignetteAmount"
-                        | "lensTcaAmount" => {
-                            obj.insert(key.to_string(), serde_json::json!(100.0));
-                        }
-                        "lensDistortionParams" | "lensMaker" | "lensModel" => {
-                            obj.insert(key.to_string(), serde_json::Value::Null);
-                        }
-                        "lensDistortionEnabled" | "lensTcaEnabled" | "lensVignetteEnabled" => {
-                            obj.insert(key.to_string(), serde_json::json!(true));
-                        }
-                        _ => {
-                            obj.insert(key.to_string(), serde_json::json!(0.0));
-                        }
-                    }
+                for def in adjustment_registry::ADJUSTMENTS
+                    .iter()
+                    .filter(|d| d.geometry)
+                {
+                    let default = def.default.to_value().unwrap_or(serde_json::Value::Null);
+                    obj.insert(def.key.to_string(), default);
                 }
             }
 
             let all_adjustments = get_all_adjustments_from_json(&temp_adjustments, is_raw);
-            let lut_path = temp_adjustments["lutPath"].as_str();
-            let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+            let lut = get_adjustments_lut(&state, &temp_adjustments)?;
             let mask_bitmaps = Vec::new();
 
             let processed_base = process_and_get_dynamic_image(
                 &context,
                 &state,
+                &app_handle,
                 &preview_base,
                 visual_hash,
                 RenderRequest {
@@ -1670,13 +2446,32 @@ fn apply_export_resize_and_watermark(
     Ok(image)
 }
 
+const GPU_REQUIRED_FOR_LUT_EXPORT: &str = "Exporting a LUT requires a GPU.";
+const GPU_REQUIRED_FOR_MASK_EXPORT: &str = "Exporting masks requires a GPU.";
+
+fn gpu_context_or_cpu_fallback(
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+) -> Option<Arc<GpuContext>> {
+    match get_or_init_gpu_context(state, app_handle) {
+        Ok(context) => Some(Arc::new(context)),
+        Err(e) => {
+            log::warn!("GPU unavailable, exporting with CPU processing: {}", e);
+            None
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn process_image_for_export_pipeline(
     path: &str,
     base_image: &DynamicImage,
     js_adjustments: &Value,
-    context: &GpuContext,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
     is_raw: bool,
+    color_space: ExportColorSpace,
     debug_tag: &str,
 ) -> Result<DynamicImage, String> {
     let (transformed_image, unscaled_crop_offset) =
@@ -1705,15 +2500,25 @@ fn process_image_for_export_pipeline(
 
     let mut all_adjustments = get_all_adjustments_from_json(js_adjustments, is_raw);
     all_adjustments.global.show_clipping = 0;
+    all_adjustments.global.output_primaries = color_space.output_primaries();
 
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+    let lut = get_adjustments_lut(state, js_adjustments)?;
+
+    let Some(context) = context else {
+        return Ok(image_processing::process_image_cpu(
+            &transformed_image,
+            &all_adjustments,
+            &mask_bitmaps,
+            lut.as_deref(),
+        ));
+    };
 
     let unique_hash = calculate_full_job_hash(path, js_adjustments);
 
     process_and_get_dynamic_image(
         context,
         state,
+        app_handle,
         &transformed_image,
         unique_hash,
         RenderRequest {
@@ -1738,7 +2543,7 @@ fn save_image_with_metadata(
         .unwrap_or("")
         .to_lowercase();
 
-    let mut image_bytes = encode_image_to_bytes(image, &extension, export_settings.jpeg_quality)?;
+    let mut image_bytes = encode_image_to_bytes(image, &extension, export_settings)?;
 
     exif_processing::write_image_with_metadata(
         &mut image_bytes,
@@ -1752,13 +2557,15 @@ fn save_image_with_metadata(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_image_for_export(
     path: &str,
     base_image: &DynamicImage,
     js_adjustments: &Value,
     export_settings: &ExportSettings,
-    context: &GpuContext,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
     is_raw: bool,
 ) -> Result<DynamicImage, String> {
     let processed_image = process_image_for_export_pipeline(
@@ -1767,13 +2574,130 @@ fn process_image_for_export(
         js_adjustments,
         context,
         state,
+        app_handle,
         is_raw,
+        ExportColorSpace::from_name(&export_settings.color_space)?,
         "process_image_for_export",
     )?;
 
     apply_export_resize_and_watermark(processed_image, export_settings)
 }
 
+const MAX_CACHED_FULL_RENDERS: usize = 8;
+
+/// Path in the app cache that full renders of `path` are written to. One file per
+/// source, and only the most recent renders are kept.
+fn full_render_cache_path(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let dir = file_management::resolve_app_cache_dir(app_handle)?.join("renders");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut renders: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    renders.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, stale) in renders.iter().skip(MAX_CACHED_FULL_RENDERS - 1) {
+        let _ = fs::remove_file(stale);
+    }
+
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    Ok(dir.join(format!("{:016x}.png", hasher.finish())))
+}
+
+/// Renders the image with its saved edits at full resolution and writes it to a PNG
+/// in the app cache, returning that file's path.
+#[tauri::command]
+async fn render_full_image(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let (source_path, _) = parse_virtual_path(&path);
+        let source_path_str = source_path.to_string_lossy().to_string();
+
+        let mut js_adjustments =
+            file_management::read_sidecar_metadata(&path, &app_handle)?.adjustments;
+        hydrate_adjustments(&state, &mut js_adjustments);
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+
+        let cached_original = state
+            .original_image
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|loaded| loaded.path == path)
+            .map(|loaded| (loaded.image.clone(), loaded.is_raw));
+
+        let (base_image, is_raw) = match cached_original {
+            Some((image, is_raw)) => (
+                composite_patches_on_image(&image, &js_adjustments)
+                    .map_err(|e| format!("Failed to composite AI patches: {}", e))?,
+                is_raw,
+            ),
+            None => {
+                let load = |bytes: &[u8]| {
+                    load_and_composite(
+                        bytes,
+                        &source_path_str,
+                        &js_adjustments,
+                        false,
+                        settings.raw_highlight_compression.unwrap_or(2.5),
+                        settings.linear_raw_mode.clone(),
+                        None,
+                    )
+                    .map_err(|e| format!("Failed to load image: {}", e))
+                };
+                let image = match read_file_mapped(&source_path) {
+                    Ok(mmap) => load(&mmap)?,
+                    Err(_) => load(&fs::read(&source_path).map_err(|e| e.to_string())?)?,
+                };
+                (image, is_raw_file(&source_path_str))
+            }
+        };
+
+        let (width, height) = base_image.dimensions();
+        let context = match gpu_processing::check_gpu_memory_budget(
+            width,
+            height,
+            settings.gpu_tile_size,
+            rendered_mask_count(&js_adjustments),
+            settings.gpu_memory_budget_mb,
+        ) {
+            Ok(()) => gpu_context_or_cpu_fallback(&state, &app_handle),
+            Err(e) => {
+                log::warn!("{} Rendering with CPU processing.", e);
+                None
+            }
+        };
+        let processed = process_image_for_export_pipeline(
+            &source_path_str,
+            &base_image,
+            &js_adjustments,
+            context.as_deref(),
+            &state,
+            &app_handle,
+            is_raw,
+            ExportColorSpace::Srgb,
+            "render_full_image",
+        )?;
+
+        let render_path = full_render_cache_path(&app_handle, &path)?;
+        let parent = render_path.parent().ok_or("Invalid render cache path")?;
+        let temp_file = NamedTempFile::new_in(parent).map_err(|e| e.to_string())?;
+        processed
+            .write_to(
+                &mut std::io::BufWriter::new(temp_file.as_file()),
+                ImageFormat::Png,
+            )
+            .map_err(|e| e.to_string())?;
+        temp_file.persist(&render_path).map_err(|e| e.to_string())?;
+        Ok(render_path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 fn build_single_mask_adjustments(all: &AllAdjustments, mask_index: usize) -> AllAdjustments {
     let mut single = AllAdjustments {
         global: all.global,
@@ -1799,15 +2723,56 @@ fn encode_grayscale_to_png(bitmap: &GrayImage) -> Result<Vec<u8>, String> {
     Ok(buf)
 }
 
+fn convert_to_bit_depth(image: &DynamicImage, bit_depth: u8) -> Result<DynamicImage, String> {
+    let has_alpha = image.color().has_alpha();
+    match (bit_depth, has_alpha) {
+        (8, false) => Ok(DynamicImage::ImageRgb8(image.to_rgb8())),
+        (8, true) => Ok(DynamicImage::ImageRgba8(image.to_rgba8())),
+        (16, false) => Ok(DynamicImage::ImageRgb16(image.to_rgb16())),
+        (16, true) => Ok(DynamicImage::ImageRgba16(image.to_rgba16())),
+        _ => Err(format!("Unsupported bit depth: {}", bit_depth)),
+    }
+}
+
+#[cfg(feature = "avif")]
+const AVIF_ENCODER_SPEED: u8 = 6;
+
+fn resolve_export_format(requested: &str) -> (String, Option<String>) {
+    let requested = requested.to_lowercase();
+    if requested == "avif" && !cfg!(feature = "avif") {
+        return (
+            "webp".to_string(),
+            Some(
+                "AVIF encoding is not available in this build, exporting as WebP instead."
+                    .to_string(),
+            ),
+        );
+    }
+    (requested, None)
+}
+
 fn encode_image_to_bytes(
     image: &DynamicImage,
     output_format: &str,
-    jpeg_quality: u8,
+    export_settings: &ExportSettings,
 ) -> Result<Vec<u8>, String> {
+    let jpeg_quality = export_settings.jpeg_quality;
+    let bit_depth = export_settings.bit_depth;
+    let color_space = ExportColorSpace::from_name(&export_settings.color_space)?;
+    let format = output_format.to_lowercase();
+    // The pipeline already rendered Display P3 pixels; only PNG and JPEG can say so.
+    if color_space == ExportColorSpace::DisplayP3
+        && !matches!(format.as_str(), "jpg" | "jpeg" | "png")
+    {
+        return Err(format!(
+            "Display P3 export is only supported for JPEG and PNG, not {}.",
+            output_format.to_uppercase()
+        ));
+    }
     let mut image_bytes = Vec::new();
     let mut cursor = Cursor::new(&mut image_bytes);
 
-    match output_format.to_lowercase().as_str() {
+    match format.as_str() {
         "jxl" => {
             let (width, height) = image.dimensions();
             let has_alpha = image.color().has_alpha();
@@ -1844,31 +2809,62 @@ fn encode_image_to_bytes(
             return Ok(jxl_data);
         }
         "webp" => {
-            let encoder = webp::Encoder::from_image(image)
+            let image_8bit = convert_to_bit_depth(image, 8)?;
+            let encoder = webp::Encoder::from_image(&image_8bit)
                 .map_err(|_| "Failed to create WebP encoder".to_string())?;
-            let webp_mem = encoder.encode(jpeg_quality as f32);
+            let webp_mem = if export_settings.lossless {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(jpeg_quality.min(100) as f32)
+            };
             return Ok(webp_mem.to_vec());
         }
+        #[cfg(feature = "avif")]
+        "avif" => {
+            // Maximum quality rather than lossless; the encoder has no lossless mode.
+            let quality = if export_settings.lossless {
+                100
+            } else {
+                jpeg_quality.clamp(1, 100)
+            };
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                &mut cursor,
+                AVIF_ENCODER_SPEED,
+                quality,
+            );
+            convert_to_bit_depth(image, 8)?
+                .write_with_encoder(encoder)
+                .map_err(|e| e.to_string())?;
+        }
         "jpg" | "jpeg" => {
             let rgb_image = image.to_rgb8();
-            let encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            let mut encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            encoder
+                .set_icc_profile(color_space.icc_profile())
+                .map_err(|e| e.to_string())?;
             rgb_image
                 .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
         "png" => {
-            let image_to_encode = if image.as_rgb32f().is_some() {
-                DynamicImage::ImageRgb16(image.to_rgb16())
-            } else {
-                image.clone()
+            let image_to_encode = match bit_depth {
+                Some(depth) => convert_to_bit_depth(image, depth)?,
+                None if image.as_rgb32f().is_some() || image.as_rgba32f().is_some() => {
+                    DynamicImage::ImageRgb16(image.to_rgb16())
+                }
+                None => image.clone(),
             };
 
+            let mut encoder = PngEncoder::new(&mut cursor);
+            encoder
+                .set_icc_profile(color_space.icc_profile())
+                .map_err(|e| e.to_string())?;
             image_to_encode
-                .write_to(&mut cursor, image::ImageFormat::Png)
+                .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
-        "tiff" => {
-            DynamicImage::ImageRgb16(image.to_rgb16())
+        "tiff" | "tif" => {
+            convert_to_bit_depth(image, bit_depth.unwrap_or(16))?
                 .write_to(&mut cursor, image::ImageFormat::Tiff)
                 .map_err(|e| e.to_string())?;
         }
@@ -1886,6 +2882,7 @@ fn export_masks_for_image(
     source_path_str: &str,
     context: &Arc<GpuContext>,
     state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
     is_raw: bool,
 ) -> Result<(), String> {
     let (transformed_image, unscaled_crop_offset) =
@@ -1913,8 +2910,7 @@ fn export_masks_for_image(
 
     if !mask_bitmaps.is_empty() {
         let all_adjustments = get_all_adjustments_from_json(js_adjustments, is_raw);
-        let lut_path = js_adjustments["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+        let lut = get_adjustments_lut(state, js_adjustments)?;
         let unique_hash = calculate_full_job_hash(source_path_str, js_adjustments);
         let output_dir = output_path_obj.parent().unwrap_or(output_path_obj);
         let stem = output_path_obj
@@ -1926,7 +2922,11 @@ fn export_masks_for_image(
             .and_then(|s| s.to_str())
             .unwrap_or("jpg");
 
-        for (i, _) in mask_bitmaps.iter().enumerate() {
+        for (i, _) in mask_bitmaps
+            .iter()
+            .enumerate()
+            .take(image_processing::MAX_MASK_COUNT)
+        {
             let single_adjustments = build_single_mask_adjustments(&all_adjustments, i);
             let full_white_mask = ImageBuffer::from_fn(img_w, img_h, |_, _| Luma([255u8]));
             let single_bitmaps: Vec<ImageBuffer<Luma<u8>, Vec<u8>>> = vec![full_white_mask];
@@ -1934,6 +2934,7 @@ fn export_masks_for_image(
             let processed = process_and_get_dynamic_image(
                 context,
                 state,
+                app_handle,
                 &transformed_image,
                 unique_hash,
                 RenderRequest {
@@ -1973,11 +2974,141 @@ fn export_masks_for_image(
     Ok(())
 }
 
+/// Output size and crop offset of `apply_all_transformations` for a source of the given
+/// size, without touching pixels. The warp and fine rotation keep the canvas size.
+fn transformed_dimensions(
+    width: u32,
+    height: u32,
+    adjustments: &Value,
+) -> ((u32, u32), (f32, f32)) {
+    let orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0);
+    let (mut w, mut h) = if matches!(orientation_steps, 1 | 3) {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let crop: Option<Crop> = serde_json::from_value(adjustments["crop"].clone()).ok();
+    if let Some(crop) = &crop {
+        let crop_w = crop.width.round().max(0.0) as u32;
+        let crop_h = crop.height.round().max(0.0) as u32;
+        let x = crop.x.round() as u32;
+        let y = crop.y.round() as u32;
+        if crop_w > 0 && crop_h > 0 {
+            if crop.canvas_color.is_some() {
+                (w, h) = (crop_w, crop_h);
+            } else if x < w && y < h {
+                (w, h) = ((w - x).min(crop_w), (h - y).min(crop_h));
+            }
+        }
+    }
+
+    let crop_offset = crop.map_or((0.0, 0.0), |c| (c.x as f32, c.y as f32));
+    ((w, h), crop_offset)
+}
+
+/// Source size of `path` and, for masks that sample pixels, the warped source to generate
+/// them from. Uses the editor's image when `path` is open and decodes the file otherwise.
+fn load_mask_source(
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    adjustments: &Value,
+    mask_def: &MaskDefinition,
+) -> Result<((u32, u32), Option<Arc<DynamicImage>>), String> {
+    let open_dimensions = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|loaded| loaded.path == path)
+        .map(|loaded| loaded.image.dimensions());
+    if let Some(dimensions) = open_dimensions {
+        let warped_image =
+            resolve_warped_image_for_masks(state, adjustments, std::slice::from_ref(mask_def));
+        return Ok((dimensions, warped_image));
+    }
+
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
+    let (source_path, _) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let bytes =
+        fs::read(&source_path).map_err(|e| format!("Failed to read {}: {}", source_path_str, e))?;
+    let mut image = load_and_composite(
+        &bytes,
+        &source_path_str,
+        adjustments,
+        false,
+        highlight_compression,
+        settings.linear_raw_mode,
+        None,
+    )
+    .map_err(|e| format!("Failed to load {}: {}", source_path_str, e))?;
+    let dimensions = image.dimensions();
+    if !mask_def.requires_warped_image() {
+        return Ok((dimensions, None));
+    }
+
+    if is_raw_file(&source_path_str) {
+        apply_cpu_default_raw_processing(&mut image);
+    }
+    let warped_image = apply_geometry_warp_owned(image, adjustments);
+    Ok((dimensions, Some(Arc::new(warped_image))))
+}
+
+/// Writes one of the image's masks as a grayscale PNG at full export resolution, after
+/// crop and rotation, so it lines up with the exported pixels.
+#[tauri::command]
+async fn export_mask_bitmap(
+    path: String,
+    mask_id: String,
+    dest: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let adjustments = file_management::read_sidecar_metadata(&path, &app_handle)?.adjustments;
+        let masks: Vec<MaskDefinition> = adjustments
+            .get("masks")
+            .and_then(|m| serde_json::from_value(m.clone()).ok())
+            .unwrap_or_default();
+        let mut mask_def = masks.into_iter().find(|m| m.id == mask_id).ok_or_else(|| {
+            format!(
+                "Mask '{}' was not found in the sidecar of {}",
+                mask_id, path
+            )
+        })?;
+        mask_def.visible = true;
+
+        let ((source_w, source_h), warped_image) =
+            load_mask_source(&state, &app_handle, &path, &adjustments, &mask_def)?;
+        let ((width, height), crop_offset) =
+            transformed_dimensions(source_w, source_h, &adjustments);
+
+        let bitmap = generate_mask_bitmap(
+            &mask_def,
+            width,
+            height,
+            1.0,
+            crop_offset,
+            warped_image.as_deref(),
+        )
+        .ok_or_else(|| format!("Mask '{}' has no sub-masks to export", mask_def.name))?;
+
+        let png_bytes = encode_grayscale_to_png(&bitmap)?;
+        fs::write(&dest, png_bytes).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 fn export_adjustments_as_lut(
     js_adjustments: &Value,
     source_path_str: &str,
     context: &Arc<GpuContext>,
     state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
 ) -> Result<Vec<u8>, String> {
     let lut_size = 33;
     let identity_image = generate_identity_lut_image(lut_size);
@@ -2000,13 +3131,13 @@ fn export_adjustments_as_lut(
     all_adjustments.global.chromatic_aberration_red_cyan = 0.0;
     all_adjustments.global.chromatic_aberration_blue_yellow = 0.0;
 
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+    let lut = get_adjustments_lut(state, js_adjustments)?;
     let unique_hash = calculate_full_job_hash(source_path_str, js_adjustments);
 
     let processed_lut = process_and_get_dynamic_image(
         context,
         state,
+        app_handle,
         &identity_image,
         unique_hash,
         RenderRequest {
@@ -2036,11 +3167,35 @@ async fn export_image(
         return Err("An export is already in progress.".to_string());
     }
 
-    let context = get_or_init_gpu_context(&state)?;
+    let context = gpu_context_or_cpu_fallback(&state, &app_handle);
     let (original_image_data, is_raw) = get_full_image_for_processing(&state)?;
-    let context = Arc::new(context);
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let (width, height) = original_image_data.dimensions();
+    gpu_processing::check_gpu_memory_budget(
+        width,
+        height,
+        settings.gpu_tile_size,
+        rendered_mask_count(&js_adjustments),
+        settings.gpu_memory_budget_mb,
+    )?;
 
-    let task = tokio::spawn(async move {
+    let requested_extension = std::path::Path::new(&output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string();
+    let (export_format, format_fallback) = resolve_export_format(&requested_extension);
+    let output_path = if let Some(message) = format_fallback {
+        let _ = app_handle.emit("export-error", message);
+        std::path::Path::new(&output_path)
+            .with_extension(&export_format)
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        output_path
+    };
+
+    start_export_task(&state, async move {
         let state = app_handle.state::<AppState>();
         let processing_result: Result<(), String> = (|| {
             let (source_path, _) = parse_virtual_path(&original_path);
@@ -2054,8 +3209,14 @@ async fn export_image(
                 .to_lowercase();
 
             if extension == "cube" {
-                let cube_bytes =
-                    export_adjustments_as_lut(&js_adjustments, &source_path_str, &context, &state)?;
+                let context = context.as_ref().ok_or(GPU_REQUIRED_FOR_LUT_EXPORT)?;
+                let cube_bytes = export_adjustments_as_lut(
+                    &js_adjustments,
+                    &source_path_str,
+                    context,
+                    &state,
+                    &app_handle,
+                )?;
                 fs::write(output_path_obj, cube_bytes).map_err(|e| e.to_string())?;
                 return Ok(());
             }
@@ -2075,8 +3236,9 @@ async fn export_image(
                 &base_image,
                 &main_export_adjustments,
                 &export_settings,
-                &context,
+                context.as_deref(),
                 &state,
+                &app_handle,
                 is_raw,
             )?;
 
@@ -2094,8 +3256,9 @@ async fn export_image(
                     &export_settings,
                     output_path_obj,
                     &source_path_str,
-                    &context,
+                    context.as_ref().ok_or(GPU_REQUIRED_FOR_MASK_EXPORT)?,
                     &state,
+                    &app_handle,
                     is_raw,
                 )?;
             }
@@ -2116,280 +3279,530 @@ async fn export_image(
             .export_task_handle
             .lock()
             .unwrap() = None;
-    });
+    })
+}
 
-    *state.export_task_handle.lock().unwrap() = Some(task);
+/// Spawns `task` as the running export. The slot is checked and filled under one
+/// lock so two exports can't both start, and the task can't clear it before it is set.
+fn start_export_task(
+    state: &AppState,
+    task: impl std::future::Future<Output = ()> + Send + 'static,
+) -> Result<(), String> {
+    let mut handle = state.export_task_handle.lock().unwrap();
+    if handle.is_some() {
+        return Err("An export is already in progress.".to_string());
+    }
+    *handle = Some(tokio::spawn(task));
     Ok(())
 }
 
-#[tauri::command]
-async fn batch_export_images(
-    output_folder: String,
-    paths: Vec<String>,
-    export_settings: ExportSettings,
-    output_format: String,
-    state: tauri::State<'_, AppState>,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+struct BatchExportItem {
+    index: usize,
+    path: String,
+    appearance_count: usize,
+    explicit_vc: Option<u32>,
+    output_path: PathBuf,
+}
 
-    if state.export_task_handle.lock().unwrap().is_some() {
-        return Err("An export is already in progress.".to_string());
+struct BatchExportJob<'a> {
+    app_handle: &'a tauri::AppHandle,
+    context: Option<&'a Arc<GpuContext>>,
+    export_settings: &'a ExportSettings,
+    output_folder: &'a Path,
+    output_format: &'a str,
+    progress_event: &'static str,
+    total_paths: usize,
+    highlight_compression: f32,
+    linear_mode: String,
+    gpu_tile_size: Option<u32>,
+    gpu_memory_budget_mb: Option<u32>,
+}
+
+fn collect_batch_export_items(paths: Vec<String>) -> Vec<BatchExportItem> {
+    let mut base_path_counts: HashMap<String, usize> = HashMap::new();
+    let mut export_items = Vec::with_capacity(paths.len());
+
+    for (i, path_str) in paths.into_iter().enumerate() {
+        let (source_path, _) = parse_virtual_path(&path_str);
+        let source_str = source_path.to_string_lossy().to_string();
+        let count = base_path_counts.entry(source_str.clone()).or_insert(0);
+        *count += 1;
+
+        let mut explicit_vc = None;
+        if let Some(idx) = path_str.rfind("vc=") {
+            let id_str = path_str[idx + 3..].split('&').next().unwrap_or("");
+            if let Ok(id) = id_str.parse::<u32>() {
+                explicit_vc = Some(id);
+            }
+        }
+        if explicit_vc.is_none() {
+            let lower = path_str.to_lowercase();
+            if let Some(idx) = lower.rfind("_vc") {
+                let id_str: String = lower[idx + 3..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                if let Ok(id) = id_str.parse::<u32>() {
+                    explicit_vc = Some(id);
+                }
+            }
+        }
+
+        export_items.push(BatchExportItem {
+            index: i,
+            path: path_str,
+            appearance_count: *count,
+            explicit_vc,
+            output_path: PathBuf::new(),
+        });
     }
 
-    let context = get_or_init_gpu_context(&state)?;
-    let context = Arc::new(context);
-    let progress_counter = Arc::new(AtomicUsize::new(0));
+    export_items
+}
+
+fn batch_output_path(job: &BatchExportJob, item: &BatchExportItem) -> PathBuf {
+    let (source_path, _) = parse_virtual_path(&item.path);
+    let file_date = exif_processing::get_creation_date_from_path(&source_path);
+
+    let filename_template = job
+        .export_settings
+        .filename_template
+        .as_deref()
+        .unwrap_or("{original_filename}_edited");
+    let mut new_stem = generate_filename_from_template(
+        filename_template,
+        &source_path,
+        item.index + 1,
+        job.total_paths,
+        &file_date,
+    );
 
+    if let Some(vc_id) = item.explicit_vc {
+        new_stem = format!("{}_VC{:02}", new_stem, vc_id);
+    } else if item.appearance_count > 1 {
+        new_stem = format!("{}_VC{:02}", new_stem, item.appearance_count - 1);
+    }
+
+    job.output_folder
+        .join(format!("{}.{}", new_stem, job.output_format))
+}
+
+/// Names every output before any worker starts, numbering names the template
+/// produces more than once so parallel workers never write the same file.
+fn assign_batch_output_paths(items: &mut [BatchExportItem], job: &BatchExportJob) {
+    items
+        .par_iter_mut()
+        .for_each(|item| item.output_path = batch_output_path(job, item));
+
+    let mut planned = HashSet::new();
+    for item in items.iter_mut() {
+        let stem = item
+            .output_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut candidate = item.output_path.clone();
+        let mut suffix = 2;
+        while !planned.insert(candidate.clone()) {
+            candidate = item
+                .output_path
+                .with_file_name(format!("{}_{}.{}", stem, suffix, job.output_format));
+            suffix += 1;
+        }
+        item.output_path = candidate;
+    }
+}
+
+fn export_batch_item(job: &BatchExportJob, item: &BatchExportItem) -> Result<(), String> {
+    let state = job.app_handle.state::<AppState>();
+    let (source_path, sidecar_path) = parse_virtual_path(&item.path);
+    let sidecar_path = resolve_sidecar_path(job.app_handle, &sidecar_path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+
+    let metadata: ImageMetadata = if sidecar_path.exists() {
+        let file_content = fs::read_to_string(sidecar_path)
+            .map_err(|e| format!("Failed to read sidecar: {}", e))?;
+        serde_json::from_str(&file_content).unwrap_or_default()
+    } else {
+        ImageMetadata::default()
+    };
+    let mut js_adjustments = metadata.adjustments;
+    hydrate_adjustments(&state, &mut js_adjustments);
+    let is_raw = is_raw_file(&source_path_str);
+
+    let output_path = &item.output_path;
+    let extension = job.output_format.to_lowercase();
+
+    if extension == "cube" {
+        let context = job.context.ok_or(GPU_REQUIRED_FOR_LUT_EXPORT)?;
+        let cube_bytes = export_adjustments_as_lut(
+            &js_adjustments,
+            &source_path_str,
+            context,
+            &state,
+            job.app_handle,
+        )?;
+        fs::write(output_path, cube_bytes).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let base_image = match read_file_mapped(Path::new(&source_path_str)) {
+        Ok(mmap) => load_and_composite(
+            &mmap,
+            &source_path_str,
+            &js_adjustments,
+            false,
+            job.highlight_compression,
+            job.linear_mode.clone(),
+            None,
+        )
+        .map_err(|e| format!("Failed to load image from mmap: {}", e))?,
+        Err(e) => {
+            log::warn!(
+                "Failed to memory-map file '{}': {}. Falling back to standard read.",
+                source_path_str,
+                e
+            );
+            let bytes = fs::read(&source_path_str).map_err(|io_err| {
+                format!("Fallback read failed for {}: {}", source_path_str, io_err)
+            })?;
+            load_and_composite(
+                &bytes,
+                &source_path_str,
+                &js_adjustments,
+                false,
+                job.highlight_compression,
+                job.linear_mode.clone(),
+                None,
+            )
+            .map_err(|e| format!("Failed to load image from bytes: {}", e))?
+        }
+    };
+
+    let (width, height) = base_image.dimensions();
+    gpu_processing::check_gpu_memory_budget(
+        width,
+        height,
+        job.gpu_tile_size,
+        rendered_mask_count(&js_adjustments),
+        job.gpu_memory_budget_mb,
+    )?;
+
+    let mut main_export_adjustments = js_adjustments.clone();
+    if job.export_settings.export_masks
+        && let Some(obj) = main_export_adjustments.as_object_mut()
+    {
+        obj.insert("masks".to_string(), serde_json::json!([]));
+    }
+
+    let final_image = process_image_for_export(
+        &source_path_str,
+        &base_image,
+        &main_export_adjustments,
+        job.export_settings,
+        job.context.map(|context| context.as_ref()),
+        &state,
+        job.app_handle,
+        is_raw,
+    )?;
+
+    save_image_with_metadata(
+        &final_image,
+        output_path,
+        &source_path_str,
+        job.export_settings,
+    )?;
+
+    if job.export_settings.export_masks {
+        export_masks_for_image(
+            &base_image,
+            &js_adjustments,
+            job.export_settings,
+            output_path,
+            &source_path_str,
+            job.context.ok_or(GPU_REQUIRED_FOR_MASK_EXPORT)?,
+            &state,
+            job.app_handle,
+            is_raw,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Exports every item on a dedicated pool, skipping the rest once the export is cancelled.
+fn run_batch_export(
+    job: &BatchExportJob,
+    paths: Vec<String>,
+) -> Result<Vec<(String, Result<(), String>)>, String> {
     let available_cores = std::thread::available_parallelism()
         .map(|n| n.get())
         .unwrap_or(1);
     let num_threads = (available_cores / 2).clamp(1, 4);
-
     log::info!(
         "Starting batch export. System cores: {}, Export threads: {}",
         available_cores,
         num_threads
     );
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|e| format!("Failed to initialize worker threads: {}", e))?;
+
+    let mut export_items = collect_batch_export_items(paths);
+    let progress_counter = AtomicUsize::new(0);
+
+    Ok(pool.install(|| {
+        assign_batch_output_paths(&mut export_items, job);
+        export_items
+            .into_par_iter()
+            .map(|item| {
+                if job
+                    .app_handle
+                    .state::<AppState>()
+                    .export_task_handle
+                    .lock()
+                    .unwrap()
+                    .is_none()
+                {
+                    return (item.path, Err("Export cancelled".to_string()));
+                }
 
-    let task = tokio::spawn(async move {
-        let state = app_handle.state::<AppState>();
-        let output_folder_path = std::path::Path::new(&output_folder);
-        let total_paths = paths.len();
-        let settings = load_settings(app_handle.clone()).unwrap_or_default();
-        let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
-        let linear_mode = settings.linear_raw_mode;
+                let current_progress = progress_counter.fetch_add(1, Ordering::SeqCst) + 1;
+                let _ = job.app_handle.emit(
+                    job.progress_event,
+                    serde_json::json!({
+                        "current": current_progress,
+                        "total": job.total_paths,
+                        "path": &item.path
+                    }),
+                );
+
+                let result = export_batch_item(job, &item);
+                (item.path, result)
+            })
+            .collect()
+    }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportSummary {
+    succeeded: usize,
+    failed: usize,
+}
+
+fn report_export_results(
+    app_handle: &tauri::AppHandle,
+    results: Vec<(String, Result<(), String>)>,
+) -> ExportSummary {
+    let mut summary = ExportSummary {
+        succeeded: 0,
+        failed: 0,
+    };
+    for (path, result) in results {
+        match result {
+            Ok(()) => summary.succeeded += 1,
+            Err(e) => {
+                summary.failed += 1;
+                let message = format!("{}: {}", path, e);
+                log::error!("Batch export error: {}", message);
+                let _ = app_handle.emit("export-error", message);
+            }
+        }
+    }
+    summary
+}
+
+#[tauri::command]
+async fn batch_export_images(
+    output_folder: String,
+    paths: Vec<String>,
+    export_settings: ExportSettings,
+    output_format: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 
-        let pool_result = rayon::ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build();
+    if state.export_task_handle.lock().unwrap().is_some() {
+        return Err("An export is already in progress.".to_string());
+    }
 
-        if let Err(e) = pool_result {
-            let _ = app_handle.emit(
-                "export-error",
-                format!("Failed to initialize worker threads: {}", e),
-            );
-            *app_handle
-                .state::<AppState>()
-                .export_task_handle
-                .lock()
-                .unwrap() = None;
-            return;
-        }
-        let pool = pool_result.unwrap();
+    let context = gpu_context_or_cpu_fallback(&state, &app_handle);
 
-        let mut base_path_counts: HashMap<String, usize> = HashMap::new();
-        let mut export_items = Vec::with_capacity(total_paths);
+    let (output_format, format_fallback) = resolve_export_format(&output_format);
+    if let Some(message) = format_fallback {
+        let _ = app_handle.emit("export-error", message);
+    }
 
-        for (i, path_str) in paths.into_iter().enumerate() {
-            let (source_path, _) = parse_virtual_path(&path_str);
-            let source_str = source_path.to_string_lossy().to_string();
-            let count = base_path_counts.entry(source_str.clone()).or_insert(0);
-            *count += 1;
+    start_export_task(&state, async move {
+        let total_paths = paths.len();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let job = BatchExportJob {
+            app_handle: &app_handle,
+            context: context.as_ref(),
+            export_settings: &export_settings,
+            output_folder: Path::new(&output_folder),
+            output_format: &output_format,
+            progress_event: "batch-export-progress",
+            total_paths,
+            highlight_compression: settings.raw_highlight_compression.unwrap_or(2.5),
+            linear_mode: settings.linear_raw_mode,
+            gpu_tile_size: settings.gpu_tile_size,
+            gpu_memory_budget_mb: settings.gpu_memory_budget_mb,
+        };
 
-            let mut explicit_vc = None;
-            if let Some(idx) = path_str.rfind("vc=") {
-                let id_str = path_str[idx + 3..].split('&').next().unwrap_or("");
-                if let Ok(id) = id_str.parse::<u32>() {
-                    explicit_vc = Some(id);
+        match run_batch_export(&job, paths) {
+            Ok(results) => {
+                tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+                let summary = report_export_results(&app_handle, results);
+                if summary.failed > 0 {
+                    let _ = app_handle.emit(
+                        "export-complete-with-errors",
+                        serde_json::json!({ "errors": summary.failed, "total": total_paths }),
+                    );
+                } else {
+                    let _ = app_handle.emit(
+                        "batch-export-progress",
+                        serde_json::json!({ "current": total_paths, "total": total_paths, "path": "" }),
+                    );
+                    let _ = app_handle.emit("export-complete", ());
                 }
             }
-            if explicit_vc.is_none() {
-                let lower = path_str.to_lowercase();
-                if let Some(idx) = lower.rfind("_vc") {
-                    let id_str: String = lower[idx + 3..]
-                        .chars()
-                        .take_while(|c| c.is_ascii_digit())
-                        .collect();
-                    if let Ok(id) = id_str.parse::<u32>() {
-                        explicit_vc = Some(id);
-                    }
-                }
+            Err(e) => {
+                let _ = app_handle.emit("export-error", e);
             }
-
-            export_items.push((i, path_str, *count, explicit_vc));
         }
 
-        let results: Vec<Result<(), String>> = pool.install(|| {
-            export_items
-                .into_par_iter()
-                .map(|(global_index, image_path_str, appearance_count, explicit_vc)| {
-                    if app_handle
-                        .state::<AppState>()
-                        .export_task_handle
-                        .lock()
-                        .unwrap()
-                        .is_none()
-                    {
-                        return Err("Export cancelled".to_string());
-                    }
-
-                    let current_progress = progress_counter.fetch_add(1, Ordering::SeqCst) + 1;
-                    let _ = app_handle.emit(
-                        "batch-export-progress",
-                        serde_json::json!({
-                            "current": current_progress,
-                            "total": total_paths,
-                            "path": &image_path_str
-                        }),
-                    );
+        *app_handle
+            .state::<AppState>()
+            .export_task_handle
+            .lock()
+            .unwrap() = None;
+    })
+}
 
-                    let result: Result<(), String> = (|| {
-                        let (source_path, sidecar_path) = parse_virtual_path(&image_path_str);
-                        let source_path_str = source_path.to_string_lossy().to_string();
+fn export_settings_from_preset(preset: &ExportPreset) -> Result<ExportSettings, String> {
+    let resize = if preset.enable_resize {
+        Some(ResizeOptions {
+            mode: serde_json::from_value(Value::String(preset.resize_mode.clone()))
+                .map_err(|e| format!("Invalid resize mode '{}': {}", preset.resize_mode, e))?,
+            value: preset.resize_value,
+            dont_enlarge: preset.dont_enlarge,
+        })
+    } else {
+        None
+    };
 
-                        let metadata: ImageMetadata = if sidecar_path.exists() {
-                            let file_content = fs::read_to_string(sidecar_path)
-                                .map_err(|e| format!("Failed to read sidecar: {}", e))?;
-                            serde_json::from_str(&file_content).unwrap_or_default()
-                        } else {
-                            ImageMetadata::default()
-                        };
-                        let mut js_adjustments = metadata.adjustments;
-                        hydrate_adjustments(&state, &mut js_adjustments);
-                        let is_raw = is_raw_file(&source_path_str);
-
-                        let original_path = std::path::Path::new(&source_path_str);
-                        let file_date = exif_processing::get_creation_date_from_path(original_path);
-
-                        let filename_template = export_settings
-                            .filename_template
-                            .as_deref()
-                            .unwrap_or("{original_filename}_edited");
-                        let mut new_stem = generate_filename_from_template(
-                            filename_template,
-                            original_path,
-                            global_index + 1,
-                            total_paths,
-                            &file_date,
-                        );
-
-                        if let Some(vc_id) = explicit_vc {
-                            new_stem = format!("{}_VC{:02}", new_stem, vc_id);
-                        } else if appearance_count > 1 {
-                            new_stem = format!("{}_VC{:02}", new_stem, appearance_count - 1);
-                        }
+    let watermark = if preset.enable_watermark {
+        let anchor = match &preset.watermark_anchor {
+            Some(anchor) => serde_json::from_value(Value::String(anchor.clone()))
+                .map_err(|e| format!("Invalid watermark anchor '{}': {}", anchor, e))?,
+            None => WatermarkAnchor::BottomRight,
+        };
+        Some(WatermarkSettings {
+            path: preset.watermark_path.clone(),
+            anchor,
+            scale: preset.watermark_scale as f32,
+            spacing: preset.watermark_spacing as f32,
+            opacity: preset.watermark_opacity as f32,
+            text: preset.watermark_text.clone(),
+            font_size: preset.watermark_font_size,
+            color: preset.watermark_color.clone(),
+        })
+    } else {
+        None
+    };
 
-                        let new_filename = format!("{}.{}", new_stem, output_format);
-                        let output_path = output_folder_path.join(new_filename);
-                        let extension = output_format.to_lowercase();
-
-                        if extension == "cube" {
-                            let cube_bytes = export_adjustments_as_lut(
-                                &js_adjustments,
-                                &source_path_str,
-                                &context,
-                                &state,
-                            )?;
-                            fs::write(&output_path, cube_bytes).map_err(|e| e.to_string())?;
-                            return Ok(());
-                        }
+    Ok(ExportSettings {
+        jpeg_quality: preset.jpeg_quality,
+        resize,
+        keep_metadata: preset.keep_metadata,
+        strip_gps: preset.strip_gps,
+        filename_template: Some(preset.filename_template.clone()),
+        watermark,
+        export_masks: preset.export_masks.unwrap_or(false),
+        bit_depth: preset.bit_depth,
+        lossless: preset.lossless.unwrap_or(false),
+        color_space: preset.color_space.clone(),
+    })
+}
 
-                        let base_image = match read_file_mapped(Path::new(&source_path_str)) {
-                            Ok(mmap) => load_and_composite(
-                                &mmap,
-                                &source_path_str,
-                                &js_adjustments,
-                                false,
-                                highlight_compression,
-                                linear_mode.clone(),
-                                None,
-                            )
-                            .map_err(|e| format!("Failed to load image from mmap: {}", e))?,
-                            Err(e) => {
-                                log::warn!(
-                                    "Failed to memory-map file '{}': {}. Falling back to standard read.",
-                                    source_path_str,
-                                    e
-                                );
-                                let bytes = fs::read(&source_path_str).map_err(|io_err| {
-                                    format!("Fallback read failed for {}: {}", source_path_str, io_err)
-                                })?;
-                                load_and_composite(
-                                    &bytes,
-                                    &source_path_str,
-                                    &js_adjustments,
-                                    false,
-                                    highlight_compression,
-                                    linear_mode.clone(),
-                                    None,
-                                )
-                                .map_err(|e| format!("Failed to load image from bytes: {}", e))?
-                            }
-                        };
+#[tauri::command]
+async fn export_images(
+    paths: Vec<String>,
+    preset: ExportPreset,
+    destination: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ExportSummary, String> {
+    if state.export_task_handle.lock().unwrap().is_some() {
+        return Err("An export is already in progress.".to_string());
+    }
 
-                        let mut main_export_adjustments = js_adjustments.clone();
-                        if export_settings.export_masks
-                            && let Some(obj) = main_export_adjustments.as_object_mut() {
-                                obj.insert("masks".to_string(), serde_json::json!([]));
-                            }
+    let export_settings = export_settings_from_preset(&preset)?;
+    let (output_format, format_fallback) = resolve_export_format(&preset.file_format);
+    if let Some(message) = format_fallback {
+        let _ = app_handle.emit("export-error", message);
+    }
+    let output_format = if output_format == "jpeg" {
+        "jpg".to_string()
+    } else {
+        output_format
+    };
 
-                        let final_image = process_image_for_export(
-                            &source_path_str,
-                            &base_image,
-                            &main_export_adjustments,
-                            &export_settings,
-                            &context,
-                            &state,
-                            is_raw,
-                        )?;
-
-                        save_image_with_metadata(&final_image, &output_path, &source_path_str, &export_settings)?;
-
-                        if export_settings.export_masks {
-                            export_masks_for_image(
-                                &base_image,
-                                &js_adjustments,
-                                &export_settings,
-                                &output_path,
-                                &source_path_str,
-                                &context,
-                                &state,
-                                is_raw
-                            )?;
-                        }
+    fs::create_dir_all(&destination)
+        .map_err(|e| format!("Failed to create destination folder: {}", e))?;
 
-                        Ok(())
-                    })();
+    let context = gpu_context_or_cpu_fallback(&state, &app_handle);
+    let (summary_tx, summary_rx) = tokio::sync::oneshot::channel();
 
-                    result
-                })
-                .collect()
-        });
+    start_export_task(&state, async move {
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let total_paths = paths.len();
+        let job = BatchExportJob {
+            app_handle: &app_handle,
+            context: context.as_ref(),
+            export_settings: &export_settings,
+            output_folder: Path::new(&destination),
+            output_format: &output_format,
+            progress_event: "export-progress",
+            total_paths,
+            highlight_compression: settings.raw_highlight_compression.unwrap_or(2.5),
+            linear_mode: settings.linear_raw_mode,
+            gpu_tile_size: settings.gpu_tile_size,
+            gpu_memory_budget_mb: settings.gpu_memory_budget_mb,
+        };
 
-        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        let _ = app_handle.emit("export-start", serde_json::json!({ "total": total_paths }));
 
-        let mut error_count = 0;
-        for result in results {
-            if let Err(e) = result {
-                error_count += 1;
-                log::error!("Batch export error: {}", e);
+        let outcome = run_batch_export(&job, paths)
+            .map(|results| report_export_results(&app_handle, results));
+        match &outcome {
+            Ok(summary) => {
+                let _ = app_handle.emit(
+                    "export-progress",
+                    serde_json::json!({ "current": total_paths, "total": total_paths, "path": "" }),
+                );
+                let _ = app_handle.emit("export-complete", summary);
+            }
+            Err(e) => {
                 let _ = app_handle.emit("export-error", e);
             }
         }
 
-        if error_count > 0 {
-            let _ = app_handle.emit(
-                "export-complete-with-errors",
-                serde_json::json!({ "errors": error_count, "total": total_paths }),
-            );
-        } else {
-            let _ = app_handle.emit(
-                "batch-export-progress",
-                serde_json::json!({ "current": total_paths, "total": total_paths, "path": "" }),
-            );
-            let _ = app_handle.emit("export-complete", ());
-        }
-
         *app_handle
             .state::<AppState>()
             .export_task_handle
             .lock()
             .unwrap() = None;
-    });
+        let _ = summary_tx.send(outcome);
+    })?;
 
-    *state.export_task_handle.lock().unwrap() = Some(task);
-    Ok(())
+    summary_rx
+        .await
+        .map_err(|_| "Export cancelled".to_string())?
 }
 
 #[tauri::command]
@@ -2406,31 +3819,20 @@ fn cancel_export(state: tauri::State<AppState>) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-async fn estimate_export_size(
-    js_adjustments: Value,
-    export_settings: ExportSettings,
-    output_format: String,
-    state: tauri::State<'_, AppState>,
-    app_handle: tauri::AppHandle,
-) -> Result<usize, String> {
-    if output_format.to_lowercase() == "cube" {
-        return Ok(1_050_000);
-    }
-
-    let context = get_or_init_gpu_context(&state)?;
-    let loaded_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No original image loaded")?;
+/// Renders the editor preview of `loaded_image` for already hydrated adjustments,
+/// without clipping warnings and encoded with the given output primaries.
+fn render_editor_preview(
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+    loaded_image: &LoadedImage,
+    adjustments: &Value,
+    output_primaries: u32,
+    debug_tag: &str,
+) -> Result<DynamicImage, String> {
+    let context = get_or_init_gpu_context(state, app_handle)?;
     let is_raw = loaded_image.is_raw;
 
-    let mut adjustments_clone = js_adjustments.clone();
-    hydrate_adjustments(&state, &mut adjustments_clone);
-
-    let new_transform_hash = calculate_transform_hash(&adjustments_clone);
+    let new_transform_hash = calculate_transform_hash(adjustments);
     let cached_preview_lock = state.cached_preview.lock().unwrap();
 
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
@@ -2446,15 +3848,15 @@ async fn estimate_export_size(
             (owned_img, s, offset)
         } else {
             drop(cached_preview_lock);
-            generate_transformed_preview(&loaded_image, &adjustments_clone, preview_dim)?
+            generate_transformed_preview(loaded_image, adjustments, preview_dim)?
         }
     } else {
         drop(cached_preview_lock);
-        generate_transformed_preview(&loaded_image, &adjustments_clone, preview_dim)?
+        generate_transformed_preview(loaded_image, adjustments, preview_dim)?
     };
 
     let (img_w, img_h) = preview_image.dimensions();
-    let mask_definitions: Vec<MaskDefinition> = adjustments_clone
+    let mask_definitions: Vec<MaskDefinition> = adjustments
         .get("masks")
         .and_then(|m| serde_json::from_value(m.clone()).ok())
         .unwrap_or_default();
@@ -2468,28 +3870,28 @@ async fn estimate_export_size(
         .iter()
         .filter_map(|def| {
             get_cached_or_generate_mask(
-                &state,
+                state,
                 def,
                 img_w,
                 img_h,
                 scale,
                 scaled_crop_offset,
-                &adjustments_clone,
+                adjustments,
             )
         })
         .collect();
 
-    let mut all_adjustments = get_all_adjustments_from_json(&adjustments_clone, is_raw);
+    let mut all_adjustments = get_all_adjustments_from_json(adjustments, is_raw);
     all_adjustments.global.show_clipping = 0;
+    all_adjustments.global.output_primaries = output_primaries;
 
-    let lut_path = adjustments_clone["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
-    let unique_hash =
-        calculate_full_job_hash(&loaded_image.path, &adjustments_clone).wrapping_add(1);
+    let lut = get_adjustments_lut(state, adjustments)?;
+    let unique_hash = calculate_full_job_hash(&loaded_image.path, adjustments).wrapping_add(1);
 
-    let processed_preview = process_and_get_dynamic_image(
+    process_and_get_dynamic_image(
         &context,
-        &state,
+        state,
+        app_handle,
         &preview_image,
         unique_hash,
         RenderRequest {
@@ -2498,14 +3900,43 @@ async fn estimate_export_size(
             lut,
             roi: None,
         },
+        debug_tag,
+    )
+}
+
+#[tauri::command]
+async fn estimate_export_size(
+    js_adjustments: Value,
+    export_settings: ExportSettings,
+    output_format: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, String> {
+    if output_format.to_lowercase() == "cube" {
+        return Ok(1_050_000);
+    }
+
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No original image loaded")?;
+
+    let mut adjustments_clone = js_adjustments.clone();
+    hydrate_adjustments(&state, &mut adjustments_clone);
+
+    let processed_preview = render_editor_preview(
+        &state,
+        &app_handle,
+        &loaded_image,
+        &adjustments_clone,
+        ExportColorSpace::from_name(&export_settings.color_space)?.output_primaries(),
         "estimate_export_size",
     )?;
 
-    let preview_bytes = encode_image_to_bytes(
-        &processed_preview,
-        &output_format,
-        export_settings.jpeg_quality,
-    )?;
+    let preview_bytes =
+        encode_image_to_bytes(&processed_preview, &output_format, &export_settings)?;
     let preview_byte_size = preview_bytes.len();
 
     let (transformed_full_res, _unscaled_crop_offset) =
@@ -2547,9 +3978,10 @@ async fn estimate_batch_export_size(
     if paths.is_empty() {
         return Ok(0);
     }
-    let context = get_or_init_gpu_context(&state)?;
+    let context = get_or_init_gpu_context(&state, &app_handle)?;
     let first_path = &paths[0];
     let (source_path, sidecar_path) = parse_virtual_path(first_path);
+    let sidecar_path = resolve_sidecar_path(&app_handle, &sidecar_path);
     let source_path_str = source_path.to_string_lossy().to_string();
     let is_raw = is_raw_file(&source_path_str);
 
@@ -2616,6 +4048,7 @@ async fn estimate_batch_export_size(
             y: c.y * raw_scale_factor as f64,
             width: c.width * raw_scale_factor as f64,
             height: c.height * raw_scale_factor as f64,
+            canvas_color: c.canvas_color,
         })
         .unwrap_or(serde_json::Value::Null);
     }
@@ -2666,15 +4099,17 @@ async fn estimate_batch_export_size(
 
     let mut all_adjustments = get_all_adjustments_from_json(&scaled_adjustments, is_raw);
     all_adjustments.global.show_clipping = 0;
+    all_adjustments.global.output_primaries =
+        ExportColorSpace::from_name(&export_settings.color_space)?.output_primaries();
 
-    let lut_path = scaled_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+    let lut = get_adjustments_lut(&state, &scaled_adjustments)?;
     let unique_hash =
         calculate_full_job_hash(&source_path_str, &scaled_adjustments).wrapping_add(1);
 
     let processed_preview = process_and_get_dynamic_image(
         &context,
         &state,
+        &app_handle,
         &preview_base,
         unique_hash,
         RenderRequest {
@@ -2686,11 +4121,8 @@ async fn estimate_batch_export_size(
         "estimate_batch_export_size",
     )?;
 
-    let preview_bytes = encode_image_to_bytes(
-        &processed_preview,
-        &output_format,
-        export_settings.jpeg_quality,
-    )?;
+    let preview_bytes =
+        encode_image_to_bytes(&processed_preview, &output_format, &export_settings)?;
     let single_image_estimated_size = preview_bytes.len();
 
     let full_w = (shrunk_w as f32 / raw_scale_factor).round() as u32;
@@ -2820,6 +4252,139 @@ async fn generate_ai_sky_mask(
     })
 }
 
+fn emit_ai_mask_progress(app_handle: &tauri::AppHandle, path: &str, kind: &str, stage: &str) {
+    let _ = app_handle.emit(
+        "ai-mask-progress",
+        serde_json::json!({ "path": path, "kind": kind, "stage": stage }),
+    );
+}
+
+/// Segments the open image with the bundled subject or sky model and appends the
+/// result to the image's sidecar as a new mask.
+#[tauri::command]
+async fn generate_ai_mask(
+    path: String,
+    kind: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<MaskDefinition, String> {
+    let (mask_type, mask_name) = match kind.as_str() {
+        "subject" => ("ai-foreground", "Subject"),
+        "sky" => ("ai-sky", "Sky"),
+        _ => return Err(format!("Unsupported AI mask kind: {}", kind)),
+    };
+
+    let is_loaded = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|loaded| loaded.path == path);
+    if !is_loaded {
+        return Err("The image must be open in the editor to generate an AI mask.".to_string());
+    }
+
+    emit_ai_mask_progress(&app_handle, &path, &kind, "loading-model");
+    let models = get_or_init_ai_models(&app_handle, &state.ai_state, &state.ai_init_lock)
+        .await
+        .map_err(|e| format!("AI masking is unavailable: {}", e))?;
+
+    let mut metadata = file_management::read_sidecar_metadata(&path, &app_handle)?;
+    let adjustments = metadata.adjustments.clone();
+
+    emit_ai_mask_progress(&app_handle, &path, &kind, "preparing");
+    let warped_image = get_cached_full_warped_image(&state, &adjustments)?;
+    let (full_w, full_h) = warped_image.dimensions();
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
+    let model_input = if full_w.max(full_h) > preview_dim {
+        Arc::new(downscale_f32_image(&warped_image, preview_dim, preview_dim))
+    } else {
+        Arc::clone(&warped_image)
+    };
+
+    emit_ai_mask_progress(&app_handle, &path, &kind, "segmenting");
+    let mask_image = match kind.as_str() {
+        "subject" => run_u2netp_model(model_input.as_ref(), &models.u2netp),
+        _ => run_sky_seg_model(model_input.as_ref(), &models.sky_seg),
+    }
+    .map_err(|e| e.to_string())?;
+
+    // Sub-mask bitmaps are stored in full-resolution warped coordinates.
+    let full_res_mask = if mask_image.dimensions() != (full_w, full_h) {
+        image::imageops::resize(
+            &mask_image,
+            full_w,
+            full_h,
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        mask_image
+    };
+    let base64_data = encode_to_base64_png(&full_res_mask)?;
+
+    let rotation = Some(adjustments["rotation"].as_f64().unwrap_or(0.0) as f32);
+    let flip_horizontal = Some(adjustments["flipHorizontal"].as_bool().unwrap_or(false));
+    let flip_vertical = Some(adjustments["flipVertical"].as_bool().unwrap_or(false));
+    let orientation_steps = Some(adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8);
+    let parameters = match kind.as_str() {
+        "subject" => serde_json::to_value(AiForegroundMaskParameters {
+            mask_data_base64: Some(base64_data),
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+            orientation_steps,
+        }),
+        _ => serde_json::to_value(AiSkyMaskParameters {
+            mask_data_base64: Some(base64_data),
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+            orientation_steps,
+        }),
+    }
+    .map_err(|e| e.to_string())?;
+    let mask = MaskDefinition {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: mask_name.to_string(),
+        visible: true,
+        invert: false,
+        opacity: 100.0,
+        blend_mode: MaskBlendMode::Normal,
+        adjustments: serde_json::json!({}),
+        sub_masks: vec![SubMask {
+            id: uuid::Uuid::new_v4().to_string(),
+            mask_type: mask_type.to_string(),
+            visible: true,
+            invert: false,
+            opacity: 100.0,
+            mode: SubMaskMode::Additive,
+            parameters,
+        }],
+    };
+
+    emit_ai_mask_progress(&app_handle, &path, &kind, "saving");
+    let mut new_adjustments = if adjustments.is_object() {
+        adjustments
+    } else {
+        serde_json::json!({})
+    };
+    let mask_value = serde_json::to_value(&mask).map_err(|e| e.to_string())?;
+    match new_adjustments
+        .get_mut("masks")
+        .and_then(|m| m.as_array_mut())
+    {
+        Some(masks) => masks.push(mask_value),
+        None => new_adjustments["masks"] = serde_json::json!([mask_value]),
+    }
+    metadata.record_history(&new_adjustments);
+    metadata.adjustments = new_adjustments;
+    file_management::persist_edited_metadata(&path, &metadata, &app_handle, &state)?;
+
+    emit_ai_mask_progress(&app_handle, &path, &kind, "done");
+    Ok(mask)
+}
+
 #[allow(clippy::too_many_arguments)]
 #[tauri::command]
 async fn generate_ai_depth_mask(
@@ -2845,7 +4410,7 @@ async fn generate_ai_depth_mask(
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
         let mut geo_hasher = DefaultHasher::new();
-        for key in GEOMETRY_KEYS {
+        for key in adjustment_registry::warp_keys() {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
                 val.to_string().hash(&mut geo_hasher);
@@ -2934,7 +4499,7 @@ async fn generate_ai_subject_mask(
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
         let mut geo_hasher = DefaultHasher::new();
-        for key in GEOMETRY_KEYS {
+        for key in adjustment_registry::warp_keys() {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
                 val.to_string().hash(&mut geo_hasher);
@@ -3080,7 +4645,7 @@ async fn precompute_ai_subject_mask(
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
         let mut geo_hasher = DefaultHasher::new();
-        for key in GEOMETRY_KEYS {
+        for key in adjustment_registry::warp_keys() {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
                 val.to_string().hash(&mut geo_hasher);
@@ -3113,8 +4678,9 @@ async fn precompute_ai_subject_mask(
 fn generate_preset_preview(
     js_adjustments: serde_json::Value,
     state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Response, String> {
-    let context = get_or_init_gpu_context(&state)?;
+    let context = get_or_init_gpu_context(&state, &app_handle)?;
 
     let loaded_image = state
         .original_image
@@ -3155,12 +4721,12 @@ fn generate_preset_preview(
         .collect();
 
     let all_adjustments = get_all_adjustments_from_json(&js_adjustments, is_raw);
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+    let lut = get_adjustments_lut(&state, &js_adjustments)?;
 
     let processed_image = process_and_get_dynamic_image(
         &context,
         &state,
+        &app_handle,
         &transformed_image,
         unique_hash,
         RenderRequest {
@@ -3240,6 +4806,7 @@ async fn invoke_generative_replace_with_mask_def(
         visible: patch_definition.visible,
         invert: patch_definition.invert,
         opacity: 100.0,
+        blend_mode: MaskBlendMode::Normal,
         adjustments: serde_json::Value::Null,
         sub_masks: patch_definition.sub_masks,
     };
@@ -3397,13 +4964,18 @@ async fn invoke_generative_replace_with_mask_def(
     Ok(result_json)
 }
 
+#[tauri::command]
+fn get_adjustment_registry() -> Vec<adjustment_registry::AdjustmentDef> {
+    adjustment_registry::ADJUSTMENTS.to_vec()
+}
+
 #[tauri::command]
 fn get_supported_file_types() -> Result<serde_json::Value, String> {
     let raw_extensions: Vec<&str> = crate::formats::RAW_EXTENSIONS
         .iter()
         .map(|(ext, _)| *ext)
         .collect();
-    let non_raw_extensions: Vec<&str> = crate::formats::NON_RAW_EXTENSIONS.to_vec();
+    let non_raw_extensions: Vec<&str> = crate::formats::supported_non_raw_extensions();
 
     Ok(serde_json::json!({
         "raw": raw_extensions,
@@ -3442,7 +5014,7 @@ async fn generate_all_community_previews(
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<HashMap<String, Vec<u8>>, String> {
-    let context = crate::image_processing::get_or_init_gpu_context(&state)?;
+    let context = crate::image_processing::get_or_init_gpu_context(&state, &app_handle)?;
     let mut results: HashMap<String, Vec<u8>> = HashMap::new();
 
     const TILE_DIM: u32 = 360;
@@ -3508,14 +5080,14 @@ async fn generate_all_community_previews(
                 .collect();
 
             let all_adjustments = get_all_adjustments_from_json(js_adjustments, *is_raw);
-            let lut_path = js_adjustments["lutPath"].as_str();
-            let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+            let lut = get_adjustments_lut(&state, js_adjustments)?;
 
             let unique_hash = preset_hash.wrapping_add(i as u64);
 
             let processed_image_dynamic = crate::image_processing::process_and_get_dynamic_image(
                 &context,
                 &state,
+                &app_handle,
                 &transformed_image,
                 unique_hash,
                 RenderRequest {
@@ -3982,7 +5554,7 @@ fn generate_preview_for_path(
     state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<Response, String> {
-    let context = get_or_init_gpu_context(&state)?;
+    let context = get_or_init_gpu_context(&state, &app_handle)?;
     let (source_path, _) = parse_virtual_path(&path);
     let source_path_str = source_path.to_string_lossy().to_string();
     let is_raw = is_raw_file(&source_path_str);
@@ -4045,12 +5617,12 @@ fn generate_preview_for_path(
         .collect();
 
     let all_adjustments = get_all_adjustments_from_json(&js_adjustments, is_raw);
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(&state, p).ok());
+    let lut = get_adjustments_lut(&state, &js_adjustments)?;
     let unique_hash = calculate_full_job_hash(&source_path_str, &js_adjustments);
     let final_image = process_and_get_dynamic_image(
         &context,
         &state,
+        &app_handle,
         &transformed_image,
         unique_hash,
         RenderRequest {
@@ -4086,6 +5658,70 @@ async fn load_and_parse_lut(
     Ok(LutParseResult { size: lut_size })
 }
 
+/// Parses each LUT and caches the ones that load, reporting size or parse error per path.
+fn parse_and_cache_luts(paths: Vec<String>, state: &AppState) -> Vec<LutValidationResult> {
+    let parsed: Vec<(String, Result<Lut, String>)> = paths
+        .into_par_iter()
+        .map(|path| {
+            let lut = lut_processing::parse_lut_file(&path).map_err(|e| e.to_string());
+            (path, lut)
+        })
+        .collect();
+
+    let mut cache = state.lut_cache.lock().unwrap();
+    parsed
+        .into_iter()
+        .map(|(path, lut)| match lut {
+            Ok(lut) => {
+                let size = lut.size;
+                cache.insert(path.clone(), Arc::new(lut));
+                LutValidationResult {
+                    path,
+                    size: Some(size),
+                    error: None,
+                }
+            }
+            Err(error) => {
+                cache.remove(&path);
+                LutValidationResult {
+                    path,
+                    size: None,
+                    error: Some(error),
+                }
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+async fn validate_lut(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<LutValidationResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        parse_and_cache_luts(vec![path], &state)
+            .pop()
+            .ok_or_else(|| "LUT validation produced no result".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Warms the LUT cache ahead of batch work so missing or broken LUTs are reported up front.
+#[tauri::command]
+async fn preload_luts(
+    paths: Vec<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<LutValidationResult>, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        parse_and_cache_luts(paths, &state)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
 fn apply_window_effect(theme: String, window: &tauri::WebviewWindow) {
     #[cfg(target_os = "windows")]
     {
@@ -4355,6 +5991,27 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_shell::init())
+        .register_asynchronous_uri_scheme_protocol(
+            file_management::THUMBNAIL_PROTOCOL,
+            |ctx, request, responder| {
+                let app_handle = ctx.app_handle().clone();
+                let request_path = request.uri().path().to_string();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let thumbnail =
+                        file_management::resolve_thumbnail_protocol_path(&app_handle, &request_path)
+                            .and_then(|path| fs::read(path).ok());
+                    let response = tauri::http::Response::builder();
+                    let response = match thumbnail {
+                        Some(bytes) => response
+                            .header("Content-Type", "image/jpeg")
+                            .header("Access-Control-Allow-Origin", "*")
+                            .body(bytes),
+                        None => response.status(404).body(Vec::new()),
+                    };
+                    responder.respond(response.unwrap());
+                });
+            },
+        )
         .setup(|app| {
             #[cfg(any(windows, target_os = "linux"))]
             {
@@ -4386,6 +6043,11 @@ pub fn run() {
             let lens_db = lens_correction::load_lensfun_db(&app_handle);
             let state = app.state::<AppState>();
             *state.lens_db.lock().unwrap() = Some(lens_db);
+            *state.preferred_gpu_adapter.lock().unwrap() = settings
+                .preferred_gpu_adapter
+                .clone()
+                .filter(|name| !name.is_empty());
+            *state.gpu_tile_size.lock().unwrap() = settings.gpu_tile_size;
 
             unsafe {
                 if let Some(backend) = &settings.processing_backend
@@ -4432,6 +6094,7 @@ pub fn run() {
 
             start_preview_worker(app_handle.clone());
             start_analytics_worker(app_handle.clone());
+            file_management::start_thumbnail_regen_worker(app_handle.clone());
             jxl_oxide::integration::register_image_decoding_hook();
 
             let window_cfg = app.config().app.windows.first().unwrap().clone();
@@ -4564,10 +6227,14 @@ pub fn run() {
         .manage(AppState {
             window_setup_complete: AtomicBool::new(false),
             gpu_crash_flag_path: Mutex::new(None),
+            preferred_gpu_adapter: Mutex::new(None),
+            gpu_tile_size: Mutex::new(None),
             original_image: Mutex::new(None),
             cached_preview: Mutex::new(None),
+            last_processed_preview: Mutex::new(None),
             gpu_context: Mutex::new(None),
             gpu_image_cache: Mutex::new(None),
+            last_processing_stats: Mutex::new(None),
             gpu_processor: Mutex::new(None),
             ai_state: Mutex::new(None),
             ai_init_lock: TokioMutex::new(()),
@@ -4579,27 +6246,45 @@ pub fn run() {
             lut_cache: Mutex::new(HashMap::new()),
             initial_file_path: Mutex::new(None),
             thumbnail_cancellation_token: Arc::new(AtomicBool::new(false)),
+            thumbnail_pause_gate: Arc::new(file_management::ThumbnailPauseGate::default()),
+            thumbnail_canceled_paths: Mutex::new(HashSet::new()),
             thumbnail_progress: Mutex::new(ThumbnailProgressTracker { total: 0, completed: 0 }),
+            thumbnail_cache_index: Mutex::new(HashSet::new()),
+            thumbnail_cache_size: Mutex::new(None),
             preview_worker_tx: Mutex::new(None),
             analytics_worker_tx: Mutex::new(None),
             mask_cache: Mutex::new(HashMap::new()),
             patch_cache: Mutex::new(HashMap::new()),
             geometry_cache: Mutex::new(HashMap::new()),
-            thumbnail_geometry_cache: Mutex::new(HashMap::new()),
+            thumbnail_geometry_cache: Mutex::new(Default::default()),
             lens_db: Mutex::new(None),
             load_image_generation: Arc::new(AtomicUsize::new(0)),
             full_warped_cache: Mutex::new(None),
+            thumbnail_regen_tx: Mutex::new(None),
+            tag_index: Mutex::new(None),
+            capture_time_cache: Mutex::new(HashMap::new()),
+            last_trash_operation: Mutex::new(None),
+            folder_watchers: Mutex::new(HashMap::new()),
+            app_cache_dir: Mutex::new(None),
+            app_data_dir: Mutex::new(None),
+            gpu_backends: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             load_image,
             apply_adjustments,
             export_image,
             batch_export_images,
+            export_images,
             cancel_export,
             estimate_export_size,
             estimate_batch_export_size,
             generate_preview_for_path,
             generate_original_transformed_preview,
+            get_embedded_preview,
+            generate_histogram,
+            generate_histogram_for_path,
+            generate_waveform_for_path,
+            generate_vectorscope_for_path,
             generate_preset_preview,
             generate_uncropped_preview,
             preview_geometry_transform,
@@ -4608,12 +6293,16 @@ pub fn run() {
             precompute_ai_subject_mask,
             generate_ai_foreground_mask,
             generate_ai_sky_mask,
+            generate_ai_mask,
+            export_mask_bitmap,
+            render_full_image,
             generate_ai_depth_mask,
             update_window_effect,
             check_ai_connector_status,
             test_ai_connector_connection,
             invoke_generative_replace_with_mask_def,
             get_supported_file_types,
+            get_adjustment_registry,
             get_log_file_path,
             frontend_log,
             save_collage,
@@ -4624,23 +6313,51 @@ pub fn run() {
             apply_denoising,
             save_denoised_image,
             load_and_parse_lut,
+            validate_lut,
+            preload_luts,
+            clear_processing_caches,
             fetch_community_presets,
             generate_all_community_previews,
             save_temp_file,
             get_image_dimensions,
+            sample_white_balance,
+            sample_pixel,
+            generate_soft_proof,
+            estimate_gpu_memory_usage,
+            self_test_gpu,
+            list_gpu_adapters,
+            set_processing_backend,
+            get_processing_stats,
             frontend_ready,
             cancel_thumbnail_generation,
+            cancel_thumbnails,
+            pause_thumbnail_generation,
+            resume_thumbnail_generation,
             image_processing::calculate_auto_adjustments,
             file_management::read_exif_for_paths,
+            file_management::read_gps_for_paths,
+            file_management::set_gps_for_paths,
             file_management::list_images_in_dir,
             file_management::list_images_recursive,
+            file_management::search_library,
+            file_management::apply_rating_rule,
+            file_management::sync_all_to_xmp,
+            file_management::import_all_from_xmp,
+            file_management::enrich_capture_times,
+            file_management::list_edited_images_recursive,
+            file_management::validate_sidecars,
             file_management::get_folder_tree,
+            file_management::get_folder_tree_shallow,
+            file_management::count_folder_images,
             file_management::get_folder_children,
             file_management::get_pinned_folder_trees,
             file_management::generate_thumbnails,
             file_management::generate_thumbnails_progressive,
             file_management::create_folder,
             file_management::delete_folder,
+            file_management::restore_last_trash_operation,
+            folder_watcher::watch_folder,
+            folder_watcher::unwatch_folder,
             file_management::copy_files,
             file_management::move_files,
             file_management::rename_folder,
@@ -4650,21 +6367,38 @@ pub fn run() {
             file_management::delete_files_from_disk,
             file_management::delete_files_with_associated,
             file_management::save_metadata_and_update_thumbnail,
+            file_management::undo_edit,
+            file_management::redo_edit,
+            file_management::create_snapshot,
+            file_management::apply_snapshot,
+            file_management::delete_snapshot,
             file_management::apply_adjustments_to_paths,
+            file_management::copy_adjustments_from_to,
             file_management::load_metadata,
+            file_management::get_cached_processed_preview,
             file_management::load_presets,
             file_management::save_presets,
             file_management::load_settings,
             file_management::save_settings,
             file_management::reset_adjustments_for_paths,
+            file_management::reset_geometry_for_paths,
+            file_management::reset_section_for_paths,
+            file_management::reset_masks_for_paths,
             file_management::apply_auto_adjustments_to_paths,
             file_management::handle_import_presets_from_file,
             file_management::handle_import_legacy_presets_from_file,
+            file_management::handle_import_legacy_presets_from_folder,
             file_management::handle_export_presets_to_file,
+            file_management::handle_export_presets_bundle,
+            file_management::handle_import_presets_bundle,
+            file_management::load_folder_config,
+            file_management::save_folder_config,
             file_management::save_community_preset,
             file_management::clear_all_sidecars,
             file_management::clear_thumbnail_cache,
+            file_management::get_thumbnail_cache_size,
             file_management::set_color_label_for_paths,
+            file_management::set_hidden_for_paths,
             file_management::import_files,
             file_management::create_virtual_copy,
             tagging::start_background_indexing,
@@ -4672,11 +6406,18 @@ pub fn run() {
             tagging::clear_all_tags,
             tagging::add_tag_for_paths,
             tagging::remove_tag_for_paths,
+            tagging::build_tag_index,
+            tagging::get_tag_tree,
+            tagging::add_tags_to_paths,
+            tagging::remove_tags_from_paths,
             culling::cull_images,
+            culling::find_similar_images,
+            culling::compute_sharpness_scores,
             lens_correction::get_lensfun_makers,
             lens_correction::get_lensfun_lenses_for_maker,
             lens_correction::autodetect_lens,
             lens_correction::get_lens_distortion_params,
+            lens_correction::match_lens_profile,
             negative_conversion::preview_negative_conversion,
             negative_conversion::convert_negatives,
         ])
@@ -4716,3 +6457,84 @@ pub fn run() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry_hash(params: &GeometryParams) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash_geometry_params(params, &mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn every_geometry_field_changes_the_hash() {
+        // Start away from the identity values so each change is a real edit of that field.
+        let baseline = GeometryParams {
+            distortion: 0.1,
+            vertical: 0.2,
+            horizontal: 0.3,
+            rotate: 0.4,
+            aspect: 0.5,
+            scale: 0.6,
+            x_offset: 0.7,
+            y_offset: 0.8,
+            lens_distortion_amount: 0.9,
+            lens_vignette_amount: 1.1,
+            lens_tca_amount: 1.2,
+            lens_distortion_enabled: true,
+            lens_tca_enabled: false,
+            lens_vignette_enabled: true,
+            lens_dist_k1: 1.3,
+            lens_dist_k2: 1.4,
+            lens_dist_k3: 1.5,
+            lens_model: 1,
+            tca_vr: 1.6,
+            tca_vb: 1.7,
+            vig_k1: 1.8,
+            vig_k2: 1.9,
+            vig_k3: 2.1,
+        };
+        let changes: [(&str, fn(&mut GeometryParams)); 23] = [
+            ("distortion", |p| p.distortion += 0.5),
+            ("vertical", |p| p.vertical += 0.5),
+            ("horizontal", |p| p.horizontal += 0.5),
+            ("rotate", |p| p.rotate += 0.5),
+            ("aspect", |p| p.aspect += 0.5),
+            ("scale", |p| p.scale += 0.5),
+            ("x_offset", |p| p.x_offset += 0.5),
+            ("y_offset", |p| p.y_offset += 0.5),
+            ("lens_distortion_amount", |p| {
+                p.lens_distortion_amount += 0.5
+            }),
+            ("lens_vignette_amount", |p| p.lens_vignette_amount += 0.5),
+            ("lens_tca_amount", |p| p.lens_tca_amount += 0.5),
+            ("lens_distortion_enabled", |p| {
+                p.lens_distortion_enabled ^= true
+            }),
+            ("lens_tca_enabled", |p| p.lens_tca_enabled ^= true),
+            ("lens_vignette_enabled", |p| p.lens_vignette_enabled ^= true),
+            ("lens_dist_k1", |p| p.lens_dist_k1 += 0.5),
+            ("lens_dist_k2", |p| p.lens_dist_k2 += 0.5),
+            ("lens_dist_k3", |p| p.lens_dist_k3 += 0.5),
+            ("lens_model", |p| p.lens_model += 1),
+            ("tca_vr", |p| p.tca_vr += 0.5),
+            ("tca_vb", |p| p.tca_vb += 0.5),
+            ("vig_k1", |p| p.vig_k1 += 0.5),
+            ("vig_k2", |p| p.vig_k2 += 0.5),
+            ("vig_k3", |p| p.vig_k3 += 0.5),
+        ];
+
+        let baseline_hash = geometry_hash(&baseline);
+        for (field, change) in changes {
+            let mut params = baseline;
+            change(&mut params);
+            assert_ne!(
+                geometry_hash(&params),
+                baseline_hash,
+                "{field} is not hashed"
+            );
+        }
+    }
+}
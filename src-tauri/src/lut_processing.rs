@@ -115,6 +115,9 @@ fn parse_3dl(path: &Path) -> Result<Lut> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut data: Vec<f32> = Vec::new();
+    let mut mesh_output_bits: Option<u32> = None;
+    let mut shaper_max: Option<u32> = None;
+    let mut is_float = false;
 
     for line in reader.lines() {
         let line = line?;
@@ -123,13 +126,28 @@ fn parse_3dl(path: &Path) -> Result<Lut> {
             continue;
         }
         let parts: Vec<&str> = trimmed.split_whitespace().collect();
+        // Lustre files may carry "3DMESH" and "Mesh <in bits> <out bits>" headers.
+        if trimmed.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            if parts[0].eq_ignore_ascii_case("mesh") && parts.len() >= 3 {
+                mesh_output_bits = Some(parts[2].parse()?);
+            }
+            continue;
+        }
         if parts.len() == 3 {
+            is_float |= parts.iter().any(|p| p.contains(['.', 'e', 'E']));
             let r: f32 = parts[0].parse()?;
             let g: f32 = parts[1].parse()?;
             let b: f32 = parts[2].parse()?;
             data.push(r);
             data.push(g);
             data.push(b);
+        } else if parts.len() > 3 && data.is_empty() && shaper_max.is_none() {
+            // The input shaper line lists the grid positions at the input bit depth.
+            let last = parts[parts.len() - 1];
+            shaper_max =
+                Some(last.parse().map_err(|e| {
+                    anyhow!("Failed to parse 3DL shaper line '{}': {}", trimmed, e)
+                })?);
         }
     }
 
@@ -147,7 +165,52 @@ fn parse_3dl(path: &Path) -> Result<Lut> {
         ));
     }
 
-    Ok(Lut { size, data })
+    // Integer output values are at the bit depth from the Mesh header, or the shaper
+    // line's depth when there is none.
+    let scale = if is_float {
+        1.0
+    } else {
+        let bits = match (mesh_output_bits, shaper_max) {
+            (Some(bits), _) => bits,
+            (None, Some(shaper_max)) => (shaper_max + 1).next_power_of_two().trailing_zeros(),
+            (None, None) => {
+                return Err(anyhow!(
+                    "3DL file has neither a Mesh header nor a shaper line to give its bit depth"
+                ));
+            }
+        };
+        if !(1..=32).contains(&bits) {
+            return Err(anyhow!("Unsupported 3DL output bit depth: {}", bits));
+        }
+        let depth_max = ((1u64 << bits) - 1) as f32;
+        let max_value = data.iter().copied().fold(0.0f32, f32::max);
+        if max_value > depth_max {
+            return Err(anyhow!(
+                "3DL values reach {}, beyond the {}-bit range given by the file header",
+                max_value,
+                bits
+            ));
+        }
+        depth_max
+    };
+
+    // 3DL lists entries with blue changing fastest; the GPU path expects red fastest like .cube.
+    let size_usize = size as usize;
+    let mut reordered = vec![0.0f32; total_values];
+    for (i, rgb) in data.chunks_exact(3).enumerate() {
+        let b = i % size_usize;
+        let g = (i / size_usize) % size_usize;
+        let r = i / (size_usize * size_usize);
+        let dst = (r + g * size_usize + b * size_usize * size_usize) * 3;
+        reordered[dst] = rgb[0] / scale;
+        reordered[dst + 1] = rgb[1] / scale;
+        reordered[dst + 2] = rgb[2] / scale;
+    }
+
+    Ok(Lut {
+        size,
+        data: reordered,
+    })
 }
 
 fn parse_hald(image: DynamicImage) -> Result<Lut> {
@@ -170,14 +233,8 @@ fn parse_hald(image: DynamicImage) -> Result<Lut> {
         ));
     }
 
-    let mut data = Vec::with_capacity((total_pixels * 3) as usize);
-    let rgb_image = image.to_rgb8();
-
-    for pixel in rgb_image.pixels() {
-        data.push(pixel[0] as f32 / 255.0);
-        data.push(pixel[1] as f32 / 255.0);
-        data.push(pixel[2] as f32 / 255.0);
-    }
+    // Converting to f32 keeps the precision of 16-bit PNG/TIFF CLUTs.
+    let data = image.to_rgb32f().into_raw();
 
     Ok(Lut { size, data })
 }
@@ -193,7 +250,7 @@ pub fn parse_lut_file(path_str: &str) -> Result<Lut> {
     match extension.as_str() {
         "cube" => parse_cube(path),
         "3dl" => parse_3dl(path),
-        "png" | "jpg" | "jpeg" | "tiff" => {
+        "png" | "jpg" | "jpeg" | "tif" | "tiff" => {
             let img = image::open(path)?;
             parse_hald(img)
         }
@@ -245,3 +302,114 @@ pub fn convert_image_to_cube_lut(image: &DynamicImage, size: u32) -> Result<Vec<
 
     Ok(out.into_bytes())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn assert_identity(lut: &Lut) {
+        let size = lut.size as usize;
+        let step = (size - 1) as f32;
+        for (i, rgb) in lut.data.chunks_exact(3).enumerate() {
+            let expected = [
+                (i % size) as f32 / step,
+                ((i / size) % size) as f32 / step,
+                (i / (size * size)) as f32 / step,
+            ];
+            for (value, expected) in rgb.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-3, "entry {}: {:?}", i, rgb);
+            }
+        }
+    }
+
+    fn identity_3dl(header: &str, shaper_max: u32, value: impl Fn(u32) -> String) -> String {
+        let size = 5;
+        let shaper: Vec<String> = (0..size)
+            .map(|i| (i * shaper_max / (size - 1)).to_string())
+            .collect();
+        let mut out = format!("{}{}\n", header, shaper.join(" "));
+        // Blue changes fastest in .3dl files.
+        for r in 0..size {
+            for g in 0..size {
+                for b in 0..size {
+                    out.push_str(&format!("{} {} {}\n", value(r), value(g), value(b)));
+                }
+            }
+        }
+        out
+    }
+
+    fn parse_3dl_str(contents: &str) -> Result<Lut> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.3dl");
+        std::fs::write(&path, contents).unwrap();
+        parse_lut_file(path.to_str().unwrap())
+    }
+
+    fn integer(output_max: u32) -> impl Fn(u32) -> String {
+        move |v| (v * output_max / 4).to_string()
+    }
+
+    #[test]
+    fn identity_3dl_is_neutral() {
+        let lut = parse_3dl_str(&identity_3dl("3DMESH\nMesh 2 12\n", 1023, integer(4095))).unwrap();
+        assert_eq!(lut.size, 5);
+        assert_identity(&lut);
+
+        let lut = parse_3dl_str(&identity_3dl("", 1023, integer(1023))).unwrap();
+        assert_identity(&lut);
+
+        let lut = parse_3dl_str(&identity_3dl("", 1023, |v| {
+            format!("{:.3}", v as f32 / 4.0)
+        }))
+        .unwrap();
+        assert_identity(&lut);
+    }
+
+    #[test]
+    fn bit_depth_comes_from_the_header_not_the_values() {
+        // 12-bit output whose values stay below 1023 must not be read as 10-bit.
+        let lut = parse_3dl_str(&identity_3dl("Mesh 2 12\n", 1023, integer(1000))).unwrap();
+        let max = lut.data.iter().copied().fold(0.0f32, f32::max);
+        assert!((max - 1000.0 / 4095.0).abs() < 1e-6);
+
+        // Float values just above 1.0 are kept as they are.
+        let lut = parse_3dl_str(&identity_3dl("", 1023, |v| {
+            format!("{:.2}", v as f32 * 0.255)
+        }))
+        .unwrap();
+        let max = lut.data.iter().copied().fold(0.0f32, f32::max);
+        assert!((max - 1.02).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integer_3dl_needs_a_matching_bit_depth() {
+        let contents = identity_3dl("", 1023, integer(4095));
+        let without_shaper: String = contents
+            .lines()
+            .skip(1)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        assert!(parse_3dl_str(&without_shaper).is_err());
+        assert!(parse_3dl_str(&contents).is_err());
+    }
+
+    #[test]
+    fn identity_hald_is_neutral() {
+        // A level 2 Hald CLUT holds a 4x4x4 grid in an 8x8 image, red changing fastest.
+        let size = 4u32;
+        let image = ImageBuffer::from_fn(8, 8, |x, y| {
+            let i = y * 8 + x;
+            let channel = |v: u32| (v * 255 / (size - 1)) as u8;
+            Rgb([
+                channel(i % size),
+                channel((i / size) % size),
+                channel(i / (size * size)),
+            ])
+        });
+        let lut = parse_hald(DynamicImage::ImageRgb8(image)).unwrap();
+        assert_eq!(lut.size, size);
+        assert_identity(&lut);
+    }
+}
@@ -2,7 +2,7 @@ use crate::ai_processing::{
     AiDepthMaskParameters, AiForegroundMaskParameters, AiSkyMaskParameters, AiSubjectMaskParameters,
 };
 use base64::{Engine as _, engine::general_purpose};
-use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Rgba};
 use imageproc::distance_transform::Norm as DilationNorm;
 use imageproc::morphology::{dilate, erode};
 use serde::{Deserialize, Serialize};
@@ -16,6 +16,27 @@ pub enum SubMaskMode {
     Subtractive,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum MaskBlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+impl MaskBlendMode {
+    pub fn shader_index(self) -> u32 {
+        match self {
+            MaskBlendMode::Normal => 0,
+            MaskBlendMode::Multiply => 1,
+            MaskBlendMode::Screen => 2,
+            MaskBlendMode::Overlay => 3,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SubMask {
@@ -44,15 +65,20 @@ pub struct MaskDefinition {
     pub invert: bool,
     #[serde(default = "default_opacity")]
     pub opacity: f32,
+    #[serde(default)]
+    pub blend_mode: MaskBlendMode,
     pub adjustments: Value,
     pub sub_masks: Vec<SubMask>,
 }
 
 impl MaskDefinition {
     pub fn requires_warped_image(&self) -> bool {
-        self.sub_masks
-            .iter()
-            .any(|sm| sm.mask_type == "color" || sm.mask_type == "luminance")
+        self.sub_masks.iter().any(|sm| {
+            matches!(
+                sm.mask_type.as_str(),
+                "color" | "luminance" | "color-range" | "luminance-range"
+            )
+        })
     }
 }
 
@@ -193,6 +219,59 @@ impl Default for ParametricMaskParameters {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct SampledMaskOrientation {
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    flip_horizontal: bool,
+    #[serde(default)]
+    flip_vertical: bool,
+    #[serde(default)]
+    orientation_steps: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LuminanceRangeMaskParameters {
+    #[serde(default = "default_range_min")]
+    min: f32,
+    #[serde(default = "default_range_max")]
+    max: f32,
+    #[serde(default = "default_range_feather")]
+    feather: f32,
+    #[serde(flatten)]
+    orientation: SampledMaskOrientation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ColorRangeMaskParameters {
+    hue: f32,
+    saturation: f32,
+    #[serde(default = "default_tolerance")]
+    tolerance: f32,
+    #[serde(default)]
+    grow: f32,
+    #[serde(default)]
+    feather: f32,
+    #[serde(flatten)]
+    orientation: SampledMaskOrientation,
+}
+
+fn default_range_min() -> f32 {
+    25.0
+}
+
+fn default_range_max() -> f32 {
+    75.0
+}
+
+fn default_range_feather() -> f32 {
+    20.0
+}
+
 fn apply_grow_and_feather(mask: &mut GrayImage, grow: f32, feather: f32, width: u32, height: u32) {
     let base_dimension = width.min(height) as f32;
 
@@ -746,36 +825,25 @@ fn generate_ai_subject_bitmap(
     Some(mask)
 }
 
-fn generate_color_bitmap(
-    params_value: &Value,
+/// Maps every output pixel back through crop, rotation, flips and coarse rotation
+/// onto the warped source image and stores `intensity` of the sampled pixel.
+fn generate_sampled_bitmap(
+    orientation: &SampledMaskOrientation,
     width: u32,
     height: u32,
     scale: f32,
     crop_offset: (f32, f32),
-    warped_image: Option<&image::DynamicImage>,
-) -> Option<GrayImage> {
-    let params: ParametricMaskParameters = serde_json::from_value(params_value.clone()).ok()?;
-    let warped = warped_image?;
+    warped: &DynamicImage,
+    intensity: impl Fn(Rgba<u8>) -> f32,
+) -> GrayImage {
     let (full_w, full_h) = warped.dimensions();
-
-    let target_x = params.target_x.round() as i32;
-    let target_y = params.target_y.round() as i32;
-    if target_x < 0 || target_y < 0 || target_x >= full_w as i32 || target_y >= full_h as i32 {
-        return None;
-    }
-
-    let ref_pixel = warped.get_pixel(target_x as u32, target_y as u32);
-    let ref_r = ref_pixel[0] as f32;
-    let ref_g = ref_pixel[1] as f32;
-    let ref_b = ref_pixel[2] as f32;
-
     let mut mask = GrayImage::new(width, height);
 
-    let angle_rad = params.rotation * PI / 180.0;
+    let angle_rad = orientation.rotation * PI / 180.0;
     let cos_a = angle_rad.cos();
     let sin_a = angle_rad.sin();
 
-    let (coarse_rotated_w, coarse_rotated_h) = if params.orientation_steps % 2 == 1 {
+    let (coarse_rotated_w, coarse_rotated_h) = if orientation.orientation_steps % 2 == 1 {
         (full_h, full_w)
     } else {
         (full_w, full_h)
@@ -786,7 +854,6 @@ fn generate_color_bitmap(
     let center_x = scaled_coarse_rotated_w / 2.0;
     let center_y = scaled_coarse_rotated_h / 2.0;
 
-    let tolerance_sq = (params.tolerance * 2.55).max(1.0).powi(2) * 3.0;
     let inv_scale = 1.0 / scale;
 
     for y_out in 0..height {
@@ -802,18 +869,18 @@ fn generate_color_bitmap(
             let x_unrotated = x_centered * cos_a + y_sin + center_x;
             let y_unrotated = -x_centered * sin_a + y_cos + center_y;
 
-            let x_unflipped = if params.flip_horizontal {
+            let x_unflipped = if orientation.flip_horizontal {
                 scaled_coarse_rotated_w - x_unrotated
             } else {
                 x_unrotated
             };
-            let y_unflipped = if params.flip_vertical {
+            let y_unflipped = if orientation.flip_vertical {
                 scaled_coarse_rotated_h - y_unrotated
             } else {
                 y_unrotated
             };
 
-            let (x_unrotated_coarse, y_unrotated_coarse) = match params.orientation_steps {
+            let (x_unrotated_coarse, y_unrotated_coarse) = match orientation.orientation_steps {
                 0 => (x_unflipped, y_unflipped),
                 1 => (y_unflipped, scaled_coarse_rotated_w - x_unflipped),
                 2 => (
@@ -829,25 +896,32 @@ fn generate_color_bitmap(
                 let y_src = (y_unrotated_coarse * inv_scale) as u32;
 
                 if x_src < full_w && y_src < full_h {
-                    let pixel = warped.get_pixel(x_src, y_src);
-                    let dist_sq = (pixel[0] as f32 - ref_r).powi(2)
-                        + (pixel[1] as f32 - ref_g).powi(2)
-                        + (pixel[2] as f32 - ref_b).powi(2);
-
-                    if dist_sq <= tolerance_sq {
-                        let intensity = 1.0 - (dist_sq.sqrt() / tolerance_sq.sqrt());
-                        mask.put_pixel(x_out, y_out, Luma([(intensity * 255.0) as u8]));
+                    let value = intensity(warped.get_pixel(x_src, y_src)).clamp(0.0, 1.0);
+                    if value > 0.0 {
+                        mask.put_pixel(x_out, y_out, Luma([(value * 255.0) as u8]));
                     }
                 }
             }
         }
     }
 
-    apply_grow_and_feather(&mut mask, params.grow, params.feather, width, height);
-    Some(mask)
+    mask
 }
 
-fn generate_luminance_bitmap(
+fn pixel_luma(pixel: Rgba<u8>) -> f32 {
+    0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32
+}
+
+fn parametric_orientation(params: &ParametricMaskParameters) -> SampledMaskOrientation {
+    SampledMaskOrientation {
+        rotation: params.rotation,
+        flip_horizontal: params.flip_horizontal,
+        flip_vertical: params.flip_vertical,
+        orientation_steps: params.orientation_steps,
+    }
+}
+
+fn generate_color_bitmap(
     params_value: &Value,
     width: u32,
     height: u32,
@@ -866,87 +940,173 @@ fn generate_luminance_bitmap(
     }
 
     let ref_pixel = warped.get_pixel(target_x as u32, target_y as u32);
-    let ref_luma =
-        0.299 * ref_pixel[0] as f32 + 0.587 * ref_pixel[1] as f32 + 0.114 * ref_pixel[2] as f32;
+    let ref_r = ref_pixel[0] as f32;
+    let ref_g = ref_pixel[1] as f32;
+    let ref_b = ref_pixel[2] as f32;
 
-    let mut mask = GrayImage::new(width, height);
+    let tolerance_sq = (params.tolerance * 2.55).max(1.0).powi(2) * 3.0;
 
-    let angle_rad = params.rotation * PI / 180.0;
-    let cos_a = angle_rad.cos();
-    let sin_a = angle_rad.sin();
+    let mut mask = generate_sampled_bitmap(
+        &parametric_orientation(&params),
+        width,
+        height,
+        scale,
+        crop_offset,
+        warped,
+        |pixel| {
+            let dist_sq = (pixel[0] as f32 - ref_r).powi(2)
+                + (pixel[1] as f32 - ref_g).powi(2)
+                + (pixel[2] as f32 - ref_b).powi(2);
+            if dist_sq <= tolerance_sq {
+                1.0 - (dist_sq.sqrt() / tolerance_sq.sqrt())
+            } else {
+                0.0
+            }
+        },
+    );
 
-    let (coarse_rotated_w, coarse_rotated_h) = if params.orientation_steps % 2 == 1 {
-        (full_h, full_w)
-    } else {
-        (full_w, full_h)
-    };
+    apply_grow_and_feather(&mut mask, params.grow, params.feather, width, height);
+    Some(mask)
+}
 
-    let scaled_coarse_rotated_w = coarse_rotated_w as f32 * scale;
-    let scaled_coarse_rotated_h = coarse_rotated_h as f32 * scale;
-    let center_x = scaled_coarse_rotated_w / 2.0;
-    let center_y = scaled_coarse_rotated_h / 2.0;
+fn generate_luminance_bitmap(
+    params_value: &Value,
+    width: u32,
+    height: u32,
+    scale: f32,
+    crop_offset: (f32, f32),
+    warped_image: Option<&image::DynamicImage>,
+) -> Option<GrayImage> {
+    let params: ParametricMaskParameters = serde_json::from_value(params_value.clone()).ok()?;
+    let warped = warped_image?;
+    let (full_w, full_h) = warped.dimensions();
+
+    let target_x = params.target_x.round() as i32;
+    let target_y = params.target_y.round() as i32;
+    if target_x < 0 || target_y < 0 || target_x >= full_w as i32 || target_y >= full_h as i32 {
+        return None;
+    }
 
+    let ref_luma = pixel_luma(warped.get_pixel(target_x as u32, target_y as u32));
     let tolerance_val = (params.tolerance * 2.55).max(1.0);
-    let inv_scale = 1.0 / scale;
 
-    for y_out in 0..height {
-        let y_uncrop = y_out as f32 + crop_offset.1;
-        let y_centered = y_uncrop - center_y;
-        let y_sin = y_centered * sin_a;
-        let y_cos = y_centered * cos_a;
+    let mut mask = generate_sampled_bitmap(
+        &parametric_orientation(&params),
+        width,
+        height,
+        scale,
+        crop_offset,
+        warped,
+        |pixel| {
+            let dist = (pixel_luma(pixel) - ref_luma).abs();
+            if dist <= tolerance_val {
+                1.0 - (dist / tolerance_val)
+            } else {
+                0.0
+            }
+        },
+    );
 
-        for x_out in 0..width {
-            let x_uncrop = x_out as f32 + crop_offset.0;
-            let x_centered = x_uncrop - center_x;
+    apply_grow_and_feather(&mut mask, params.grow, params.feather, width, height);
+    Some(mask)
+}
 
-            let x_unrotated = x_centered * cos_a + y_sin + center_x;
-            let y_unrotated = -x_centered * sin_a + y_cos + center_y;
+/// Selects pixels whose luminance (0-100) lies in `[min, max]`, ramping off over
+/// `feather` on either side.
+fn generate_luminance_range_bitmap(
+    params_value: &Value,
+    width: u32,
+    height: u32,
+    scale: f32,
+    crop_offset: (f32, f32),
+    warped_image: Option<&image::DynamicImage>,
+) -> Option<GrayImage> {
+    let params: LuminanceRangeMaskParameters = serde_json::from_value(params_value.clone()).ok()?;
+    let warped = warped_image?;
 
-            let x_unflipped = if params.flip_horizontal {
-                scaled_coarse_rotated_w - x_unrotated
-            } else {
-                x_unrotated
-            };
-            let y_unflipped = if params.flip_vertical {
-                scaled_coarse_rotated_h - y_unrotated
+    let min = params.min.min(params.max).clamp(0.0, 100.0);
+    let max = params.max.max(params.min).clamp(0.0, 100.0);
+    let feather = params.feather.max(0.0);
+
+    Some(generate_sampled_bitmap(
+        &params.orientation,
+        width,
+        height,
+        scale,
+        crop_offset,
+        warped,
+        |pixel| {
+            let luma = pixel_luma(pixel) / 2.55;
+            let outside = (min - luma).max(luma - max).max(0.0);
+            if feather > 0.0 {
+                1.0 - outside / feather
+            } else if outside > 0.0 {
+                0.0
             } else {
-                y_unrotated
-            };
+                1.0
+            }
+        },
+    ))
+}
 
-            let (x_unrotated_coarse, y_unrotated_coarse) = match params.orientation_steps {
-                0 => (x_unflipped, y_unflipped),
-                1 => (y_unflipped, scaled_coarse_rotated_w - x_unflipped),
-                2 => (
-                    scaled_coarse_rotated_w - x_unflipped,
-                    scaled_coarse_rotated_h - y_unflipped,
-                ),
-                3 => (scaled_coarse_rotated_h - y_unflipped, x_unflipped),
-                _ => (x_unflipped, y_unflipped),
-            };
+/// Selects pixels near a target hue (degrees) and saturation (0-100). `tolerance`
+/// widens both the hue window and the saturation window.
+fn generate_color_range_bitmap(
+    params_value: &Value,
+    width: u32,
+    height: u32,
+    scale: f32,
+    crop_offset: (f32, f32),
+    warped_image: Option<&image::DynamicImage>,
+) -> Option<GrayImage> {
+    let params: ColorRangeMaskParameters = serde_json::from_value(params_value.clone()).ok()?;
+    let warped = warped_image?;
 
-            if x_unrotated_coarse >= 0.0 && y_unrotated_coarse >= 0.0 {
-                let x_src = (x_unrotated_coarse * inv_scale) as u32;
-                let y_src = (y_unrotated_coarse * inv_scale) as u32;
+    let target_hue = params.hue.rem_euclid(360.0);
+    let target_saturation = (params.saturation / 100.0).clamp(0.0, 1.0);
+    let tolerance = params.tolerance.clamp(1.0, 100.0);
+    let hue_tolerance = tolerance * 1.8;
+    let saturation_tolerance = tolerance / 100.0;
 
-                if x_src < full_w && y_src < full_h {
-                    let pixel = warped.get_pixel(x_src, y_src);
-                    let luma =
-                        0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
-                    let dist = (luma - ref_luma).abs();
-
-                    if dist <= tolerance_val {
-                        let intensity = 1.0 - (dist / tolerance_val);
-                        mask.put_pixel(x_out, y_out, Luma([(intensity * 255.0) as u8]));
-                    }
-                }
-            }
-        }
-    }
+    let mut mask = generate_sampled_bitmap(
+        &params.orientation,
+        width,
+        height,
+        scale,
+        crop_offset,
+        warped,
+        |pixel| {
+            let (hue, saturation) = pixel_hue_saturation(pixel);
+            let raw_hue_dist = (hue - target_hue).abs();
+            let hue_dist = raw_hue_dist.min(360.0 - raw_hue_dist);
+            let saturation_dist = (saturation - target_saturation).abs();
+            1.0 - (hue_dist / hue_tolerance).max(saturation_dist / saturation_tolerance)
+        },
+    );
 
     apply_grow_and_feather(&mut mask, params.grow, params.feather, width, height);
     Some(mask)
 }
 
+fn pixel_hue_saturation(pixel: Rgba<u8>) -> (f32, f32) {
+    let r = pixel[0] as f32 / 255.0;
+    let g = pixel[1] as f32 / 255.0;
+    let b = pixel[2] as f32 / 255.0;
+    let c_max = r.max(g).max(b);
+    let delta = c_max - r.min(g).min(b);
+    if delta <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let hue = if c_max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if c_max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, delta / c_max)
+}
+
 fn generate_all_bitmap(width: u32, height: u32) -> GrayImage {
     GrayImage::from_pixel(width, height, Luma([255]))
 }
@@ -1001,6 +1161,22 @@ fn generate_sub_mask_bitmap(
             crop_offset,
             warped_image,
         ),
+        "luminance-range" => generate_luminance_range_bitmap(
+            &sub_mask.parameters,
+            width,
+            height,
+            scale,
+            crop_offset,
+            warped_image,
+        ),
+        "color-range" => generate_color_range_bitmap(
+            &sub_mask.parameters,
+            width,
+            height,
+            scale,
+            crop_offset,
+            warped_image,
+        ),
         "ai-subject" => {
             generate_ai_subject_bitmap(&sub_mask.parameters, width, height, scale, crop_offset)
         }
@@ -1082,3 +1258,124 @@ pub fn generate_mask_bitmap(
 
     Some(final_mask)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use serde_json::json;
+
+    fn gray_ramp() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 1, |x, _| {
+            Rgba([x as u8, x as u8, x as u8, 255])
+        }))
+    }
+
+    fn hue_ramp() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(360, 1, |x, _| {
+            let h = x as f32 / 60.0;
+            let ramp = 1.0 - (h % 2.0 - 1.0).abs();
+            let (r, g, b) = match h as u32 {
+                0 => (1.0, ramp, 0.0),
+                1 => (ramp, 1.0, 0.0),
+                2 => (0.0, 1.0, ramp),
+                3 => (0.0, ramp, 1.0),
+                4 => (ramp, 0.0, 1.0),
+                _ => (1.0, 0.0, ramp),
+            };
+            let to_u8 = |v: f32| (v * 255.0).round() as u8;
+            Rgba([to_u8(r), to_u8(g), to_u8(b), 255])
+        }))
+    }
+
+    fn sub_mask(mask_type: &str, mode: &str, parameters: Value) -> Value {
+        json!({
+            "id": mask_type,
+            "type": mask_type,
+            "visible": true,
+            "mode": mode,
+            "parameters": parameters,
+        })
+    }
+
+    #[test]
+    fn luminance_range_selects_midtones_of_a_gradient() {
+        let image = gray_ramp();
+        let params = json!({ "min": 40.0, "max": 60.0, "feather": 0.0 });
+        let mask = generate_luminance_range_bitmap(&params, 256, 1, 1.0, (0.0, 0.0), Some(&image))
+            .unwrap();
+
+        assert_eq!(mask.get_pixel(128, 0)[0], 255);
+        assert_eq!(mask.get_pixel(20, 0)[0], 0);
+        assert_eq!(mask.get_pixel(230, 0)[0], 0);
+    }
+
+    #[test]
+    fn luminance_range_feathers_past_its_edges() {
+        let image = gray_ramp();
+        let params = json!({ "min": 40.0, "max": 60.0, "feather": 10.0 });
+        let mask = generate_luminance_range_bitmap(&params, 256, 1, 1.0, (0.0, 0.0), Some(&image))
+            .unwrap();
+
+        let halfway = mask.get_pixel(166, 0)[0];
+        assert!((100..150).contains(&halfway), "{halfway}");
+        assert_eq!(mask.get_pixel(200, 0)[0], 0);
+    }
+
+    #[test]
+    fn color_range_selects_the_target_hue() {
+        let image = hue_ramp();
+        let params = json!({ "hue": 120.0, "saturation": 100.0, "tolerance": 10.0 });
+        let mask =
+            generate_color_range_bitmap(&params, 360, 1, 1.0, (0.0, 0.0), Some(&image)).unwrap();
+
+        assert_eq!(mask.get_pixel(120, 0)[0], 255);
+        assert!(mask.get_pixel(130, 0)[0] > 0);
+        assert!(mask.get_pixel(130, 0)[0] < 255);
+        assert_eq!(mask.get_pixel(0, 0)[0], 0);
+        assert_eq!(mask.get_pixel(240, 0)[0], 0);
+    }
+
+    #[test]
+    fn color_range_wraps_around_red() {
+        let image = hue_ramp();
+        let params = json!({ "hue": 355.0, "saturation": 100.0, "tolerance": 10.0 });
+        let mask =
+            generate_color_range_bitmap(&params, 360, 1, 1.0, (0.0, 0.0), Some(&image)).unwrap();
+
+        assert!(mask.get_pixel(5, 0)[0] > 0);
+        assert!(mask.get_pixel(350, 0)[0] > 0);
+        assert_eq!(mask.get_pixel(180, 0)[0], 0);
+    }
+
+    #[test]
+    fn range_masks_compose_with_subtractive_sub_masks() {
+        let image = gray_ramp();
+        let definition: MaskDefinition = serde_json::from_value(json!({
+            "id": "mask",
+            "name": "Shadows",
+            "visible": true,
+            "invert": false,
+            "adjustments": {},
+            "subMasks": [
+                sub_mask(
+                    "luminance-range",
+                    "additive",
+                    json!({ "min": 0.0, "max": 100.0, "feather": 0.0 }),
+                ),
+                sub_mask(
+                    "luminance-range",
+                    "subtractive",
+                    json!({ "min": 50.0, "max": 100.0, "feather": 0.0 }),
+                ),
+            ],
+        }))
+        .unwrap();
+
+        assert!(definition.requires_warped_image());
+        let mask =
+            generate_mask_bitmap(&definition, 256, 1, 1.0, (0.0, 0.0), Some(&image)).unwrap();
+        assert_eq!(mask.get_pixel(60, 0)[0], 255);
+        assert_eq!(mask.get_pixel(200, 0)[0], 0);
+    }
+}
@@ -34,6 +34,70 @@ fn get_attr_as_f64(attrs: &HashMap<String, String>, key: &str) -> Option<f64> {
         .and_then(|s| s.trim_start_matches('+').parse::<f64>().ok())
 }
 
+const AS_SHOT_DEFAULT: f64 = 5500.0;
+const MAX_MIRED_SHIFT: f64 = 150.0;
+
+/// Basic develop settings mirrored between our adjustments and the `crs:`
+/// namespace of XMP sidecars, as `(crs property, adjustment key)`.
+pub const CRS_DEVELOP_SETTINGS: &[(&str, &str)] = &[
+    ("Exposure2012", "exposure"),
+    ("Contrast2012", "contrast"),
+    ("Highlights2012", "highlights"),
+    ("Shadows2012", "shadows"),
+    ("Whites2012", "whites"),
+    ("Blacks2012", "blacks"),
+    ("Temperature", "temperature"),
+    ("Tint", "tint"),
+    ("Vibrance", "vibrance"),
+    ("Saturation", "saturation"),
+];
+
+fn temperature_from_kelvin(kelvin: f64, as_shot_kelvin: f64) -> f64 {
+    let mired_delta = 1_000_000.0 / kelvin - 1_000_000.0 / as_shot_kelvin;
+    ((-mired_delta / MAX_MIRED_SHIFT) * 100.0).clamp(-100.0, 100.0)
+}
+
+fn format_crs_signed(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    if formatted.trim_start_matches(['0', '.']).is_empty() {
+        formatted
+    } else if value < 0.0 {
+        format!("-{}", formatted)
+    } else {
+        format!("+{}", formatted)
+    }
+}
+
+/// Formats one of our slider values the way Camera Raw stores `crs_key`.
+///
+/// Returns `None` for `Temperature`: Camera Raw stores an absolute Kelvin value,
+/// which can't be derived from our relative slider without the as-shot white
+/// balance.
+pub fn adjustment_to_crs(crs_key: &str, value: f64) -> Option<String> {
+    Some(match crs_key {
+        "Exposure2012" => format_crs_signed(value.clamp(-5.0, 5.0), 2),
+        "Shadows2012" => format_crs_signed((value / 1.5).clamp(-100.0, 100.0), 0),
+        "Temperature" => return None,
+        "Tint" => format_crs_signed((value * 1.5).clamp(-150.0, 150.0), 0),
+        _ => format_crs_signed(value.clamp(-100.0, 100.0), 0),
+    })
+}
+
+/// Inverse of [`adjustment_to_crs`], approximating our slider value from a
+/// Camera Raw setting.
+pub fn crs_to_adjustment(crs_key: &str, raw: &str) -> Option<f64> {
+    let value = raw.trim().trim_start_matches('+').parse::<f64>().ok()?;
+    if !value.is_finite() {
+        return None;
+    }
+    Some(match crs_key {
+        "Shadows2012" => (value * 1.5).clamp(-100.0, 100.0),
+        "Temperature" => temperature_from_kelvin(value, AS_SHOT_DEFAULT),
+        "Tint" => ((value / 150.0) * 100.0).clamp(-100.0, 100.0),
+        _ => value,
+    })
+}
+
 fn extract_xmp_name(xmp_content: &str) -> Option<String> {
     let re =
         Regex::new(r#"(?s)<crs:Name>.*?<rdf:Alt>.*?<rdf:li[^>]*>([^<]+)</rdf:li>.*?</crs:Name>"#)
@@ -166,16 +230,10 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
     }
 
     if let Some(adjusted_k) = get_attr_as_f64(&attrs, "Temperature") {
-        const AS_SHOT_DEFAULT: f64 = 5500.0;
-        const MAX_MIRED_SHIFT: f64 = 150.0;
         let as_shot_k = get_attr_as_f64(&attrs, "AsShotTemperature").unwrap_or(AS_SHOT_DEFAULT);
-        let mired_adjusted = 1_000_000.0 / adjusted_k;
-        let mired_as_shot = 1_000_000.0 / as_shot_k;
-        let mired_delta = mired_adjusted - mired_as_shot;
-        let temp_value = (-mired_delta / MAX_MIRED_SHIFT) * 100.0;
         adjustments.insert(
             "temperature".to_string(),
-            json!(temp_value.clamp(-100.0, 100.0)),
+            json!(temperature_from_kelvin(adjusted_k, as_shot_k)),
         );
     }
 
@@ -18,15 +18,20 @@ pub fn develop_raw_image(
     highlight_compression: f32,
     linear_mode: String,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
-) -> Result<DynamicImage> {
-    let (developed_image, orientation) = develop_internal(
+    on_stage: Option<&dyn Fn(&str)>,
+) -> Result<(DynamicImage, bool)> {
+    let (developed_image, orientation, is_linear_format) = develop_internal(
         file_bytes,
         fast_demosaic,
         highlight_compression,
         linear_mode,
         cancel_token,
+        on_stage,
     )?;
-    Ok(apply_orientation(developed_image, orientation))
+    Ok((
+        apply_orientation(developed_image, orientation),
+        is_linear_format,
+    ))
 }
 
 fn is_linear_raw_format(raw_image: &RawImage) -> bool {
@@ -51,7 +56,14 @@ fn develop_internal(
     highlight_compression: f32,
     linear_mode: String,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
-) -> Result<(DynamicImage, Orientation)> {
+    on_stage: Option<&dyn Fn(&str)>,
+) -> Result<(DynamicImage, Orientation, bool)> {
+    let report_stage = |stage: &str| {
+        if let Some(callback) = on_stage {
+            callback(stage);
+        }
+    };
+
     let check_cancel = || -> Result<()> {
         if let Some((tracker, generation)) = &cancel_token
             && tracker.load(Ordering::SeqCst) != *generation
@@ -77,6 +89,7 @@ fn develop_internal(
         .unwrap_or(Orientation::Normal);
 
     let is_linear_format = is_linear_raw_format(&raw_image);
+    report_stage("decoded");
 
     let (apply_ungamma, apply_calibration) = match linear_mode.as_str() {
         "gamma" => (true, true),
@@ -111,6 +124,8 @@ fn develop_internal(
                 && (apply_calibration || step != ProcessingStep::Calibrate)
         });
     } else if fast_demosaic {
+        // A superpixel demosaic that bins each 2x2 CFA block, so this path
+        // already decodes at half resolution per side.
         developer.demosaic_algorithm = DemosaicAlgorithm::Speed;
         developer.steps.retain(|&step| step != ProcessingStep::SRgb);
     } else {
@@ -121,6 +136,7 @@ fn develop_internal(
     let mut developed_intermediate = developer.develop_intermediate(&raw_image)?;
 
     drop(raw_image);
+    report_stage("demosaiced");
 
     let denominator = (original_white_level - original_black_level).max(1.0);
     let rescale_factor = (u32::MAX as f32 - original_black_level) / denominator;
@@ -227,7 +243,7 @@ fn develop_internal(
         }
     };
 
-    Ok((dynamic_image, orientation))
+    Ok((dynamic_image, orientation, is_linear_format))
 }
 
 pub fn get_fast_demosaic_scale_factor(
@@ -252,3 +268,43 @@ pub fn get_fast_demosaic_scale_factor(
     }
     1.0
 }
+
+/// Returns the developed (cropped, unoriented) size of a RAW file and its EXIF
+/// orientation without decoding any pixel data.
+pub fn get_raw_dimensions(file_bytes: &[u8]) -> Option<(u32, u32, u16)> {
+    let source = RawSource::new_from_slice(file_bytes);
+    let decoder = rawler::get_decoder(&source).ok()?;
+    let params = RawDecodeParams::default();
+    let raw_img = decoder.raw_image(&source, &params, true).ok()?;
+    let (width, height) = raw_img
+        .crop_area
+        .map_or((raw_img.width, raw_img.height), |area| (area.d.w, area.d.h));
+    let orientation = decoder
+        .raw_metadata(&source, &params)
+        .ok()
+        .and_then(|metadata| metadata.exif.orientation)
+        .unwrap_or(1);
+    Some((width as u32, height as u32, orientation))
+}
+
+pub fn extract_embedded_preview(file_bytes: &[u8]) -> Result<DynamicImage> {
+    let source = RawSource::new_from_slice(file_bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let params = RawDecodeParams::default();
+
+    let preview = match decoder.full_image(&source, &params)? {
+        Some(image) => image,
+        None => decoder
+            .preview_image(&source, &params)?
+            .ok_or_else(|| anyhow!("No embedded preview found in RAW file"))?,
+    };
+
+    let orientation = decoder
+        .raw_metadata(&source, &params)
+        .ok()
+        .and_then(|metadata| metadata.exif.orientation)
+        .map(Orientation::from_u16)
+        .unwrap_or(Orientation::Normal);
+
+    Ok(apply_orientation(preview, orientation))
+}
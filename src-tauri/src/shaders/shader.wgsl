@@ -30,6 +30,27 @@ struct ColorCalibrationSettings {
     _pad1: f32,
 }
 
+struct SplitToning {
+    shadow_hue: f32,
+    shadow_saturation: f32,
+    highlight_hue: f32,
+    highlight_saturation: f32,
+    balance: f32,
+    _pad1: f32,
+    _pad2: f32,
+    _pad3: f32,
+}
+
+struct ChannelMixer {
+    red: vec4<f32>,
+    green: vec4<f32>,
+    blue: vec4<f32>,
+    monochrome: u32,
+    strength: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
 struct GlobalAdjustments {
     exposure: f32,
     brightness: f32,
@@ -111,6 +132,41 @@ struct GlobalAdjustments {
     flare_amount: f32,
 
     _pad_creative_1: f32,
+
+    channel_mixer: ChannelMixer,
+    split_toning: SplitToning,
+
+    defringe_purple_amount: f32,
+    defringe_green_amount: f32,
+    defringe_purple_hue_min: f32,
+    defringe_purple_hue_max: f32,
+    defringe_green_hue_min: f32,
+    defringe_green_hue_max: f32,
+    _pad_defringe1: f32,
+    _pad_defringe2: f32,
+
+    grain_color: u32,
+    grain_highlight_rolloff: f32,
+    glow_radius: f32,
+    halation_radius: f32,
+
+    invert: u32,
+    film_base_r: f32,
+    film_base_g: f32,
+    film_base_b: f32,
+    negative_black_r: f32,
+    negative_black_g: f32,
+    negative_black_b: f32,
+    _pad_negative1: f32,
+    negative_white_r: f32,
+    negative_white_g: f32,
+    negative_white_b: f32,
+    _pad_negative2: f32,
+
+    output_primaries: u32,
+    _pad_output1: f32,
+    _pad_output2: f32,
+    _pad_output3: f32,
 }
 
 struct MaskAdjustments {
@@ -136,10 +192,10 @@ struct MaskAdjustments {
     glow_amount: f32,
     halation_amount: f32,
     flare_amount: f32,
-    _pad1: f32,
+    grain_amount: f32,
 
-    _pad_cg1: f32,
-    _pad_cg2: f32,
+    grain_size: f32,
+    grain_roughness: f32,
     _pad_cg3: f32,
     color_grading_shadows: ColorGradeSettings,
     color_grading_midtones: ColorGradeSettings,
@@ -158,15 +214,17 @@ struct MaskAdjustments {
     red_curve_count: u32,
     green_curve_count: u32,
     blue_curve_count: u32,
-    _pad_end4: f32,
-    _pad_end5: f32,
-    _pad_end6: f32,
+    blend_mode: u32,
+    grain_color: u32,
+    grain_highlight_rolloff: f32,
     _pad_end7: f32,
+
+    channel_mixer: ChannelMixer,
 }
 
 struct AllAdjustments {
     global: GlobalAdjustments,
-    mask_adjustments: array<MaskAdjustments, 8>,
+    mask_adjustments: array<MaskAdjustments, 16>,
     mask_count: u32,
     tile_offset_x: u32,
     tile_offset_y: u32,
@@ -213,6 +271,13 @@ const HSL_RANGES: array<HslRange, 8> = array<HslRange, 8>(
 @group(0) @binding(17) var flare_texture: texture_2d<f32>;
 @group(0) @binding(18) var flare_sampler: sampler;
 
+@group(0) @binding(19) var glow_blur_texture: texture_2d<f32>;
+@group(0) @binding(20) var halation_blur_texture: texture_2d<f32>;
+
+// Masks 8..15 live in the layers of one array texture so they don't need a binding each.
+// Must match MASK_ATLAS_BINDING (13 + MAX_MASKS) in gpu_processing.rs.
+@group(0) @binding(21) var mask_atlas: texture_2d_array<f32>;
+
 const LUMA_COEFF = vec3<f32>(0.2126, 0.7152, 0.0722);
 
 fn get_luma(c: vec3<f32>) -> f32 {
@@ -227,6 +292,29 @@ fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
     return select(higher, lower, c <= cutoff);
 }
 
+// Linear sRGB to linear Display P3 and Rec. 2020, all D65.
+const SRGB_TO_DISPLAY_P3 = mat3x3<f32>(
+    vec3<f32>(0.822462, 0.0331942, 0.0170826),
+    vec3<f32>(0.177538, 0.9668058, 0.0723974),
+    vec3<f32>(0.0, 0.0, 0.9105199),
+);
+const SRGB_TO_REC2020 = mat3x3<f32>(
+    vec3<f32>(0.6274039, 0.0690973, 0.0163914),
+    vec3<f32>(0.329283, 0.9195404, 0.0880133),
+    vec3<f32>(0.0433131, 0.0113623, 0.8955953),
+);
+
+// Mirrors `color_management::srgb_linear_to_output_primaries`.
+fn srgb_linear_to_output_primaries(c: vec3<f32>, output_primaries: u32) -> vec3<f32> {
+    if (output_primaries == 1u) {
+        return SRGB_TO_DISPLAY_P3 * c;
+    }
+    if (output_primaries == 2u) {
+        return SRGB_TO_REC2020 * c;
+    }
+    return c;
+}
+
 fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
     let c_clamped = clamp(c, vec3<f32>(0.0), vec3<f32>(1.0));
     let cutoff = vec3<f32>(0.0031308);
@@ -568,6 +656,19 @@ fn apply_white_balance(color: vec3<f32>, temp: f32, tnt: f32) -> vec3<f32> {
     return rgb;
 }
 
+fn apply_channel_mixer(color: vec3<f32>, mixer: ChannelMixer) -> vec3<f32> {
+    if (mixer.strength <= 0.0) { return color; }
+    var mixed = vec3<f32>(
+        dot(color, mixer.red.xyz) + mixer.red.w,
+        dot(color, mixer.green.xyz) + mixer.green.w,
+        dot(color, mixer.blue.xyz) + mixer.blue.w
+    );
+    if (mixer.monochrome == 1u) {
+        mixed = vec3<f32>(get_luma(mixed));
+    }
+    return mix(color, mixed, mixer.strength);
+}
+
 fn apply_creative_color(color: vec3<f32>, sat: f32, vib: f32) -> vec3<f32> {
     var processed = color;
     let luma = get_luma(processed);
@@ -659,6 +760,24 @@ fn apply_hsl_panel(color: vec3<f32>, hsl_adjustments: array<HslColor, 8>, coords
     return final_color;
 }
 
+fn apply_split_toning(color: vec3<f32>, st: SplitToning) -> vec3<f32> {
+    if (st.shadow_saturation <= 0.001 && st.highlight_saturation <= 0.001) { return color; }
+    let luma = get_luma(max(vec3(0.0), color));
+    let pivot = clamp(0.5 - st.balance * 0.4, 0.1, 0.9);
+    let highlight_weight = smoothstep(pivot - 0.35, pivot + 0.35, luma);
+    let shadow_weight = 1.0 - highlight_weight;
+    var toned = color;
+    if (st.shadow_saturation > 0.001) {
+        let tint_rgb = hsv_to_rgb(vec3<f32>(st.shadow_hue, 1.0, 1.0));
+        toned += (tint_rgb - vec3<f32>(get_luma(tint_rgb))) * luma * st.shadow_saturation * shadow_weight;
+    }
+    if (st.highlight_saturation > 0.001) {
+        let tint_rgb = hsv_to_rgb(vec3<f32>(st.highlight_hue, 1.0, 1.0));
+        toned += (tint_rgb - vec3<f32>(get_luma(tint_rgb))) * luma * st.highlight_saturation * highlight_weight;
+    }
+    return toned;
+}
+
 fn apply_color_grading(color: vec3<f32>, shadows: ColorGradeSettings, midtones: ColorGradeSettings, highlights: ColorGradeSettings, blending: f32, balance: f32) -> vec3<f32> {
     let luma = get_luma(max(vec3(0.0), color));
     let base_shadow_crossover = 0.1;
@@ -895,6 +1014,75 @@ fn apply_noise_reduction(color: vec3<f32>, coords_i: vec2<i32>, luma_amount: f32
     return color;
 }
 
+// A range whose max is below its min wraps through 0°, e.g. 330..30.
+fn get_hue_range_weight(hue: f32, hue_min: f32, hue_max: f32) -> f32 {
+    const FEATHER: f32 = 10.0;
+    var span = hue_max - hue_min;
+    if (span < 0.0) { span += 360.0; }
+    let center = hue_min + span * 0.5;
+    let raw_dist = abs(hue - center) % 360.0;
+    let dist = min(raw_dist, 360.0 - raw_dist);
+    return 1.0 - smoothstep(span * 0.5, span * 0.5 + FEATHER, dist);
+}
+
+fn apply_negative_inversion(color_input_space: vec3<f32>, is_raw: u32, adj: GlobalAdjustments) -> vec3<f32> {
+    if (adj.invert == 0u) {
+        return color_input_space;
+    }
+    var linear_rgb = color_input_space;
+    if (is_raw == 0u) {
+        linear_rgb = srgb_to_linear(color_input_space);
+    }
+    let base = max(srgb_to_linear(vec3<f32>(adj.film_base_r, adj.film_base_g, adj.film_base_b)), vec3<f32>(0.0001));
+    let inverted = max(base - linear_rgb, vec3<f32>(0.0)) / base;
+    let black = vec3<f32>(adj.negative_black_r, adj.negative_black_g, adj.negative_black_b);
+    let white = vec3<f32>(adj.negative_white_r, adj.negative_white_g, adj.negative_white_b);
+    let positive = max((inverted - black) / max(white - black, vec3<f32>(0.0001)), vec3<f32>(0.0));
+    if (is_raw == 0u) {
+        return linear_to_srgb(positive);
+    }
+    return positive;
+}
+
+// Fringes sit within a few pixels of a high-contrast edge, so the edge test looks at the
+// luma range of a small cross around the pixel instead of a wide blur.
+fn get_defringe_edge_mask(color: vec3<f32>, coords: vec2<i32>, scale: f32, is_raw: u32, adj: GlobalAdjustments) -> f32 {
+    let max_coords = vec2<i32>(textureDimensions(input_texture)) - vec2<i32>(1);
+    let reach = i32(ceil(2.0 * scale));
+    var offsets = array<vec2<i32>, 4>(
+        vec2<i32>(reach, 0), vec2<i32>(-reach, 0), vec2<i32>(0, reach), vec2<i32>(0, -reach)
+    );
+    let center = pow(max(get_luma(color), 0.0001), 0.5);
+    var lo = center;
+    var hi = center;
+    for (var i = 0u; i < 4u; i = i + 1u) {
+        let sample_coords = clamp(coords + offsets[i], vec2<i32>(0), max_coords);
+        var neighbor = apply_negative_inversion(textureLoad(input_texture, vec2<u32>(sample_coords), 0).rgb, is_raw, adj);
+        if (is_raw == 0u) {
+            neighbor = srgb_to_linear(neighbor);
+        }
+        let perc = pow(max(get_luma(neighbor), 0.0001), 0.5);
+        lo = min(lo, perc);
+        hi = max(hi, perc);
+    }
+    return smoothstep(0.05, 0.2, hi - lo);
+}
+
+fn apply_defringe(color: vec3<f32>, coords: vec2<i32>, scale: f32, is_raw: u32, adj: GlobalAdjustments) -> vec3<f32> {
+    if (adj.defringe_purple_amount <= 0.0 && adj.defringe_green_amount <= 0.0) { return color; }
+
+    let edge_mask = get_defringe_edge_mask(color, coords, scale, is_raw, adj);
+    if (edge_mask < 0.001) { return color; }
+
+    let hsv = rgb_to_hsv(max(color, vec3<f32>(0.0)));
+    let saturation_mask = smoothstep(0.05, 0.25, hsv.y);
+    let purple_weight = get_hue_range_weight(hsv.x, adj.defringe_purple_hue_min, adj.defringe_purple_hue_max) * adj.defringe_purple_amount;
+    let green_weight = get_hue_range_weight(hsv.x, adj.defringe_green_hue_min, adj.defringe_green_hue_max) * adj.defringe_green_amount;
+    let amount = clamp(max(purple_weight, green_weight) * edge_mask * saturation_mask, 0.0, 1.0);
+
+    return mix(color, vec3<f32>(get_luma(color)), amount);
+}
+
 fn apply_ca_correction(coords: vec2<u32>, ca_rc: f32, ca_by: f32) -> vec3<f32> {
     let dims = vec2<f32>(textureDimensions(input_texture));
     let center = dims / 2.0;
@@ -994,6 +1182,35 @@ fn agx_full_transform(color_in: vec3<f32>) -> vec3<f32> {
     return final_color;
 }
 
+fn reinhard_tonemap(c: vec3<f32>) -> vec3<f32> {
+    let x = max(c, vec3<f32>(0.0));
+    let luma = get_luma(x);
+    if (luma <= 0.0) {
+        return vec3<f32>(0.0);
+    }
+    const WHITE_SQ: f32 = 16.0;
+    let mapped_luma = luma * (1.0 + luma / WHITE_SQ) / (1.0 + luma);
+    return clamp(x * (mapped_luma / luma), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn hable_partial(x: vec3<f32>) -> vec3<f32> {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+fn filmic_tonemap(c: vec3<f32>) -> vec3<f32> {
+    const EXPOSURE_BIAS: f32 = 2.0;
+    const WHITE_POINT: f32 = 11.2;
+    let curr = hable_partial(max(c, vec3<f32>(0.0)) * EXPOSURE_BIAS);
+    let white_scale = vec3<f32>(1.0) / hable_partial(vec3<f32>(WHITE_POINT));
+    return clamp(curr * white_scale, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
 fn legacy_tonemap(c: vec3<f32>) -> vec3<f32> {
     const a: f32 = 2.51;
     const b: f32 = 0.03;
@@ -1070,6 +1287,7 @@ fn apply_all_adjustments(
 
     processed_rgb = apply_color_calibration(processed_rgb, adj.color_calibration);
     processed_rgb = apply_hsl_panel(processed_rgb, adj.hsl, coords_i);
+    processed_rgb = apply_channel_mixer(processed_rgb, adj.channel_mixer);
     processed_rgb = apply_creative_color(processed_rgb, adj.saturation, adj.vibrance);
 
     return processed_rgb;
@@ -1096,6 +1314,7 @@ fn apply_all_mask_adjustments(
     processed_rgb = apply_tonal_adjustments(processed_rgb, tonal_blurred, is_raw, adj.contrast, adj.shadows, adj.whites, adj.blacks);
 
     processed_rgb = apply_hsl_panel(processed_rgb, adj.hsl, coords_i);
+    processed_rgb = apply_channel_mixer(processed_rgb, adj.channel_mixer);
     processed_rgb = apply_color_grading(processed_rgb, adj.color_grading_shadows, adj.color_grading_midtones, adj.color_grading_highlights, adj.color_grading_blending, adj.color_grading_balance);
     processed_rgb = apply_creative_color(processed_rgb, adj.saturation, adj.vibrance);
 
@@ -1112,7 +1331,12 @@ fn get_mask_influence(mask_index: u32, coords: vec2<u32>) -> f32 {
         case 5u: { return textureLoad(mask5, coords, 0).r; }
         case 6u: { return textureLoad(mask6, coords, 0).r; }
         case 7u: { return textureLoad(mask7, coords, 0).r; }
-        default: { return 0.0; }
+        default: {
+            if (mask_index - 8u < textureNumLayers(mask_atlas)) {
+                return textureLoad(mask_atlas, coords, i32(mask_index - 8u), 0).r;
+            }
+            return 0.0;
+        }
     }
 }
 
@@ -1311,6 +1535,64 @@ fn apply_halation(
     return contrast_reduced + halation_glow * amount * 2.5;
 }
 
+fn grain_noise(coord: vec2<f32>, grain_frequency: f32, roughness: f32, seed: vec2<f32>) -> f32 {
+    let noise_base = gradient_noise(coord * grain_frequency + seed);
+    let noise_rough = gradient_noise(coord * grain_frequency * 0.6 + seed + vec2<f32>(5.2, 1.3));
+    return mix(noise_base, noise_rough, roughness);
+}
+
+fn apply_grain(color: vec3<f32>, coord: vec2<f32>, grain_amount: f32, grain_size: f32, roughness: f32, scale: f32, color_grain: u32, highlight_rolloff: f32) -> vec3<f32> {
+    let amount = grain_amount * 0.5;
+    // Frequency is relative to the image's reference dimension so preview and export match.
+    let grain_frequency = (1.0 / max(grain_size, 0.1)) / scale;
+    let luma = max(0.0, get_luma(color));
+    let rolloff_start = mix(0.6, 0.15, clamp(highlight_rolloff, 0.0, 1.0));
+    let luma_mask = smoothstep(0.0, 0.15, luma) * (1.0 - smoothstep(rolloff_start, 1.0, luma));
+
+    let mono = grain_noise(coord, grain_frequency, roughness, vec2<f32>(0.0));
+    var noise = vec3<f32>(mono);
+    if (color_grain == 1u) {
+        let chroma = vec3<f32>(
+            grain_noise(coord, grain_frequency, roughness, vec2<f32>(17.3, 41.7)),
+            grain_noise(coord, grain_frequency, roughness, vec2<f32>(73.1, 9.4)),
+            grain_noise(coord, grain_frequency, roughness, vec2<f32>(29.8, 63.2)),
+        );
+        noise = mix(noise, chroma, 0.6);
+    }
+    return color + noise * amount * luma_mask;
+}
+
+// Blends a fully adjusted mask layer onto the base like a Photoshop adjustment layer.
+// Done in sRGB so overlay pivots around mid grey; the result is limited to display range.
+// sRGB encoding without the upper clamp, so highlights above 1.0 survive mask blending.
+fn linear_to_srgb_unbounded(c: vec3<f32>) -> vec3<f32> {
+    let c_pos = max(c, vec3<f32>(0.0));
+    let higher = 1.055 * pow(c_pos, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, c_pos * 12.92, c_pos <= vec3<f32>(0.0031308));
+}
+
+// Screen falls back to the brighter input above 1.0, where 1 - (1 - a)(1 - b) would darken.
+fn screen_unbounded(a: vec3<f32>, b: vec3<f32>) -> vec3<f32> {
+    let over = (a > vec3<f32>(1.0)) | (b > vec3<f32>(1.0));
+    return select(1.0 - (1.0 - a) * (1.0 - b), max(a, b), over);
+}
+
+fn blend_mask_layer(base_linear: vec3<f32>, layer_linear: vec3<f32>, mode: u32) -> vec3<f32> {
+    let base = linear_to_srgb_unbounded(base_linear);
+    let layer = linear_to_srgb_unbounded(layer_linear);
+    var blended = layer;
+    if (mode == 1u) {
+        blended = base * layer;
+    } else if (mode == 2u) {
+        blended = screen_unbounded(base, layer);
+    } else if (mode == 3u) {
+        let low = 2.0 * base * layer;
+        let high = screen_unbounded(2.0 * base - 1.0, layer);
+        blended = select(high, low, base < vec3<f32>(0.5));
+    }
+    return srgb_to_linear(blended);
+}
+
 fn scale_mask_adjustments(adj: MaskAdjustments, influence: f32) -> MaskAdjustments {
     var scaled = adj;
 
@@ -1337,6 +1619,7 @@ fn scale_mask_adjustments(adj: MaskAdjustments, influence: f32) -> MaskAdjustmen
     scaled.glow_amount *= influence;
     scaled.halation_amount *= influence;
     scaled.flare_amount *= influence;
+    scaled.grain_amount *= influence;
 
     scaled.color_grading_shadows.saturation *= influence;
     scaled.color_grading_shadows.luminance *= influence;
@@ -1353,6 +1636,8 @@ fn scale_mask_adjustments(adj: MaskAdjustments, influence: f32) -> MaskAdjustmen
         scaled.hsl[i].luminance *= influence;
     }
 
+    scaled.channel_mixer.strength *= influence;
+
     return scaled;
 }
 
@@ -1376,6 +1661,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         color_from_texture = apply_ca_correction(absolute_coord, ca_rc, ca_by);
     }
     let original_alpha = textureLoad(input_texture, absolute_coord, 0).a;
+    color_from_texture = apply_negative_inversion(color_from_texture, adjustments.global.is_raw_image, adjustments.global);
 
     var initial_linear_rgb: vec3<f32>;
     if (adjustments.global.is_raw_image == 0u) {
@@ -1384,10 +1670,14 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         initial_linear_rgb = color_from_texture;
     }
 
-    let sharpness_blurred = textureLoad(sharpness_blur_texture, id.xy, 0).rgb;
-    let tonal_blurred = textureLoad(tonal_blur_texture, id.xy, 0).rgb;
-    let clarity_blurred = textureLoad(clarity_blur_texture, id.xy, 0).rgb;
-    let structure_blurred = textureLoad(structure_blur_texture, id.xy, 0).rgb;
+    let sharpness_blurred = apply_negative_inversion(textureLoad(sharpness_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+    let tonal_blurred = apply_negative_inversion(textureLoad(tonal_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+    let clarity_blurred = apply_negative_inversion(textureLoad(clarity_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+    let structure_blurred = apply_negative_inversion(textureLoad(structure_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+    let glow_blurred = apply_negative_inversion(textureLoad(glow_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+    let halation_blurred = apply_negative_inversion(textureLoad(halation_blur_texture, id.xy, 0).rgb, adjustments.global.is_raw_image, adjustments.global);
+
+    initial_linear_rgb = apply_defringe(initial_linear_rgb, absolute_coord_i, scale, adjustments.global.is_raw_image, adjustments.global);
 
     var locally_contrasted_rgb = initial_linear_rgb;
     locally_contrasted_rgb = apply_local_contrast(locally_contrasted_rgb, sharpness_blurred, adjustments.global.sharpness, adjustments.global.is_raw_image, 0u);
@@ -1410,7 +1700,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     if (adjustments.global.glow_amount > 0.0) {
         processed_rgb = apply_glow_bloom(
             processed_rgb,
-            structure_blurred,
+            glow_blurred,
             adjustments.global.glow_amount,
             adjustments.global.is_raw_image,
             adjustments.global.exposure, adjustments.global.brightness, adjustments.global.contrast, adjustments.global.whites
@@ -1419,7 +1709,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     if (adjustments.global.halation_amount > 0.0) {
         processed_rgb = apply_halation(
             processed_rgb,
-            clarity_blurred,
+            halation_blurred,
             adjustments.global.halation_amount,
             adjustments.global.is_raw_image,
             adjustments.global.exposure, adjustments.global.brightness, adjustments.global.contrast, adjustments.global.whites
@@ -1462,7 +1752,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
             if (scaled_adj.glow_amount > 0.0) {
                 composite_rgb_linear = apply_glow_bloom(
                     composite_rgb_linear,
-                    structure_blurred,
+                    glow_blurred,
                     scaled_adj.glow_amount,
                     adjustments.global.is_raw_image,
                     adjustments.global.exposure + scaled_adj.exposure,
@@ -1474,7 +1764,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
             if (scaled_adj.halation_amount > 0.0) {
                 composite_rgb_linear = apply_halation(
                     composite_rgb_linear,
-                    clarity_blurred,
+                    halation_blurred,
                     scaled_adj.halation_amount,
                     adjustments.global.is_raw_image,
                     adjustments.global.exposure + scaled_adj.exposure,
@@ -1484,20 +1774,37 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
                 );
             }
 
-            composite_rgb_linear = apply_all_mask_adjustments(
-                composite_rgb_linear,
-                scaled_adj,
-                absolute_coord_i,
-                id.xy,
-                scale,
-                adjustments.global.is_raw_image,
-                adjustments.global.tonemapper_mode,
-                tonal_blurred,
-                structure_blurred
-            );
+            if (mask_adj.blend_mode == 0u) {
+                composite_rgb_linear = apply_all_mask_adjustments(
+                    composite_rgb_linear,
+                    scaled_adj,
+                    absolute_coord_i,
+                    id.xy,
+                    scale,
+                    adjustments.global.is_raw_image,
+                    adjustments.global.tonemapper_mode,
+                    tonal_blurred,
+                    structure_blurred
+                );
+            } else {
+                let layer_rgb_linear = apply_all_mask_adjustments(
+                    composite_rgb_linear,
+                    mask_adj,
+                    absolute_coord_i,
+                    id.xy,
+                    scale,
+                    adjustments.global.is_raw_image,
+                    adjustments.global.tonemapper_mode,
+                    tonal_blurred,
+                    structure_blurred
+                );
+                let blended = blend_mask_layer(composite_rgb_linear, layer_rgb_linear, mask_adj.blend_mode);
+                composite_rgb_linear = mix(composite_rgb_linear, blended, influence);
+            }
         }
     }
 
+    composite_rgb_linear = apply_split_toning(composite_rgb_linear, adjustments.global.split_toning);
     composite_rgb_linear = apply_color_grading(
         composite_rgb_linear,
         adjustments.global.color_grading_shadows,
@@ -1527,9 +1834,20 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         }
     }
 
+    // Wide-gamut output converts the unclipped linear color before the tone mapper
+    // encodes it. AgX maps into sRGB itself, so its result is converted instead.
+    let output_primaries = adjustments.global.output_primaries;
+    if (output_primaries != 0u && adjustments.global.tonemapper_mode != 1u) {
+        composite_rgb_linear = srgb_linear_to_output_primaries(composite_rgb_linear, output_primaries);
+    }
+
     var base_srgb: vec3<f32>;
     if (adjustments.global.tonemapper_mode == 1u) {
         base_srgb = agx_full_transform(composite_rgb_linear);
+    } else if (adjustments.global.tonemapper_mode == 2u) {
+        base_srgb = linear_to_srgb(reinhard_tonemap(composite_rgb_linear));
+    } else if (adjustments.global.tonemapper_mode == 3u) {
+        base_srgb = linear_to_srgb(filmic_tonemap(composite_rgb_linear));
     } else if (adjustments.global.is_raw_image == 1u) {
         var srgb_emulated = linear_to_srgb(composite_rgb_linear);
         const BRIGHTNESS_GAMMA: f32 = 1.1;
@@ -1540,6 +1858,9 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     } else {
         base_srgb = linear_to_srgb(composite_rgb_linear);
     }
+    if (output_primaries != 0u && adjustments.global.tonemapper_mode == 1u) {
+        base_srgb = linear_to_srgb(srgb_linear_to_output_primaries(srgb_to_linear(base_srgb), output_primaries));
+    }
 
     var final_rgb = apply_all_curves(base_srgb,
         adjustments.global.luma_curve, adjustments.global.luma_curve_count,
@@ -1568,18 +1889,17 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
 
     if (adjustments.global.grain_amount > 0.0) {
         let g = adjustments.global;
-        let coord = vec2<f32>(absolute_coord_i);
-        let amount = g.grain_amount * 0.5;
-        let grain_frequency = (1.0 / max(g.grain_size, 0.1)) / scale;
-        let roughness = g.grain_roughness;
-        let luma = max(0.0, get_luma(final_rgb));
-        let luma_mask = smoothstep(0.0, 0.15, luma) * (1.0 - smoothstep(0.6, 1.0, luma));
-        let base_coord = coord * grain_frequency;
-        let rough_coord = coord * grain_frequency * 0.6;
-        let noise_base = gradient_noise(base_coord);
-        let noise_rough = gradient_noise(rough_coord + vec2<f32>(5.2, 1.3));
-        let noise_val = mix(noise_base, noise_rough, roughness);
-        final_rgb += vec3<f32>(noise_val) * amount * luma_mask;
+        final_rgb = apply_grain(final_rgb, vec2<f32>(absolute_coord_i), g.grain_amount, g.grain_size, g.grain_roughness, scale, g.grain_color, g.grain_highlight_rolloff);
+    }
+
+    for (var i = 0u; i < adjustments.mask_count; i = i + 1u) {
+        let mask_adj = adjustments.mask_adjustments[i];
+        if (mask_adj.grain_amount > 0.0) {
+            let influence = get_mask_influence(i, absolute_coord);
+            if (influence > 0.001) {
+                final_rgb = apply_grain(final_rgb, vec2<f32>(absolute_coord_i), mask_adj.grain_amount * influence, mask_adj.grain_size, mask_adj.grain_roughness, scale, mask_adj.grain_color, mask_adj.grain_highlight_rolloff);
+            }
+        }
     }
 
     if (adjustments.global.show_clipping == 1u) {
@@ -5,6 +5,7 @@ use ndarray::{Array, Axis};
 use ort::session::Session;
 use ort::value::Tensor;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -287,7 +288,8 @@ pub async fn start_background_indexing(
         );
 
         let state_clone = app_handle_clone.state::<AppState>();
-        let gpu_context = crate::gpu_processing::get_or_init_gpu_context(&state_clone).ok();
+        let gpu_context =
+            crate::gpu_processing::get_or_init_gpu_context(&state_clone, &app_handle_clone).ok();
 
         let image_paths: Vec<PathBuf> = match fs::read_dir(&folder_path) {
             Ok(entries) => entries
@@ -330,9 +332,11 @@ pub async fn start_background_indexing(
                 async move {
                     let path_str = path.to_string_lossy().to_string();
                     let (_, sidecar_path) = parse_virtual_path(&path_str);
+                    let existing_sidecar_path =
+                        file_management::resolve_sidecar_path(&app_handle_inner, &sidecar_path);
 
-                    let mut metadata: ImageMetadata = if sidecar_path.exists() {
-                        fs::read_to_string(&sidecar_path)
+                    let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+                        fs::read_to_string(&existing_sidecar_path)
                             .ok()
                             .and_then(|c| serde_json::from_str(&c).ok())
                             .unwrap_or_default()
@@ -378,7 +382,11 @@ pub async fn start_background_indexing(
 
                                     if let Ok(json_string) = serde_json::to_string_pretty(&metadata)
                                     {
-                                        let _ = fs::write(sidecar_path, json_string);
+                                        let _ = file_management::write_sidecar_with_fallback(
+                                            &app_handle_inner,
+                                            &sidecar_path,
+                                            &json_string,
+                                        );
                                     }
                                 }
                             }
@@ -405,6 +413,7 @@ pub async fn start_background_indexing(
             .await;
 
         println!("Background indexing finished for: {}", folder_path);
+        invalidate_tag_index(&app_handle_clone.state::<AppState>());
         let _ = app_handle_clone.emit("indexing-finished", ());
 
         *app_handle_clone
@@ -420,13 +429,15 @@ pub async fn start_background_indexing(
 }
 
 fn modify_tags_for_path(
+    app_handle: &AppHandle,
     path_str: &str,
     modify_fn: impl Fn(&mut Vec<String>),
-) -> Result<(), String> {
+) -> Result<(Vec<String>, Vec<String>), String> {
     let (_, sidecar_path) = parse_virtual_path(path_str);
+    let existing_sidecar_path = file_management::resolve_sidecar_path(app_handle, &sidecar_path);
 
-    let mut metadata: ImageMetadata = if sidecar_path.exists() {
-        fs::read_to_string(&sidecar_path)
+    let mut metadata: ImageMetadata = if existing_sidecar_path.exists() {
+        fs::read_to_string(&existing_sidecar_path)
             .ok()
             .and_then(|content| serde_json::from_str(&content).ok())
             .unwrap_or_default()
@@ -434,12 +445,25 @@ fn modify_tags_for_path(
         ImageMetadata::default()
     };
 
+    let original: HashSet<String> = metadata
+        .tags
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
     let mut tags = metadata.tags.unwrap_or_default();
     modify_fn(&mut tags);
 
     tags.sort_unstable();
     tags.dedup();
 
+    let added: Vec<String> = tags
+        .iter()
+        .filter(|t| !original.contains(*t))
+        .cloned()
+        .collect();
+    let removed: Vec<String> = original.into_iter().filter(|t| !tags.contains(t)).collect();
+
     if tags.is_empty() {
         metadata.tags = None;
     } else {
@@ -447,39 +471,273 @@ fn modify_tags_for_path(
     }
 
     let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
-    fs::write(sidecar_path, json_string).map_err(|e| e.to_string())
+    file_management::write_sidecar_keeping_tag_index(app_handle, &sidecar_path, &json_string)?;
+    Ok((added, removed))
+}
+
+/// Aggregate tag counts for every sidecar below `root`, kept in `AppState` for autocomplete.
+pub struct TagIndex {
+    root: PathBuf,
+    counts: HashMap<String, usize>,
+}
+
+impl TagIndex {
+    fn sorted(&self) -> Vec<(String, usize)> {
+        let mut entries: Vec<(String, usize)> = self
+            .counts
+            .iter()
+            .map(|(tag, count)| (tag.clone(), *count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries
+    }
+
+    fn apply_delta(&mut self, path: &str, added: &[String], removed: &[String]) {
+        let (source_path, _) = parse_virtual_path(path);
+        if !source_path.starts_with(&self.root) {
+            return;
+        }
+        for tag in added.iter().filter(|t| !t.starts_with(COLOR_TAG_PREFIX)) {
+            *self.counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        for tag in removed.iter().filter(|t| !t.starts_with(COLOR_TAG_PREFIX)) {
+            if let Some(count) = self.counts.get_mut(tag) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(tag);
+                }
+            }
+        }
+    }
+}
+
+pub fn invalidate_tag_index(state: &AppState) {
+    *state.tag_index.lock().unwrap() = None;
 }
 
 #[tauri::command]
-pub fn add_tag_for_paths(paths: Vec<String>, tag: String) -> Result<(), String> {
-    paths.par_iter().for_each(|path| {
-        let tag_clone = tag.clone();
-        if let Err(e) = modify_tags_for_path(path, |tags| {
-            if !tags.contains(&tag_clone) {
-                tags.push(tag_clone.clone());
+pub async fn build_tag_index(
+    root: String,
+    app_handle: AppHandle,
+) -> Result<Vec<(String, usize)>, String> {
+    tokio::task::spawn_blocking(move || library_tag_counts(&root, &app_handle.state::<AppState>()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn library_tag_counts(root: &str, state: &AppState) -> Result<Vec<(String, usize)>, String> {
+    let root_path = PathBuf::from(root);
+    if !root_path.is_dir() {
+        return Err(format!("Root path does not exist: {}", root));
+    }
+
+    if let Some(index) = state.tag_index.lock().unwrap().as_ref()
+        && index.root == root_path
+    {
+        return Ok(index.sorted());
+    }
+
+    let sidecars: Vec<PathBuf> = WalkDir::new(&root_path)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && p.extension().and_then(|s| s.to_str()) == Some("rrdata"))
+        .collect();
+
+    let counts = sidecars
+        .par_iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(path).ok()?;
+            serde_json::from_str::<ImageMetadata>(&content).ok()?.tags
+        })
+        .fold(HashMap::new, |mut counts: HashMap<String, usize>, tags| {
+            for tag in tags
+                .into_iter()
+                .filter(|t| !t.starts_with(COLOR_TAG_PREFIX))
+            {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+            counts
+        })
+        .reduce(HashMap::new, |mut a, b| {
+            for (tag, count) in b {
+                *a.entry(tag).or_insert(0) += count;
+            }
+            a
+        });
+
+    let index = TagIndex {
+        root: root_path,
+        counts,
+    };
+    let sorted = index.sorted();
+    *state.tag_index.lock().unwrap() = Some(index);
+    Ok(sorted)
+}
+
+const TAG_HIERARCHY_SEPARATOR: &str = "|";
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagTreeNode {
+    pub name: String,
+    pub path: String,
+    pub count: usize,
+    pub children: Vec<TagTreeNode>,
+}
+
+fn insert_tag_path(
+    nodes: &mut Vec<TagTreeNode>,
+    prefix: &str,
+    segments: &[&str],
+    depth: usize,
+    count: usize,
+) {
+    let name = segments[depth];
+    let index = match nodes.iter().position(|n| n.name == name) {
+        Some(index) => index,
+        None => {
+            nodes.push(TagTreeNode {
+                name: name.to_string(),
+                path: format!(
+                    "{}{}",
+                    prefix,
+                    segments[..=depth].join(TAG_HIERARCHY_SEPARATOR)
+                ),
+                count: 0,
+                children: Vec::new(),
+            });
+            nodes.len() - 1
+        }
+    };
+    if depth + 1 == segments.len() {
+        nodes[index].count += count;
+    } else {
+        insert_tag_path(
+            &mut nodes[index].children,
+            prefix,
+            segments,
+            depth + 1,
+            count,
+        );
+    }
+}
+
+fn sort_tag_tree(nodes: &mut [TagTreeNode]) {
+    nodes.sort_by_key(|n| n.name.to_lowercase());
+    for node in nodes {
+        sort_tag_tree(&mut node.children);
+    }
+}
+
+/// Nests `|`-delimited tags from the library index into a tree. Counts are per exact
+/// tag; intermediate levels that were never assigned directly have a count of zero.
+#[tauri::command]
+pub async fn get_tag_tree(root: String, app_handle: AppHandle) -> Result<Vec<TagTreeNode>, String> {
+    tokio::task::spawn_blocking(move || {
+        let counts = library_tag_counts(&root, &app_handle.state::<AppState>())?;
+        Ok(build_tag_tree(counts))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn build_tag_tree(counts: Vec<(String, usize)>) -> Vec<TagTreeNode> {
+    let mut tree = Vec::new();
+    for (tag, count) in counts {
+        let (prefix, tag) = match tag.strip_prefix(USER_TAG_PREFIX) {
+            Some(rest) => (USER_TAG_PREFIX, rest),
+            None => ("", tag.as_str()),
+        };
+        let segments: Vec<&str> = tag
+            .split(TAG_HIERARCHY_SEPARATOR)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !segments.is_empty() {
+            insert_tag_path(&mut tree, prefix, &segments, 0, count);
+        }
+    }
+    sort_tag_tree(&mut tree);
+    tree
+}
+
+fn update_tags_for_paths(
+    paths: &[String],
+    app_handle: &AppHandle,
+    action: &str,
+    modify_fn: impl Fn(&mut Vec<String>) + Sync,
+) {
+    let deltas: Vec<(&String, (Vec<String>, Vec<String>))> = paths
+        .par_iter()
+        .filter_map(
+            |path| match modify_tags_for_path(app_handle, path, &modify_fn) {
+                Ok(delta) => Some((path, delta)),
+                Err(e) => {
+                    eprintln!("Failed to {} tags for {}: {}", action, path, e);
+                    None
+                }
+            },
+        )
+        .collect();
+
+    let state = app_handle.state::<AppState>();
+    if let Some(index) = state.tag_index.lock().unwrap().as_mut() {
+        for (path, (added, removed)) in &deltas {
+            index.apply_delta(path, added, removed);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn add_tags_to_paths(
+    paths: Vec<String>,
+    tags: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    update_tags_for_paths(&paths, &app_handle, "add", |current| {
+        for tag in &tags {
+            if !current.contains(tag) {
+                current.push(tag.clone());
             }
-        }) {
-            eprintln!("Failed to add tag to {}: {}", path, e);
         }
     });
     Ok(())
 }
 
 #[tauri::command]
-pub fn remove_tag_for_paths(paths: Vec<String>, tag: String) -> Result<(), String> {
-    paths.par_iter().for_each(|path| {
-        let tag_clone = tag.clone();
-        if let Err(e) = modify_tags_for_path(path, |tags| {
-            tags.retain(|t| t != &tag_clone);
-        }) {
-            eprintln!("Failed to remove tag from {}: {}", path, e);
-        }
+pub fn remove_tags_from_paths(
+    paths: Vec<String>,
+    tags: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    update_tags_for_paths(&paths, &app_handle, "remove", |current| {
+        current.retain(|t| !tags.contains(t));
     });
     Ok(())
 }
 
 #[tauri::command]
-pub fn clear_ai_tags(root_path: String) -> Result<usize, String> {
+pub fn add_tag_for_paths(
+    paths: Vec<String>,
+    tag: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    add_tags_to_paths(paths, vec![tag], app_handle)
+}
+
+#[tauri::command]
+pub fn remove_tag_for_paths(
+    paths: Vec<String>,
+    tag: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    remove_tags_from_paths(paths, vec![tag], app_handle)
+}
+
+#[tauri::command]
+pub fn clear_ai_tags(root_path: String, app_handle: AppHandle) -> Result<usize, String> {
     if !Path::new(&root_path).exists() {
         return Err(format!("Root path does not exist: {}", root_path));
     }
@@ -506,7 +764,8 @@ pub fn clear_ai_tags(root_path: String) -> Result<usize, String> {
                     metadata.tags = None;
                 }
                 if let Ok(json_string) = serde_json::to_string_pretty(&metadata)
-                    && fs::write(path, json_string).is_ok()
+                    && file_management::write_sidecar_with_fallback(&app_handle, path, &json_string)
+                        .is_ok()
                 {
                     updated_count += 1;
                 }
@@ -517,7 +776,7 @@ pub fn clear_ai_tags(root_path: String) -> Result<usize, String> {
 }
 
 #[tauri::command]
-pub fn clear_all_tags(root_path: String) -> Result<usize, String> {
+pub fn clear_all_tags(root_path: String, app_handle: AppHandle) -> Result<usize, String> {
     if !Path::new(&root_path).exists() {
         return Err(format!("Root path does not exist: {}", root_path));
     }
@@ -542,7 +801,8 @@ pub fn clear_all_tags(root_path: String) -> Result<usize, String> {
                     metadata.tags = None;
                 }
                 if let Ok(json_string) = serde_json::to_string_pretty(&metadata)
-                    && fs::write(path, json_string).is_ok()
+                    && file_management::write_sidecar_with_fallback(&app_handle, path, &json_string)
+                        .is_ok()
                 {
                     updated_count += 1;
                 }
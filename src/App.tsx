@@ -279,6 +279,7 @@ function App() {
   const [pinnedFolderTrees, setPinnedFolderTrees] = useState<any[]>([]);
   const [imageList, setImageList] = useState<Array<ImageFile>>([]);
   const [imageRatings, setImageRatings] = useState<Record<string, number>>({});
+  const [captureTimes, setCaptureTimes] = useState<Record<string, number>>({});
   const [sortCriteria, setSortCriteria] = useState<SortCriteria>({ key: 'name', order: SortDirection.Ascending });
   const [filterCriteria, setFilterCriteria] = useState<FilterCriteria>({
     colors: [],
@@ -1139,6 +1140,30 @@ function App() {
     }
   };
 
+  useEffect(() => {
+    if (sortCriteria.key !== 'captureTime') {
+      return;
+    }
+    const missing = imageList
+      .filter((image) => image.capture_time == null && captureTimes[image.path] === undefined)
+      .map((image) => image.path);
+    if (missing.length === 0) {
+      return;
+    }
+    let cancelled = false;
+    invoke(Invokes.EnrichCaptureTimes, { paths: missing })
+      .then((times: any) => {
+        if (!cancelled) {
+          setCaptureTimes((prev) => ({ ...prev, ...times }));
+        }
+      })
+      .catch((err) => console.error('Failed to resolve capture times:', err));
+    return () => {
+      cancelled = true;
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [imageList, sortCriteria.key]);
+
   const sortedImageList = useMemo(() => {
     let processedList = imageList;
 
@@ -1345,6 +1370,12 @@ function App() {
         case 'date':
           comparison = a.modified - b.modified;
           break;
+        case 'captureTime':
+          comparison = compareNullable(
+            a.capture_time ?? captureTimes[a.path] ?? null,
+            b.capture_time ?? captureTimes[b.path] ?? null,
+          );
+          break;
         case 'rating':
           comparison = (imageRatings[a.path] || 0) - (imageRatings[b.path] || 0);
           break;
@@ -1360,7 +1391,16 @@ function App() {
       return order === SortDirection.Ascending ? comparison : -comparison;
     });
     return list;
-  }, [imageList, sortCriteria, imageRatings, filterCriteria, supportedTypes, searchCriteria, appSettings]);
+  }, [
+    imageList,
+    sortCriteria,
+    imageRatings,
+    captureTimes,
+    filterCriteria,
+    supportedTypes,
+    searchCriteria,
+    appSettings,
+  ]);
 
   useEffect(() => {
     if (selectedImage?.path && selectedImage.isReady && finalPreviewUrl) {
@@ -1517,6 +1557,8 @@ function App() {
           roi: roi || null,
           computeWaveform: !!isWaveformVisible,
           activeWaveformChannel: activeWaveformChannelRef.current || null,
+          waveformMode: appSettings?.waveformMode || null,
+          histogramScale: appSettings?.histogramScale || null,
         });
 
         if (currentPath !== selectedImagePathRef.current) return;
@@ -1589,7 +1631,14 @@ function App() {
         }
       }
     },
-    [selectedImage?.isReady, selectedImage?.path, calculateROI, isWaveformVisible],
+    [
+      selectedImage?.isReady,
+      selectedImage?.path,
+      calculateROI,
+      isWaveformVisible,
+      appSettings?.waveformMode,
+      appSettings?.histogramScale,
+    ],
   );
 
   const generateUncroppedPreview = useCallback(
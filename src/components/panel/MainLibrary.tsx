@@ -1636,6 +1636,7 @@ export default function MainLibrary({
     return [
       { key: 'name', label: 'File Name' },
       { key: 'date', label: 'Date Modified' },
+      { key: 'captureTime', label: 'Capture Time' },
       { key: 'rating', label: 'Rating' },
       { key: 'date_taken', label: 'Date Taken', disabled: !exifEnabled },
       { key: 'focal_length', label: 'Focal Length', disabled: !exifEnabled },
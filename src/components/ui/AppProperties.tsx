@@ -49,6 +49,7 @@ export enum Invokes {
   CullImages = 'cull_images',
   DeleteFolder = 'delete_folder',
   DuplicateFile = 'duplicate_file',
+  EnrichCaptureTimes = 'enrich_capture_times',
   EstimateBatchExportSize = 'estimate_batch_export_size',
   EstimateExportSize = 'estimate_export_size',
   ExportImage = 'export_image',
@@ -179,6 +180,8 @@ export interface AppSettings {
   isWaveformVisible?: boolean;
   waveformHeight?: number;
   activeWaveformChannel?: string;
+  waveformMode?: string;
+  histogramScale?: string;
 }
 
 export interface BrushSettings {
@@ -213,6 +216,7 @@ export interface ImageFile {
   tags: Array<string> | null;
   exif: { [key: string]: string } | null;
   is_virtual_copy: boolean;
+  capture_time?: number | null;
 }
 
 export interface Option {
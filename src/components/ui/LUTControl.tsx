@@ -26,7 +26,7 @@ export default function LUTControl({
         filters: [
           {
             name: 'LUT Files',
-            extensions: ['cube', '3dl', 'png', 'jpg', 'jpeg', 'tiff'],
+            extensions: ['cube', '3dl', 'png', 'jpg', 'jpeg', 'tif', 'tiff'],
           },
         ],
       });
@@ -25,7 +25,7 @@ export enum PasteMode {
 export interface CopyPasteSettings {
   mode: PasteMode;
   includedAdjustments: Array<string>;
-  knownAdjustments: Array<string>;
+  excludedAdjustments?: Array<string>;
 }
 
 export enum BasicAdjustment {
@@ -290,8 +290,11 @@ export interface MaskAdjustments {
   whites: number;
 }
 
+export type MaskBlendMode = 'normal' | 'multiply' | 'screen' | 'overlay';
+
 export interface MaskContainer {
   adjustments: MaskAdjustments;
+  blendMode?: MaskBlendMode;
   id?: any;
   invert: boolean;
   name: string;